@@ -1,88 +1,445 @@
 //! Archive creation and constants for self-appending binaries.
 //!
-//! This module handles creating tar.gz archives from patch directories
-//! and defines the magic marker used for self-appending binary detection.
-
-use crate::patch;
-use flate2::write::GzEncoder;
-use flate2::Compression;
-use std::fs;
-use std::io::{self, Write};
-use std::path::Path;
-use tar::Builder;
+//! This module handles creating patch containers (compressed tar or indexed)
+//! from patch directories, opening them again as a [`PatchSource`], and
+//! defines the magic marker used for self-appending binary detection.
+//!
+//! # Tar Container Layout
+//!
+//! ```text
+//! ┌─────────────────────────┐
+//! │   Compressed tar        │  ← gzip, zstd or xz stream
+//! ├─────────────────────────┤
+//! │   Codec (1 byte)        │  ← see [`ArchiveCodec`]
+//! ├─────────────────────────┤
+//! │   Magic (8 bytes)       │  ← "GRAFTTAR"
+//! └─────────────────────────┘
+//! ```
+//!
+//! Archives without the codec trailer are read as plain tar.gz.
+//!
+//! # Reproducibility
+//!
+//! Containers are byte-identical for identical patch contents: entries are
+//! sorted by path, tar headers carry a fixed mtime ([`ArchiveOptions::mtime`]),
+//! zeroed uid/gid and mode 0644 (0755 for executables), and the gzip header
+//! has no timestamp.
+
+use crate::indexed_archive::{self, IndexedArchiveSource};
+use crate::patch::{self, PatchSource, TarSource};
+use crate::utils::file_ops::is_executable;
+use flate2::read::GzDecoder;
+use flate2::{Compression, GzBuilder};
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tar::{Builder, EntryType, Header};
+use xz2::read::XzDecoder;
+use xz2::write::XzEncoder;
 
 /// Magic marker at end of self-appending binary.
 /// Used to detect if a binary has patch data appended.
 pub const MAGIC_MARKER: &[u8; 8] = b"GRAFTPCH";
 
-/// Create a tar.gz archive from a patch directory.
-///
-/// The archive will contain:
-/// - manifest.json (required)
-/// - diffs/*.diff (if present)
-/// - files/* (if present)
-/// - .graft_assets/* (if present, for icons/metadata)
-///
-/// Returns the compressed bytes.
-pub fn create_archive_bytes(patch_dir: &Path) -> io::Result<Vec<u8>> {
-    let mut buffer = Vec::new();
+/// Magic marker at end of a tar container, preceded by the codec byte.
+pub const CODEC_MARKER: &[u8; 8] = b"GRAFTTAR";
+
+/// Size of the tar container trailer (codec + magic).
+const CODEC_TRAILER_LEN: usize = 9;
+
+/// Permissions recorded for tar entries.
+const ENTRY_MODE: u32 = 0o644;
+
+/// Permissions recorded for tar entries of executable files.
+const EXECUTABLE_ENTRY_MODE: u32 = 0o755;
+
+/// Operating system byte written to the gzip header ("unknown").
+const GZIP_OS_UNKNOWN: u8 = 255;
+
+/// Compression codec for the tar container.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ArchiveCodec {
+    #[default]
+    Gzip,
+    Zstd,
+    Xz,
+}
+
+impl ArchiveCodec {
+    /// Parse a codec name as used on the command line.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "gzip" | "gz" => Some(ArchiveCodec::Gzip),
+            "zstd" | "zst" => Some(ArchiveCodec::Zstd),
+            "xz" => Some(ArchiveCodec::Xz),
+            _ => None,
+        }
+    }
+
+    /// Name of this codec as used on the command line.
+    pub fn name(self) -> &'static str {
+        match self {
+            ArchiveCodec::Gzip => "gzip",
+            ArchiveCodec::Zstd => "zstd",
+            ArchiveCodec::Xz => "xz",
+        }
+    }
+
+    /// Identifier stored in the container trailer.
+    fn id(self) -> u8 {
+        match self {
+            ArchiveCodec::Gzip => 1,
+            ArchiveCodec::Zstd => 2,
+            ArchiveCodec::Xz => 3,
+        }
+    }
+
+    fn from_id(id: u8) -> Option<Self> {
+        match id {
+            1 => Some(ArchiveCodec::Gzip),
+            2 => Some(ArchiveCodec::Zstd),
+            3 => Some(ArchiveCodec::Xz),
+            _ => None,
+        }
+    }
 
-    {
-        let encoder = GzEncoder::new(&mut buffer, Compression::default());
-        let mut archive = Builder::new(encoder);
+    /// Level used when none is specified.
+    pub fn default_level(self) -> u32 {
+        match self {
+            ArchiveCodec::Gzip => 6,
+            ArchiveCodec::Zstd => 3,
+            ArchiveCodec::Xz => 6,
+        }
+    }
+
+    /// Range of supported compression levels.
+    pub fn levels(self) -> std::ops::RangeInclusive<u32> {
+        match self {
+            ArchiveCodec::Gzip => 0..=9,
+            ArchiveCodec::Zstd => 1..=22,
+            ArchiveCodec::Xz => 0..=9,
+        }
+    }
+}
+
+/// Container format used for the patch data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ContainerFormat {
+    /// Single compressed stream over a tar archive
+    Tar,
+    /// Independently compressed entries with a trailing index
+    #[default]
+    Indexed,
+}
+
+impl ContainerFormat {
+    /// Parse a container name as used on the command line.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "tar" | "targz" | "tar.gz" => Some(ContainerFormat::Tar),
+            "indexed" => Some(ContainerFormat::Indexed),
+            _ => None,
+        }
+    }
 
-        // Add manifest.json (required)
-        let manifest_path = patch_dir.join(patch::MANIFEST_FILENAME);
-        archive.append_path_with_name(&manifest_path, patch::MANIFEST_FILENAME)?;
+    /// Name of this container as used on the command line.
+    pub fn name(self) -> &'static str {
+        match self {
+            ContainerFormat::Tar => "tar",
+            ContainerFormat::Indexed => "indexed",
+        }
+    }
 
-        // Add diffs directory if it exists
-        let diffs_path = patch_dir.join(patch::DIFFS_DIR);
-        if diffs_path.is_dir() {
-            add_directory_contents(&mut archive, &diffs_path, patch::DIFFS_DIR)?;
+    /// Detect the container format from its trailing bytes.
+    pub fn detect(data: &[u8]) -> Self {
+        if indexed_archive::is_indexed_archive(data) {
+            ContainerFormat::Indexed
+        } else {
+            ContainerFormat::Tar
         }
+    }
+}
 
-        // Add files directory if it exists
-        let files_path = patch_dir.join(patch::FILES_DIR);
-        if files_path.is_dir() {
-            add_directory_contents(&mut archive, &files_path, patch::FILES_DIR)?;
+/// How to package a patch directory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ArchiveOptions {
+    pub container: ContainerFormat,
+    /// Codec for the tar container (ignored by indexed containers)
+    pub codec: ArchiveCodec,
+    /// Compression level (`None` = codec default)
+    pub level: Option<u32>,
+    /// Modification time recorded for tar entries, in seconds since the
+    /// Unix epoch (`SOURCE_DATE_EPOCH`-style; 0 by default)
+    pub mtime: u64,
+}
+
+impl ArchiveOptions {
+    /// Short description, e.g. "tar, zstd level 19".
+    pub fn describe(&self) -> String {
+        match self.container {
+            ContainerFormat::Tar => format!(
+                "tar, {} level {}",
+                self.codec.name(),
+                self.level.unwrap_or(self.codec.default_level())
+            ),
+            ContainerFormat::Indexed => "indexed".to_string(),
         }
+    }
+}
 
-        // Add assets directory if it exists
-        let assets_path = patch_dir.join(patch::ASSETS_DIR);
-        if assets_path.is_dir() {
-            add_directory_contents(&mut archive, &assets_path, patch::ASSETS_DIR)?;
+/// Create a patch container from a patch directory.
+pub fn create_container_bytes(patch_dir: &Path, options: &ArchiveOptions) -> io::Result<Vec<u8>> {
+    match options.container {
+        ContainerFormat::Tar => pack_tar(options.codec, options.level, |writer| {
+            write_tar(patch_dir, options.mtime, writer).map(|_| ())
+        }),
+        ContainerFormat::Indexed => {
+            indexed_archive::create_indexed_archive_bytes(&collect_patch_files(patch_dir)?)
         }
+    }
+}
+
+/// Open an in-memory patch container, detecting its format.
+pub fn open_bytes(data: Arc<[u8]>) -> io::Result<Box<dyn PatchSource + Send + Sync>> {
+    match ContainerFormat::detect(&data) {
+        ContainerFormat::Indexed => Ok(Box::new(IndexedArchiveSource::from_bytes(data)?)),
+        ContainerFormat::Tar => Ok(Box::new(TarSource::new(data)?)),
+    }
+}
+
+/// Open a patch container stored at `offset..offset + len` in a file.
+///
+/// Indexed containers are read in place; tar containers are loaded into memory.
+pub fn open_file_region(
+    path: &Path,
+    offset: u64,
+    len: u64,
+) -> io::Result<Box<dyn PatchSource + Send + Sync>> {
+    if read_container_format(path, offset, len)? == ContainerFormat::Indexed {
+        return Ok(Box::new(IndexedArchiveSource::from_file_region(
+            path, offset, len,
+        )?));
+    }
+
+    let mut file = File::open(path)?;
+    file.seek(SeekFrom::Start(offset))?;
+    let mut data = vec![0u8; len as usize];
+    file.read_exact(&mut data)?;
+    open_bytes(data.into())
+}
+
+/// Detect the format of a container stored at `offset..offset + len` in a file.
+pub fn read_container_format(path: &Path, offset: u64, len: u64) -> io::Result<ContainerFormat> {
+    if len < indexed_archive::INDEX_MAGIC.len() as u64 {
+        return Ok(ContainerFormat::Tar);
+    }
+    let mut file = File::open(path)?;
+    file.seek(SeekFrom::Start(offset + len - indexed_archive::INDEX_MAGIC.len() as u64))?;
+    let mut magic = [0u8; 8];
+    file.read_exact(&mut magic)?;
+    if &magic == indexed_archive::INDEX_MAGIC {
+        Ok(ContainerFormat::Indexed)
+    } else {
+        Ok(ContainerFormat::Tar)
+    }
+}
 
-        // Finish the archive
-        let encoder = archive.into_inner()?;
-        encoder.finish()?;
+/// Split a tar container into its codec and compressed stream.
+///
+/// Data without the codec trailer is treated as tar.gz.
+pub fn split_codec(data: &[u8]) -> io::Result<(ArchiveCodec, &[u8])> {
+    if data.len() < CODEC_TRAILER_LEN || !data.ends_with(CODEC_MARKER) {
+        return Ok((ArchiveCodec::Gzip, data));
     }
 
+    let codec_pos = data.len() - CODEC_TRAILER_LEN;
+    let codec = ArchiveCodec::from_id(data[codec_pos]).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("unknown archive codec id {}", data[codec_pos]),
+        )
+    })?;
+    Ok((codec, &data[..codec_pos]))
+}
+
+/// Create a decompressing reader over a compressed tar stream.
+pub fn decoder<'a, R>(codec: ArchiveCodec, stream: R) -> io::Result<Box<dyn Read + Send + 'a>>
+where
+    R: Read + Send + 'a,
+{
+    Ok(match codec {
+        ArchiveCodec::Gzip => Box::new(GzDecoder::new(stream)),
+        ArchiveCodec::Zstd => Box::new(zstd::Decoder::new(stream)?),
+        ArchiveCodec::Xz => Box::new(XzDecoder::new(stream)),
+    })
+}
+
+/// Compress tar bytes and append the codec trailer.
+pub fn compress_tar(tar: &[u8], codec: ArchiveCodec, level: Option<u32>) -> io::Result<Vec<u8>> {
+    pack_tar(codec, level, |writer| writer.write_all(tar))
+}
+
+/// Compress the tar that `write` produces as it is written, and append the
+/// codec trailer.
+fn pack_tar<F>(codec: ArchiveCodec, level: Option<u32>, write: F) -> io::Result<Vec<u8>>
+where
+    F: FnOnce(&mut dyn Write) -> io::Result<()>,
+{
+    let level = level.unwrap_or(codec.default_level());
+    if !codec.levels().contains(&level) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "{} level must be between {} and {}",
+                codec.name(),
+                codec.levels().start(),
+                codec.levels().end()
+            ),
+        ));
+    }
+
+    let mut buffer = match codec {
+        ArchiveCodec::Gzip => {
+            let mut encoder = GzBuilder::new()
+                .mtime(0)
+                .operating_system(GZIP_OS_UNKNOWN)
+                .write(Vec::new(), Compression::new(level));
+            write(&mut encoder)?;
+            encoder.finish()?
+        }
+        ArchiveCodec::Zstd => {
+            let mut encoder = zstd::Encoder::new(Vec::new(), level as i32)?;
+            write(&mut encoder)?;
+            encoder.finish()?
+        }
+        ArchiveCodec::Xz => {
+            let mut encoder = XzEncoder::new(Vec::new(), level);
+            write(&mut encoder)?;
+            encoder.finish()?
+        }
+    };
+
+    buffer.push(codec.id());
+    buffer.extend_from_slice(CODEC_MARKER);
     Ok(buffer)
 }
 
-/// Recursively add directory contents to the archive.
-fn add_directory_contents<W: Write>(
-    archive: &mut Builder<W>,
+/// List the files that belong in a patch container as `(payload path, file path)`.
+///
+/// Includes manifest.json and the contents of diffs/, files/ and .graft_assets/,
+/// sorted by payload path so the result doesn't depend on directory order.
+pub fn collect_patch_files(patch_dir: &Path) -> io::Result<Vec<(String, PathBuf)>> {
+    let mut files = vec![(
+        patch::MANIFEST_FILENAME.to_string(),
+        patch_dir.join(patch::MANIFEST_FILENAME),
+    )];
+
+    for dir in [patch::DIFFS_DIR, patch::FILES_DIR, patch::ASSETS_DIR] {
+        let path = patch_dir.join(dir);
+        if path.is_dir() {
+            collect_directory_contents(&path, dir, &mut files)?;
+        }
+    }
+
+    files.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(files)
+}
+
+/// Recursively collect directory contents with their payload paths.
+fn collect_directory_contents(
     dir: &Path,
-    archive_prefix: &str,
+    prefix: &str,
+    files: &mut Vec<(String, PathBuf)>,
 ) -> io::Result<()> {
     for entry in fs::read_dir(dir)? {
         let entry = entry?;
         let path = entry.path();
-        let file_name = entry.file_name();
-        let archive_path = format!("{}/{}", archive_prefix, file_name.to_string_lossy());
+        let payload_path = format!("{}/{}", prefix, entry.file_name().to_string_lossy());
 
         if path.is_file() {
-            archive.append_path_with_name(&path, &archive_path)?;
+            files.push((payload_path, path));
         } else if path.is_dir() {
             // Recursively add subdirectories (for nested file structures in files/)
-            add_directory_contents(archive, &path, &archive_path)?;
+            collect_directory_contents(&path, &payload_path, files)?;
         }
     }
     Ok(())
 }
 
+/// Create a gzip-compressed tar archive from a patch directory.
+///
+/// The archive will contain:
+/// - manifest.json (required)
+/// - diffs/*.diff (if present)
+/// - files/* (if present)
+/// - .graft_assets/* (if present, for icons/metadata)
+///
+/// Returns the compressed bytes, followed by the codec trailer.
+pub fn create_archive_bytes(patch_dir: &Path) -> io::Result<Vec<u8>> {
+    let options = ArchiveOptions {
+        container: ContainerFormat::Tar,
+        ..Default::default()
+    };
+    create_container_bytes(patch_dir, &options)
+}
+
+/// Create an uncompressed tar archive from a patch directory.
+///
+/// Headers are normalized (see the module docs), with `mtime` as the
+/// modification time of every entry.
+pub fn create_tar_bytes(patch_dir: &Path, mtime: u64) -> io::Result<Vec<u8>> {
+    write_tar(patch_dir, mtime, Vec::new())
+}
+
+/// Size of the uncompressed tar archive of a patch directory, without
+/// holding it in memory.
+pub fn tar_size(patch_dir: &Path, mtime: u64) -> io::Result<u64> {
+    Ok(write_tar(patch_dir, mtime, ByteCounter(0))?.0)
+}
+
+/// A writer that only counts the bytes written to it.
+struct ByteCounter(u64);
+
+impl Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Write the uncompressed tar archive of a patch directory to `writer`,
+/// one file at a time, and return the writer.
+pub fn write_tar<W: Write>(patch_dir: &Path, mtime: u64, writer: W) -> io::Result<W> {
+    let mut archive = Builder::new(writer);
+
+    for (archive_path, path) in collect_patch_files(patch_dir)? {
+        let file = File::open(&path)?;
+        let metadata = file.metadata()?;
+
+        let mut header = Header::new_gnu();
+        header.set_entry_type(EntryType::Regular);
+        header.set_size(metadata.len());
+        header.set_mode(if is_executable(&metadata) {
+            EXECUTABLE_ENTRY_MODE
+        } else {
+            ENTRY_MODE
+        });
+        header.set_mtime(mtime);
+        header.set_uid(0);
+        header.set_gid(0);
+        header.set_username("")?;
+        header.set_groupname("")?;
+        archive.append_data(&mut header, &archive_path, file)?;
+    }
+
+    archive.into_inner()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -175,6 +532,202 @@ mod tests {
             .any(|p| p.to_string_lossy().contains("files/new_file.bin")));
     }
 
+    #[test]
+    fn open_bytes_detects_container_format() {
+        let patch_dir = tempdir().unwrap();
+
+        fs::write(
+            patch_dir.path().join("manifest.json"),
+            r#"{"version": 1, "name": "Test", "entries": []}"#,
+        )
+        .unwrap();
+        fs::create_dir(patch_dir.path().join("files")).unwrap();
+        fs::write(patch_dir.path().join("files/new.bin"), b"new").unwrap();
+
+        for format in [ContainerFormat::Tar, ContainerFormat::Indexed] {
+            let options = ArchiveOptions {
+                container: format,
+                ..Default::default()
+            };
+            let data = create_container_bytes(patch_dir.path(), &options).unwrap();
+            assert_eq!(ContainerFormat::detect(&data), format);
+
+            let source = open_bytes(data.into()).unwrap();
+            assert_eq!(source.read_manifest().unwrap().name, "Test");
+            assert_eq!(source.read_file("new.bin").unwrap(), b"new");
+        }
+    }
+
+    #[test]
+    fn open_file_region_reads_both_formats() {
+        let patch_dir = tempdir().unwrap();
+
+        fs::write(
+            patch_dir.path().join("manifest.json"),
+            r#"{"version": 1, "name": "Test", "entries": []}"#,
+        )
+        .unwrap();
+
+        for format in [ContainerFormat::Tar, ContainerFormat::Indexed] {
+            let options = ArchiveOptions {
+                container: format,
+                ..Default::default()
+            };
+            let data = create_container_bytes(patch_dir.path(), &options).unwrap();
+            let exe_path = patch_dir.path().join(format!("patcher-{}", format.name()));
+            let mut exe = b"stub".to_vec();
+            exe.extend_from_slice(&data);
+            fs::write(&exe_path, &exe).unwrap();
+
+            let source = open_file_region(&exe_path, 4, data.len() as u64).unwrap();
+            assert_eq!(source.read_manifest().unwrap().name, "Test");
+        }
+    }
+
+    #[test]
+    fn parses_container_names() {
+        assert_eq!(ContainerFormat::parse("tar"), Some(ContainerFormat::Tar));
+        assert_eq!(ContainerFormat::parse("targz"), Some(ContainerFormat::Tar));
+        assert_eq!(ContainerFormat::parse("indexed"), Some(ContainerFormat::Indexed));
+        assert_eq!(ContainerFormat::parse("zip"), None);
+    }
+
+    #[test]
+    fn each_codec_roundtrips() {
+        let patch_dir = tempdir().unwrap();
+
+        fs::write(
+            patch_dir.path().join("manifest.json"),
+            r#"{"version": 1, "name": "Test", "entries": []}"#,
+        )
+        .unwrap();
+        fs::create_dir(patch_dir.path().join("diffs")).unwrap();
+        fs::write(patch_dir.path().join("diffs/a.bin.diff"), vec![7u8; 10_000]).unwrap();
+
+        for codec in [ArchiveCodec::Gzip, ArchiveCodec::Zstd, ArchiveCodec::Xz] {
+            let options = ArchiveOptions {
+                container: ContainerFormat::Tar,
+                codec,
+                level: Some(*codec.levels().end()),
+                ..Default::default()
+            };
+            let data = create_container_bytes(patch_dir.path(), &options).unwrap();
+            assert_eq!(split_codec(&data).unwrap().0, codec);
+
+            // Streaming the tar into the encoder packs the same bytes as compressing it whole
+            let tar = create_tar_bytes(patch_dir.path(), options.mtime).unwrap();
+            assert_eq!(tar_size(patch_dir.path(), options.mtime).unwrap(), tar.len() as u64);
+            assert_eq!(compress_tar(&tar, codec, options.level).unwrap(), data);
+
+            let source = open_bytes(data.into()).unwrap();
+            assert_eq!(source.read_diff("a.bin").unwrap(), vec![7u8; 10_000]);
+        }
+    }
+
+    #[test]
+    fn legacy_archive_without_trailer_is_gzip() {
+        let data = b"\x1f\x8b legacy";
+        let (codec, stream) = split_codec(data).unwrap();
+        assert_eq!(codec, ArchiveCodec::Gzip);
+        assert_eq!(stream, data);
+    }
+
+    #[test]
+    fn rejects_out_of_range_level() {
+        let result = compress_tar(b"", ArchiveCodec::Gzip, Some(10));
+        assert!(result.is_err());
+    }
+
+    /// Write the same patch contents in a different order, with different
+    /// timestamps and permissions, as a second machine might.
+    fn create_patch_dir_variant(dir: &Path, reversed: bool) {
+        let mut files = vec![
+            ("manifest.json", &br#"{"version": 1, "name": "Test", "entries": []}"#[..]),
+            ("diffs/a.bin.diff", &b"diff a"[..]),
+            ("diffs/b.bin.diff", &b"diff b"[..]),
+            ("files/sub/c.bin", &b"new c"[..]),
+            ("files/d.bin", &b"new d"[..]),
+        ];
+        if reversed {
+            files.reverse();
+        }
+
+        for (name, content) in files {
+            let path = dir.join(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, content).unwrap();
+
+            if reversed {
+                let old = std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000);
+                let file = fs::File::options().write(true).open(&path).unwrap();
+                file.set_modified(old).unwrap();
+
+                #[cfg(unix)]
+                {
+                    use std::os::unix::fs::PermissionsExt;
+                    fs::set_permissions(&path, fs::Permissions::from_mode(0o600)).unwrap();
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn output_is_byte_identical_across_runs() {
+        let first = tempdir().unwrap();
+        let second = tempdir().unwrap();
+        create_patch_dir_variant(first.path(), false);
+        create_patch_dir_variant(second.path(), true);
+
+        let all_options = [
+            ArchiveOptions {
+                container: ContainerFormat::Tar,
+                ..Default::default()
+            },
+            ArchiveOptions {
+                container: ContainerFormat::Tar,
+                codec: ArchiveCodec::Zstd,
+                level: Some(19),
+                mtime: 1_700_000_000,
+            },
+            ArchiveOptions {
+                container: ContainerFormat::Tar,
+                codec: ArchiveCodec::Xz,
+                ..Default::default()
+            },
+            ArchiveOptions::default(),
+        ];
+
+        for options in all_options {
+            let a = create_container_bytes(first.path(), &options).unwrap();
+            let b = create_container_bytes(second.path(), &options).unwrap();
+            assert_eq!(a, b, "{} output differs", options.describe());
+        }
+    }
+
+    #[test]
+    fn tar_headers_are_normalized() {
+        let patch_dir = tempdir().unwrap();
+        create_patch_dir_variant(patch_dir.path(), true);
+
+        let tar_data = create_tar_bytes(patch_dir.path(), 42).unwrap();
+        let mut archive = Archive::new(&tar_data[..]);
+
+        let mut paths = Vec::new();
+        for entry in archive.entries().unwrap() {
+            let entry = entry.unwrap();
+            let header = entry.header();
+            assert_eq!(header.mtime().unwrap(), 42);
+            assert_eq!(header.uid().unwrap(), 0);
+            assert_eq!(header.gid().unwrap(), 0);
+            assert_eq!(header.mode().unwrap(), 0o644);
+            paths.push(entry.path().unwrap().to_string_lossy().into_owned());
+        }
+
+        let mut sorted = paths.clone();
+        sorted.sort();
+        assert_eq!(paths, sorted);
+    }
+
     #[test]
     fn magic_marker_is_correct() {
         assert_eq!(MAGIC_MARKER, b"GRAFTPCH");
@@ -0,0 +1,226 @@
+//! Automatic discovery of the folder a patch should be applied to.
+//!
+//! A manifest's `discovery` section lists install path templates and marker
+//! files. Templates are expanded, folders missing a marker are dropped, and
+//! the rest are ranked by how many of the manifest's pre-patch hashes match.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::utils::hash::hash_bytes;
+use crate::utils::manifest::{Manifest, ManifestEntry};
+
+/// A folder that might be the patch target.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Candidate {
+    pub path: PathBuf,
+    /// Entries whose file is present with the expected pre-patch hash
+    pub matches: usize,
+    /// Entries with a pre-patch hash that could be checked
+    pub checked: usize,
+}
+
+/// Find candidate target folders for a manifest, best match first.
+///
+/// Returns an empty list if the manifest has no discovery section.
+pub fn discover(manifest: &Manifest) -> Vec<Candidate> {
+    let Some(discovery) = &manifest.discovery else {
+        return Vec::new();
+    };
+    let paths = discovery.paths.iter().filter_map(|t| expand_template(t));
+    rank_candidates(paths, &discovery.markers, &manifest.entries)
+}
+
+/// Score existing folders that contain all `markers`, best match first.
+///
+/// Folders with the same score keep their original order.
+pub fn rank_candidates(
+    paths: impl IntoIterator<Item = PathBuf>,
+    markers: &[String],
+    entries: &[ManifestEntry],
+) -> Vec<Candidate> {
+    let mut candidates: Vec<Candidate> = Vec::new();
+
+    for path in paths {
+        if !path.is_dir()
+            || !markers.iter().all(|m| path.join(m).exists())
+            || candidates.iter().any(|c| c.path == path)
+        {
+            continue;
+        }
+        let (matches, checked) = score(&path, entries);
+        candidates.push(Candidate { path, matches, checked });
+    }
+
+    candidates.sort_by_key(|c| std::cmp::Reverse(c.matches));
+    candidates
+}
+
+/// Count the entries whose pre-patch hash matches the file in `dir`.
+fn score(dir: &Path, entries: &[ManifestEntry]) -> (usize, usize) {
+    let mut matches = 0;
+    let mut checked = 0;
+
+    for entry in entries {
+        let expected = match entry {
+            ManifestEntry::Patch { .. } => entry.patch_origins().into_iter().map(|(hash, _)| hash).collect(),
+            ManifestEntry::Delete { original_hash, .. } => vec![original_hash.as_str()],
+            ManifestEntry::Require { hash, .. } => vec![hash.as_str()],
+            ManifestEntry::Add { .. } | ManifestEntry::EditJson { .. } | ManifestEntry::EditIni { .. } => continue,
+        };
+        checked += 1;
+        if let Ok(data) = fs::read(dir.join(entry.file()))
+            && expected.contains(&hash_bytes(&data).as_str())
+        {
+            matches += 1;
+        }
+    }
+
+    (matches, checked)
+}
+
+/// Expand `~`, `%VAR%`, `$VAR` and `${VAR}` in an install path template.
+///
+/// Returns None if the template refers to an unset variable.
+pub fn expand_template(template: &str) -> Option<PathBuf> {
+    expand_with(template, dirs::home_dir().as_deref(), &|name| std::env::var(name).ok())
+}
+
+fn expand_with(template: &str, home: Option<&Path>, lookup: &dyn Fn(&str) -> Option<String>) -> Option<PathBuf> {
+    let mut out = String::new();
+    let mut rest = template;
+
+    if let Some(after) = rest.strip_prefix('~')
+        && (after.is_empty() || after.starts_with(['/', '\\']))
+    {
+        out.push_str(home?.to_str()?);
+        rest = after;
+    }
+
+    while let Some(pos) = rest.find(['%', '$']) {
+        out.push_str(&rest[..pos]);
+        let marker = rest.as_bytes()[pos];
+        let after = &rest[pos + 1..];
+
+        let (name, remainder) = if marker == b'%' {
+            match after.find('%') {
+                Some(end) if end > 0 => (&after[..end], &after[end + 1..]),
+                _ => ("", after),
+            }
+        } else if let Some(braced) = after.strip_prefix('{') {
+            match braced.find('}') {
+                Some(end) => (&braced[..end], &braced[end + 1..]),
+                None => ("", after),
+            }
+        } else {
+            let end = after
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(after.len());
+            (&after[..end], &after[end..])
+        };
+
+        if name.is_empty() {
+            // Not a variable reference, keep the character
+            out.push(marker as char);
+        } else {
+            out.push_str(&lookup(name)?);
+        }
+        rest = remainder;
+    }
+    out.push_str(rest);
+
+    Some(PathBuf::from(out))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn lookup(name: &str) -> Option<String> {
+        match name {
+            "ProgramFiles" => Some("C:\\Program Files".to_string()),
+            "XDG_DATA_HOME" => Some("/home/user/.local/share".to_string()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn expands_variables_and_home() {
+        let home = Path::new("/home/user");
+        let expand = |t: &str| expand_with(t, Some(home), &lookup);
+
+        assert_eq!(
+            expand("~/.steam/steam/steamapps/common/Game"),
+            Some(PathBuf::from("/home/user/.steam/steam/steamapps/common/Game"))
+        );
+        assert_eq!(
+            expand("%ProgramFiles%\\Game"),
+            Some(PathBuf::from("C:\\Program Files\\Game"))
+        );
+        assert_eq!(
+            expand("$XDG_DATA_HOME/Game"),
+            Some(PathBuf::from("/home/user/.local/share/Game"))
+        );
+        assert_eq!(
+            expand("${XDG_DATA_HOME}/Game"),
+            Some(PathBuf::from("/home/user/.local/share/Game"))
+        );
+        assert_eq!(expand("/opt/100%/Game"), Some(PathBuf::from("/opt/100%/Game")));
+        assert_eq!(expand("%UNSET%/Game"), None);
+    }
+
+    #[test]
+    fn ranks_by_matching_hashes() {
+        let root = tempdir().unwrap();
+        let (good, partial, unmarked) = (
+            root.path().join("good"),
+            root.path().join("partial"),
+            root.path().join("unmarked"),
+        );
+        for dir in [&good, &partial, &unmarked] {
+            fs::create_dir(dir).unwrap();
+            fs::write(dir.join("a.bin"), b"a").unwrap();
+        }
+        fs::write(good.join("b.bin"), b"b").unwrap();
+        fs::write(unmarked.join("b.bin"), b"b").unwrap();
+        for dir in [&good, &partial] {
+            fs::write(dir.join("game.exe"), b"").unwrap();
+        }
+
+        let entries = vec![
+            ManifestEntry::Patch {
+                file: "a.bin".to_string(),
+                original_hash: hash_bytes(b"a"),
+                diff_hash: "x".to_string(),
+                final_hash: "y".to_string(),
+                alternatives: Vec::new(),
+                fallback: false,
+            },
+            ManifestEntry::Require {
+                file: "b.bin".to_string(),
+                hash: hash_bytes(b"b"),
+            },
+            ManifestEntry::Add {
+                file: "c.bin".to_string(),
+                final_hash: "z".to_string(),
+            },
+        ];
+        let paths = vec![partial.clone(), root.path().join("missing"), good.clone(), unmarked];
+
+        let candidates = rank_candidates(paths, &["game.exe".to_string()], &entries);
+
+        assert_eq!(
+            candidates,
+            vec![
+                Candidate { path: good, matches: 2, checked: 2 },
+                Candidate { path: partial, matches: 1, checked: 2 },
+            ]
+        );
+    }
+
+    #[test]
+    fn no_discovery_section_finds_nothing() {
+        assert!(discover(&Manifest::default()).is_empty());
+    }
+}
@@ -0,0 +1,440 @@
+//! Seekable patch container with independently compressed entries.
+//!
+//! Unlike the compressed tar archive, any single payload can be read without
+//! decompressing the ones before it. The index sits at the end of the
+//! container, so a patcher can locate it by reading backwards from the end
+//! of its own executable.
+//!
+//! # Layout
+//!
+//! ```text
+//! ┌─────────────────────────┐
+//! │   Entry data            │  ← each entry compressed on its own
+//! ├─────────────────────────┤
+//! │   Index (JSON)          │  ← paths, offsets, sizes, compression
+//! ├─────────────────────────┤
+//! │   Index size (8 bytes)  │  ← u64 LE
+//! ├─────────────────────────┤
+//! │   Magic (8 bytes)       │  ← "GRAFTIDX"
+//! └─────────────────────────┘
+//! ```
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+
+use crate::patch::PatchSource;
+use crate::utils::file_ops::is_executable;
+
+/// Magic marker at the end of an indexed container.
+pub const INDEX_MAGIC: &[u8; 8] = b"GRAFTIDX";
+
+/// Size of the container trailer (index size + magic).
+const TRAILER_LEN: u64 = 16;
+
+/// Largest decompressed-to-stored ratio trusted when preallocating a payload;
+/// the size recorded in the index is only checked once decompression is done.
+const MAX_PREALLOC_RATIO: u64 = 32;
+
+/// Compression applied to a single entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EntryCompression {
+    /// Stored as-is (used when compression doesn't help)
+    None,
+    Deflate,
+    Zstd,
+}
+
+/// Location and encoding of one entry in the container.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexEntry {
+    /// Payload path (e.g. "diffs/game.bin.diff")
+    pub path: String,
+    /// Offset of the stored data from the start of the container
+    pub offset: u64,
+    /// Size of the stored (possibly compressed) data
+    pub stored_size: u64,
+    /// Size of the payload after decompression
+    pub size: u64,
+    pub compression: EntryCompression,
+    /// Whether the payload was packed from an executable file
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub executable: bool,
+}
+
+/// Index of all entries in the container.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContainerIndex {
+    pub entries: Vec<IndexEntry>,
+}
+
+/// Check whether `data` ends with an indexed container trailer.
+pub fn is_indexed_archive(data: &[u8]) -> bool {
+    data.len() as u64 >= TRAILER_LEN && data.ends_with(INDEX_MAGIC)
+}
+
+/// Compress a payload, picking whichever of zstd, deflate or no compression
+/// produces the smallest result.
+fn compress_entry(data: &[u8]) -> io::Result<(EntryCompression, Vec<u8>)> {
+    let zstd_data = zstd::encode_all(data, zstd::DEFAULT_COMPRESSION_LEVEL)?;
+
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data)?;
+    let deflate_data = encoder.finish()?;
+
+    let mut best = (EntryCompression::None, data.to_vec());
+    if deflate_data.len() < best.1.len() {
+        best = (EntryCompression::Deflate, deflate_data);
+    }
+    if zstd_data.len() < best.1.len() {
+        best = (EntryCompression::Zstd, zstd_data);
+    }
+    Ok(best)
+}
+
+/// Decompress a stored payload.
+///
+/// Never produces more than one byte past the size in the index, so a
+/// corrupt index can't make it allocate without bound.
+fn decompress_entry(entry: &IndexEntry, stored: &[u8]) -> io::Result<Vec<u8>> {
+    let capacity = entry.size.min(entry.stored_size.saturating_mul(MAX_PREALLOC_RATIO));
+    let mut data = Vec::with_capacity(capacity as usize);
+    let limit = entry.size.saturating_add(1);
+    match entry.compression {
+        EntryCompression::None => data.extend_from_slice(stored),
+        EntryCompression::Deflate => {
+            DeflateDecoder::new(stored).take(limit).read_to_end(&mut data)?;
+        }
+        EntryCompression::Zstd => {
+            zstd::Decoder::new(stored)?.take(limit).read_to_end(&mut data)?;
+        }
+    }
+
+    if data.len() as u64 != entry.size {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("size mismatch for '{}' in patch container", entry.path),
+        ));
+    }
+    Ok(data)
+}
+
+/// Create an indexed container from a list of `(payload path, file path)` pairs.
+pub fn create_indexed_archive_bytes(files: &[(String, PathBuf)]) -> io::Result<Vec<u8>> {
+    let mut buffer = Vec::new();
+    let mut index = ContainerIndex::default();
+
+    for (path, file_path) in files {
+        let data = fs::read(file_path)?;
+        let (compression, stored) = compress_entry(&data)?;
+        let executable = is_executable(&fs::metadata(file_path)?);
+
+        index.entries.push(IndexEntry {
+            path: path.clone(),
+            offset: buffer.len() as u64,
+            stored_size: stored.len() as u64,
+            size: data.len() as u64,
+            compression,
+            executable,
+        });
+        buffer.extend_from_slice(&stored);
+    }
+
+    let index_data = serde_json::to_vec(&index)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    buffer.extend_from_slice(&index_data);
+    buffer.extend_from_slice(&(index_data.len() as u64).to_le_bytes());
+    buffer.extend_from_slice(INDEX_MAGIC);
+
+    Ok(buffer)
+}
+
+/// Parse the index from the trailing `index + size + magic` bytes.
+///
+/// `tail` must end at the end of the container; `container_len` is the total
+/// container size, used to bounds-check entry offsets.
+fn parse_index(tail: &[u8], container_len: u64) -> io::Result<HashMap<String, IndexEntry>> {
+    let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+
+    if !is_indexed_archive(tail) {
+        return Err(invalid("not an indexed patch container"));
+    }
+
+    let size_start = tail.len() - TRAILER_LEN as usize;
+    let mut size_bytes = [0u8; 8];
+    size_bytes.copy_from_slice(&tail[size_start..size_start + 8]);
+    let index_len = u64::from_le_bytes(size_bytes);
+    if index_len > size_start as u64 {
+        return Err(invalid("invalid index size in patch container"));
+    }
+
+    let index_data = &tail[size_start - index_len as usize..size_start];
+    let index: ContainerIndex = serde_json::from_slice(index_data)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+    let data_len = container_len - TRAILER_LEN - index_len;
+    let mut entries = HashMap::new();
+    for entry in index.entries {
+        if entry
+            .offset
+            .checked_add(entry.stored_size)
+            .is_none_or(|end| end > data_len)
+        {
+            return Err(invalid("index entry extends past patch data"));
+        }
+        entries.insert(entry.path.clone(), entry);
+    }
+    Ok(entries)
+}
+
+/// Where the container bytes live.
+#[derive(Debug, Clone)]
+enum Backing {
+    Memory(Arc<[u8]>),
+    File { path: PathBuf, offset: u64 },
+}
+
+/// Reads payloads from an indexed container, decompressing one entry at a time.
+#[derive(Debug, Clone)]
+pub struct IndexedArchiveSource {
+    backing: Backing,
+    entries: HashMap<String, IndexEntry>,
+}
+
+impl IndexedArchiveSource {
+    /// Open a container held in memory.
+    pub fn from_bytes(data: impl Into<Arc<[u8]>>) -> io::Result<Self> {
+        let data = data.into();
+        let entries = parse_index(&data, data.len() as u64)?;
+        Ok(IndexedArchiveSource {
+            backing: Backing::Memory(data),
+            entries,
+        })
+    }
+
+    /// Open a container stored at `offset..offset + len` in `path`.
+    ///
+    /// Only the trailer and index are read; payloads are read on demand.
+    pub fn from_file_region(path: &Path, offset: u64, len: u64) -> io::Result<Self> {
+        let mut file = File::open(path)?;
+        let file_len = file.metadata()?.len();
+        if len < TRAILER_LEN || offset.checked_add(len).is_none_or(|end| end > file_len) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "invalid patch container region",
+            ));
+        }
+
+        // Read the fixed trailer to learn the index size
+        let end = offset + len;
+        file.seek(SeekFrom::Start(end - TRAILER_LEN))?;
+        let mut trailer = [0u8; TRAILER_LEN as usize];
+        file.read_exact(&mut trailer)?;
+        if !is_indexed_archive(&trailer) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not an indexed patch container",
+            ));
+        }
+        let mut size_bytes = [0u8; 8];
+        size_bytes.copy_from_slice(&trailer[..8]);
+        let index_len = u64::from_le_bytes(size_bytes);
+        if index_len > len - TRAILER_LEN {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "invalid index size in patch container",
+            ));
+        }
+
+        // Read index + trailer and parse
+        let tail_len = index_len + TRAILER_LEN;
+        file.seek(SeekFrom::Start(end - tail_len))?;
+        let mut tail = vec![0u8; tail_len as usize];
+        file.read_exact(&mut tail)?;
+        let entries = parse_index(&tail, len)?;
+
+        Ok(IndexedArchiveSource {
+            backing: Backing::File {
+                path: path.to_path_buf(),
+                offset,
+            },
+            entries,
+        })
+    }
+
+    /// All entries in the container, sorted by path.
+    pub fn entries(&self) -> Vec<&IndexEntry> {
+        let mut entries: Vec<_> = self.entries.values().collect();
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+        entries
+    }
+}
+
+impl PatchSource for IndexedArchiveSource {
+    fn read(&self, path: &str) -> io::Result<Vec<u8>> {
+        let entry = self.entries.get(path).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("'{}' not found in patch container", path),
+            )
+        })?;
+
+        match &self.backing {
+            Backing::Memory(data) => {
+                let start = entry.offset as usize;
+                let end = start + entry.stored_size as usize;
+                decompress_entry(entry, &data[start..end])
+            }
+            Backing::File { path, offset } => {
+                let mut file = File::open(path)?;
+                file.seek(SeekFrom::Start(offset + entry.offset))?;
+                let mut stored = vec![0u8; entry.stored_size as usize];
+                file.read_exact(&mut stored)?;
+                decompress_entry(entry, &stored)
+            }
+        }
+    }
+
+    fn contains(&self, path: &str) -> bool {
+        self.entries.contains_key(path)
+    }
+
+    fn size(&self, path: &str) -> io::Result<u64> {
+        self.entries.get(path).map(|entry| entry.size).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("'{}' not found in patch container", path),
+            )
+        })
+    }
+
+    fn is_executable(&self, path: &str) -> bool {
+        self.entries.get(path).is_some_and(|entry| entry.executable)
+    }
+
+    fn payload_paths(&self) -> io::Result<Vec<String>> {
+        Ok(self.entries().into_iter().map(|e| e.path.clone()).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn create_files(dir: &Path) -> Vec<(String, PathBuf)> {
+        fs::write(dir.join("manifest.json"), br#"{"version": 1, "name": "T", "entries": []}"#)
+            .unwrap();
+        fs::write(dir.join("big.diff"), vec![b'a'; 4096]).unwrap();
+        fs::write(dir.join("tiny.bin"), b"x").unwrap();
+        vec![
+            ("manifest.json".to_string(), dir.join("manifest.json")),
+            ("diffs/big.bin.diff".to_string(), dir.join("big.diff")),
+            ("files/tiny.bin".to_string(), dir.join("tiny.bin")),
+        ]
+    }
+
+    #[test]
+    fn roundtrip_from_bytes() {
+        let dir = tempdir().unwrap();
+        let files = create_files(dir.path());
+
+        let data = create_indexed_archive_bytes(&files).unwrap();
+        assert!(is_indexed_archive(&data));
+
+        let source = IndexedArchiveSource::from_bytes(data).unwrap();
+        assert_eq!(source.read_diff("big.bin").unwrap(), vec![b'a'; 4096]);
+        assert_eq!(source.read_file("tiny.bin").unwrap(), b"x");
+        assert_eq!(source.read_manifest().unwrap().name, "T");
+        assert_eq!(source.size("diffs/big.bin.diff").unwrap(), 4096);
+        assert!(source.size("diffs/missing.bin.diff").is_err());
+    }
+
+    #[test]
+    fn compressible_entries_are_compressed() {
+        let dir = tempdir().unwrap();
+        let files = create_files(dir.path());
+
+        let data = create_indexed_archive_bytes(&files).unwrap();
+        let source = IndexedArchiveSource::from_bytes(data).unwrap();
+
+        let entries = source.entries();
+        let big = entries.iter().find(|e| e.path == "diffs/big.bin.diff").unwrap();
+        assert_ne!(big.compression, EntryCompression::None);
+        assert!(big.stored_size < big.size);
+
+        let tiny = entries.iter().find(|e| e.path == "files/tiny.bin").unwrap();
+        assert_eq!(tiny.compression, EntryCompression::None);
+    }
+
+    #[test]
+    fn reads_from_file_region() {
+        let dir = tempdir().unwrap();
+        let files = create_files(dir.path());
+        let data = create_indexed_archive_bytes(&files).unwrap();
+
+        let exe_path = dir.path().join("patcher");
+        let mut exe = b"stub executable bytes".to_vec();
+        let offset = exe.len() as u64;
+        exe.extend_from_slice(&data);
+        exe.extend_from_slice(b"outer trailer");
+        fs::write(&exe_path, &exe).unwrap();
+
+        let source =
+            IndexedArchiveSource::from_file_region(&exe_path, offset, data.len() as u64).unwrap();
+        assert_eq!(source.read_diff("big.bin").unwrap(), vec![b'a'; 4096]);
+        assert_eq!(source.read_file("tiny.bin").unwrap(), b"x");
+    }
+
+    #[test]
+    fn rejects_data_without_magic() {
+        let result = IndexedArchiveSource::from_bytes(&b"not a container at all"[..]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn rejects_corrupt_index_offsets() {
+        let index = ContainerIndex {
+            entries: vec![IndexEntry {
+                path: "manifest.json".to_string(),
+                offset: 0,
+                stored_size: 1000,
+                size: 1000,
+                compression: EntryCompression::None,
+                executable: false,
+            }],
+        };
+        let index_data = serde_json::to_vec(&index).unwrap();
+        let mut data = index_data.clone();
+        data.extend_from_slice(&(index_data.len() as u64).to_le_bytes());
+        data.extend_from_slice(INDEX_MAGIC);
+
+        let result = IndexedArchiveSource::from_bytes(data);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn rejects_wrong_payload_size() {
+        let (compression, stored) = compress_entry(&[b'a'; 4096]).unwrap();
+        for size in [u64::MAX, 100] {
+            let entry = IndexEntry {
+                path: "diffs/big.bin.diff".to_string(),
+                offset: 0,
+                stored_size: stored.len() as u64,
+                size,
+                compression,
+                executable: false,
+            };
+            let result = decompress_entry(&entry, &stored);
+            assert!(matches!(result, Err(e) if e.kind() == io::ErrorKind::InvalidData));
+        }
+    }
+}
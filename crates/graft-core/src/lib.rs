@@ -1,4 +1,9 @@
 pub mod archive;
+pub mod discovery;
+pub mod indexed_archive;
 pub mod patch;
 pub mod path_restrictions;
 pub mod utils;
+
+#[cfg(any(test, feature = "test-support"))]
+pub mod test_support;
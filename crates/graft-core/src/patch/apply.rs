@@ -1,28 +1,62 @@
-use std::fs;
+use std::fs::{self, File};
+use std::io;
 use std::path::Path;
 
 use crate::patch::backup::rollback;
+use crate::patch::cancel::{self, CancelToken};
+use crate::patch::edit::edited;
+use crate::patch::progress::Reporter;
+use crate::patch::source::{alternative_diff_payload_path, diff_payload_path, file_payload_path, PatchSource};
 use crate::patch::verify::verify_entry;
-use crate::patch::{PatchError, Progress, ProgressAction, DIFFS_DIR, DIFF_EXTENSION, FILES_DIR};
-use crate::utils::diff::apply_diff;
+use crate::patch::{PatchError, Progress, ProgressAction};
+use crate::utils::diff::apply_diff_reader;
+use crate::utils::file_ops::{set_executable, ProgressReader};
+use crate::utils::hash::{hash_bytes, hash_reader};
 use crate::utils::manifest::ManifestEntry;
 
 /// Apply a single manifest entry to the target directory.
 ///
-/// - Patch: reads original file, applies diff, writes result
-/// - Add: copies file from patch files/ directory
+/// Payloads (diffs and new files) are read from `source`, which can be an
+/// extracted patch directory or any other [`PatchSource`].
+///
+/// - Patch: reads original file, applies the diff from whichever accepted
+///   original it matches, writes result. If the file matches none and the
+///   entry has a fallback, writes the full new file
+/// - Add: writes file from patch files/ payload
 /// - Delete: removes file from target directory
-pub fn apply_entry(
+/// - Require: nothing to do, the file was checked during validation
+/// - EditJson/EditIni: sets the value in the target file in place
+pub fn apply_entry<S>(
+    entry: &ManifestEntry,
+    target_dir: &Path,
+    source: &S,
+) -> Result<(), PatchError>
+where
+    S: PatchSource + ?Sized,
+{
+    apply_entry_with_progress(entry, target_dir, source, None, |_| {}).map(|_| ())
+}
+
+/// Apply a single manifest entry, reporting the bytes of its payload used so far.
+///
+/// The payload is the diff of a Patch entry or the new file of an Add entry;
+/// see [`entry_bytes`]. Returns true if a Patch entry's fallback was used.
+///
+/// Returns [`PatchError::Cancelled`] if `cancel` is set while the payload is
+/// read. A new file written only partly is removed.
+fn apply_entry_with_progress<S>(
     entry: &ManifestEntry,
     target_dir: &Path,
-    patch_dir: &Path,
-) -> Result<(), PatchError> {
+    source: &S,
+    cancel: Option<&CancelToken>,
+    on_progress: impl FnMut(u64),
+) -> Result<bool, PatchError>
+where
+    S: PatchSource + ?Sized,
+{
     match entry {
-        ManifestEntry::Patch { file, .. } => {
+        ManifestEntry::Patch { file, fallback, .. } => {
             let target_path = target_dir.join(file);
-            let diff_path = patch_dir
-                .join(DIFFS_DIR)
-                .join(format!("{}{}", file, DIFF_EXTENSION));
 
             // Validate files exist before attempting operations
             if !target_path.exists() {
@@ -31,7 +65,7 @@ pub fn apply_entry(
                     reason: "target file not found".to_string(),
                 });
             }
-            if !diff_path.exists() {
+            if !source.has_diff(file) {
                 return Err(PatchError::ValidationFailed {
                     file: file.clone(),
                     reason: "diff file not found in patch".to_string(),
@@ -43,16 +77,35 @@ pub fn apply_entry(
                 reason: format!("failed to read original file: {}", e),
             })?;
 
-            let diff_data = fs::read(&diff_path).map_err(|e| PatchError::ApplyFailed {
+            // Pick the diff from the original the file matches. With no match,
+            // the main diff is applied and verification reports the result.
+            let actual_hash = hash_bytes(&original_data);
+            let origin = entry.patch_origins().iter().position(|(h, _)| *h == actual_hash);
+            if *fallback && origin.is_none() {
+                write_new_file(file, &target_path, source, cancel, on_progress)?;
+                return Ok(true);
+            }
+
+            let diff_data = match origin {
+                Some(index) if index > 0 => source.read_alternative_diff(file, &actual_hash),
+                _ => source.read_diff(file),
+            }
+            .map_err(|e| PatchError::ApplyFailed {
                 file: file.clone(),
                 reason: format!("failed to read diff file: {}", e),
             })?;
 
-            let patched_data =
-                apply_diff(&original_data, &diff_data).map_err(|e| PatchError::ApplyFailed {
-                    file: file.clone(),
-                    reason: format!("failed to apply diff: {}", e),
-                })?;
+            let diff = ProgressReader::new(&diff_data[..], on_progress).with_cancel(cancel);
+            let patched_data = match apply_diff_reader(&original_data, diff) {
+                Ok(data) => data,
+                Err(e) => {
+                    cancel::check(cancel)?;
+                    return Err(PatchError::ApplyFailed {
+                        file: file.clone(),
+                        reason: format!("failed to apply diff: {}", e),
+                    });
+                }
+            };
 
             fs::write(&target_path, patched_data).map_err(|e| PatchError::ApplyFailed {
                 file: file.clone(),
@@ -60,21 +113,17 @@ pub fn apply_entry(
             })?;
         }
         ManifestEntry::Add { file, .. } => {
-            let source_path = patch_dir.join(FILES_DIR).join(file);
             let target_path = target_dir.join(file);
 
             // Validate source file exists
-            if !source_path.exists() {
+            if !source.has_file(file) {
                 return Err(PatchError::ValidationFailed {
                     file: file.clone(),
                     reason: "source file not found in patch".to_string(),
                 });
             }
 
-            fs::copy(&source_path, &target_path).map_err(|e| PatchError::ApplyFailed {
-                file: file.clone(),
-                reason: format!("failed to copy new file: {}", e),
-            })?;
+            write_new_file(file, &target_path, source, cancel, on_progress)?;
         }
         ManifestEntry::Delete { file, .. } => {
             let target_path = target_dir.join(file);
@@ -87,11 +136,94 @@ pub fn apply_entry(
                 })?;
             }
         }
+        ManifestEntry::Require { .. } => {}
+        ManifestEntry::EditJson { file, .. } | ManifestEntry::EditIni { file, .. } => {
+            let target_path = target_dir.join(file);
+
+            let data = fs::read(&target_path).map_err(|e| PatchError::ApplyFailed {
+                file: file.clone(),
+                reason: format!("failed to read file: {}", e),
+            })?;
+            let data = edited(entry, &data).map_err(|e| PatchError::ApplyFailed {
+                file: file.clone(),
+                reason: e.to_string(),
+            })?;
+            fs::write(&target_path, data).map_err(|e| PatchError::ApplyFailed {
+                file: file.clone(),
+                reason: format!("failed to write edited file: {}", e),
+            })?;
+        }
     }
 
+    Ok(false)
+}
+
+/// Write the new file of an Add entry, or a Patch entry's fallback, to the target.
+///
+/// The file is removed again if writing it fails or is cancelled.
+fn write_new_file<S>(
+    file: &str,
+    target_path: &Path,
+    source: &S,
+    cancel: Option<&CancelToken>,
+    on_progress: impl FnMut(u64),
+) -> Result<(), PatchError>
+where
+    S: PatchSource + ?Sized,
+{
+    let data = source.read_file(file).map_err(|e| PatchError::ApplyFailed {
+        file: file.to_string(),
+        reason: format!("failed to read new file: {}", e),
+    })?;
+
+    let mut reader = ProgressReader::new(&data[..], on_progress).with_cancel(cancel);
+    if let Err(e) = File::create(target_path).and_then(|mut output| io::copy(&mut reader, &mut output)) {
+        let _ = fs::remove_file(target_path);
+        cancel::check(cancel)?;
+        return Err(PatchError::ApplyFailed {
+            file: file.to_string(),
+            reason: format!("failed to write new file: {}", e),
+        });
+    }
+
+    if source.is_executable(&file_payload_path(file)) {
+        set_executable(target_path).map_err(|e| PatchError::ApplyFailed {
+            file: file.to_string(),
+            reason: format!("failed to make new file executable: {}", e),
+        })?;
+    }
     Ok(())
 }
 
+/// Bytes of payload an entry applies: its diff or new file.
+///
+/// For a Patch entry this is the payload picked for the original the target
+/// file matches, like [`apply_entry`] does: an alternative diff, or the new
+/// file if it matches none and the entry has a fallback. The target is only
+/// hashed when the entry has more than one payload to pick from.
+fn entry_bytes<S>(entry: &ManifestEntry, target_dir: &Path, source: &S) -> u64
+where
+    S: PatchSource + ?Sized,
+{
+    let payload = match entry {
+        ManifestEntry::Patch { file, alternatives, fallback, .. } if !alternatives.is_empty() || *fallback => {
+            let actual_hash = File::open(target_dir.join(file)).and_then(hash_reader).unwrap_or_default();
+            match entry.patch_origins().iter().position(|(h, _)| *h == actual_hash) {
+                Some(index) if index > 0 => alternative_diff_payload_path(file, &actual_hash),
+                None if *fallback => file_payload_path(file),
+                _ => diff_payload_path(file),
+            }
+        }
+        ManifestEntry::Patch { file, .. } => diff_payload_path(file),
+        ManifestEntry::Add { file, .. } => file_payload_path(file),
+        ManifestEntry::Delete { .. }
+        | ManifestEntry::Require { .. }
+        | ManifestEntry::EditJson { .. }
+        | ManifestEntry::EditIni { .. } => return 0,
+    };
+    source.size(&payload).unwrap_or(0)
+}
+
 /// Apply all entries with progress callback and automatic rollback on failure.
 ///
 /// This is a batch operation that:
@@ -100,39 +232,58 @@ pub fn apply_entry(
 /// 3. Applies the entry and verifies the result
 /// 4. On any failure, rolls back all previously applied entries
 ///
+/// Once `cancel` is set, the entries applied so far are rolled back the same
+/// way and [`PatchError::Cancelled`] is returned.
+///
+/// Returns the files that didn't match their original and were replaced by
+/// their Patch entry's fallback.
+///
 /// Note: This assumes backup_entries has already been called to create backups.
-pub fn apply_entries<F>(
+pub fn apply_entries<S, F>(
     entries: &[ManifestEntry],
     target_dir: &Path,
-    patch_dir: &Path,
+    source: &S,
     backup_dir: &Path,
-    mut on_progress: Option<F>,
-) -> Result<(), PatchError>
+    cancel: Option<&CancelToken>,
+    on_progress: Option<F>,
+) -> Result<Vec<String>, PatchError>
 where
+    S: PatchSource + ?Sized,
     F: FnMut(Progress),
 {
-    let total = entries.len();
+    let sizes: Vec<u64> = entries.iter().map(|entry| entry_bytes(entry, target_dir, source)).collect();
+    let overall_bytes = sizes.iter().sum();
+    let mut reporter = Reporter::new(on_progress, entries.len(), overall_bytes);
     let mut applied = Vec::new();
+    let mut fallbacks = Vec::new();
 
     for (index, entry) in entries.iter().enumerate() {
+        if let Err(e) = cancel::check(cancel) {
+            rollback(&applied, target_dir, backup_dir, None::<fn(Progress)>)?;
+            return Err(e);
+        }
+
         let action = match entry {
             ManifestEntry::Patch { .. } => ProgressAction::Patching,
             ManifestEntry::Add { .. } => ProgressAction::Adding,
             ManifestEntry::Delete { .. } => ProgressAction::Deleting,
+            ManifestEntry::Require { .. } => ProgressAction::Skipping,
+            ManifestEntry::EditJson { .. } | ManifestEntry::EditIni { .. } => ProgressAction::Editing,
         };
 
-        if let Some(ref mut callback) = on_progress {
-            callback(Progress {
-                file: entry.file(),
-                index,
-                total,
-                action,
-            });
-        }
-
-        if let Err(e) = apply_entry(entry, target_dir, patch_dir) {
-            rollback(&applied, target_dir, backup_dir, None::<fn(Progress)>)?;
-            return Err(e);
+        reporter.start(entry.file(), index, action, sizes[index]);
+
+        match apply_entry_with_progress(entry, target_dir, source, cancel, |done| reporter.advance(done)) {
+            Ok(true) => fallbacks.push(entry.file().to_string()),
+            Ok(false) => {}
+            Err(e) => {
+                // A fallback cancelled midway has already replaced the original
+                if e == PatchError::Cancelled && matches!(entry, ManifestEntry::Patch { .. }) {
+                    applied.push(entry);
+                }
+                rollback(&applied, target_dir, backup_dir, None::<fn(Progress)>)?;
+                return Err(e);
+            }
         }
 
         if let Err(e) = verify_entry(entry, target_dir) {
@@ -143,12 +294,13 @@ where
         applied.push(entry);
     }
 
-    Ok(())
+    Ok(fallbacks)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::patch::{DIFFS_DIR, DIFF_EXTENSION, FILES_DIR};
     use crate::utils::diff::create_diff;
     use crate::utils::hash::hash_bytes;
     use tempfile::tempdir;
@@ -181,6 +333,8 @@ mod tests {
             original_hash: hash_bytes(original_content),
             diff_hash: hash_bytes(&diff_data),
             final_hash: hash_bytes(new_content),
+            alternatives: Vec::new(),
+            fallback: false,
         };
 
         apply_entry(&entry, target_dir.path(), patch_dir.path()).unwrap();
@@ -189,6 +343,42 @@ mod tests {
         assert_eq!(result, new_content);
     }
 
+    #[test]
+    fn apply_edit_entries() {
+        let target_dir = tempdir().unwrap();
+        let patch_dir = tempdir().unwrap();
+        fs::write(target_dir.path().join("settings.json"), b"{\"video\": {\"vsync\": false}}").unwrap();
+        fs::write(target_dir.path().join("game.ini"), b"[Video]\nVSync=0\n").unwrap();
+
+        let entries = [
+            ManifestEntry::EditJson {
+                file: "settings.json".to_string(),
+                pointer: "/video/vsync".to_string(),
+                value: serde_json::json!(true),
+            },
+            ManifestEntry::EditIni {
+                file: "game.ini".to_string(),
+                section: "Video".to_string(),
+                key: "VSync".to_string(),
+                value: "1".to_string(),
+            },
+        ];
+        for entry in &entries {
+            assert!(matches!(
+                crate::patch::verify_entry(entry, target_dir.path()),
+                Err(PatchError::VerificationFailed { .. })
+            ));
+            apply_entry(entry, target_dir.path(), patch_dir.path()).unwrap();
+            crate::patch::verify_entry(entry, target_dir.path()).unwrap();
+        }
+
+        assert_eq!(
+            fs::read(target_dir.path().join("settings.json")).unwrap(),
+            b"{\"video\":{\"vsync\":true}}"
+        );
+        assert_eq!(fs::read(target_dir.path().join("game.ini")).unwrap(), b"[Video]\nVSync=1\n");
+    }
+
     #[test]
     fn apply_add_entry() {
         let target_dir = tempdir().unwrap();
@@ -267,6 +457,8 @@ mod tests {
             original_hash: "x".to_string(),
             diff_hash: "y".to_string(),
             final_hash: "z".to_string(),
+            alternatives: Vec::new(),
+            fallback: false,
         };
 
         let result = apply_entry(&entry, target_dir.path(), patch_dir.path());
@@ -286,6 +478,8 @@ mod tests {
             original_hash: "x".to_string(),
             diff_hash: "y".to_string(),
             final_hash: "z".to_string(),
+            alternatives: Vec::new(),
+            fallback: false,
         };
 
         let result = apply_entry(&entry, target_dir.path(), patch_dir.path());
@@ -305,4 +499,338 @@ mod tests {
         let result = apply_entry(&entry, target_dir.path(), patch_dir.path());
         assert!(matches!(result, Err(PatchError::ValidationFailed { .. })));
     }
+
+    #[test]
+    fn apply_entries_from_archive_source() {
+        let target_dir = tempdir().unwrap();
+        let patch_dir = tempdir().unwrap();
+        let backup_dir = tempdir().unwrap();
+
+        let original_content = b"original content";
+        let new_content = b"modified content";
+        let added_content = b"added content";
+
+        fs::write(target_dir.path().join("file.bin"), original_content).unwrap();
+
+        let diff_data = create_diff(original_content, new_content).unwrap();
+        fs::create_dir_all(patch_dir.path().join(DIFFS_DIR)).unwrap();
+        fs::write(
+            patch_dir
+                .path()
+                .join(DIFFS_DIR)
+                .join(format!("file.bin{}", DIFF_EXTENSION)),
+            &diff_data,
+        )
+        .unwrap();
+        fs::create_dir_all(patch_dir.path().join(FILES_DIR)).unwrap();
+        fs::write(patch_dir.path().join(FILES_DIR).join("new.bin"), added_content).unwrap();
+        fs::write(
+            patch_dir.path().join("manifest.json"),
+            r#"{"version": 1, "name": "TestPatcher", "entries": []}"#,
+        )
+        .unwrap();
+
+        let data = crate::archive::create_archive_bytes(patch_dir.path()).unwrap();
+        let source = crate::patch::TarSource::new(data).unwrap();
+
+        let entries = vec![
+            ManifestEntry::Patch {
+                file: "file.bin".to_string(),
+                original_hash: hash_bytes(original_content),
+                diff_hash: hash_bytes(&diff_data),
+                final_hash: hash_bytes(new_content),
+                alternatives: Vec::new(),
+                fallback: false,
+            },
+            ManifestEntry::Add {
+                file: "new.bin".to_string(),
+                final_hash: hash_bytes(added_content),
+            },
+        ];
+
+        apply_entries(
+            &entries,
+            target_dir.path(),
+            &source,
+            backup_dir.path(),
+            None,
+            None::<fn(Progress)>,
+        )
+        .unwrap();
+
+        assert_eq!(fs::read(target_dir.path().join("file.bin")).unwrap(), new_content);
+        assert_eq!(fs::read(target_dir.path().join("new.bin")).unwrap(), added_content);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn added_files_keep_their_execute_permission() {
+        use crate::archive::{create_container_bytes, open_bytes, ArchiveOptions, ContainerFormat};
+        use std::os::unix::fs::PermissionsExt;
+
+        let patch_dir = tempdir().unwrap();
+        fs::write(
+            patch_dir.path().join("manifest.json"),
+            r#"{"version": 1, "name": "TestPatcher", "entries": []}"#,
+        )
+        .unwrap();
+        fs::create_dir_all(patch_dir.path().join(FILES_DIR)).unwrap();
+        let tool = patch_dir.path().join(FILES_DIR).join("tool.sh");
+        fs::write(&tool, b"#!/bin/sh").unwrap();
+        fs::set_permissions(&tool, fs::Permissions::from_mode(0o755)).unwrap();
+        fs::write(patch_dir.path().join(FILES_DIR).join("data.bin"), b"data").unwrap();
+
+        let entries = vec![
+            ManifestEntry::Add {
+                file: "tool.sh".to_string(),
+                final_hash: hash_bytes(b"#!/bin/sh"),
+            },
+            ManifestEntry::Add {
+                file: "data.bin".to_string(),
+                final_hash: hash_bytes(b"data"),
+            },
+        ];
+
+        for container in [ContainerFormat::Tar, ContainerFormat::Indexed] {
+            let options = ArchiveOptions {
+                container,
+                ..Default::default()
+            };
+            let data = create_container_bytes(patch_dir.path(), &options).unwrap();
+            let source = open_bytes(data.into()).unwrap();
+
+            let target_dir = tempdir().unwrap();
+            for entry in &entries {
+                apply_entry(entry, target_dir.path(), source.as_ref()).unwrap();
+            }
+
+            let mode = |file: &str| fs::metadata(target_dir.path().join(file)).unwrap().permissions().mode();
+            assert_ne!(mode("tool.sh") & 0o111, 0, "{:?}", container);
+            assert_eq!(mode("data.bin") & 0o111, 0, "{:?}", container);
+        }
+    }
+
+    #[test]
+    fn fallback_replaces_files_that_do_not_match() {
+        let patch_dir = tempdir().unwrap();
+        let backup_dir = tempdir().unwrap();
+        let diff_data = create_diff(b"original", b"patched").unwrap();
+        fs::create_dir_all(patch_dir.path().join(DIFFS_DIR)).unwrap();
+        fs::write(patch_dir.path().join(DIFFS_DIR).join(format!("file.bin{}", DIFF_EXTENSION)), &diff_data).unwrap();
+        fs::create_dir_all(patch_dir.path().join(FILES_DIR)).unwrap();
+        fs::write(patch_dir.path().join(FILES_DIR).join("file.bin"), b"patched").unwrap();
+        let entries = vec![ManifestEntry::Patch {
+            file: "file.bin".to_string(),
+            original_hash: hash_bytes(b"original"),
+            diff_hash: hash_bytes(&diff_data),
+            final_hash: hash_bytes(b"patched"),
+            alternatives: Vec::new(),
+            fallback: true,
+        }];
+
+        let cases = [(&b"original"[..], 0, diff_data.len()), (&b"modded by someone else"[..], 1, b"patched".len())];
+        for (content, expected_fallbacks, payload_size) in cases {
+            let target_dir = tempdir().unwrap();
+            fs::write(target_dir.path().join("file.bin"), content).unwrap();
+
+            crate::patch::validate_entries(&entries, target_dir.path(), None, None::<fn(Progress)>).unwrap();
+            let mut total = 0;
+            let fallbacks = apply_entries(
+                &entries,
+                target_dir.path(),
+                patch_dir.path(),
+                backup_dir.path(),
+                None,
+                Some(|p: Progress| total = p.overall_bytes_total),
+            )
+            .unwrap();
+
+            assert_eq!(fallbacks.len(), expected_fallbacks);
+            assert_eq!(total, payload_size as u64);
+            assert_eq!(fs::read(target_dir.path().join("file.bin")).unwrap(), b"patched");
+        }
+    }
+
+    #[test]
+    fn patches_each_accepted_original() {
+        let patch_dir = tempdir().unwrap();
+        let backup_dir = tempdir().unwrap();
+        let diff_data = create_diff(b"original", b"patched").unwrap();
+        let alt_diff = create_diff(b"regional original", b"patched").unwrap();
+        let alt_hash = hash_bytes(b"regional original");
+        fs::create_dir_all(patch_dir.path().join(DIFFS_DIR)).unwrap();
+        fs::write(patch_dir.path().join(DIFFS_DIR).join(format!("file.bin{}", DIFF_EXTENSION)), &diff_data).unwrap();
+        fs::write(
+            patch_dir.path().join(DIFFS_DIR).join(format!("file.bin.{}{}", alt_hash, DIFF_EXTENSION)),
+            &alt_diff,
+        )
+        .unwrap();
+        let entries = vec![ManifestEntry::Patch {
+            file: "file.bin".to_string(),
+            original_hash: hash_bytes(b"original"),
+            diff_hash: hash_bytes(&diff_data),
+            final_hash: hash_bytes(b"patched"),
+            alternatives: vec![crate::utils::manifest::PatchOrigin {
+                original_hash: alt_hash,
+                diff_hash: hash_bytes(&alt_diff),
+            }],
+            fallback: false,
+        }];
+
+        for (content, diff) in [(&b"original"[..], &diff_data), (b"regional original", &alt_diff)] {
+            let target_dir = tempdir().unwrap();
+            fs::write(target_dir.path().join("file.bin"), content).unwrap();
+
+            crate::patch::validate_entries(&entries, target_dir.path(), None, None::<fn(Progress)>).unwrap();
+            let mut total = 0;
+            apply_entries(
+                &entries,
+                target_dir.path(),
+                patch_dir.path(),
+                backup_dir.path(),
+                None,
+                Some(|p: Progress| total = p.overall_bytes_total),
+            )
+            .unwrap();
+
+            assert_eq!(fs::read(target_dir.path().join("file.bin")).unwrap(), b"patched");
+            assert_eq!(total, diff.len() as u64);
+        }
+
+        let target_dir = tempdir().unwrap();
+        fs::write(target_dir.path().join("file.bin"), b"unknown").unwrap();
+        assert!(crate::patch::validate_entries(&entries, target_dir.path(), None, None::<fn(Progress)>).is_err());
+    }
+
+    #[test]
+    fn reports_bytes_of_each_entry() {
+        let target_dir = tempdir().unwrap();
+        let patch_dir = tempdir().unwrap();
+        let backup_dir = tempdir().unwrap();
+
+        let added = vec![1u8; 3 << 20];
+        fs::create_dir_all(patch_dir.path().join(FILES_DIR)).unwrap();
+        fs::write(patch_dir.path().join(FILES_DIR).join("big.bin"), &added).unwrap();
+        fs::write(patch_dir.path().join(FILES_DIR).join("small.bin"), b"small").unwrap();
+
+        let entries = vec![
+            ManifestEntry::Add {
+                file: "big.bin".to_string(),
+                final_hash: hash_bytes(&added),
+            },
+            ManifestEntry::Add {
+                file: "small.bin".to_string(),
+                final_hash: hash_bytes(b"small"),
+            },
+        ];
+
+        let mut reports = Vec::new();
+        apply_entries(
+            &entries,
+            target_dir.path(),
+            patch_dir.path(),
+            backup_dir.path(),
+            None,
+            Some(|p: Progress| reports.push((p.index, p.bytes_done, p.overall_bytes_done, p.overall_bytes_total))),
+        )
+        .unwrap();
+
+        let total = added.len() as u64 + 5;
+        assert_eq!(reports.first(), Some(&(0, 0, 0, total)));
+        assert!(reports.iter().any(|&(index, done, _, _)| index == 0 && done > 0 && done < added.len() as u64));
+        assert!(reports.contains(&(1, 0, added.len() as u64, total)));
+        assert_eq!(reports.last(), Some(&(1, 5, total, total)));
+    }
+
+    #[test]
+    fn cancel_rolls_back_applied_entries() {
+        let target_dir = tempdir().unwrap();
+        let patch_dir = tempdir().unwrap();
+        let backup_dir = tempdir().unwrap();
+
+        fs::write(target_dir.path().join("file.bin"), b"original").unwrap();
+        let diff_data = create_diff(b"original", b"modified").unwrap();
+        fs::create_dir_all(patch_dir.path().join(DIFFS_DIR)).unwrap();
+        fs::write(patch_dir.path().join(DIFFS_DIR).join(format!("file.bin{}", DIFF_EXTENSION)), &diff_data).unwrap();
+        fs::create_dir_all(patch_dir.path().join(FILES_DIR)).unwrap();
+        fs::write(patch_dir.path().join(FILES_DIR).join("new.bin"), b"new").unwrap();
+
+        let entries = vec![
+            ManifestEntry::Patch {
+                file: "file.bin".to_string(),
+                original_hash: hash_bytes(b"original"),
+                diff_hash: hash_bytes(&diff_data),
+                final_hash: hash_bytes(b"modified"),
+                alternatives: Vec::new(),
+                fallback: false,
+            },
+            ManifestEntry::Add {
+                file: "new.bin".to_string(),
+                final_hash: hash_bytes(b"new"),
+            },
+        ];
+        crate::patch::backup_entries(
+            &entries,
+            target_dir.path(),
+            backup_dir.path(),
+            Default::default(),
+            None,
+            None::<fn(Progress)>,
+        )
+        .unwrap();
+
+        // Cancel while the first entry is being applied
+        let cancel = CancelToken::new();
+        let result = apply_entries(
+            &entries,
+            target_dir.path(),
+            patch_dir.path(),
+            backup_dir.path(),
+            Some(&cancel),
+            Some(|p: Progress| {
+                if p.index == 0 {
+                    cancel.cancel();
+                }
+            }),
+        );
+
+        assert_eq!(result, Err(PatchError::Cancelled));
+        assert_eq!(fs::read(target_dir.path().join("file.bin")).unwrap(), b"original");
+        assert!(!target_dir.path().join("new.bin").exists());
+    }
+
+    #[test]
+    fn cancel_stops_a_copy_midway_and_removes_the_partial_file() {
+        let target_dir = tempdir().unwrap();
+        let patch_dir = tempdir().unwrap();
+        let backup_dir = tempdir().unwrap();
+
+        let added = vec![7u8; 3 * crate::utils::file_ops::PROGRESS_INTERVAL as usize];
+        fs::create_dir_all(patch_dir.path().join(FILES_DIR)).unwrap();
+        fs::write(patch_dir.path().join(FILES_DIR).join("big.bin"), &added).unwrap();
+        let entries = vec![ManifestEntry::Add {
+            file: "big.bin".to_string(),
+            final_hash: hash_bytes(&added),
+        }];
+
+        let cancel = CancelToken::new();
+        let mut copied = 0;
+        let result = apply_entries(
+            &entries,
+            target_dir.path(),
+            patch_dir.path(),
+            backup_dir.path(),
+            Some(&cancel),
+            Some(|p: Progress| {
+                copied = p.bytes_done;
+                if p.bytes_done > 0 {
+                    cancel.cancel();
+                }
+            }),
+        );
+
+        assert_eq!(result, Err(PatchError::Cancelled));
+        assert!(copied < added.len() as u64);
+        assert!(!target_dir.path().join("big.bin").exists());
+    }
 }
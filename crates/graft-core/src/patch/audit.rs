@@ -0,0 +1,506 @@
+//! Deep consistency checks for a patch before release.
+//!
+//! Unlike [`validate_patch_dir`](crate::patch::validate_patch_dir), which only
+//! checks that referenced payloads exist, an audit hashes every payload,
+//! looks for duplicate entries and orphaned payloads, and can replay every
+//! diff against an original tree in memory.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use crate::patch::constants::{ASSETS_DIR, MANIFEST_FILENAME};
+use crate::patch::error::PatchError;
+use crate::patch::source::{alternative_diff_payload_path, diff_payload_path, file_payload_path, PatchSource};
+use crate::utils::diff::apply_diff;
+use crate::utils::hash::hash_bytes;
+use crate::utils::manifest::ManifestEntry;
+
+/// A problem found while auditing a patch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuditIssue {
+    /// More than one manifest entry targets the same file
+    DuplicateEntry(String),
+    /// Diff referenced by a Patch entry is missing
+    MissingDiff(String),
+    /// File referenced by an Add entry is missing
+    MissingFile(String),
+    /// Payload could not be read
+    UnreadablePayload { path: String, reason: String },
+    /// Diff bytes don't hash to `diff_hash`
+    DiffHashMismatch { file: String, expected: String, actual: String },
+    /// Added file doesn't hash to `final_hash`
+    FileHashMismatch { file: String, expected: String, actual: String },
+    /// Payload not referenced by any manifest entry
+    OrphanedPayload(String),
+    /// File to patch is missing from the original tree
+    OriginalMissing(String),
+    /// Original file doesn't hash to `original_hash`
+    OriginalHashMismatch { file: String, expected: String, actual: String },
+    /// Diff could not be applied to the original file
+    DiffApplyFailed { file: String, reason: String },
+    /// Diff output doesn't hash to `final_hash`
+    FinalHashMismatch { file: String, expected: String, actual: String },
+    /// Optional component is defined inconsistently
+    InvalidComponent { component: String, reason: String },
+}
+
+impl fmt::Display for AuditIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuditIssue::DuplicateEntry(file) => {
+                write!(f, "'{}' appears in more than one manifest entry", file)
+            }
+            AuditIssue::MissingDiff(file) => write!(f, "diff file not found for '{}'", file),
+            AuditIssue::MissingFile(file) => write!(f, "file not found: '{}'", file),
+            AuditIssue::UnreadablePayload { path, reason } => {
+                write!(f, "cannot read '{}': {}", path, reason)
+            }
+            AuditIssue::DiffHashMismatch { file, expected, actual } => write!(
+                f,
+                "diff hash mismatch for '{}': expected {}, got {}",
+                file, expected, actual
+            ),
+            AuditIssue::FileHashMismatch { file, expected, actual } => write!(
+                f,
+                "file hash mismatch for '{}': expected {}, got {}",
+                file, expected, actual
+            ),
+            AuditIssue::OrphanedPayload(path) => {
+                write!(f, "'{}' is not referenced by the manifest", path)
+            }
+            AuditIssue::OriginalMissing(file) => {
+                write!(f, "'{}' not found in original directory", file)
+            }
+            AuditIssue::OriginalHashMismatch { file, expected, actual } => write!(
+                f,
+                "original hash mismatch for '{}': expected {}, got {}",
+                file, expected, actual
+            ),
+            AuditIssue::DiffApplyFailed { file, reason } => {
+                write!(f, "diff for '{}' failed to apply: {}", file, reason)
+            }
+            AuditIssue::FinalHashMismatch { file, expected, actual } => write!(
+                f,
+                "patched '{}' hash mismatch: expected {}, got {}",
+                file, expected, actual
+            ),
+            AuditIssue::InvalidComponent { component, reason } => {
+                write!(f, "component '{}' {}", component, reason)
+            }
+        }
+    }
+}
+
+/// Result of auditing a patch.
+#[derive(Debug, Clone, Default)]
+pub struct AuditReport {
+    /// Number of manifest entries checked
+    pub entries_checked: usize,
+    /// Number of diffs replayed against the original tree
+    pub diffs_replayed: usize,
+    pub issues: Vec<AuditIssue>,
+}
+
+impl AuditReport {
+    /// True if no issues were found.
+    pub fn is_ok(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+/// Audit a patch.
+///
+/// Checks that:
+/// - No file appears in more than one entry, except for edits of different
+///   settings in the same file
+/// - Every diff hashes to its `diff_hash` and every added or fallback file to its `final_hash`
+/// - Every payload under diffs/ and files/ is referenced by the manifest
+/// - Component ids are unique, required components exist, and every file of
+///   a component has entries and belongs to no other component
+///
+/// If `original_dir` is given, every diff is also applied in memory to the
+/// matching original file, checking both `original_hash` and `final_hash`.
+///
+/// Only a missing or invalid manifest is returned as an error; everything
+/// else is collected in the report.
+pub fn audit_patch<S>(source: &S, original_dir: Option<&Path>) -> Result<AuditReport, PatchError>
+where
+    S: PatchSource + ?Sized,
+{
+    let manifest = source.read_manifest()?;
+    let mut report = AuditReport {
+        entries_checked: manifest.entries.len(),
+        ..Default::default()
+    };
+
+    let mut seen_files = HashSet::new();
+    let mut seen_settings = HashSet::new();
+    for entry in &manifest.entries {
+        let duplicate = if entry.is_edit() {
+            let mut name = entry.setting().unwrap_or_default();
+            if matches!(entry, ManifestEntry::EditIni { .. }) {
+                name.make_ascii_lowercase();
+            }
+            !seen_settings.insert((entry.file(), name))
+        } else {
+            !seen_files.insert(entry.file())
+        };
+        if duplicate {
+            report.issues.push(AuditIssue::DuplicateEntry(entry.file().to_string()));
+        }
+    }
+    // An edited file can't also be replaced, patched or deleted
+    let mut edited_files = HashSet::new();
+    for entry in manifest.entries.iter().filter(|entry| entry.is_edit()) {
+        if seen_files.contains(entry.file()) && edited_files.insert(entry.file()) {
+            report.issues.push(AuditIssue::DuplicateEntry(entry.file().to_string()));
+        }
+    }
+
+    let mut component_ids = HashSet::new();
+    let mut component_files = HashSet::new();
+    for component in &manifest.components {
+        let mut invalid = |reason: String| {
+            report.issues.push(AuditIssue::InvalidComponent {
+                component: component.id.clone(),
+                reason,
+            })
+        };
+        if !component_ids.insert(component.id.as_str()) {
+            invalid("is defined more than once".to_string());
+        }
+        for required in &component.requires {
+            if !manifest.components.iter().any(|c| &c.id == required) {
+                invalid(format!("requires unknown component '{}'", required));
+            }
+        }
+        for file in &component.files {
+            if !component_files.insert(file.as_str()) {
+                invalid(format!("lists '{}', which belongs to another component", file));
+            }
+            if !manifest.entries.iter().any(|entry| entry.file() == file) {
+                invalid(format!("lists '{}', which has no manifest entry", file));
+            }
+        }
+    }
+
+    let mut referenced = HashSet::new();
+    for entry in &manifest.entries {
+        match entry {
+            ManifestEntry::Patch {
+                file,
+                final_hash,
+                fallback,
+                ..
+            } => {
+                if *fallback {
+                    check_file_payload(source, file, final_hash, &mut referenced, &mut report);
+                }
+
+                // The diff from each accepted original
+                let mut diffs = Vec::new();
+                for (index, (original_hash, diff_hash)) in entry.patch_origins().into_iter().enumerate() {
+                    let path = match index {
+                        0 => diff_payload_path(file),
+                        _ => alternative_diff_payload_path(file, original_hash),
+                    };
+                    referenced.insert(path.clone());
+                    if !source.contains(&path) {
+                        report.issues.push(AuditIssue::MissingDiff(file.clone()));
+                        continue;
+                    }
+                    let Some(diff) = read_payload(source, &path, &mut report) else {
+                        continue;
+                    };
+
+                    let actual = hash_bytes(&diff);
+                    if actual != diff_hash {
+                        report.issues.push(AuditIssue::DiffHashMismatch {
+                            file: file.clone(),
+                            expected: diff_hash.to_string(),
+                            actual,
+                        });
+                    }
+                    diffs.push((original_hash, diff));
+                }
+
+                if let Some(original_dir) = original_dir
+                    && !diffs.is_empty()
+                {
+                    replay_diff(original_dir, file, &diffs, final_hash, &mut report);
+                }
+            }
+            ManifestEntry::Add { file, final_hash } => {
+                check_file_payload(source, file, final_hash, &mut referenced, &mut report);
+            }
+            ManifestEntry::Delete { .. }
+            | ManifestEntry::Require { .. }
+            | ManifestEntry::EditJson { .. }
+            | ManifestEntry::EditIni { .. } => {
+                // No payload
+            }
+        }
+    }
+
+    let assets_prefix = format!("{}/", ASSETS_DIR);
+    let payloads = source.payload_paths().map_err(|e| PatchError::ManifestError {
+        reason: format!("failed to list payloads: {}", e),
+    })?;
+    for path in payloads {
+        if path != MANIFEST_FILENAME && !path.starts_with(&assets_prefix) && !referenced.contains(&path) {
+            report.issues.push(AuditIssue::OrphanedPayload(path));
+        }
+    }
+
+    Ok(report)
+}
+
+/// Check the new file of an Add entry, or the fallback of a Patch entry.
+fn check_file_payload<S>(
+    source: &S,
+    file: &str,
+    final_hash: &str,
+    referenced: &mut HashSet<String>,
+    report: &mut AuditReport,
+) where
+    S: PatchSource + ?Sized,
+{
+    let path = file_payload_path(file);
+    referenced.insert(path.clone());
+    if !source.contains(&path) {
+        report.issues.push(AuditIssue::MissingFile(file.to_string()));
+        return;
+    }
+    let Some(data) = read_payload(source, &path, report) else {
+        return;
+    };
+
+    let actual = hash_bytes(&data);
+    if actual != final_hash {
+        report.issues.push(AuditIssue::FileHashMismatch {
+            file: file.to_string(),
+            expected: final_hash.to_string(),
+            actual,
+        });
+    }
+}
+
+/// Read a payload, recording a failure in the report.
+fn read_payload<S>(source: &S, path: &str, report: &mut AuditReport) -> Option<Vec<u8>>
+where
+    S: PatchSource + ?Sized,
+{
+    match source.read(path) {
+        Ok(data) => Some(data),
+        Err(e) => {
+            report.issues.push(AuditIssue::UnreadablePayload {
+                path: path.to_string(),
+                reason: e.to_string(),
+            });
+            None
+        }
+    }
+}
+
+/// Apply the diff from the original file in memory and check both hashes.
+fn replay_diff(
+    original_dir: &Path,
+    file: &str,
+    diffs: &[(&str, Vec<u8>)],
+    final_hash: &str,
+    report: &mut AuditReport,
+) {
+    let Ok(original) = fs::read(original_dir.join(file)) else {
+        report.issues.push(AuditIssue::OriginalMissing(file.to_string()));
+        return;
+    };
+
+    let actual = hash_bytes(&original);
+    let Some((_, diff)) = diffs.iter().find(|(original_hash, _)| *original_hash == actual) else {
+        let expected: Vec<_> = diffs.iter().map(|(original_hash, _)| *original_hash).collect();
+        report.issues.push(AuditIssue::OriginalHashMismatch {
+            file: file.to_string(),
+            expected: expected.join(" or "),
+            actual,
+        });
+        return;
+    };
+
+    report.diffs_replayed += 1;
+    match apply_diff(&original, diff) {
+        Ok(patched) => {
+            let actual = hash_bytes(&patched);
+            if actual != final_hash {
+                report.issues.push(AuditIssue::FinalHashMismatch {
+                    file: file.to_string(),
+                    expected: final_hash.to_string(),
+                    actual,
+                });
+            }
+        }
+        Err(e) => report.issues.push(AuditIssue::DiffApplyFailed {
+            file: file.to_string(),
+            reason: e.to_string(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::diff::create_diff;
+    use crate::utils::manifest::Manifest;
+    use tempfile::tempdir;
+
+    /// Create an original tree and a consistent patch for it.
+    fn create_patch(original_dir: &Path, patch_dir: &Path) -> Manifest {
+        fs::write(original_dir.join("game.bin"), b"old content").unwrap();
+        let diff = create_diff(b"old content", b"new content").unwrap();
+
+        fs::create_dir_all(patch_dir.join("diffs")).unwrap();
+        fs::write(patch_dir.join("diffs/game.bin.diff"), &diff).unwrap();
+        fs::create_dir_all(patch_dir.join("files")).unwrap();
+        fs::write(patch_dir.join("files/new.bin"), b"added").unwrap();
+
+        let manifest = Manifest {
+            version: 1,
+            name: "Test".to_string(),
+            title: None,
+            allow_restricted: false,
+            excluded: Vec::new(),
+            discovery: None,
+            backup_dir: None,
+            backup: Default::default(),
+            post_apply: Vec::new(),
+            components: Vec::new(),
+            entries: vec![
+                ManifestEntry::Patch {
+                    file: "game.bin".to_string(),
+                    original_hash: hash_bytes(b"old content"),
+                    diff_hash: hash_bytes(&diff),
+                    final_hash: hash_bytes(b"new content"),
+                    alternatives: Vec::new(),
+                    fallback: false,
+                },
+                ManifestEntry::Add {
+                    file: "new.bin".to_string(),
+                    final_hash: hash_bytes(b"added"),
+                },
+            ],
+        };
+        manifest.save(&patch_dir.join(MANIFEST_FILENAME)).unwrap();
+        manifest
+    }
+
+    #[test]
+    fn consistent_patch_passes() {
+        let original = tempdir().unwrap();
+        let patch = tempdir().unwrap();
+        create_patch(original.path(), patch.path());
+
+        let report = audit_patch(patch.path(), Some(original.path())).unwrap();
+        assert!(report.is_ok(), "{:?}", report.issues);
+        assert_eq!(report.entries_checked, 2);
+        assert_eq!(report.diffs_replayed, 1);
+    }
+
+    #[test]
+    fn detects_payload_hash_mismatches() {
+        let original = tempdir().unwrap();
+        let patch = tempdir().unwrap();
+        create_patch(original.path(), patch.path());
+        fs::write(patch.path().join("files/new.bin"), b"tampered").unwrap();
+        fs::write(patch.path().join("diffs/game.bin.diff"), b"garbage").unwrap();
+
+        let report = audit_patch(patch.path(), None).unwrap();
+        assert!(report
+            .issues
+            .iter()
+            .any(|i| matches!(i, AuditIssue::FileHashMismatch { file, .. } if file == "new.bin")));
+        assert!(report
+            .issues
+            .iter()
+            .any(|i| matches!(i, AuditIssue::DiffHashMismatch { file, .. } if file == "game.bin")));
+    }
+
+    #[test]
+    fn detects_orphans_and_duplicates() {
+        let original = tempdir().unwrap();
+        let patch = tempdir().unwrap();
+        let mut manifest = create_patch(original.path(), patch.path());
+        manifest.entries.push(ManifestEntry::Delete {
+            file: "new.bin".to_string(),
+            original_hash: "x".to_string(),
+        });
+        manifest.save(&patch.path().join(MANIFEST_FILENAME)).unwrap();
+        fs::write(patch.path().join("files/stray.bin"), b"stray").unwrap();
+
+        let report = audit_patch(patch.path(), None).unwrap();
+        assert!(report
+            .issues
+            .contains(&AuditIssue::DuplicateEntry("new.bin".to_string())));
+        assert!(report
+            .issues
+            .contains(&AuditIssue::OrphanedPayload("files/stray.bin".to_string())));
+    }
+
+    #[test]
+    fn edits_of_one_file_conflict_only_on_the_same_setting() {
+        let original = tempdir().unwrap();
+        let patch = tempdir().unwrap();
+        let mut manifest = create_patch(original.path(), patch.path());
+        let ini_edit = |file: &str, key: &str| ManifestEntry::EditIni {
+            file: file.to_string(),
+            section: "Video".to_string(),
+            key: key.to_string(),
+            value: "1".to_string(),
+        };
+        manifest.entries.push(ini_edit("game.ini", "Width"));
+        manifest.entries.push(ini_edit("game.ini", "Height"));
+        manifest.entries.push(ini_edit("game.ini", "width"));
+        manifest.entries.insert(0, ini_edit("game.bin", "Width"));
+        manifest.save(&patch.path().join(MANIFEST_FILENAME)).unwrap();
+
+        let report = audit_patch(patch.path(), None).unwrap();
+        assert_eq!(
+            report.issues,
+            vec![
+                AuditIssue::DuplicateEntry("game.ini".to_string()),
+                AuditIssue::DuplicateEntry("game.bin".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn replay_detects_wrong_original() {
+        let original = tempdir().unwrap();
+        let patch = tempdir().unwrap();
+        create_patch(original.path(), patch.path());
+        fs::write(original.path().join("game.bin"), b"other content").unwrap();
+
+        let report = audit_patch(patch.path(), Some(original.path())).unwrap();
+        assert!(matches!(
+            report.issues.as_slice(),
+            [AuditIssue::OriginalHashMismatch { .. }]
+        ));
+    }
+
+    #[test]
+    fn replay_detects_wrong_final_hash() {
+        let original = tempdir().unwrap();
+        let patch = tempdir().unwrap();
+        let mut manifest = create_patch(original.path(), patch.path());
+        if let ManifestEntry::Patch { final_hash, .. } = &mut manifest.entries[0] {
+            *final_hash = hash_bytes(b"something else");
+        }
+        manifest.save(&patch.path().join(MANIFEST_FILENAME)).unwrap();
+
+        let report = audit_patch(patch.path(), Some(original.path())).unwrap();
+        assert!(matches!(
+            report.issues.as_slice(),
+            [AuditIssue::FinalHashMismatch { .. }]
+        ));
+    }
+}
@@ -1,59 +1,265 @@
 //! Backup and rollback operations for patch application.
+//!
+//! A backed up file is stored in one of three forms (see [`BackupFormat`]): a
+//! plain copy at the same relative path in the backup set, a zstd compressed
+//! copy under `.graft-zst/`, or a zstd compressed reverse diff from the
+//! patched file back to the original under `.graft-rdiff/`. Each form has its
+//! own directory so a stored file never collides with a target file that
+//! happens to be named like another form. Readers accept all of them, so a
+//! backup set can mix forms.
 
-use std::fs;
-use std::path::Path;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
 
+use crate::patch::cancel::{self, CancelToken};
+use crate::patch::progress::{file_size, Reporter};
+use crate::patch::restore::is_legacy_backup;
 use crate::patch::PatchError;
-use crate::patch::{Progress, ProgressAction};
-use crate::utils::file_ops::{backup_file, restore_file};
-use crate::utils::manifest::ManifestEntry;
+use crate::patch::{Progress, ProgressAction, BACKUP_DIR};
+use crate::utils::diff::{apply_diff, create_diff};
+use crate::utils::file_ops::ProgressReader;
+use crate::utils::manifest::{BackupOptions, ManifestEntry};
+
+/// Directory within a backup set for zstd compressed copies
+const COMPRESSED_DIR: &str = ".graft-zst";
+/// Directory within a backup set for reverse diffs
+const REVERSE_DIFF_DIR: &str = ".graft-rdiff";
+
+/// A reverse diff replaces a full copy only if it is at most this fraction of the original size.
+const REVERSE_DIFF_MAX_RATIO: usize = 4;
+
+/// Every backup format, in the order readers look for them.
+const FORMATS: [BackupFormat; 3] = [BackupFormat::Copy, BackupFormat::Compressed, BackupFormat::ReverseDiff];
+
+/// How a file is stored in a backup set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackupFormat {
+    /// Plain copy of the original file
+    Copy,
+    /// zstd compressed copy
+    Compressed,
+    /// zstd compressed diff from the patched file to the original.
+    /// Restoring it requires the unmodified patched file.
+    ReverseDiff,
+}
+
+impl BackupFormat {
+    /// Where a backup of `file` in this format is stored within `backup_dir`.
+    fn stored_path(self, backup_dir: &Path, file: &str) -> PathBuf {
+        match self {
+            BackupFormat::Copy => backup_dir.join(file),
+            BackupFormat::Compressed => backup_dir.join(COMPRESSED_DIR).join(file),
+            BackupFormat::ReverseDiff => backup_dir.join(REVERSE_DIFF_DIR).join(file),
+        }
+    }
+}
+
+impl std::fmt::Display for BackupFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BackupFormat::Copy => write!(f, "copy"),
+            BackupFormat::Compressed => write!(f, "compressed"),
+            BackupFormat::ReverseDiff => write!(f, "reverse diff"),
+        }
+    }
+}
+
+/// Find the stored backup of `file` in `backup_dir`, if any.
+///
+/// Backups written by older versions stored nested files by filename only;
+/// in such a flat `.patch-backup` (see [`is_legacy_backup`]) those are still
+/// found.
+pub fn find_backup(backup_dir: &Path, file: &str) -> Option<(BackupFormat, PathBuf)> {
+    let found = FORMATS
+        .into_iter()
+        .map(|format| (format, format.stored_path(backup_dir, file)))
+        .find(|(_, p)| p.is_file());
+    if found.is_some() {
+        return found;
+    }
+
+    let is_legacy = backup_dir.file_name() == Some(BACKUP_DIR.as_ref())
+        && backup_dir.parent().is_some_and(is_legacy_backup);
+    if !is_legacy {
+        return None;
+    }
+    let legacy = backup_dir.join(Path::new(file).file_name()?);
+    (legacy != backup_dir.join(file) && legacy.is_file()).then_some((BackupFormat::Copy, legacy))
+}
+
+/// Split a path stored in a backup set into the backed up file and its format.
+///
+/// `stored` is relative to the set and uses `/` separators.
+pub fn parse_stored_path(stored: &str) -> (&str, BackupFormat) {
+    match stored.split_once('/') {
+        Some((COMPRESSED_DIR, file)) => (file, BackupFormat::Compressed),
+        Some((REVERSE_DIFF_DIR, file)) => (file, BackupFormat::ReverseDiff),
+        _ => (stored, BackupFormat::Copy),
+    }
+}
+
+/// Read the original content of `file` from `backup_dir`.
+///
+/// `target_path` is the patched file, read only for reverse diff backups.
+/// Returns None if there is no backup of `file`.
+pub fn read_backup(backup_dir: &Path, file: &str, target_path: &Path) -> io::Result<Option<Vec<u8>>> {
+    let Some((format, path)) = find_backup(backup_dir, file) else {
+        return Ok(None);
+    };
+    let data = match format {
+        BackupFormat::Copy => fs::read(&path)?,
+        BackupFormat::Compressed => zstd::decode_all(File::open(&path)?)?,
+        BackupFormat::ReverseDiff => {
+            let diff = zstd::decode_all(File::open(&path)?)?;
+            apply_diff(&fs::read(target_path)?, &diff)?
+        }
+    };
+    Ok(Some(data))
+}
+
+/// Restore the original content of `file` from `backup_dir` to `target_path`.
+///
+/// Copies are streamed rather than read into memory. Returns false if there
+/// is no backup of `file`.
+pub fn restore_backup(backup_dir: &Path, file: &str, target_path: &Path) -> io::Result<bool> {
+    let Some((format, path)) = find_backup(backup_dir, file) else {
+        return Ok(false);
+    };
+    if let Some(parent) = target_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    match format {
+        BackupFormat::Copy => {
+            fs::copy(&path, target_path)?;
+        }
+        BackupFormat::Compressed => {
+            let mut output = File::create(target_path)?;
+            zstd::stream::copy_decode(File::open(&path)?, &mut output)?;
+        }
+        BackupFormat::ReverseDiff => {
+            let diff = zstd::decode_all(File::open(&path)?)?;
+            let original = apply_diff(&fs::read(target_path)?, &diff)?;
+            fs::write(target_path, original)?;
+        }
+    }
+    Ok(true)
+}
+
+/// Store `source` as the backup of `file`, replacing any other stored form.
+///
+/// `on_progress` receives the number of bytes of `source` read so far. If
+/// storing fails or `cancel` is set midway, the partial backup is removed.
+pub(crate) fn store_backup(
+    source: &Path,
+    backup_dir: &Path,
+    file: &str,
+    compress: bool,
+    cancel: Option<&CancelToken>,
+    on_progress: impl FnMut(u64),
+) -> io::Result<()> {
+    let format = if compress { BackupFormat::Compressed } else { BackupFormat::Copy };
+    let path = format.stored_path(backup_dir, file);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    remove_backup(backup_dir, file)?;
+
+    let mut input = ProgressReader::new(File::open(source)?, on_progress).with_cancel(cancel);
+    let stored = File::create(&path).and_then(|mut output| {
+        if compress {
+            zstd::stream::copy_encode(input, &mut output, zstd::DEFAULT_COMPRESSION_LEVEL)?;
+            output.sync_all()
+        } else {
+            // Copy in chunks to report progress, keeping permissions like fs::copy
+            io::copy(&mut input, &mut output)?;
+            fs::set_permissions(&path, fs::metadata(source)?.permissions())
+        }
+    });
+    if stored.is_err() {
+        let _ = fs::remove_file(&path);
+    }
+    stored
+}
+
+/// Remove every stored form of the backup of `file`.
+fn remove_backup(backup_dir: &Path, file: &str) -> io::Result<()> {
+    for format in FORMATS {
+        let stored = format.stored_path(backup_dir, file);
+        if stored.is_file() {
+            fs::remove_file(stored)?;
+        }
+    }
+    Ok(())
+}
 
 /// Backup all files that will be modified or deleted.
 ///
-/// Creates a backup directory and copies files that will be changed by the patch.
+/// Creates a backup directory and copies files that will be changed by the patch,
+/// compressed if `options.compress` is set.
 /// This should be called after validation but before applying any changes.
 ///
 /// - Patch entries: backs up the original file
 /// - Delete entries: backs up the file (if it exists)
 /// - Add entries: nothing to backup (new files)
+///
+/// Stops with [`PatchError::Cancelled`] once `cancel` is set; the target is
+/// untouched at this point.
 pub fn backup_entries<F>(
     entries: &[ManifestEntry],
     target_dir: &Path,
     backup_dir: &Path,
-    mut on_progress: Option<F>,
+    options: BackupOptions,
+    cancel: Option<&CancelToken>,
+    on_progress: Option<F>,
 ) -> Result<(), PatchError>
 where
     F: FnMut(Progress),
 {
-    let total = entries.len();
+    let copied_size = |entry: &ManifestEntry| match entry {
+        ManifestEntry::Patch { file, .. }
+        | ManifestEntry::Delete { file, .. }
+        | ManifestEntry::EditJson { file, .. }
+        | ManifestEntry::EditIni { file, .. } => file_size(&target_dir.join(file)),
+        ManifestEntry::Add { .. } | ManifestEntry::Require { .. } => 0,
+    };
+    let overall_bytes = entries.iter().map(copied_size).sum();
+    let mut reporter = Reporter::new(on_progress, entries.len(), overall_bytes);
+
     for (index, entry) in entries.iter().enumerate() {
+        cancel::check(cancel)?;
         let action = match entry {
-            ManifestEntry::Patch { .. } | ManifestEntry::Delete { .. } => ProgressAction::BackingUp,
-            ManifestEntry::Add { .. } => ProgressAction::Skipping,
+            ManifestEntry::Patch { .. }
+            | ManifestEntry::Delete { .. }
+            | ManifestEntry::EditJson { .. }
+            | ManifestEntry::EditIni { .. } => ProgressAction::BackingUp,
+            ManifestEntry::Add { .. } | ManifestEntry::Require { .. } => ProgressAction::Skipping,
         };
+        reporter.start(entry.file(), index, action, copied_size(entry));
 
-        if let Some(ref mut callback) = on_progress {
-            callback(Progress {
-                file: entry.file(),
-                index,
-                total,
-                action,
-            });
-        }
         match entry {
-            ManifestEntry::Patch { file, .. } | ManifestEntry::Delete { file, .. } => {
+            ManifestEntry::Patch { file, .. }
+            | ManifestEntry::Delete { file, .. }
+            | ManifestEntry::EditJson { file, .. }
+            | ManifestEntry::EditIni { file, .. } => {
                 let target_path = target_dir.join(file);
 
                 // Only backup if file exists (delete entries may already be gone)
                 if target_path.exists() {
-                    backup_file(&target_path, backup_dir).map_err(|e| PatchError::BackupFailed {
-                        file: file.clone(),
-                        reason: e.to_string(),
-                    })?;
+                    let stored = store_backup(&target_path, backup_dir, file, options.compress, cancel, |done| {
+                        reporter.advance(done)
+                    });
+                    if let Err(e) = stored {
+                        cancel::check(cancel)?;
+                        return Err(PatchError::BackupFailed {
+                            file: file.clone(),
+                            reason: e.to_string(),
+                        });
+                    }
                 }
             }
-            ManifestEntry::Add { .. } => {
-                // Nothing to backup for new files
+            ManifestEntry::Add { .. } | ManifestEntry::Require { .. } => {
+                // Nothing to backup for new or untouched files
             }
         }
     }
@@ -61,58 +267,113 @@ where
     Ok(())
 }
 
+/// Replace backups of patched files by reverse diffs where much smaller.
+///
+/// Call after a successful apply, while the target holds the patched files.
+/// A reverse diff is kept only if it is at most a quarter of the original's
+/// size. Returns the number of backups converted.
+pub fn store_reverse_diffs(
+    entries: &[ManifestEntry],
+    target_dir: &Path,
+    backup_dir: &Path,
+) -> Result<usize, PatchError> {
+    let mut converted = 0;
+    for entry in entries {
+        let ManifestEntry::Patch { file, .. } = entry else {
+            continue;
+        };
+        let backup_error = |e: io::Error| PatchError::BackupFailed {
+            file: file.clone(),
+            reason: e.to_string(),
+        };
+
+        let target_path = target_dir.join(file);
+        let Some(original) = read_backup(backup_dir, file, &target_path).map_err(backup_error)? else {
+            continue;
+        };
+        if matches!(find_backup(backup_dir, file), Some((BackupFormat::ReverseDiff, _))) {
+            continue;
+        }
+
+        let patched = fs::read(&target_path).map_err(backup_error)?;
+        let diff = create_diff(&patched, &original).map_err(backup_error)?;
+        let compressed = zstd::encode_all(diff.as_slice(), zstd::DEFAULT_COMPRESSION_LEVEL).map_err(backup_error)?;
+        if compressed.len().saturating_mul(REVERSE_DIFF_MAX_RATIO) > original.len() {
+            continue;
+        }
+
+        // Only drop the full copy once the diff is known to restore it
+        let restored = apply_diff(&patched, &diff).map_err(backup_error)?;
+        let restored_hash = crate::utils::hash::hash_bytes(&restored);
+        if !entry.patch_origins().iter().any(|(original_hash, _)| *original_hash == restored_hash) {
+            continue;
+        }
+        let path = BackupFormat::ReverseDiff.stored_path(backup_dir, file);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(backup_error)?;
+        }
+        fs::write(path, compressed).map_err(backup_error)?;
+        for format in [BackupFormat::Copy, BackupFormat::Compressed] {
+            let full_copy = format.stored_path(backup_dir, file);
+            if full_copy.is_file() {
+                fs::remove_file(full_copy).map_err(backup_error)?;
+            }
+        }
+        converted += 1;
+    }
+    Ok(converted)
+}
+
 /// Rollback applied changes by restoring from backup and removing added files.
 ///
 /// This should be called when an error occurs during patch application to
 /// restore the target directory to its original state.
 ///
-/// - Patch entries: restores the original file from backup
+/// - Patch and edit entries: restores the original file from backup
 /// - Delete entries: restores the file from backup (if backup exists)
 /// - Add entries: removes the newly added file
 pub fn rollback<F>(
     applied: &[&ManifestEntry],
     target_dir: &Path,
     backup_dir: &Path,
-    mut on_progress: Option<F>,
+    on_progress: Option<F>,
 ) -> Result<(), PatchError>
 where
     F: FnMut(Progress),
 {
-    let total = applied.len();
+    let mut reporter = Reporter::new(on_progress, applied.len(), 0);
     for (index, entry) in applied.iter().enumerate() {
         let action = match entry {
-            ManifestEntry::Patch { .. } => ProgressAction::Restoring,
+            ManifestEntry::Patch { .. } | ManifestEntry::EditJson { .. } | ManifestEntry::EditIni { .. } => {
+                ProgressAction::Restoring
+            }
             ManifestEntry::Add { .. } => ProgressAction::Removing,
             ManifestEntry::Delete { .. } => ProgressAction::Restoring,
+            ManifestEntry::Require { .. } => ProgressAction::Skipping,
         };
+        reporter.start(entry.file(), index, action, 0);
 
-        if let Some(ref mut callback) = on_progress {
-            callback(Progress {
-                file: entry.file(),
-                index,
-                total,
-                action,
-            });
-        }
         match entry {
-            ManifestEntry::Patch { file, .. } => {
-                // Patch entries always have backups (validated to exist)
+            ManifestEntry::Patch { file, .. }
+            | ManifestEntry::EditJson { file, .. }
+            | ManifestEntry::EditIni { file, .. } => {
+                // Patch and edit entries always have backups (validated to exist)
                 let target_path = target_dir.join(file);
-                restore_file(&target_path, backup_dir).map_err(|e| PatchError::RollbackFailed {
+                match restore_backup(backup_dir, file, &target_path) {
+                    Ok(true) => Ok(()),
+                    Ok(false) => Err(io::Error::new(io::ErrorKind::NotFound, "backup file not found")),
+                    Err(e) => Err(e),
+                }
+                .map_err(|e| PatchError::RollbackFailed {
                     reason: format!("failed to restore '{}': {}", file, e),
                 })?;
             }
             ManifestEntry::Delete { file, .. } => {
                 // Only restore if we have a backup (file existed before patch)
-                let backup_path = backup_dir.join(file);
-                if backup_path.exists() {
-                    let target_path = target_dir.join(file);
-                    restore_file(&target_path, backup_dir).map_err(|e| {
-                        PatchError::RollbackFailed {
-                            reason: format!("failed to restore '{}': {}", file, e),
-                        }
-                    })?;
-                }
+                let target_path = target_dir.join(file);
+                restore_backup(backup_dir, file, &target_path).map_err(|e| PatchError::RollbackFailed {
+                    reason: format!("failed to restore '{}': {}", file, e),
+                })?;
             }
             ManifestEntry::Add { file, .. } => {
                 // Remove the newly added file
@@ -123,8 +384,123 @@ where
                     })?;
                 }
             }
+            ManifestEntry::Require { .. } => {
+                // Never modified
+            }
         }
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::patch::validate_backup;
+    use crate::utils::hash::hash_bytes;
+    use tempfile::tempdir;
+
+    fn patch_entry(file: &str, original: &[u8], patched: &[u8]) -> ManifestEntry {
+        ManifestEntry::Patch {
+            file: file.to_string(),
+            original_hash: hash_bytes(original),
+            diff_hash: String::new(),
+            final_hash: hash_bytes(patched),
+            alternatives: Vec::new(),
+            fallback: false,
+        }
+    }
+
+    #[test]
+    fn filename_fallback_is_only_for_legacy_backups() {
+        let target = tempdir().unwrap();
+        let legacy = target.path().join(BACKUP_DIR);
+        fs::create_dir(&legacy).unwrap();
+        fs::write(legacy.join("file.bin"), b"original").unwrap();
+        assert_eq!(find_backup(&legacy, "data/file.bin"), Some((BackupFormat::Copy, legacy.join("file.bin"))));
+
+        let set = tempdir().unwrap();
+        fs::write(set.path().join("file.bin"), b"other file").unwrap();
+        assert_eq!(find_backup(set.path(), "data/file.bin"), None);
+        assert!(find_backup(set.path(), "file.bin").is_some());
+    }
+
+    #[test]
+    fn compressed_backup_restores_nested_file() {
+        let target = tempdir().unwrap();
+        let backup = tempdir().unwrap();
+        let original = b"original content ".repeat(100);
+        fs::create_dir(target.path().join("data")).unwrap();
+        fs::write(target.path().join("data/file.bin"), &original).unwrap();
+
+        let entries = vec![patch_entry("data/file.bin", &original, b"patched")];
+        let options = BackupOptions { compress: true, reverse_diffs: false };
+        backup_entries(&entries, target.path(), backup.path(), options, None, None::<fn(Progress)>).unwrap();
+
+        let (format, path) = find_backup(backup.path(), "data/file.bin").unwrap();
+        assert_eq!(format, BackupFormat::Compressed);
+        assert!(fs::metadata(path).unwrap().len() < original.len() as u64);
+
+        fs::write(target.path().join("data/file.bin"), b"patched").unwrap();
+        validate_backup(&entries, target.path(), backup.path(), None::<fn(Progress)>).unwrap();
+        rollback(&entries.iter().collect::<Vec<_>>(), target.path(), backup.path(), None::<fn(Progress)>).unwrap();
+        assert_eq!(fs::read(target.path().join("data/file.bin")).unwrap(), original);
+    }
+
+    #[test]
+    fn stored_forms_do_not_collide_with_similarly_named_files() {
+        let target = tempdir().unwrap();
+        let backup = tempdir().unwrap();
+        fs::write(target.path().join("file.bin"), b"plain").unwrap();
+        fs::write(target.path().join("file.bin.zst"), b"named like a compressed backup").unwrap();
+
+        let entries = vec![
+            patch_entry("file.bin", b"plain", b"patched"),
+            patch_entry("file.bin.zst", b"named like a compressed backup", b"patched"),
+        ];
+        let options = BackupOptions { compress: true, reverse_diffs: false };
+        backup_entries(&entries, target.path(), backup.path(), options, None, None::<fn(Progress)>).unwrap();
+        assert_eq!(parse_stored_path(".graft-zst/file.bin.zst"), ("file.bin.zst", BackupFormat::Compressed));
+        assert_eq!(parse_stored_path("file.bin.zst"), ("file.bin.zst", BackupFormat::Copy));
+
+        fs::write(target.path().join("file.bin"), b"patched").unwrap();
+        fs::write(target.path().join("file.bin.zst"), b"patched").unwrap();
+        rollback(&entries.iter().collect::<Vec<_>>(), target.path(), backup.path(), None::<fn(Progress)>).unwrap();
+        assert_eq!(fs::read(target.path().join("file.bin")).unwrap(), b"plain");
+        assert_eq!(fs::read(target.path().join("file.bin.zst")).unwrap(), b"named like a compressed backup");
+    }
+
+    #[test]
+    fn reverse_diff_replaces_copy_when_much_smaller() {
+        let target = tempdir().unwrap();
+        let backup = tempdir().unwrap();
+        let original: Vec<u8> = (0..20_000u32).flat_map(|i| i.to_le_bytes()).collect();
+        let mut patched = original.clone();
+        patched[100..104].copy_from_slice(b"edit");
+        fs::write(target.path().join("big.bin"), &original).unwrap();
+        fs::write(target.path().join("small.bin"), b"a").unwrap();
+
+        let entries = vec![
+            patch_entry("big.bin", &original, &patched),
+            patch_entry("small.bin", b"a", b"b"),
+        ];
+        backup_entries(&entries, target.path(), backup.path(), BackupOptions::default(), None, None::<fn(Progress)>)
+            .unwrap();
+        fs::write(target.path().join("big.bin"), &patched).unwrap();
+        fs::write(target.path().join("small.bin"), b"b").unwrap();
+
+        assert_eq!(store_reverse_diffs(&entries, target.path(), backup.path()).unwrap(), 1);
+        assert_eq!(find_backup(backup.path(), "big.bin").unwrap().0, BackupFormat::ReverseDiff);
+        assert_eq!(find_backup(backup.path(), "small.bin").unwrap().0, BackupFormat::Copy);
+        validate_backup(&entries, target.path(), backup.path(), None::<fn(Progress)>).unwrap();
+
+        // A reverse diff can't restore a file that changed after patching
+        fs::write(target.path().join("big.bin"), b"modified").unwrap();
+        assert!(validate_backup(&entries, target.path(), backup.path(), None::<fn(Progress)>).is_err());
+
+        fs::write(target.path().join("big.bin"), &patched).unwrap();
+        rollback(&entries.iter().collect::<Vec<_>>(), target.path(), backup.path(), None::<fn(Progress)>).unwrap();
+        assert_eq!(fs::read(target.path().join("big.bin")).unwrap(), original);
+        assert_eq!(fs::read(target.path().join("small.bin")).unwrap(), b"a");
+    }
+}
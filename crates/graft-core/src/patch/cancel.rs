@@ -0,0 +1,36 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use crate::patch::PatchError;
+
+/// Flag for stopping a batch operation from another thread.
+///
+/// Clones share the same flag, so one clone can be handed to a worker
+/// while another is cancelled from a UI or signal handler. Batch
+/// operations check it before each entry and while copying an entry's data;
+/// a cancelled apply rolls back the entries it already applied.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Request cancellation
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// Return [`PatchError::Cancelled`] if cancellation was requested.
+pub(crate) fn check(cancel: Option<&CancelToken>) -> Result<(), PatchError> {
+    match cancel {
+        Some(token) if token.is_cancelled() => Err(PatchError::Cancelled),
+        _ => Ok(()),
+    }
+}
@@ -8,7 +8,29 @@ pub const DIFF_EXTENSION: &str = ".diff";
 pub const MANIFEST_FILENAME: &str = "manifest.json";
 /// Directory name for backups during patch application
 pub const BACKUP_DIR: &str = ".patch-backup";
+/// Filename for the list of restore points within the backup directory
+pub const BACKUP_INDEX_FILENAME: &str = "index.json";
+/// Filename for the applied patch's manifest within a backup set
+pub const BACKUP_MANIFEST_FILENAME: &str = ".graft-manifest.json";
+/// Filename in the target pointing to backups stored outside it
+pub const BACKUP_MARKER_FILENAME: &str = ".graft-backup";
 /// Directory name for patcher assets (icons, etc.)
 pub const ASSETS_DIR: &str = ".graft_assets";
+/// Filename for exclude patterns in the modified directory
+pub const IGNORE_FILENAME: &str = ".graftignore";
 /// Filename for the patcher icon
 pub const ICON_FILENAME: &str = "icon.png";
+/// Filename for the banner shown at the top of the patcher window
+pub const BANNER_FILENAME: &str = "banner.png";
+/// Filename for the patcher's colors and fonts
+pub const THEME_FILENAME: &str = "theme.json";
+/// Filename for the Markdown readme shown on the welcome screen
+pub const README_FILENAME: &str = "README.md";
+/// Filename for the Markdown changelog shown on the welcome screen
+pub const CHANGELOG_FILENAME: &str = "CHANGELOG.md";
+/// Filename for the Markdown license the user must accept before applying
+pub const EULA_FILENAME: &str = "EULA.md";
+/// Directory within the assets for the patcher's message catalogs
+pub const LANG_DIR: &str = "lang";
+/// File extension for message catalogs
+pub const LANG_EXTENSION: &str = ".lang";
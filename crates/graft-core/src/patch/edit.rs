@@ -0,0 +1,35 @@
+//! Setting edits of [`ManifestEntry::EditJson`] and [`ManifestEntry::EditIni`].
+
+use crate::utils::edit::{ini_value, json_value, set_ini, set_json};
+use crate::utils::manifest::ManifestEntry;
+use std::io;
+
+/// Content of a file after applying the edit `entry` to `data`.
+///
+/// Fails if the file can't be parsed or the setting has no place in it,
+/// which is how edits are validated. Other entries leave `data` as it is.
+pub(crate) fn edited(entry: &ManifestEntry, data: &[u8]) -> io::Result<Vec<u8>> {
+    match entry {
+        ManifestEntry::EditJson { pointer, value, .. } => set_json(data, pointer, value),
+        ManifestEntry::EditIni { section, key, value, .. } => set_ini(data, section, key, value.trim()),
+        _ => Ok(data.to_vec()),
+    }
+}
+
+/// Value an edit sets, formatted like [`current_value`]
+pub(crate) fn new_value(entry: &ManifestEntry) -> String {
+    match entry {
+        ManifestEntry::EditJson { value, .. } => value.to_string(),
+        ManifestEntry::EditIni { value, .. } => value.trim().to_string(),
+        _ => String::new(),
+    }
+}
+
+/// Current value of the edited setting in `data`, formatted like [`new_value`]
+pub(crate) fn current_value(entry: &ManifestEntry, data: &[u8]) -> io::Result<Option<String>> {
+    match entry {
+        ManifestEntry::EditJson { pointer, .. } => Ok(json_value(data, pointer)?.map(|v| v.to_string())),
+        ManifestEntry::EditIni { section, key, .. } => ini_value(data, section, key),
+        _ => Ok(None),
+    }
+}
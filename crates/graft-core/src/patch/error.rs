@@ -24,6 +24,10 @@ pub enum PatchError {
     ManifestError { reason: String },
     /// Path restrictions violated (system dirs, executables, etc.)
     RestrictedPaths(Vec<RestrictionViolation>),
+    /// Cancelled by the user; applied entries were rolled back
+    Cancelled,
+    /// A post-apply action failed; the patch itself was applied
+    PostApplyFailed { action: String, reason: String },
 }
 
 impl fmt::Display for PatchError {
@@ -67,6 +71,12 @@ impl fmt::Display for PatchError {
                 }
                 Ok(())
             }
+            PatchError::Cancelled => {
+                write!(f, "cancelled")
+            }
+            PatchError::PostApplyFailed { action, reason } => {
+                write!(f, "cannot {}: {}", action, reason)
+            }
         }
     }
 }
@@ -0,0 +1,323 @@
+//! Inspecting, pruning and exporting the backups of a target directory.
+
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::patch::backup::{parse_stored_path, BackupFormat};
+use crate::patch::restore::{
+    backup_root, delete_backup_set, forget_restore_point, is_legacy_backup, link_backup_root, BackupIndex,
+    RestorePoint,
+};
+use crate::patch::{BACKUP_DIR, BACKUP_INDEX_FILENAME, BACKUP_MANIFEST_FILENAME};
+use crate::utils::file_ops::{copy_dir, remove_dir_all_robust};
+
+/// Id reported for a flat backup from before restore points.
+pub const LEGACY_SET_ID: &str = "legacy";
+
+/// A backed up file within a backup set.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BackupFile {
+    /// Path of the original file, relative to the target
+    pub file: String,
+    pub format: BackupFormat,
+    /// Size on disk
+    pub size: u64,
+}
+
+/// A backup set and the restore point it belongs to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BackupSet {
+    pub id: String,
+    pub dir: PathBuf,
+    /// None for sets kept after rollback and for legacy backups
+    pub restore_point: Option<RestorePoint>,
+    pub files: Vec<BackupFile>,
+}
+
+impl BackupSet {
+    /// Total size of the set on disk.
+    pub fn size(&self) -> u64 {
+        self.files.iter().map(|f| f.size).sum()
+    }
+
+    /// Display label, e.g. "MyPatcher v2" or "Base-v1 (no restore point)".
+    pub fn label(&self) -> String {
+        match &self.restore_point {
+            Some(point) => point.label(),
+            None => format!("{} (no restore point)", self.id),
+        }
+    }
+}
+
+/// List the backup sets of `target_dir`.
+///
+/// Sets with a restore point come first, oldest first, followed by sets that
+/// were kept after their patch was rolled back.
+pub fn backup_sets(target_dir: &Path) -> io::Result<Vec<BackupSet>> {
+    let root = backup_root(target_dir);
+    if is_legacy_backup(target_dir) {
+        return Ok(vec![BackupSet {
+            id: LEGACY_SET_ID.to_string(),
+            files: collect_files(&root)?,
+            dir: root,
+            restore_point: None,
+        }]);
+    }
+
+    let index = BackupIndex::load(target_dir)?;
+    let mut sets = Vec::new();
+    for point in &index.restore_points {
+        let dir = root.join(&point.id);
+        let files = if dir.is_dir() { collect_files(&dir)? } else { Vec::new() };
+        sets.push(BackupSet {
+            id: point.id.clone(),
+            dir,
+            restore_point: Some(point.clone()),
+            files,
+        });
+    }
+
+    let mut orphans = Vec::new();
+    if root.is_dir() {
+        for entry in fs::read_dir(&root)? {
+            let entry = entry?;
+            let id = entry.file_name().to_string_lossy().into_owned();
+            if entry.file_type()?.is_dir() && !index.contains(&id) {
+                orphans.push(BackupSet {
+                    files: collect_files(&entry.path())?,
+                    id,
+                    dir: entry.path(),
+                    restore_point: None,
+                });
+            }
+        }
+    }
+    orphans.sort_by(|a, b| a.id.cmp(&b.id));
+    sets.extend(orphans);
+
+    Ok(sets)
+}
+
+/// Find a backup set by id, or the latest restore point if `id` is None.
+pub fn find_backup_set(target_dir: &Path, id: Option<&str>) -> io::Result<Option<BackupSet>> {
+    let sets = backup_sets(target_dir)?;
+    Ok(match id {
+        Some(id) => sets.into_iter().find(|s| s.id == id),
+        None => sets.into_iter().rev().find(|s| s.restore_point.is_some() || s.id == LEGACY_SET_ID),
+    })
+}
+
+fn collect_files(set_dir: &Path) -> io::Result<Vec<BackupFile>> {
+    let mut files = Vec::new();
+    collect_files_in(set_dir, "", &mut files)?;
+    files.sort_by(|a, b| a.file.cmp(&b.file));
+    Ok(files)
+}
+
+fn collect_files_in(dir: &Path, prefix: &str, files: &mut Vec<BackupFile>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let stored = if prefix.is_empty() { name } else { format!("{}/{}", prefix, name) };
+
+        if entry.file_type()?.is_dir() {
+            collect_files_in(&entry.path(), &stored, files)?;
+        } else if stored != BACKUP_INDEX_FILENAME && stored != BACKUP_MANIFEST_FILENAME {
+            let (file, format) = parse_stored_path(&stored);
+            files.push(BackupFile {
+                file: file.to_string(),
+                format,
+                size: entry.metadata()?.len(),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Remove backup sets that are no longer needed.
+///
+/// Sets without a restore point are always removed. With `keep`, only the
+/// `keep` most recent restore points are kept; older patches can then no
+/// longer be rolled back. Returns the ids of the removed sets.
+pub fn prune_backups(target_dir: &Path, keep: Option<usize>) -> io::Result<Vec<String>> {
+    let sets = backup_sets(target_dir)?;
+    let points = sets.iter().filter(|s| s.restore_point.is_some()).count();
+    let drop_points = keep.map_or(0, |keep| points.saturating_sub(keep));
+
+    let mut removed = Vec::new();
+    for (position, set) in sets.iter().enumerate() {
+        if set.id == LEGACY_SET_ID {
+            continue;
+        }
+        if set.restore_point.is_some() {
+            if position >= drop_points {
+                continue;
+            }
+            forget_restore_point(target_dir, &set.id)?;
+        }
+        delete_backup_set(target_dir, &set.id)?;
+        removed.push(set.id.clone());
+    }
+    Ok(removed)
+}
+
+/// Copy all backups of `target_dir` to the directory `dest`.
+///
+/// `dest` must not exist yet or be empty.
+pub fn export_backups(target_dir: &Path, dest: &Path) -> io::Result<()> {
+    let root = backup_root(target_dir);
+    if !root.is_dir() {
+        return Err(io::Error::new(io::ErrorKind::NotFound, format!("no backups in {}", root.display())));
+    }
+    if fs::read_dir(dest).is_ok_and(|mut entries| entries.next().is_some()) {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} is not empty", dest.display()),
+        ));
+    }
+    copy_dir(&root, dest)
+}
+
+/// Move all backups of `target_dir` to `dest` and point the target at them.
+///
+/// Moving to `<target>/.patch-backup` brings external backups back into the target.
+pub fn move_backups(target_dir: &Path, dest: &Path) -> io::Result<()> {
+    let root = std::path::absolute(backup_root(target_dir))?;
+    if std::path::absolute(dest)? == root {
+        return Ok(());
+    }
+    export_backups(target_dir, dest)?;
+    link_backup_root(target_dir, dest)?;
+    remove_dir_all_robust(&root)
+}
+
+/// Write all backups of `target_dir` to a zstd compressed tar archive.
+///
+/// Paths in the archive start with `.patch-backup/`, so extracting it into the
+/// target restores an in-target backup.
+pub fn archive_backups(target_dir: &Path, archive: &Path) -> io::Result<()> {
+    let root = backup_root(target_dir);
+    if !root.is_dir() {
+        return Err(io::Error::new(io::ErrorKind::NotFound, format!("no backups in {}", root.display())));
+    }
+    let encoder = zstd::Encoder::new(File::create(archive)?, zstd::DEFAULT_COMPRESSION_LEVEL)?;
+    let mut builder = tar::Builder::new(encoder);
+    builder.append_dir_all(BACKUP_DIR, &root)?;
+    builder.into_inner()?.finish()?.sync_all()
+}
+
+/// Format a byte count for display, e.g. "1.5 MB".
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+/// Format a Unix timestamp for display, e.g. "2024-03-01 14:05 UTC".
+pub fn format_timestamp(secs: u64) -> String {
+    // Civil date from days since 1970-01-01 (proleptic Gregorian calendar)
+    let days = (secs / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let doe = days.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    let time = secs % 86_400;
+    format!("{:04}-{:02}-{:02} {:02}:{:02} UTC", year, month, day, time / 3600, time % 3600 / 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::patch::restore::{backup_set_dir, list_restore_points, record_restore_point};
+    use crate::utils::manifest::Manifest;
+    use tempfile::tempdir;
+
+    fn applied(target: &Path, name: &str, version: u32) {
+        let manifest = Manifest::new(version, name.to_string(), None);
+        record_restore_point(target, &manifest).unwrap();
+        let dir = backup_set_dir(target, &format!("{}-v{}", name, version));
+        fs::create_dir_all(dir.join("data")).unwrap();
+        fs::write(dir.join("data/a.bin"), b"aaaa").unwrap();
+        fs::create_dir_all(dir.join(".graft-zst")).unwrap();
+        fs::write(dir.join(".graft-zst/b.bin"), b"bb").unwrap();
+    }
+
+    #[test]
+    fn lists_sets_with_formats_and_orphans() {
+        let target = tempdir().unwrap();
+        applied(target.path(), "Base", 1);
+        fs::create_dir_all(backup_set_dir(target.path(), "Old-v1")).unwrap();
+
+        let sets = backup_sets(target.path()).unwrap();
+        assert_eq!(sets.len(), 2);
+        assert_eq!(sets[0].id, "Base-v1");
+        assert_eq!(sets[0].size(), 6);
+        assert_eq!(
+            sets[0].files,
+            vec![
+                BackupFile { file: "b.bin".to_string(), format: BackupFormat::Compressed, size: 2 },
+                BackupFile { file: "data/a.bin".to_string(), format: BackupFormat::Copy, size: 4 },
+            ]
+        );
+        assert!(sets[1].restore_point.is_none());
+        assert_eq!(find_backup_set(target.path(), None).unwrap().unwrap().id, "Base-v1");
+    }
+
+    #[test]
+    fn prune_keeps_most_recent_restore_points() {
+        let target = tempdir().unwrap();
+        applied(target.path(), "Base", 1);
+        applied(target.path(), "Base", 2);
+        applied(target.path(), "Base", 3);
+        fs::create_dir_all(backup_set_dir(target.path(), "Old-v1")).unwrap();
+
+        let removed = prune_backups(target.path(), Some(2)).unwrap();
+        assert_eq!(removed, ["Base-v1", "Old-v1"]);
+        let ids: Vec<_> = list_restore_points(target.path()).unwrap().into_iter().map(|p| p.id).collect();
+        assert_eq!(ids, ["Base-v2", "Base-v3"]);
+    }
+
+    #[test]
+    fn move_relinks_target() {
+        let target = tempdir().unwrap();
+        let external = tempdir().unwrap();
+        let dest = external.path().join("backups");
+        applied(target.path(), "Base", 1);
+
+        move_backups(target.path(), &dest).unwrap();
+        assert!(!target.path().join(BACKUP_DIR).exists());
+        assert_eq!(backup_root(target.path()), dest);
+        assert_eq!(list_restore_points(target.path()).unwrap().len(), 1);
+
+        move_backups(target.path(), &target.path().join(BACKUP_DIR)).unwrap();
+        assert!(target.path().join(BACKUP_DIR).join("Base-v1/data/a.bin").exists());
+        assert!(!dest.exists());
+    }
+
+    #[test]
+    fn formats_sizes() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1536), "1.5 KB");
+        assert_eq!(format_size(3 * 1024 * 1024 * 1024), "3.0 GB");
+    }
+
+    #[test]
+    fn formats_timestamps() {
+        assert_eq!(format_timestamp(0), "1970-01-01 00:00 UTC");
+        assert_eq!(format_timestamp(1_709_301_900), "2024-03-01 14:05 UTC");
+    }
+}
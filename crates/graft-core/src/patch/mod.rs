@@ -1,7 +1,15 @@
 pub mod apply;
+pub mod audit;
 pub mod backup;
+mod cancel;
 mod constants;
+mod edit;
 mod error;
+pub mod maintenance;
+pub mod post_apply;
+mod progress;
+pub mod restore;
+pub mod source;
 pub mod validate;
 pub mod verify;
 
@@ -22,6 +30,7 @@ pub enum ProgressAction {
     Patching,
     Adding,
     Deleting,
+    Editing,
 
     // Rollback phase
     Restoring,
@@ -29,6 +38,9 @@ pub enum ProgressAction {
 }
 
 /// Progress information passed to callbacks during batch operations.
+///
+/// Each entry is reported when it starts, with `bytes_done` 0, and again as
+/// its file data is hashed, copied or patched.
 #[derive(Debug, Clone)]
 pub struct Progress<'a> {
     /// File being processed
@@ -39,12 +51,36 @@ pub struct Progress<'a> {
     pub total: usize,
     /// Action being performed
     pub action: ProgressAction,
+    /// Bytes of the current entry processed so far
+    pub bytes_done: u64,
+    /// Bytes the current entry will process (0 if it has no file data)
+    pub bytes_total: u64,
+    /// Bytes of all entries processed so far, including the current one
+    pub overall_bytes_done: u64,
+    /// Bytes all entries will process
+    pub overall_bytes_total: u64,
 }
 
 // Re-export public items
 pub use apply::{apply_entries, apply_entry};
-pub use backup::{backup_entries, rollback};
-pub use constants::{ASSETS_DIR, BACKUP_DIR, DIFFS_DIR, DIFF_EXTENSION, FILES_DIR, ICON_FILENAME, MANIFEST_FILENAME};
+pub use audit::{audit_patch, AuditIssue, AuditReport};
+pub use backup::{
+    backup_entries, find_backup, parse_stored_path, read_backup, restore_backup, rollback, store_reverse_diffs,
+    BackupFormat,
+};
+pub use cancel::CancelToken;
+pub use constants::{ASSETS_DIR, BACKUP_DIR, BACKUP_INDEX_FILENAME, BACKUP_MANIFEST_FILENAME, BACKUP_MARKER_FILENAME, BANNER_FILENAME, CHANGELOG_FILENAME, DIFFS_DIR, DIFF_EXTENSION, EULA_FILENAME, FILES_DIR, ICON_FILENAME, IGNORE_FILENAME, LANG_DIR, LANG_EXTENSION, MANIFEST_FILENAME, README_FILENAME, THEME_FILENAME};
 pub use error::PatchError;
+pub use maintenance::{
+    archive_backups, backup_sets, export_backups, find_backup_set, format_size, format_timestamp, move_backups, prune_backups,
+    BackupFile, BackupSet, LEGACY_SET_ID,
+};
+pub use restore::{
+    applied_manifest, backup_root, backup_set_dir, default_backup_root, delete_backup_set, forget_component, forget_restore_point, has_backups,
+    has_restore_point, is_external_backup, is_legacy_backup, list_restore_points, prepare_backup_root, record_restore_point,
+    latest_backup_manifest, load_backup_manifest, restore_point_id, rollback_set_dir, set_backup_root, BackupIndex, RestorePoint,
+};
+pub use post_apply::run_post_action;
+pub use source::{PatchSource, TarSource};
 pub use validate::{validate_backup, validate_entries, validate_patch_dir, validate_patched_entries, validate_path_restrictions};
 pub use verify::verify_entry;
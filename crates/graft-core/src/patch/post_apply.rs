@@ -0,0 +1,106 @@
+//! Steps after a successful apply, declared in the manifest's `post_apply`.
+
+use crate::patch::PatchError;
+use crate::path_restrictions::escapes_target;
+use crate::utils::manifest::PostAction;
+use std::ffi::OsStr;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::process::Command;
+
+/// Run a post-apply action in `target`.
+///
+/// Deleting a folder that doesn't exist succeeds. Launched programs and
+/// opened files are not waited for. Actions should have passed
+/// [`validate_path_restrictions`](crate::patch::validate_path_restrictions)
+/// with the rest of the manifest; paths outside `target` are refused here too.
+pub fn run_post_action(action: &PostAction, target: &Path) -> Result<(), PatchError> {
+    if let Some(path) = action.path()
+        && escapes_target(path)
+    {
+        return Err(PatchError::PostApplyFailed {
+            action: action.to_string(),
+            reason: "path leads outside the target folder".to_string(),
+        });
+    }
+
+    let result = match action {
+        PostAction::DeleteDir { path } => match fs::remove_dir_all(target.join(path)) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+            result => result,
+        },
+        PostAction::Launch { path, args } => Command::new(target.join(path))
+            .args(args)
+            .current_dir(target)
+            .spawn()
+            .map(drop),
+        PostAction::OpenFile { path } => open(target.join(path).as_os_str()),
+        PostAction::OpenUrl { url } => open(OsStr::new(url)),
+    };
+    result.map_err(|e| PatchError::PostApplyFailed {
+        action: action.to_string(),
+        reason: e.to_string(),
+    })
+}
+
+/// Open a file or URL with the system's default application
+fn open(item: &OsStr) -> io::Result<()> {
+    #[cfg(target_os = "windows")]
+    let program = "explorer";
+    #[cfg(target_os = "macos")]
+    let program = "open";
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    let program = "xdg-open";
+
+    Command::new(program).arg(item).spawn().map(drop)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn deletes_folder_within_target() {
+        let target = tempdir().unwrap();
+        let cache = target.path().join("cache/shaders");
+        fs::create_dir_all(&cache).unwrap();
+        fs::write(cache.join("a.bin"), b"stale").unwrap();
+        fs::write(target.path().join("game.dat"), b"data").unwrap();
+
+        let action = PostAction::DeleteDir { path: "cache/shaders".to_string() };
+        run_post_action(&action, target.path()).unwrap();
+        assert!(!cache.exists());
+        assert!(target.path().join("cache").exists());
+        assert!(target.path().join("game.dat").exists());
+
+        // Already gone
+        run_post_action(&action, target.path()).unwrap();
+    }
+
+    #[test]
+    fn refuses_paths_outside_target() {
+        let parent = tempdir().unwrap();
+        let target = parent.path().join("game");
+        fs::create_dir_all(target.join("sub")).unwrap();
+
+        for path in ["sub/..", "../game", "/abs"] {
+            let action = PostAction::DeleteDir { path: path.to_string() };
+            let err = run_post_action(&action, &target).unwrap_err();
+            assert!(matches!(err, PatchError::PostApplyFailed { .. }), "{}", path);
+        }
+        assert!(target.join("sub").exists());
+    }
+
+    #[test]
+    fn launching_missing_program_fails() {
+        let target = tempdir().unwrap();
+        let action = PostAction::Launch {
+            path: "missing-game".to_string(),
+            args: Vec::new(),
+        };
+        let err = run_post_action(&action, target.path()).unwrap_err();
+        assert!(matches!(err, PatchError::PostApplyFailed { .. }));
+    }
+}
@@ -0,0 +1,75 @@
+use std::fs;
+use std::path::Path;
+
+use crate::patch::{Progress, ProgressAction};
+
+/// Reports the entries of a batch operation and the bytes processed within them.
+pub(crate) struct Reporter<'a, F> {
+    on_progress: Option<F>,
+    total: usize,
+    overall_bytes_total: u64,
+    /// Bytes of the entries before the current one
+    finished_bytes: u64,
+    current: Option<Entry<'a>>,
+}
+
+struct Entry<'a> {
+    file: &'a str,
+    index: usize,
+    action: ProgressAction,
+    bytes_total: u64,
+}
+
+impl<'a, F: FnMut(Progress)> Reporter<'a, F> {
+    pub(crate) fn new(on_progress: Option<F>, total: usize, overall_bytes_total: u64) -> Self {
+        Reporter {
+            on_progress,
+            total,
+            overall_bytes_total,
+            finished_bytes: 0,
+            current: None,
+        }
+    }
+
+    /// Start the next entry, which will process `bytes_total` bytes.
+    pub(crate) fn start(&mut self, file: &'a str, index: usize, action: ProgressAction, bytes_total: u64) {
+        if let Some(entry) = self.current.take() {
+            self.finished_bytes += entry.bytes_total;
+        }
+        self.current = Some(Entry {
+            file,
+            index,
+            action,
+            bytes_total,
+        });
+        self.report(0);
+    }
+
+    /// Report that `bytes_done` bytes of the current entry have been processed.
+    pub(crate) fn advance(&mut self, bytes_done: u64) {
+        self.report(bytes_done);
+    }
+
+    fn report(&mut self, bytes_done: u64) {
+        let (Some(callback), Some(entry)) = (self.on_progress.as_mut(), &self.current) else {
+            return;
+        };
+        // Files can grow between sizing and reading; never report past the total
+        let bytes_done = bytes_done.min(entry.bytes_total);
+        callback(Progress {
+            file: entry.file,
+            index: entry.index,
+            total: self.total,
+            action: entry.action,
+            bytes_done,
+            bytes_total: entry.bytes_total,
+            overall_bytes_done: self.finished_bytes + bytes_done,
+            overall_bytes_total: self.overall_bytes_total,
+        });
+    }
+}
+
+/// Size of a file, or 0 if it doesn't exist.
+pub(crate) fn file_size(path: &Path) -> u64 {
+    fs::metadata(path).map(|m| m.len()).unwrap_or(0)
+}
@@ -0,0 +1,615 @@
+//! Restore points: one backup set per applied patch.
+//!
+//! Each applied patch backs up into its own directory,
+//! `.patch-backup/<name>-v<version>/`, together with a copy of its manifest
+//! (so it can be rolled back without the patch), and is recorded in
+//! `.patch-backup/index.json` in the order patches were applied. Patches can
+//! be stacked, and are rolled back in reverse order (last applied first).
+//!
+//! Backups can also live outside the target (see [`set_backup_root`]). A
+//! marker file in the target then records where they are.
+//!
+//! A patch with optional components saves only the components it was
+//! applied with (see [`applied_manifest`]), and each of them can be rolled
+//! back on its own (see [`forget_component`]).
+//!
+//! Older versions wrote a single flat `.patch-backup` without an index. Such
+//! a backup is treated as the restore point of whichever patch asks for it.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::discovery::expand_template;
+use crate::patch::backup::find_backup;
+use crate::patch::{PatchError, BACKUP_DIR, BACKUP_INDEX_FILENAME, BACKUP_MANIFEST_FILENAME, BACKUP_MARKER_FILENAME};
+use crate::utils::file_ops::remove_dir_all_robust;
+use crate::utils::hash::hash_bytes;
+use crate::utils::manifest::{Manifest, ManifestEntry};
+
+/// A patch that has been applied and can be rolled back.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RestorePoint {
+    /// Backup set directory name within `.patch-backup`
+    pub id: String,
+    pub name: String,
+    pub version: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// Unix timestamp (seconds) of when the patch was applied
+    pub created: u64,
+}
+
+impl RestorePoint {
+    pub fn for_manifest(manifest: &Manifest) -> Self {
+        let created = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        RestorePoint {
+            id: restore_point_id(manifest),
+            name: manifest.name.clone(),
+            version: manifest.version,
+            title: manifest.title.clone(),
+            created,
+        }
+    }
+
+    /// Display label, e.g. "MyPatcher v2".
+    pub fn label(&self) -> String {
+        format!("{} v{}", self.title.as_deref().unwrap_or(&self.name), self.version)
+    }
+}
+
+/// Restore points of a target directory, oldest first.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackupIndex {
+    /// Absolute path of the target the backups belong to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<PathBuf>,
+    pub restore_points: Vec<RestorePoint>,
+}
+
+impl BackupIndex {
+    /// Load the index of `target_dir`. A missing index is empty.
+    pub fn load(target_dir: &Path) -> io::Result<Self> {
+        Self::load_from(&backup_root(target_dir))
+    }
+
+    fn load_from(root: &Path) -> io::Result<Self> {
+        match fs::read(root.join(BACKUP_INDEX_FILENAME)) {
+            Ok(data) => serde_json::from_slice(&data).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(BackupIndex::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn save(&self, target_dir: &Path) -> io::Result<()> {
+        let root = backup_root(target_dir);
+        fs::create_dir_all(&root)?;
+        let content =
+            serde_json::to_string_pretty(self).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        fs::write(root.join(BACKUP_INDEX_FILENAME), content)
+    }
+
+    /// The most recently applied patch.
+    pub fn latest(&self) -> Option<&RestorePoint> {
+        self.restore_points.last()
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.restore_points.iter().any(|p| p.id == id)
+    }
+}
+
+/// Directory holding the backups of `target_dir`.
+///
+/// This is `.patch-backup` inside the target, unless the target's marker
+/// file points to an external location.
+pub fn backup_root(target_dir: &Path) -> PathBuf {
+    fs::read_to_string(target_dir.join(BACKUP_MARKER_FILENAME))
+        .ok()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| target_dir.join(BACKUP_DIR))
+}
+
+/// Returns true if the backups of `target_dir` are stored outside it.
+pub fn is_external_backup(target_dir: &Path) -> bool {
+    target_dir.join(BACKUP_MARKER_FILENAME).is_file()
+}
+
+/// Store the backups of `target_dir` in `root`.
+///
+/// An external `root` is recorded in a marker file in the target; choosing
+/// `.patch-backup` again removes the marker. Fails if the target already has
+/// restore points elsewhere, or if `root` holds another target's backups.
+pub fn set_backup_root(target_dir: &Path, root: &Path) -> io::Result<()> {
+    let root = std::path::absolute(root)?;
+    let current = std::path::absolute(backup_root(target_dir))?;
+
+    if current != root && has_backups(target_dir) {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("target already has restore points in {}", current.display()),
+        ));
+    }
+
+    let index = BackupIndex::load_from(&root)?;
+    if let Some(owner) = &index.target
+        && !index.restore_points.is_empty()
+        && *owner != std::path::absolute(target_dir)?
+    {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} holds the backups of {}", root.display(), owner.display()),
+        ));
+    }
+
+    link_backup_root(target_dir, &root)
+}
+
+/// Point the marker file of `target_dir` at `root`, without any checks.
+pub(crate) fn link_backup_root(target_dir: &Path, root: &Path) -> io::Result<()> {
+    let root = std::path::absolute(root)?;
+    let marker = target_dir.join(BACKUP_MARKER_FILENAME);
+    if root == std::path::absolute(target_dir.join(BACKUP_DIR))? {
+        if marker.exists() {
+            fs::remove_file(marker)?;
+        }
+        Ok(())
+    } else {
+        fs::write(marker, root.to_string_lossy().as_bytes())
+    }
+}
+
+/// The manifest's default backup location for `target_dir`, if it has one.
+///
+/// `~` and environment variables are expanded. A relative path is placed in
+/// the per-user data directory (e.g. `%LOCALAPPDATA%` or `~/.local/share`),
+/// with a subdirectory per target so that several installs can be patched.
+pub fn default_backup_root(manifest: &Manifest, target_dir: &Path) -> Option<PathBuf> {
+    default_backup_root_in(manifest, target_dir, dirs::data_local_dir())
+}
+
+fn default_backup_root_in(manifest: &Manifest, target_dir: &Path, data_dir: Option<PathBuf>) -> Option<PathBuf> {
+    let path = expand_template(manifest.backup_dir.as_deref()?)?;
+    if path.is_absolute() {
+        Some(path)
+    } else {
+        Some(data_dir?.join(path).join(target_key(target_dir)))
+    }
+}
+
+/// Short, stable directory name for a target, from its canonical path.
+fn target_key(target_dir: &Path) -> String {
+    let target = fs::canonicalize(target_dir)
+        .or_else(|_| std::path::absolute(target_dir))
+        .unwrap_or_else(|_| target_dir.to_path_buf());
+    hash_bytes(target.to_string_lossy().as_bytes())[..16].to_string()
+}
+
+/// Pick where `manifest`'s backups go before applying it to `target_dir`.
+///
+/// `requested` (e.g. `--backup-dir`) wins. Otherwise a target without restore
+/// points uses the manifest default, and one with restore points keeps
+/// its current location, so stacked patches share a single index.
+pub fn prepare_backup_root(target_dir: &Path, requested: Option<&Path>, manifest: &Manifest) -> io::Result<PathBuf> {
+    if let Some(root) = requested {
+        set_backup_root(target_dir, root)?;
+    } else if !has_backups(target_dir)
+        && let Some(root) = default_backup_root(manifest, target_dir)
+    {
+        set_backup_root(target_dir, &root)?;
+    }
+    Ok(backup_root(target_dir))
+}
+
+/// Backup set name for a manifest: `<name>-v<version>`.
+///
+/// Characters that are awkward in directory names are replaced with `_`.
+pub fn restore_point_id(manifest: &Manifest) -> String {
+    let name: String = manifest
+        .name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.' { c } else { '_' })
+        .collect();
+    format!("{}-v{}", name, manifest.version)
+}
+
+/// Directory holding the backup set with the given id.
+pub fn backup_set_dir(target_dir: &Path, id: &str) -> PathBuf {
+    backup_root(target_dir).join(id)
+}
+
+/// Returns true if `target_dir` has a flat backup from before restore points.
+pub fn is_legacy_backup(target_dir: &Path) -> bool {
+    let backup_dir = target_dir.join(BACKUP_DIR);
+    if is_external_backup(target_dir) || backup_dir.join(BACKUP_INDEX_FILENAME).exists() {
+        return false;
+    }
+    fs::read_dir(&backup_dir)
+        .map(|entries| entries.flatten().any(|e| e.file_type().is_ok_and(|t| t.is_file())))
+        .unwrap_or(false)
+}
+
+/// List the restore points of `target_dir`, oldest first.
+pub fn list_restore_points(target_dir: &Path) -> io::Result<Vec<RestorePoint>> {
+    Ok(BackupIndex::load(target_dir)?.restore_points)
+}
+
+/// Returns true if `target_dir` has any restore point.
+pub fn has_backups(target_dir: &Path) -> bool {
+    is_legacy_backup(target_dir) || BackupIndex::load(target_dir).is_ok_and(|index| !index.restore_points.is_empty())
+}
+
+/// Returns true if `manifest` has a restore point in `target_dir`.
+pub fn has_restore_point(target_dir: &Path, manifest: &Manifest) -> bool {
+    is_legacy_backup(target_dir)
+        || BackupIndex::load(target_dir).is_ok_and(|index| index.contains(&restore_point_id(manifest)))
+}
+
+/// Record `manifest` as the latest restore point, before its files are backed up.
+///
+/// Creates the backup set and saves the manifest into it. Fails with
+/// `AlreadyExists` if the restore point or its backup set already exists, so
+/// the backups of an earlier apply are never overwritten.
+pub fn record_restore_point(target_dir: &Path, manifest: &Manifest) -> io::Result<()> {
+    let mut index = BackupIndex::load(target_dir)?;
+    let point = RestorePoint::for_manifest(manifest);
+
+    let set_dir = backup_set_dir(target_dir, &point.id);
+    if index.contains(&point.id) {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} is already applied; roll it back first", point.id),
+        ));
+    }
+    if set_dir.exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("a leftover backup of {} is in {}; remove it first", point.id, set_dir.display()),
+        ));
+    }
+    fs::create_dir_all(&set_dir)?;
+    manifest.save(&set_dir.join(BACKUP_MANIFEST_FILENAME))?;
+
+    if index.restore_points.is_empty() {
+        index.target = Some(std::path::absolute(target_dir)?);
+    }
+    index.restore_points.push(point);
+    index.save(target_dir)?;
+    Ok(())
+}
+
+/// Load the manifest saved in the backup set `id`, if it has one.
+pub fn load_backup_manifest(target_dir: &Path, id: &str) -> io::Result<Option<Manifest>> {
+    match Manifest::load(&backup_set_dir(target_dir, id).join(BACKUP_MANIFEST_FILENAME)) {
+        Ok(manifest) => Ok(Some(manifest)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Load the manifest of the most recently applied patch from its backup.
+///
+/// This is the patch to roll back next. Fails for backups written before
+/// manifests were saved with them.
+pub fn latest_backup_manifest(target_dir: &Path) -> Result<Manifest, PatchError> {
+    let no_manifest = |reason: String| PatchError::RollbackFailed {
+        reason: format!("{}; pass the patch's manifest.json instead", reason),
+    };
+    if is_legacy_backup(target_dir) {
+        return Err(no_manifest("backup was made by an older version without a manifest".to_string()));
+    }
+
+    let index = BackupIndex::load(target_dir).map_err(|e| PatchError::RollbackFailed {
+        reason: format!("failed to read backup index: {}", e),
+    })?;
+    let Some(latest) = index.latest() else {
+        return Err(PatchError::RollbackFailed {
+            reason: format!("no restore points in {}", backup_root(target_dir).display()),
+        });
+    };
+    load_backup_manifest(target_dir, &latest.id)
+        .map_err(|e| PatchError::ManifestError { reason: e.to_string() })?
+        .ok_or_else(|| no_manifest(format!("backup of {} has no manifest", latest.label())))
+}
+
+/// The part of `manifest` that was applied to `target_dir`.
+///
+/// A patch with components is applied with only some of them, and the
+/// manifest saved in its backup set has just those. Other patches, and
+/// patches without a restore point, are returned as they are.
+pub fn applied_manifest(target_dir: &Path, manifest: &Manifest) -> Manifest {
+    if manifest.components.is_empty() {
+        return manifest.clone();
+    }
+    load_backup_manifest(target_dir, &restore_point_id(manifest))
+        .ok()
+        .flatten()
+        .unwrap_or_else(|| manifest.clone())
+}
+
+/// Drop the component `id` from the restore point of `manifest`, the
+/// manifest saved with it, once the component's files are rolled back.
+///
+/// The saved manifest loses the component and its entries, and their backups
+/// are deleted, so the rest of the patch can still be rolled back.
+pub fn forget_component(target_dir: &Path, manifest: &Manifest, id: &str) -> io::Result<()> {
+    let set_dir = backup_set_dir(target_dir, &restore_point_id(manifest));
+    let remaining = manifest.without_component(id)?;
+    for entry in manifest.component_entries(id) {
+        // Added and required files have no backup of their own
+        if matches!(entry, ManifestEntry::Add { .. } | ManifestEntry::Require { .. }) {
+            continue;
+        }
+        if let Some((_, path)) = find_backup(&set_dir, entry.file()) {
+            fs::remove_file(path)?;
+        }
+    }
+    remaining.save(&set_dir.join(BACKUP_MANIFEST_FILENAME))
+}
+
+/// Remove the restore point for `id` from the index, keeping its files.
+pub fn forget_restore_point(target_dir: &Path, id: &str) -> io::Result<()> {
+    let mut index = BackupIndex::load(target_dir)?;
+    let before = index.restore_points.len();
+    index.restore_points.retain(|p| p.id != id);
+    if index.restore_points.len() != before {
+        index.save(target_dir)?;
+    }
+    Ok(())
+}
+
+/// Delete the backup set for `id`.
+///
+/// The backup directory itself (and the target's marker file) is removed
+/// once no restore points or sets remain.
+pub fn delete_backup_set(target_dir: &Path, id: &str) -> io::Result<()> {
+    let backup_dir = backup_root(target_dir);
+    if is_legacy_backup(target_dir) {
+        return remove_dir_all_robust(&backup_dir);
+    }
+
+    let set_dir = backup_set_dir(target_dir, id);
+    if set_dir.exists() {
+        remove_dir_all_robust(&set_dir)?;
+    }
+
+    let index = BackupIndex::load(target_dir)?;
+    let only_index = fs::read_dir(&backup_dir)
+        .map(|entries| entries.flatten().all(|e| e.file_name() == BACKUP_INDEX_FILENAME))
+        .unwrap_or(false);
+    if index.restore_points.is_empty() && only_index {
+        remove_dir_all_robust(&backup_dir)?;
+        let marker = target_dir.join(BACKUP_MARKER_FILENAME);
+        if marker.exists() {
+            fs::remove_file(marker)?;
+        }
+    }
+    Ok(())
+}
+
+/// Find the backup set to roll `manifest` back from.
+///
+/// Fails if the patch has no restore point, or if other patches were applied
+/// on top of it and have to be rolled back first.
+pub fn rollback_set_dir(target_dir: &Path, manifest: &Manifest) -> Result<PathBuf, PatchError> {
+    if is_legacy_backup(target_dir) {
+        return Ok(target_dir.join(BACKUP_DIR));
+    }
+
+    let index = BackupIndex::load(target_dir).map_err(|e| PatchError::RollbackFailed {
+        reason: format!("failed to read backup index: {}", e),
+    })?;
+    let id = restore_point_id(manifest);
+
+    if !index.contains(&id) {
+        return Err(PatchError::RollbackFailed {
+            reason: format!("no restore point for {} in {}", id, backup_root(target_dir).display()),
+        });
+    }
+    if let Some(latest) = index.latest()
+        && latest.id != id
+    {
+        return Err(PatchError::RollbackFailed {
+            reason: format!("{} was applied after this patch; roll it back first", latest.label()),
+        });
+    }
+
+    Ok(backup_set_dir(target_dir, &id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn manifest(name: &str, version: u32) -> Manifest {
+        Manifest::new(version, name.to_string(), None)
+    }
+
+    #[test]
+    fn id_uses_name_and_version() {
+        assert_eq!(restore_point_id(&manifest("MyPatcher", 3)), "MyPatcher-v3");
+        assert_eq!(restore_point_id(&manifest("My Game/DLC", 1)), "My_Game_DLC-v1");
+    }
+
+    #[test]
+    fn forgetting_a_component_keeps_other_backups() {
+        let target = tempdir().unwrap();
+        let patch = |file: &str| ManifestEntry::Patch {
+            file: file.to_string(),
+            original_hash: String::new(),
+            diff_hash: String::new(),
+            final_hash: String::new(),
+            alternatives: Vec::new(),
+            fallback: false,
+        };
+        let mut m = manifest("Base", 1);
+        m.entries = vec![
+            patch("data.bin"),
+            ManifestEntry::Add { file: "hd/data.bin".to_string(), final_hash: String::new() },
+            patch("hd/tex.bin"),
+        ];
+        m.components = vec![crate::utils::manifest::Component {
+            id: "hd".to_string(),
+            name: "HD".to_string(),
+            description: None,
+            default: false,
+            requires: Vec::new(),
+            files: vec!["hd/data.bin".to_string(), "hd/tex.bin".to_string()],
+        }];
+        record_restore_point(target.path(), &m).unwrap();
+        let set_dir = backup_set_dir(target.path(), "Base-v1");
+        fs::create_dir_all(set_dir.join("hd")).unwrap();
+        fs::write(set_dir.join("data.bin"), b"base").unwrap();
+        fs::write(set_dir.join("hd/tex.bin"), b"tex").unwrap();
+
+        forget_component(target.path(), &m, "hd").unwrap();
+        assert!(set_dir.join("data.bin").exists());
+        assert!(!set_dir.join("hd/tex.bin").exists());
+        assert_eq!(applied_manifest(target.path(), &m).entries, vec![patch("data.bin")]);
+    }
+
+    #[test]
+    fn records_in_apply_order() {
+        let target = tempdir().unwrap();
+        record_restore_point(target.path(), &manifest("Base", 1)).unwrap();
+        record_restore_point(target.path(), &manifest("Base", 2)).unwrap();
+        let result = record_restore_point(target.path(), &manifest("Base", 1));
+        assert!(matches!(result, Err(e) if e.kind() == io::ErrorKind::AlreadyExists));
+
+        let ids: Vec<_> = list_restore_points(target.path())
+            .unwrap()
+            .into_iter()
+            .map(|p| p.id)
+            .collect();
+        assert_eq!(ids, ["Base-v1", "Base-v2"]);
+    }
+
+    #[test]
+    fn rollback_is_last_in_first_out() {
+        let target = tempdir().unwrap();
+        let (first, second) = (manifest("Base", 1), manifest("Base", 2));
+        record_restore_point(target.path(), &first).unwrap();
+        record_restore_point(target.path(), &second).unwrap();
+
+        assert!(matches!(
+            rollback_set_dir(target.path(), &first),
+            Err(PatchError::RollbackFailed { reason }) if reason.contains("Base v2")
+        ));
+        assert_eq!(
+            rollback_set_dir(target.path(), &second).unwrap(),
+            backup_set_dir(target.path(), "Base-v2")
+        );
+
+        assert_eq!(latest_backup_manifest(target.path()).unwrap(), second);
+
+        forget_restore_point(target.path(), "Base-v2").unwrap();
+        assert!(rollback_set_dir(target.path(), &first).is_ok());
+        assert_eq!(latest_backup_manifest(target.path()).unwrap(), first);
+        assert!(rollback_set_dir(target.path(), &manifest("Other", 1)).is_err());
+    }
+
+    #[test]
+    fn deleting_last_set_removes_backup_dir() {
+        let target = tempdir().unwrap();
+        let m = manifest("Base", 1);
+        record_restore_point(target.path(), &m).unwrap();
+        fs::write(backup_set_dir(target.path(), "Base-v1").join("a.bin"), b"a").unwrap();
+
+        forget_restore_point(target.path(), "Base-v1").unwrap();
+        delete_backup_set(target.path(), "Base-v1").unwrap();
+
+        assert!(!target.path().join(BACKUP_DIR).exists());
+    }
+
+    #[test]
+    fn refuses_to_reuse_an_existing_backup_set() {
+        let target = tempdir().unwrap();
+        let m = manifest("Base", 1);
+        let set_dir = backup_set_dir(target.path(), "Base-v1");
+        fs::create_dir_all(&set_dir).unwrap();
+        fs::write(set_dir.join("a.bin"), b"earlier backup").unwrap();
+
+        let result = record_restore_point(target.path(), &m);
+        assert!(matches!(result, Err(e) if e.kind() == io::ErrorKind::AlreadyExists));
+        assert!(!has_restore_point(target.path(), &m));
+        assert_eq!(fs::read(set_dir.join("a.bin")).unwrap(), b"earlier backup");
+    }
+
+    #[test]
+    fn flat_backup_is_legacy_restore_point() {
+        let target = tempdir().unwrap();
+        fs::create_dir(target.path().join(BACKUP_DIR)).unwrap();
+        fs::write(target.path().join(BACKUP_DIR).join("a.bin"), b"a").unwrap();
+
+        let m = manifest("Old", 1);
+        assert!(has_restore_point(target.path(), &m));
+        assert_eq!(rollback_set_dir(target.path(), &m).unwrap(), target.path().join(BACKUP_DIR));
+    }
+
+    #[test]
+    fn external_root_is_linked_by_marker() {
+        let target = tempdir().unwrap();
+        let external = tempdir().unwrap();
+        let root = external.path().join("backups");
+        let m = manifest("Base", 1);
+
+        assert_eq!(prepare_backup_root(target.path(), Some(&root), &m).unwrap(), root);
+        assert!(is_external_backup(target.path()));
+        record_restore_point(target.path(), &m).unwrap();
+        assert_eq!(backup_set_dir(target.path(), "Base-v1"), root.join("Base-v1"));
+        assert!(root.join(BACKUP_INDEX_FILENAME).exists());
+        assert!(!target.path().join(BACKUP_DIR).exists());
+
+        // Stacked patches keep using the same location
+        assert!(set_backup_root(target.path(), &target.path().join(BACKUP_DIR)).is_err());
+        let other = tempdir().unwrap();
+        assert!(set_backup_root(other.path(), &root).is_err());
+
+        forget_restore_point(target.path(), "Base-v1").unwrap();
+        delete_backup_set(target.path(), "Base-v1").unwrap();
+        assert!(!root.exists());
+        assert!(!target.path().join(BACKUP_MARKER_FILENAME).exists());
+    }
+
+    #[test]
+    fn manifest_default_is_relative_to_user_data() {
+        let target = tempdir().unwrap();
+        let mut m = manifest("Base", 1);
+        assert_eq!(default_backup_root(&m, target.path()), None);
+
+        m.backup_dir = Some("MyGame/backups".to_string());
+        if let Some(data) = dirs::data_local_dir() {
+            let root = default_backup_root(&m, target.path()).unwrap();
+            assert_eq!(root.parent(), Some(data.join("MyGame/backups").as_path()));
+        }
+    }
+
+    #[test]
+    fn relative_default_keeps_targets_apart() {
+        let data = tempdir().unwrap();
+        let (first, second) = (tempdir().unwrap(), tempdir().unwrap());
+        let mut m = manifest("Base", 1);
+        m.backup_dir = Some("MyGame/backups".to_string());
+
+        let mut roots = Vec::new();
+        for target in [first.path(), second.path()] {
+            let root = default_backup_root_in(&m, target, Some(data.path().to_path_buf())).unwrap();
+            set_backup_root(target, &root).unwrap();
+            record_restore_point(target, &m).unwrap();
+            assert_eq!(backup_root(target), root);
+            roots.push(root);
+        }
+        assert_ne!(roots[0], roots[1]);
+        assert_eq!(default_backup_root_in(&m, first.path(), Some(data.path().to_path_buf())).unwrap(), roots[0]);
+        assert!(has_restore_point(first.path(), &m) && has_restore_point(second.path(), &m));
+    }
+}
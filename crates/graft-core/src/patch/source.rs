@@ -0,0 +1,425 @@
+//! Read-only access to patch payloads (manifest, diffs, new files).
+//!
+//! Patch operations read their payloads through the [`PatchSource`] trait
+//! instead of assuming an extracted patch directory on disk. This lets the
+//! patcher stub apply a patch directly from memory or from a region of its
+//! own executable, without first unpacking everything to a temp directory.
+//!
+//! Available sources:
+//! - [`Path`]: an extracted patch directory
+//! - [`TarSource`]: compressed tar archive bytes held in memory
+//! - [`IndexedArchiveSource`](crate::indexed_archive::IndexedArchiveSource):
+//!   an indexed container, in memory or read in place from a file
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use tar::Archive;
+
+use crate::archive::{self, ArchiveCodec};
+use crate::patch::constants::{DIFFS_DIR, DIFF_EXTENSION, FILES_DIR, MANIFEST_FILENAME};
+use crate::patch::error::PatchError;
+use crate::utils::file_ops;
+use crate::utils::manifest::Manifest;
+
+/// Relative payload path of the diff for `file`.
+pub fn diff_payload_path(file: &str) -> String {
+    format!("{}/{}{}", DIFFS_DIR, file, DIFF_EXTENSION)
+}
+
+/// Relative payload path of the diff for `file` from one of its alternative
+/// originals (see [`crate::utils::manifest::PatchOrigin`]).
+pub fn alternative_diff_payload_path(file: &str, original_hash: &str) -> String {
+    format!("{}/{}.{}{}", DIFFS_DIR, file, original_hash, DIFF_EXTENSION)
+}
+
+/// Relative payload path of the new file content for `file`.
+pub fn file_payload_path(file: &str) -> String {
+    format!("{}/{}", FILES_DIR, file)
+}
+
+/// Read-only access to the payloads of a patch.
+///
+/// Paths are relative to the patch root and always use `/` as separator
+/// (e.g. `manifest.json`, `diffs/game.bin.diff`, `files/new.bin`).
+pub trait PatchSource {
+    /// Read the full contents of a payload.
+    fn read(&self, path: &str) -> io::Result<Vec<u8>>;
+
+    /// Check whether a payload exists.
+    fn contains(&self, path: &str) -> bool;
+
+    /// Size of a payload in bytes.
+    ///
+    /// The default reads the payload; sources with an index override it.
+    fn size(&self, path: &str) -> io::Result<u64> {
+        self.read(path).map(|data| data.len() as u64)
+    }
+
+    /// Check whether a payload was packed from an executable file.
+    ///
+    /// The default reports false; sources that record permissions override it.
+    fn is_executable(&self, _path: &str) -> bool {
+        false
+    }
+
+    /// List all payload paths, sorted.
+    fn payload_paths(&self) -> io::Result<Vec<String>>;
+
+    /// Load and parse the manifest.
+    fn read_manifest(&self) -> Result<Manifest, PatchError> {
+        if !self.contains(MANIFEST_FILENAME) {
+            return Err(PatchError::ManifestNotFound);
+        }
+        let data = self.read(MANIFEST_FILENAME).map_err(|e| PatchError::ManifestError {
+            reason: e.to_string(),
+        })?;
+        Manifest::from_slice(&data).map_err(|e| PatchError::ManifestError {
+            reason: e.to_string(),
+        })
+    }
+
+    /// Check whether the diff for `file` exists.
+    fn has_diff(&self, file: &str) -> bool {
+        self.contains(&diff_payload_path(file))
+    }
+
+    /// Read the diff for `file`.
+    fn read_diff(&self, file: &str) -> io::Result<Vec<u8>> {
+        self.read(&diff_payload_path(file))
+    }
+
+    /// Read the diff for `file` from the alternative original with `original_hash`.
+    fn read_alternative_diff(&self, file: &str, original_hash: &str) -> io::Result<Vec<u8>> {
+        self.read(&alternative_diff_payload_path(file, original_hash))
+    }
+
+    /// Check whether the new file content for `file` exists.
+    fn has_file(&self, file: &str) -> bool {
+        self.contains(&file_payload_path(file))
+    }
+
+    /// Read the new file content for `file`.
+    fn read_file(&self, file: &str) -> io::Result<Vec<u8>> {
+        self.read(&file_payload_path(file))
+    }
+}
+
+/// An extracted patch directory.
+impl PatchSource for Path {
+    fn read(&self, path: &str) -> io::Result<Vec<u8>> {
+        fs::read(self.join(path))
+    }
+
+    fn contains(&self, path: &str) -> bool {
+        self.join(path).exists()
+    }
+
+    fn size(&self, path: &str) -> io::Result<u64> {
+        fs::metadata(self.join(path)).map(|m| m.len())
+    }
+
+    fn is_executable(&self, path: &str) -> bool {
+        fs::metadata(self.join(path)).is_ok_and(|m| file_ops::is_executable(&m))
+    }
+
+    fn payload_paths(&self) -> io::Result<Vec<String>> {
+        Ok(archive::collect_patch_files(self)?
+            .into_iter()
+            .filter(|(_, file_path)| file_path.exists())
+            .map(|(path, _)| path)
+            .collect())
+    }
+}
+
+impl PatchSource for PathBuf {
+    fn read(&self, path: &str) -> io::Result<Vec<u8>> {
+        self.as_path().read(path)
+    }
+
+    fn contains(&self, path: &str) -> bool {
+        self.as_path().contains(path)
+    }
+
+    fn size(&self, path: &str) -> io::Result<u64> {
+        self.as_path().size(path)
+    }
+
+    fn is_executable(&self, path: &str) -> bool {
+        self.as_path().is_executable(path)
+    }
+
+    fn payload_paths(&self) -> io::Result<Vec<String>> {
+        self.as_path().payload_paths()
+    }
+}
+
+/// Normalize an archive entry path to the `/`-separated form used by sources.
+fn normalize_entry_path(path: &Path) -> String {
+    let path = path.to_string_lossy().replace('\\', "/");
+    path.trim_start_matches("./").to_string()
+}
+
+/// A compressed tar patch archive (gzip, zstd or xz) held in memory.
+///
+/// Payloads are decompressed on demand, so memory use stays at roughly the
+/// compressed size. Decompressors are kept between reads and resumed where
+/// they stopped, so reading each payload directory in archive order (as
+/// applying a manifest sorted by file does for `diffs/` and `files/`) costs
+/// a single pass over the archive. Reading an earlier payload restarts from
+/// the beginning; for random access to large patches prefer the indexed
+/// container.
+pub struct TarSource {
+    data: Arc<[u8]>,
+    /// Length of the compressed stream (excluding the codec trailer)
+    stream_len: usize,
+    codec: ArchiveCodec,
+    /// Location of each payload in the decompressed tar stream
+    entries: HashMap<String, TarEntry>,
+    /// Decompressors left where previous reads stopped, least recently used first
+    cursors: Mutex<Vec<TarCursor>>,
+}
+
+/// Number of decompressors a [`TarSource`] keeps for resuming reads.
+const MAX_TAR_CURSORS: usize = 3;
+
+/// Location of a payload in the decompressed tar stream.
+#[derive(Clone, Copy)]
+struct TarEntry {
+    offset: u64,
+    size: u64,
+    executable: bool,
+}
+
+/// Decompressed tar stream and the number of bytes already consumed from it.
+struct TarCursor {
+    reader: Box<dyn Read + Send>,
+    position: u64,
+}
+
+/// Compressed stream shared with the source, readable without borrowing it.
+struct SharedStream {
+    data: Arc<[u8]>,
+    position: usize,
+    end: usize,
+}
+
+impl Read for SharedStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = buf.len().min(self.end - self.position);
+        buf[..len].copy_from_slice(&self.data[self.position..self.position + len]);
+        self.position += len;
+        Ok(len)
+    }
+}
+
+impl TarSource {
+    /// Create a source from tar container bytes.
+    ///
+    /// Scans the archive once to index entry names, which also checks that
+    /// the archive can be fully decompressed.
+    pub fn new(data: impl Into<Arc<[u8]>>) -> io::Result<Self> {
+        let data = data.into();
+        let (codec, stream) = archive::split_codec(&data)?;
+        let stream_len = stream.len();
+        let mut entries = HashMap::new();
+
+        let mut archive = Archive::new(archive::decoder(codec, stream)?);
+        for entry in archive.entries()? {
+            let entry = entry?;
+            if entry.header().entry_type().is_file() {
+                entries.insert(
+                    normalize_entry_path(&entry.path()?),
+                    TarEntry {
+                        offset: entry.raw_file_position(),
+                        size: entry.size(),
+                        executable: entry.header().mode()? & 0o111 != 0,
+                    },
+                );
+            }
+        }
+        drop(archive);
+
+        Ok(TarSource {
+            data,
+            stream_len,
+            codec,
+            entries,
+            cursors: Mutex::new(Vec::new()),
+        })
+    }
+
+    fn entry(&self, path: &str) -> io::Result<TarEntry> {
+        self.entries.get(path).copied().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("'{}' not found in patch archive", path),
+            )
+        })
+    }
+
+    /// Start decompressing from the beginning of the archive.
+    fn rewind(&self) -> io::Result<TarCursor> {
+        let stream = SharedStream {
+            data: Arc::clone(&self.data),
+            position: 0,
+            end: self.stream_len,
+        };
+        Ok(TarCursor {
+            reader: archive::decoder(self.codec, stream)?,
+            position: 0,
+        })
+    }
+}
+
+impl Clone for TarSource {
+    fn clone(&self) -> Self {
+        TarSource {
+            data: Arc::clone(&self.data),
+            stream_len: self.stream_len,
+            codec: self.codec,
+            entries: self.entries.clone(),
+            cursors: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+impl PatchSource for TarSource {
+    fn read(&self, path: &str) -> io::Result<Vec<u8>> {
+        let entry = self.entry(path)?;
+
+        // Resume the decompressor closest before the entry, if any
+        let mut cursors = self.cursors.lock().unwrap_or_else(|e| e.into_inner());
+        let closest = cursors
+            .iter()
+            .enumerate()
+            .filter(|(_, cursor)| cursor.position <= entry.offset)
+            .max_by_key(|(_, cursor)| cursor.position)
+            .map(|(index, _)| index);
+        let mut cursor = match closest {
+            Some(index) => cursors.remove(index),
+            None => self.rewind()?,
+        };
+
+        let skip = entry.offset - cursor.position;
+        if io::copy(&mut (&mut cursor.reader).take(skip), &mut io::sink())? != skip {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        let mut data = vec![0u8; entry.size as usize];
+        cursor.reader.read_exact(&mut data)?;
+        cursor.position = entry.offset + entry.size;
+
+        if cursors.len() == MAX_TAR_CURSORS {
+            cursors.remove(0);
+        }
+        cursors.push(cursor);
+        Ok(data)
+    }
+
+    fn contains(&self, path: &str) -> bool {
+        self.entries.contains_key(path)
+    }
+
+    fn size(&self, path: &str) -> io::Result<u64> {
+        self.entry(path).map(|entry| entry.size)
+    }
+
+    fn is_executable(&self, path: &str) -> bool {
+        self.entries.get(path).is_some_and(|entry| entry.executable)
+    }
+
+    fn payload_paths(&self) -> io::Result<Vec<String>> {
+        let mut paths: Vec<_> = self.entries.keys().cloned().collect();
+        paths.sort();
+        Ok(paths)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::archive::create_archive_bytes;
+    use crate::test_support::create_patch_dir;
+    use tempfile::tempdir;
+
+    #[test]
+    fn directory_source_reads_payloads() {
+        let dir = tempdir().unwrap();
+        create_patch_dir(dir.path());
+
+        let source = dir.path();
+        assert!(source.has_diff("game.bin"));
+        assert_eq!(source.read_diff("game.bin").unwrap(), vec![1u8; 300]);
+        assert_eq!(source.read_file("data/new.bin").unwrap(), b"new data");
+        assert_eq!(source.read_manifest().unwrap().name, "TestPatcher");
+    }
+
+    #[test]
+    fn directory_source_missing_manifest() {
+        let dir = tempdir().unwrap();
+
+        let result = dir.path().read_manifest();
+        assert!(matches!(result, Err(PatchError::ManifestNotFound)));
+    }
+
+    #[test]
+    fn tar_source_reads_payloads() {
+        let dir = tempdir().unwrap();
+        create_patch_dir(dir.path());
+        let data = create_archive_bytes(dir.path()).unwrap();
+
+        let source = TarSource::new(data).unwrap();
+        assert!(source.has_diff("game.bin"));
+        assert!(!source.has_diff("missing.bin"));
+        assert_eq!(source.read_diff("game.bin").unwrap(), vec![1u8; 300]);
+        assert_eq!(source.read_file("data/new.bin").unwrap(), b"new data");
+        assert_eq!(source.read_manifest().unwrap().name, "TestPatcher");
+        assert!(source.read_file("missing.bin").is_err());
+    }
+
+    #[test]
+    fn tar_source_reads_payloads_in_any_order() {
+        let dir = tempdir().unwrap();
+        create_patch_dir(dir.path());
+        let data = create_archive_bytes(dir.path()).unwrap();
+
+        let source = TarSource::new(data).unwrap();
+        for _ in 0..2 {
+            assert_eq!(source.read_manifest().unwrap().name, "TestPatcher");
+            assert_eq!(source.read_file("data/new.bin").unwrap(), b"new data");
+            assert_eq!(source.read_diff("game.bin").unwrap(), vec![1u8; 300]);
+        }
+        assert_eq!(source.clone().read_diff("game.bin").unwrap(), vec![1u8; 300]);
+    }
+
+    #[test]
+    fn sources_list_same_payloads() {
+        let dir = tempdir().unwrap();
+        create_patch_dir(dir.path());
+        let data = create_archive_bytes(dir.path()).unwrap();
+
+        let expected = vec![
+            ".graft_assets/icon.png".to_string(),
+            "diffs/game.bin.diff".to_string(),
+            "files/data/new.bin".to_string(),
+            "manifest.json".to_string(),
+        ];
+        assert_eq!(dir.path().payload_paths().unwrap(), expected);
+        let tar = TarSource::new(data).unwrap();
+        assert_eq!(tar.payload_paths().unwrap(), expected);
+
+        let diff_path = diff_payload_path("game.bin");
+        assert_eq!(dir.path().size(&diff_path).unwrap(), 300);
+        assert_eq!(tar.size(&diff_path).unwrap(), 300);
+        assert!(tar.size("missing.bin").is_err());
+    }
+
+    #[test]
+    fn tar_source_rejects_invalid_data() {
+        let result = TarSource::new(&b"not an archive"[..]);
+        assert!(result.is_err());
+    }
+}
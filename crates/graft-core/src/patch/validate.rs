@@ -1,51 +1,67 @@
-use crate::patch::constants::{DIFFS_DIR, DIFF_EXTENSION, FILES_DIR, MANIFEST_FILENAME};
+use crate::patch::backup::{find_backup, read_backup, BackupFormat};
+use crate::patch::cancel::{self, CancelToken};
+use crate::patch::edit::edited;
 use crate::patch::error::PatchError;
+use crate::patch::progress::{file_size, Reporter};
+use crate::patch::source::{alternative_diff_payload_path, PatchSource};
 use crate::patch::verify::verify_entry;
 use crate::patch::{Progress, ProgressAction};
 use crate::path_restrictions;
-use crate::utils::hash::hash_bytes;
+use crate::utils::file_ops::ProgressReader;
+use crate::utils::hash::{hash_bytes, hash_reader};
 use crate::utils::manifest::{Manifest, ManifestEntry};
-use std::fs;
+use std::fs::File;
 use std::path::Path;
 
-/// Validate that a patch directory contains all required files.
+/// Validate that a patch contains all required files.
+///
+/// `source` is usually an extracted patch directory, but any [`PatchSource`]
+/// (e.g. an in-memory archive) can be validated the same way.
 ///
 /// Checks that:
 /// - manifest.json exists and is valid
-/// - All diff files referenced by Patch entries exist
+/// - All diff files referenced by Patch entries exist, including those from
+///   alternative originals, and their new files if they have a fallback
 /// - All files referenced by Add entries exist
 ///
 /// Returns the loaded Manifest on success.
-pub fn validate_patch_dir(patch_dir: &Path) -> Result<Manifest, PatchError> {
-    // Check manifest exists
-    let manifest_path = patch_dir.join(MANIFEST_FILENAME);
-    if !manifest_path.exists() {
-        return Err(PatchError::ManifestNotFound);
-    }
-
+pub fn validate_patch_dir<S>(source: &S) -> Result<Manifest, PatchError>
+where
+    S: PatchSource + ?Sized,
+{
     // Load and parse manifest
-    let manifest = Manifest::load(&manifest_path).map_err(|e| PatchError::ManifestError {
-        reason: e.to_string(),
-    })?;
+    let manifest = source.read_manifest()?;
 
     // Check all referenced files exist
     for entry in &manifest.entries {
         match entry {
-            ManifestEntry::Patch { file, .. } => {
-                let diff_path = patch_dir
-                    .join(DIFFS_DIR)
-                    .join(format!("{}{}", file, DIFF_EXTENSION));
-                if !diff_path.exists() {
+            ManifestEntry::Patch {
+                file,
+                alternatives,
+                fallback,
+                ..
+            } => {
+                if !source.has_diff(file) {
                     return Err(PatchError::DiffNotFound(file.clone()));
                 }
+                for origin in alternatives {
+                    if !source.contains(&alternative_diff_payload_path(file, &origin.original_hash)) {
+                        return Err(PatchError::DiffNotFound(file.clone()));
+                    }
+                }
+                if *fallback && !source.has_file(file) {
+                    return Err(PatchError::FileNotFound(file.clone()));
+                }
             }
             ManifestEntry::Add { file, .. } => {
-                let file_path = patch_dir.join(FILES_DIR).join(file);
-                if !file_path.exists() {
+                if !source.has_file(file) {
                     return Err(PatchError::FileNotFound(file.clone()));
                 }
             }
-            ManifestEntry::Delete { .. } => {
+            ManifestEntry::Delete { .. }
+            | ManifestEntry::Require { .. }
+            | ManifestEntry::EditJson { .. }
+            | ManifestEntry::EditIni { .. } => {
                 // Nothing to check - file should exist in target, not in patch
             }
         }
@@ -57,67 +73,102 @@ pub fn validate_patch_dir(patch_dir: &Path) -> Result<Manifest, PatchError> {
 /// Validate all manifest entries against a target directory before applying.
 ///
 /// Checks that:
-/// - For Patch entries: file exists and hash matches original_hash
+/// - For Patch entries: file exists and hash matches original_hash or one of
+///   the alternative originals, unless the entry has a fallback
 /// - For Add entries: file does NOT already exist
 /// - For Delete entries: if file exists, hash matches original_hash
+/// - For Require entries: file exists and hash matches
+/// - For EditJson/EditIni entries: file exists and the edit can be made
 ///
 /// This should be called before applying any changes to ensure the target
-/// directory is in the expected state.
+/// directory is in the expected state. Stops with [`PatchError::Cancelled`]
+/// once `cancel` is set.
 pub fn validate_entries<F>(
     entries: &[ManifestEntry],
     target_dir: &Path,
-    mut on_progress: Option<F>,
+    cancel: Option<&CancelToken>,
+    on_progress: Option<F>,
 ) -> Result<(), PatchError>
 where
     F: FnMut(Progress),
 {
-    let total = entries.len();
+    // Every file that exists is hashed, except those to be added or edited
+    let hashed_size = |entry: &ManifestEntry| match entry {
+        ManifestEntry::Add { .. } | ManifestEntry::EditJson { .. } | ManifestEntry::EditIni { .. } => 0,
+        _ => file_size(&target_dir.join(entry.file())),
+    };
+    let overall_bytes = entries.iter().map(hashed_size).sum();
+    let mut reporter = Reporter::new(on_progress, entries.len(), overall_bytes);
+
     for (index, entry) in entries.iter().enumerate() {
+        cancel::check(cancel)?;
         let action = match entry {
             ManifestEntry::Patch { .. } => ProgressAction::Validating,
             ManifestEntry::Add { .. } => ProgressAction::CheckingNotExists,
-            ManifestEntry::Delete { .. } => ProgressAction::Validating,
+            ManifestEntry::Delete { .. }
+            | ManifestEntry::Require { .. }
+            | ManifestEntry::EditJson { .. }
+            | ManifestEntry::EditIni { .. } => ProgressAction::Validating,
         };
+        reporter.start(entry.file(), index, action, hashed_size(entry));
 
-        if let Some(ref mut callback) = on_progress {
-            callback(Progress {
-                file: entry.file(),
-                index,
-                total,
-                action,
-            });
-        }
         match entry {
-            ManifestEntry::Patch {
-                file,
-                original_hash,
-                ..
-            } => {
+            ManifestEntry::Patch { file, .. } | ManifestEntry::Require { file, .. } => {
                 let target_path = target_dir.join(file);
 
                 if !target_path.exists() {
+                    let reason = if matches!(entry, ManifestEntry::Require { .. }) {
+                        "required file not found in target"
+                    } else {
+                        "file not found in target"
+                    };
                     return Err(PatchError::ValidationFailed {
                         file: file.clone(),
-                        reason: "file not found in target".to_string(),
+                        reason: reason.to_string(),
                     });
                 }
 
-                let data = fs::read(&target_path).map_err(|e| PatchError::ValidationFailed {
+                let actual_hash = hash_file(&target_path, &mut reporter).map_err(|e| PatchError::ValidationFailed {
                     file: file.clone(),
                     reason: format!("failed to read file: {}", e),
                 })?;
-
-                let actual_hash = hash_bytes(&data);
-                if &actual_hash != original_hash {
+                let expected = match entry {
+                    ManifestEntry::Require { hash, .. } => vec![hash.as_str()],
+                    _ => original_hashes(entry),
+                };
+                // A file with a fallback is replaced instead of patched
+                let fallback = matches!(entry, ManifestEntry::Patch { fallback: true, .. });
+                if !expected.contains(&actual_hash.as_str()) && !fallback {
                     return Err(PatchError::ValidationFailed {
                         file: file.clone(),
                         reason: format!(
                             "hash mismatch: expected {}, got {}",
-                            original_hash, actual_hash
+                            expected.join(" or "),
+                            actual_hash
                         ),
                     });
                 }
             }
+            ManifestEntry::EditJson { file, .. } | ManifestEntry::EditIni { file, .. } => {
+                let target_path = target_dir.join(file);
+
+                if !target_path.exists() {
+                    return Err(PatchError::ValidationFailed {
+                        file: file.clone(),
+                        reason: "file not found in target".to_string(),
+                    });
+                }
+
+                // Try the edit in memory, so a file that can't be parsed fails before anything changes
+                let data = std::fs::read(&target_path).map_err(|e| PatchError::ValidationFailed {
+                    file: file.clone(),
+                    reason: format!("failed to read file: {}", e),
+                })?;
+                edited(entry, &data).map_err(|e| PatchError::ValidationFailed {
+                    file: file.clone(),
+                    reason: e.to_string(),
+                })?;
+            }
             ManifestEntry::Add { file, .. } => {
                 let target_path = target_dir.join(file);
 
@@ -133,12 +184,11 @@ where
 
                 // Only validate hash if file exists - already gone is fine
                 if target_path.exists() {
-                    let data = fs::read(&target_path).map_err(|e| PatchError::ValidationFailed {
-                        file: file.clone(),
-                        reason: format!("failed to read file: {}", e),
-                    })?;
-
-                    let actual_hash = hash_bytes(&data);
+                    let actual_hash =
+                        hash_file(&target_path, &mut reporter).map_err(|e| PatchError::ValidationFailed {
+                            file: file.clone(),
+                            reason: format!("failed to read file: {}", e),
+                        })?;
                     if &actual_hash != original_hash {
                         return Err(PatchError::ValidationFailed {
                             file: file.clone(),
@@ -156,83 +206,91 @@ where
     Ok(())
 }
 
+/// Hashes of the originals a Patch entry accepts.
+fn original_hashes(entry: &ManifestEntry) -> Vec<&str> {
+    entry.patch_origins().into_iter().map(|(original_hash, _)| original_hash).collect()
+}
+
+/// Hash a target file in chunks, reporting the bytes read.
+fn hash_file<F: FnMut(Progress)>(path: &Path, reporter: &mut Reporter<F>) -> std::io::Result<String> {
+    hash_reader(ProgressReader::new(File::open(path)?, |done| reporter.advance(done)))
+}
+
 /// Validate that backup directory contains expected files with correct hashes.
 ///
 /// This should be called before rolling back to ensure the backup is intact.
+/// Compressed and reverse diff backups are decoded first; a reverse diff is
+/// applied to the patched file in `target_dir`, so it only validates while that
+/// file is unmodified.
 ///
 /// Checks that:
 /// - For Patch entries: backup file MUST exist with hash matching original_hash
+///   or an alternative original (any hash if the entry has a fallback, since
+///   the target may not have matched)
 /// - For Delete entries: if backup exists, hash MUST match original_hash (missing OK)
+/// - For EditJson/EditIni entries: backup file MUST exist (no hash to check)
 /// - For Add entries: no backup expected
 pub fn validate_backup<F>(
     entries: &[ManifestEntry],
+    target_dir: &Path,
     backup_dir: &Path,
-    mut on_progress: Option<F>,
+    on_progress: Option<F>,
 ) -> Result<(), PatchError>
 where
     F: FnMut(Progress),
 {
-    let total = entries.len();
+    let mut reporter = Reporter::new(on_progress, entries.len(), 0);
     for (index, entry) in entries.iter().enumerate() {
         let action = match entry {
-            ManifestEntry::Patch { .. } => ProgressAction::Validating,
-            ManifestEntry::Add { .. } => ProgressAction::Skipping,
+            ManifestEntry::Patch { .. } | ManifestEntry::EditJson { .. } | ManifestEntry::EditIni { .. } => {
+                ProgressAction::Validating
+            }
+            ManifestEntry::Add { .. } | ManifestEntry::Require { .. } => ProgressAction::Skipping,
             ManifestEntry::Delete { .. } => ProgressAction::Validating,
         };
-
-        if let Some(ref mut callback) = on_progress {
-            callback(Progress {
-                file: entry.file(),
-                index,
-                total,
-                action,
-            });
-        }
-        match entry {
-            ManifestEntry::Patch {
-                file,
-                original_hash,
-                ..
-            } => {
-                let backup_path = backup_dir.join(file);
-                if !backup_path.exists() {
-                    return Err(PatchError::RollbackFailed {
-                        reason: format!("backup file not found: {}", file),
-                    });
-                }
-                let data = fs::read(&backup_path).map_err(|e| PatchError::RollbackFailed {
-                    reason: format!("failed to read backup '{}': {}", file, e),
-                })?;
-                let actual_hash = hash_bytes(&data);
-                if &actual_hash != original_hash {
-                    return Err(PatchError::RollbackFailed {
-                        reason: format!(
-                            "backup hash mismatch for '{}': expected {}, got {}",
-                            file, original_hash, actual_hash
-                        ),
-                    });
-                }
+        reporter.start(entry.file(), index, action, 0);
+
+        let (file, expected, required) = match entry {
+            ManifestEntry::Patch { file, fallback, .. } => (file, (!fallback).then(|| original_hashes(entry)), true),
+            ManifestEntry::Delete { file, original_hash } => (file, Some(vec![original_hash.as_str()]), false),
+            // Edits don't record the original file's hash
+            ManifestEntry::EditJson { file, .. } | ManifestEntry::EditIni { file, .. } => (file, None, true),
+            ManifestEntry::Add { .. } | ManifestEntry::Require { .. } => {
+                // No backup for added or untouched files
+                continue;
             }
-            ManifestEntry::Delete { file, original_hash } => {
-                let backup_path = backup_dir.join(file);
-                if backup_path.exists() {
-                    let data = fs::read(&backup_path).map_err(|e| PatchError::RollbackFailed {
-                        reason: format!("failed to read backup '{}': {}", file, e),
-                    })?;
-                    let actual_hash = hash_bytes(&data);
-                    if &actual_hash != original_hash {
-                        return Err(PatchError::RollbackFailed {
-                            reason: format!(
-                                "backup hash mismatch for '{}': expected {}, got {}",
-                                file, original_hash, actual_hash
-                            ),
-                        });
-                    }
-                }
-            }
-            ManifestEntry::Add { .. } => {
-                // No backup for added files
+        };
+
+        let data = read_backup(backup_dir, file, &target_dir.join(file)).map_err(|e| PatchError::RollbackFailed {
+            reason: format!("failed to read backup '{}': {}", file, e),
+        })?;
+        let Some(data) = data else {
+            if required {
+                return Err(PatchError::RollbackFailed {
+                    reason: format!("backup file not found: {}", file),
+                });
             }
+            continue;
+        };
+
+        let Some(expected) = expected else {
+            continue;
+        };
+        let actual_hash = hash_bytes(&data);
+        if !expected.contains(&actual_hash.as_str()) {
+            let reason = match find_backup(backup_dir, file) {
+                Some((BackupFormat::ReverseDiff, _)) => format!(
+                    "backup of '{}' is a reverse diff and cannot restore it: the patched file has changed",
+                    file
+                ),
+                _ => format!(
+                    "backup hash mismatch for '{}': expected {}, got {}",
+                    file,
+                    expected.join(" or "),
+                    actual_hash
+                ),
+            };
+            return Err(PatchError::RollbackFailed { reason });
         }
     }
     Ok(())
@@ -250,22 +308,14 @@ where
 pub fn validate_patched_entries<F>(
     entries: &[ManifestEntry],
     target_dir: &Path,
-    mut on_progress: Option<F>,
+    on_progress: Option<F>,
 ) -> Result<(), PatchError>
 where
     F: FnMut(Progress),
 {
-    let total = entries.len();
+    let mut reporter = Reporter::new(on_progress, entries.len(), 0);
     for (index, entry) in entries.iter().enumerate() {
-        if let Some(ref mut callback) = on_progress {
-            callback(Progress {
-                file: entry.file(),
-                index,
-                total,
-                action: ProgressAction::Validating,
-            });
-        }
-
+        reporter.start(entry.file(), index, ProgressAction::Validating, 0);
         verify_entry(entry, target_dir)?;
     }
     Ok(())
@@ -372,4 +422,46 @@ mod tests {
         let manifest = result.unwrap();
         assert_eq!(manifest.entries.len(), 3);
     }
+
+    #[test]
+    fn validates_patch_from_archive() {
+        let dir = tempdir().unwrap();
+        fs::write(
+            dir.path().join("manifest.json"),
+            r#"{"version": 1, "name": "TestPatcher", "entries": [
+                {"operation": "patch", "file": "modified.bin", "original_hash": "a", "diff_hash": "b", "final_hash": "c"},
+                {"operation": "add", "file": "missing.bin", "final_hash": "d"}
+            ]}"#,
+        )
+        .unwrap();
+        fs::create_dir(dir.path().join("diffs")).unwrap();
+        fs::write(dir.path().join("diffs/modified.bin.diff"), b"diff data").unwrap();
+
+        let data = crate::archive::create_archive_bytes(dir.path()).unwrap();
+        let source = crate::patch::TarSource::new(data).unwrap();
+
+        let result = validate_patch_dir(&source);
+        assert!(matches!(result, Err(PatchError::FileNotFound(f)) if f == "missing.bin"));
+    }
+
+    #[test]
+    fn require_entries_check_presence_and_hash() {
+        let target = tempdir().unwrap();
+        fs::write(target.path().join("game.exe"), b"right product").unwrap();
+        let require = |file: &str, content: &[u8]| ManifestEntry::Require {
+            file: file.to_string(),
+            hash: hash_bytes(content),
+        };
+
+        let ok = [require("game.exe", b"right product")];
+        assert!(validate_entries(&ok, target.path(), None, None::<fn(Progress)>).is_ok());
+
+        let wrong = [require("game.exe", b"other product")];
+        let result = validate_entries(&wrong, target.path(), None, None::<fn(Progress)>);
+        assert!(matches!(result, Err(PatchError::ValidationFailed { file, .. }) if file == "game.exe"));
+
+        let missing = [require("launcher.exe", b"anything")];
+        let result = validate_entries(&missing, target.path(), None, None::<fn(Progress)>);
+        assert!(matches!(result, Err(PatchError::ValidationFailed { reason, .. }) if reason.contains("required")));
+    }
 }
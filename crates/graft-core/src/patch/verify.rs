@@ -1,6 +1,7 @@
 use std::fs;
 use std::path::Path;
 
+use crate::patch::edit::{current_value, new_value};
 use crate::patch::PatchError;
 use crate::utils::hash::hash_bytes;
 use crate::utils::manifest::ManifestEntry;
@@ -10,12 +11,18 @@ use crate::utils::manifest::ManifestEntry;
 /// - Patch: verifies file matches final_hash
 /// - Add: verifies file matches final_hash
 /// - Delete: verifies file no longer exists
+/// - Require: verifies file still matches hash
+/// - EditJson/EditIni: verifies the setting has its new value
 pub fn verify_entry(entry: &ManifestEntry, target_dir: &Path) -> Result<(), PatchError> {
     match entry {
         ManifestEntry::Patch {
             file, final_hash, ..
         }
-        | ManifestEntry::Add { file, final_hash } => {
+        | ManifestEntry::Add { file, final_hash }
+        | ManifestEntry::Require {
+            file,
+            hash: final_hash,
+        } => {
             let target_path = target_dir.join(file);
 
             let data = fs::read(&target_path).map_err(|e| PatchError::VerificationFailed {
@@ -45,6 +52,22 @@ pub fn verify_entry(entry: &ManifestEntry, target_dir: &Path) -> Result<(), Patc
                 });
             }
         }
+        ManifestEntry::EditJson { file, .. } | ManifestEntry::EditIni { file, .. } => {
+            let name = entry.setting().unwrap_or_default();
+            let expected = new_value(entry);
+            let actual = fs::read(target_dir.join(file)).and_then(|data| current_value(entry, &data));
+            let actual = match actual {
+                Ok(Some(value)) if value == expected => return Ok(()),
+                Ok(Some(value)) => format!("{} = {}", name, value),
+                Ok(None) => format!("{} not set", name),
+                Err(e) => format!("failed to read file: {}", e),
+            };
+            return Err(PatchError::VerificationFailed {
+                file: file.clone(),
+                expected: format!("{} = {}", name, expected),
+                actual,
+            });
+        }
     }
 
     Ok(())
@@ -67,6 +90,8 @@ mod tests {
             original_hash: "x".to_string(),
             diff_hash: "y".to_string(),
             final_hash: hash_bytes(content),
+            alternatives: Vec::new(),
+            fallback: false,
         };
 
         let result = verify_entry(&entry, target_dir.path());
@@ -84,6 +109,8 @@ mod tests {
             original_hash: "x".to_string(),
             diff_hash: "y".to_string(),
             final_hash: "expected_hash".to_string(),
+            alternatives: Vec::new(),
+            fallback: false,
         };
 
         let result = verify_entry(&entry, target_dir.path());
@@ -168,6 +195,8 @@ mod tests {
             original_hash: "x".to_string(),
             diff_hash: "y".to_string(),
             final_hash: "z".to_string(),
+            alternatives: Vec::new(),
+            fallback: false,
         };
 
         let result = verify_entry(&entry, target_dir.path());
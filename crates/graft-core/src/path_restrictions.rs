@@ -5,8 +5,18 @@
 //! - Patching system directories
 //! - Patching executable files
 //! - Patching inside .app bundles (macOS)
-
-use crate::utils::manifest::Manifest;
+//!
+//! Paths excluded when the patch was created are rejected regardless of
+//! `allow_restricted`.
+//!
+//! Post-apply actions are checked like entries, except that launching an
+//! executable is their purpose. Action paths outside the target folder,
+//! deleting the target folder itself, its backups or excluded paths, and
+//! opening anything but a web page are never allowed.
+
+use crate::patch::BACKUP_DIR;
+use crate::utils::file_filter::FileFilter;
+use crate::utils::manifest::{Manifest, PostAction};
 use std::path::Path;
 
 /// A violation of path restrictions.
@@ -18,6 +28,12 @@ pub enum RestrictionViolation {
     ProtectedPath { path: String, reason: String },
     /// File has a blocked extension (executable)
     BlockedExtension { path: String, extension: String },
+    /// Path was excluded when the patch was created
+    Excluded { path: String },
+    /// Exclude pattern in the manifest is not a valid glob
+    InvalidExclude { reason: String },
+    /// Post-apply URL is not an http or https address
+    UnsupportedUrl { url: String },
 }
 
 impl std::fmt::Display for RestrictionViolation {
@@ -32,24 +48,36 @@ impl std::fmt::Display for RestrictionViolation {
             RestrictionViolation::BlockedExtension { path, extension } => {
                 write!(f, "{}: Cannot patch executable files ({})", path, extension)
             }
+            RestrictionViolation::Excluded { path } => {
+                write!(f, "{}: Path is excluded from this patch", path)
+            }
+            RestrictionViolation::InvalidExclude { reason } => {
+                write!(f, "Invalid exclude pattern: {}", reason)
+            }
+            RestrictionViolation::UnsupportedUrl { url } => {
+                write!(f, "{}: Only http and https addresses can be opened", url)
+            }
         }
     }
 }
 
 /// Check all paths in a manifest against restrictions.
 ///
-/// If `manifest.allow_restricted` is true, all checks are bypassed.
+/// Excluded paths and post-apply actions are always checked. If
+/// `manifest.allow_restricted` is true, all other checks are bypassed.
 /// Returns Ok(()) if all paths are allowed, Err with violations if any are blocked.
 pub fn check_manifest(
     manifest: &Manifest,
     target_dir: &Path,
 ) -> Result<(), Vec<RestrictionViolation>> {
+    check_excluded(manifest)?;
+    let mut violations = check_post_apply(manifest);
+
     if manifest.allow_restricted {
-        return Ok(()); // Restrictions disabled for this patch
+        // Restrictions disabled for this patch
+        return if violations.is_empty() { Ok(()) } else { Err(violations) };
     }
 
-    let mut violations = Vec::new();
-
     for entry in &manifest.entries {
         let file = entry.file();
         if let Err(v) = check_path(file, target_dir) {
@@ -57,6 +85,36 @@ pub fn check_manifest(
         }
     }
 
+    for action in &manifest.post_apply {
+        if let Some(path) = action.path()
+            && !escapes_target(path)
+            && let Err(v) = check_post_path(action, path, target_dir)
+        {
+            violations.push(v);
+        }
+    }
+
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(violations)
+    }
+}
+
+/// Check that no entry touches a path excluded when the patch was created.
+fn check_excluded(manifest: &Manifest) -> Result<(), Vec<RestrictionViolation>> {
+    let filter = FileFilter::new(&[], &manifest.excluded)
+        .map_err(|e| vec![RestrictionViolation::InvalidExclude { reason: e.to_string() }])?;
+
+    let violations: Vec<_> = manifest
+        .entries
+        .iter()
+        .filter(|entry| !filter.is_included(entry.file()))
+        .map(|entry| RestrictionViolation::Excluded {
+            path: entry.file().to_string(),
+        })
+        .collect();
+
     if violations.is_empty() {
         Ok(())
     } else {
@@ -64,6 +122,95 @@ pub fn check_manifest(
     }
 }
 
+/// Check that post-apply actions stay inside the target folder, don't delete
+/// the folder itself, its backups or excluded paths, and only open web pages.
+fn check_post_apply(manifest: &Manifest) -> Vec<RestrictionViolation> {
+    // Invalid patterns are reported by check_excluded
+    let filter = FileFilter::new(&[], &manifest.excluded).unwrap_or_default();
+    manifest
+        .post_apply
+        .iter()
+        .filter_map(|action| {
+            if let Some(path) = action.path().filter(|path| escapes_target(path)) {
+                return Some(RestrictionViolation::PathTraversal { path: path.to_string() });
+            }
+            match action {
+                PostAction::DeleteDir { path } => {
+                    protected_dir_reason(manifest, &filter, path).map(|reason| RestrictionViolation::ProtectedPath {
+                        path: path.clone(),
+                        reason: reason.to_string(),
+                    })
+                }
+                PostAction::OpenUrl { url } if !is_web_url(url) => {
+                    Some(RestrictionViolation::UnsupportedUrl { url: url.clone() })
+                }
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// Why a folder in the target must not be deleted, if it must not.
+///
+/// Protects the target folder itself, the backups in `.patch-backup` or the
+/// manifest's backup location, and paths excluded from the patch along with
+/// the folders containing them.
+fn protected_dir_reason(manifest: &Manifest, filter: &FileFilter, path: &str) -> Option<&'static str> {
+    let dir = normalized(path);
+    let overlaps = |other: &str| {
+        let other = normalized(other);
+        other == dir || other.starts_with(&format!("{}/", dir)) || dir.starts_with(&format!("{}/", other))
+    };
+
+    if dir.is_empty() {
+        Some("Cannot delete the target folder")
+    } else if overlaps(BACKUP_DIR) || manifest.backup_dir.as_deref().is_some_and(overlaps) {
+        Some("Cannot delete the backups")
+    } else if filter.is_excluded(&dir)
+        || manifest.excluded.iter().any(|pattern| normalized(pattern).starts_with(&format!("{}/", dir)))
+    {
+        Some("Cannot delete paths excluded from the patch")
+    } else {
+        None
+    }
+}
+
+/// `path` with `/` separators and without `.` components, "" for the target folder itself.
+fn normalized(path: &str) -> String {
+    Path::new(path)
+        .components()
+        .filter(|c| !matches!(c, std::path::Component::CurDir))
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// True if `path` is absolute or has a `..` component, so it may lead out of
+/// the target folder.
+pub(crate) fn escapes_target(path: &str) -> bool {
+    Path::new(path).components().any(|c| {
+        matches!(
+            c,
+            std::path::Component::Prefix(_) | std::path::Component::RootDir | std::path::Component::ParentDir
+        )
+    })
+}
+
+fn is_web_url(url: &str) -> bool {
+    let url = url.to_ascii_lowercase();
+    url.starts_with("http://") || url.starts_with("https://")
+}
+
+/// Check the path of a post-apply action; executables may be launched.
+fn check_post_path(action: &PostAction, path: &str, target_dir: &Path) -> Result<(), RestrictionViolation> {
+    check_path_traversal(path)?;
+    if !matches!(action, PostAction::Launch { .. }) {
+        check_blocked_extension(path)?;
+    }
+    check_protected_path(path, target_dir)?;
+    Ok(())
+}
+
 /// Check a single file path against all restrictions.
 fn check_path(file: &str, target_dir: &Path) -> Result<(), RestrictionViolation> {
     check_path_traversal(file)?;
@@ -315,11 +462,19 @@ mod tests {
             name: "TestPatcher".to_string(),
             title: None,
             allow_restricted: true,
+            excluded: Vec::new(),
+            discovery: None,
+            backup_dir: None,
+            backup: Default::default(),
+            post_apply: Vec::new(),
+            components: Vec::new(),
             entries: vec![ManifestEntry::Patch {
                 file: "../../../etc/passwd".to_string(),
                 original_hash: "a".to_string(),
                 diff_hash: "b".to_string(),
                 final_hash: "c".to_string(),
+                alternatives: Vec::new(),
+                fallback: false,
             }],
         };
 
@@ -334,11 +489,19 @@ mod tests {
             name: "TestPatcher".to_string(),
             title: None,
             allow_restricted: false,
+            excluded: Vec::new(),
+            discovery: None,
+            backup_dir: None,
+            backup: Default::default(),
+            post_apply: Vec::new(),
+            components: Vec::new(),
             entries: vec![ManifestEntry::Patch {
                 file: "../secret.txt".to_string(),
                 original_hash: "a".to_string(),
                 diff_hash: "b".to_string(),
                 final_hash: "c".to_string(),
+                alternatives: Vec::new(),
+                fallback: false,
             }],
         };
 
@@ -352,6 +515,101 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn excluded_paths_blocked_even_when_unrestricted() {
+        let mut manifest = Manifest::new(1, "TestPatcher".to_string(), None);
+        manifest.allow_restricted = true;
+        manifest.excluded = vec!["*.sav".to_string()];
+        manifest.entries = vec![ManifestEntry::Delete {
+            file: "slot1.sav".to_string(),
+            original_hash: "a".to_string(),
+        }];
+
+        let violations = check_manifest(&manifest, Path::new("/tmp")).unwrap_err();
+        assert_eq!(
+            violations,
+            vec![RestrictionViolation::Excluded {
+                path: "slot1.sav".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn post_apply_actions_are_checked() {
+        let mut manifest = Manifest::new(1, "TestPatcher".to_string(), None);
+        manifest.post_apply = vec![
+            PostAction::DeleteDir { path: "cache/shaders".to_string() },
+            PostAction::Launch { path: "run.sh".to_string(), args: Vec::new() },
+            PostAction::OpenUrl { url: "https://example.com/notes".to_string() },
+        ];
+        assert!(check_manifest(&manifest, Path::new("/tmp")).is_ok());
+
+        manifest.post_apply = vec![
+            PostAction::OpenFile { path: "../notes.txt".to_string() },
+            PostAction::OpenFile { path: "/etc/passwd".to_string() },
+            PostAction::OpenFile { path: "setup.sh".to_string() },
+        ];
+        let violations = check_manifest(&manifest, Path::new("/tmp")).unwrap_err();
+        assert_eq!(violations.len(), 3);
+
+        // Never allowed, even for unrestricted patches
+        manifest.allow_restricted = true;
+        manifest.post_apply = vec![
+            PostAction::DeleteDir { path: ".".to_string() },
+            PostAction::OpenUrl { url: "file:///etc/passwd".to_string() },
+        ];
+        let violations = check_manifest(&manifest, Path::new("/tmp")).unwrap_err();
+        assert!(matches!(violations[0], RestrictionViolation::ProtectedPath { .. }));
+        assert!(matches!(violations[1], RestrictionViolation::UnsupportedUrl { .. }));
+    }
+
+    #[test]
+    fn post_apply_cannot_delete_backups_or_excluded_paths() {
+        let mut manifest = Manifest::new(1, "TestPatcher".to_string(), None);
+        manifest.allow_restricted = true;
+        manifest.backup_dir = Some("saves/backups".to_string());
+        manifest.excluded = vec!["config".to_string(), "mods/**".to_string()];
+        let protected = [".patch-backup", "./.patch-backup/TestPatcher-v1", "saves", "config", "data/config", "mods"];
+        manifest.post_apply = protected
+            .iter()
+            .map(|path| PostAction::DeleteDir { path: path.to_string() })
+            .collect();
+
+        let violations = check_manifest(&manifest, Path::new("/tmp")).unwrap_err();
+        assert_eq!(violations.len(), protected.len());
+        assert!(violations.iter().all(|v| matches!(v, RestrictionViolation::ProtectedPath { .. })));
+
+        manifest.post_apply = vec![
+            PostAction::DeleteDir { path: "cache".to_string() },
+            PostAction::DeleteDir { path: "mods-cache".to_string() },
+        ];
+        assert!(check_manifest(&manifest, Path::new("/tmp")).is_ok());
+    }
+
+    #[test]
+    fn post_apply_paths_stay_in_target_even_when_unrestricted() {
+        let mut manifest = Manifest::new(1, "TestPatcher".to_string(), None);
+        manifest.allow_restricted = true;
+        manifest.post_apply = vec![
+            PostAction::DeleteDir { path: "sub/..".to_string() },
+            PostAction::Launch { path: "../x".to_string(), args: Vec::new() },
+            PostAction::OpenFile { path: "/abs".to_string() },
+        ];
+
+        let violations = check_manifest(&manifest, Path::new("/tmp")).unwrap_err();
+        assert_eq!(
+            violations,
+            vec![
+                RestrictionViolation::PathTraversal { path: "sub/..".to_string() },
+                RestrictionViolation::PathTraversal { path: "../x".to_string() },
+                RestrictionViolation::PathTraversal { path: "/abs".to_string() },
+            ]
+        );
+
+        manifest.post_apply = vec![PostAction::Launch { path: "bin/game".to_string(), args: Vec::new() }];
+        assert!(check_manifest(&manifest, Path::new("/tmp")).is_ok());
+    }
+
     #[cfg(target_os = "macos")]
     #[test]
     fn macos_app_bundle_is_blocked() {
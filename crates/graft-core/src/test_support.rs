@@ -0,0 +1,32 @@
+//! Fixtures shared by the tests of graft-core and the crates built on it.
+//!
+//! Only compiled for graft-core's own tests and with the `test-support`
+//! feature, which the other crates enable as a dev-dependency.
+
+use std::fs;
+use std::path::Path;
+
+use crate::patch::{ASSETS_DIR, DIFFS_DIR, FILES_DIR, ICON_FILENAME, MANIFEST_FILENAME};
+
+/// Write a small patch directory named "TestPatcher" into `dir`.
+///
+/// The manifest has a Patch entry for `game.bin` (a 300-byte diff), an Add
+/// entry for `data/new.bin` ("new data") and a Delete entry for `old.bin`.
+/// The hashes are placeholders, and an icon asset is included.
+pub fn create_patch_dir(dir: &Path) {
+    fs::write(
+        dir.join(MANIFEST_FILENAME),
+        r#"{"version": 2, "name": "TestPatcher", "entries": [
+            {"operation": "patch", "file": "game.bin", "original_hash": "a", "diff_hash": "b", "final_hash": "c"},
+            {"operation": "add", "file": "data/new.bin", "final_hash": "d"},
+            {"operation": "delete", "file": "old.bin", "original_hash": "e"}
+        ]}"#,
+    )
+    .unwrap();
+    fs::create_dir_all(dir.join(DIFFS_DIR)).unwrap();
+    fs::write(dir.join(DIFFS_DIR).join("game.bin.diff"), vec![1u8; 300]).unwrap();
+    fs::create_dir_all(dir.join(FILES_DIR).join("data")).unwrap();
+    fs::write(dir.join(FILES_DIR).join("data").join("new.bin"), b"new data").unwrap();
+    fs::create_dir_all(dir.join(ASSETS_DIR)).unwrap();
+    fs::write(dir.join(ASSETS_DIR).join(ICON_FILENAME), b"png").unwrap();
+}
@@ -1,4 +1,4 @@
-use std::io;
+use std::io::{self, Read};
 
 pub fn create_diff(old: &[u8], new: &[u8]) -> io::Result<Vec<u8>> {
     let mut diff = Vec::new();
@@ -7,8 +7,13 @@ pub fn create_diff(old: &[u8], new: &[u8]) -> io::Result<Vec<u8>> {
 }
 
 pub fn apply_diff(orig: &[u8], diff: &[u8]) -> io::Result<Vec<u8>> {
+    apply_diff_reader(orig, diff)
+}
+
+/// Apply a diff read from a stream, e.g. to track how much has been applied.
+pub fn apply_diff_reader(orig: &[u8], mut diff: impl Read) -> io::Result<Vec<u8>> {
     let mut output = Vec::new();
-    bsdiff::patch(orig, &mut diff.as_ref(), &mut output)?;
+    bsdiff::patch(orig, &mut diff, &mut output)?;
     Ok(output)
 }
 
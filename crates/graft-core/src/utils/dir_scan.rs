@@ -3,6 +3,7 @@ use std::fs;
 use std::io;
 use std::path::Path;
 
+use crate::utils::file_filter::FileFilter;
 use crate::utils::hash::hash_bytes;
 
 /// Represents a detected difference between two directories.
@@ -47,10 +48,10 @@ pub fn list_files(dir: &Path) -> io::Result<Vec<String>> {
         let entry = entry?;
         let file_type = entry.file_type()?;
 
-        if file_type.is_file() {
-            if let Some(name) = entry.file_name().to_str() {
-                files.push(name.to_string());
-            }
+        if file_type.is_file()
+            && let Some(name) = entry.file_name().to_str()
+        {
+            files.push(name.to_string());
         }
     }
 
@@ -60,10 +61,16 @@ pub fn list_files(dir: &Path) -> io::Result<Vec<String>> {
 
 /// Compare two directories and categorize files into changes.
 /// Returns entries for: patch (modified), add (new), delete (removed).
-/// Unchanged files (same hash) are skipped.
-pub fn categorize_files(orig_dir: &Path, new_dir: &Path) -> io::Result<Vec<FileChange>> {
-    let orig_files: HashSet<String> = list_files(orig_dir)?.into_iter().collect();
-    let new_files: HashSet<String> = list_files(new_dir)?.into_iter().collect();
+/// Unchanged files (same hash) and files rejected by `filter` are skipped.
+pub fn categorize_files(orig_dir: &Path, new_dir: &Path, filter: &FileFilter) -> io::Result<Vec<FileChange>> {
+    let orig_files: HashSet<String> = list_files(orig_dir)?
+        .into_iter()
+        .filter(|f| filter.is_included(f))
+        .collect();
+    let new_files: HashSet<String> = list_files(new_dir)?
+        .into_iter()
+        .filter(|f| filter.is_included(f))
+        .collect();
 
     let mut changes = Vec::new();
 
@@ -176,7 +183,7 @@ mod tests {
         fs::write(orig_dir.path().join("file.bin"), b"original").unwrap();
         fs::write(new_dir.path().join("file.bin"), b"modified").unwrap();
 
-        let changes = categorize_files(orig_dir.path(), new_dir.path()).unwrap();
+        let changes = categorize_files(orig_dir.path(), new_dir.path(), &FileFilter::default()).unwrap();
 
         assert_eq!(changes.len(), 1);
         assert!(matches!(
@@ -193,7 +200,7 @@ mod tests {
 
         fs::write(new_dir.path().join("new_file.bin"), b"new content").unwrap();
 
-        let changes = categorize_files(orig_dir.path(), new_dir.path()).unwrap();
+        let changes = categorize_files(orig_dir.path(), new_dir.path(), &FileFilter::default()).unwrap();
 
         assert_eq!(changes.len(), 1);
         assert!(matches!(
@@ -209,7 +216,7 @@ mod tests {
 
         fs::write(orig_dir.path().join("old_file.bin"), b"old content").unwrap();
 
-        let changes = categorize_files(orig_dir.path(), new_dir.path()).unwrap();
+        let changes = categorize_files(orig_dir.path(), new_dir.path(), &FileFilter::default()).unwrap();
 
         assert_eq!(changes.len(), 1);
         assert!(matches!(
@@ -226,7 +233,7 @@ mod tests {
         fs::write(orig_dir.path().join("same.bin"), b"same content").unwrap();
         fs::write(new_dir.path().join("same.bin"), b"same content").unwrap();
 
-        let changes = categorize_files(orig_dir.path(), new_dir.path()).unwrap();
+        let changes = categorize_files(orig_dir.path(), new_dir.path(), &FileFilter::default()).unwrap();
 
         assert!(changes.is_empty());
     }
@@ -250,7 +257,7 @@ mod tests {
         // Old (only in orig)
         fs::write(orig_dir.path().join("old.bin"), b"old").unwrap();
 
-        let changes = categorize_files(orig_dir.path(), new_dir.path()).unwrap();
+        let changes = categorize_files(orig_dir.path(), new_dir.path(), &FileFilter::default()).unwrap();
 
         assert_eq!(changes.len(), 3);
 
@@ -264,7 +271,7 @@ mod tests {
         let orig_dir = tempdir().unwrap();
         let new_dir = tempdir().unwrap();
 
-        let changes = categorize_files(orig_dir.path(), new_dir.path()).unwrap();
+        let changes = categorize_files(orig_dir.path(), new_dir.path(), &FileFilter::default()).unwrap();
 
         assert!(changes.is_empty());
     }
@@ -273,7 +280,7 @@ mod tests {
     fn categorize_nonexistent_directory_errors() {
         let new_dir = tempdir().unwrap();
 
-        let result = categorize_files(Path::new("/nonexistent"), new_dir.path());
+        let result = categorize_files(Path::new("/nonexistent"), new_dir.path(), &FileFilter::default());
 
         assert!(result.is_err());
     }
@@ -0,0 +1,236 @@
+//! In-place edits of single settings in JSON and INI files.
+//!
+//! JSON documents are re-serialized with their key order kept: pretty printed
+//! with two-space indentation if the original spanned several lines, compact
+//! otherwise, so other values keep their content but not necessarily their
+//! formatting. INI files are edited line by line, so comments, blank lines and
+//! the layout of every other line are left as they were.
+
+use serde_json::Value;
+use std::io;
+
+fn invalid(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+/// Split a JSON pointer (RFC 6901) like `/graphics/quality` into its tokens.
+fn pointer_tokens(pointer: &str) -> io::Result<Vec<String>> {
+    let Some(rest) = pointer.strip_prefix('/') else {
+        return Err(invalid(format!("JSON pointer '{}' must start with '/'", pointer)));
+    };
+    Ok(rest.split('/').map(|t| t.replace("~1", "/").replace("~0", "~")).collect())
+}
+
+/// Read the value at `pointer` in a JSON document, None if it isn't set.
+pub fn json_value(data: &[u8], pointer: &str) -> io::Result<Option<Value>> {
+    let document: Value = serde_json::from_slice(data).map_err(|e| invalid(format!("invalid JSON: {}", e)))?;
+    pointer_tokens(pointer)?;
+    Ok(document.pointer(pointer).cloned())
+}
+
+/// Set the value at `pointer` in a JSON document.
+///
+/// Missing objects along the way are created. An existing array element can
+/// be replaced; appending (`-` or the index one past the end) is rejected,
+/// since the result couldn't be verified or rolled forward again.
+pub fn set_json(data: &[u8], pointer: &str, value: &Value) -> io::Result<Vec<u8>> {
+    let mut document: Value = serde_json::from_slice(data).map_err(|e| invalid(format!("invalid JSON: {}", e)))?;
+    let tokens = pointer_tokens(pointer)?;
+    let (last, parents) = tokens.split_last().expect("split always yields a token");
+
+    let mut current = &mut document;
+    for token in parents {
+        current = match current {
+            Value::Object(map) => map.entry(token.as_str()).or_insert_with(|| Value::Object(Default::default())),
+            Value::Array(items) => {
+                let len = items.len();
+                token
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|index| items.get_mut(index))
+                    .ok_or_else(|| invalid(format!("{}: index '{}' out of range ({} items)", pointer, token, len)))?
+            }
+            _ => return Err(invalid(format!("{}: '{}' is not inside an object or array", pointer, token))),
+        };
+    }
+    match current {
+        Value::Object(map) => {
+            map.insert(last.clone(), value.clone());
+        }
+        Value::Array(items) => match last.parse::<usize>() {
+            Ok(index) if index < items.len() => items[index] = value.clone(),
+            _ => return Err(invalid(format!("{}: index '{}' out of range ({} items)", pointer, last, items.len()))),
+        },
+        _ => return Err(invalid(format!("{}: '{}' is not inside an object or array", pointer, last))),
+    }
+
+    // A trailing newline alone doesn't make a document pretty printed
+    let multiline = data.trim_ascii_end().contains(&b'\n');
+    let mut output = if multiline {
+        serde_json::to_vec_pretty(&document)
+    } else {
+        serde_json::to_vec(&document)
+    }
+    .map_err(|e| invalid(e.to_string()))?;
+    if data.ends_with(b"\n") {
+        output.push(b'\n');
+    }
+    Ok(output)
+}
+
+/// A line of an INI file
+enum IniLine<'a> {
+    Section(&'a str),
+    /// Key and the byte offset of its `=`
+    Entry(&'a str, usize),
+    Other,
+}
+
+fn parse_ini_line(line: &str) -> IniLine<'_> {
+    let trimmed = line.trim();
+    if trimmed.starts_with(';') || trimmed.starts_with('#') {
+        IniLine::Other
+    } else if let Some(name) = trimmed.strip_prefix('[').and_then(|t| t.strip_suffix(']')) {
+        IniLine::Section(name.trim())
+    } else if let Some(eq) = line.find('=') {
+        IniLine::Entry(line[..eq].trim(), eq)
+    } else {
+        IniLine::Other
+    }
+}
+
+/// Read the value of `key` in `section` of an INI file, None if it isn't set.
+///
+/// Section and key names are case-insensitive; the empty section holds the
+/// keys before the first section header.
+pub fn ini_value(data: &[u8], section: &str, key: &str) -> io::Result<Option<String>> {
+    let text = std::str::from_utf8(data).map_err(|_| invalid("INI file is not valid UTF-8"))?;
+    let mut current = "";
+    for line in text.lines() {
+        match parse_ini_line(line) {
+            IniLine::Section(name) => current = name,
+            IniLine::Entry(name, eq) if current.eq_ignore_ascii_case(section) && name.eq_ignore_ascii_case(key) => {
+                return Ok(Some(line[eq + 1..].trim().to_string()));
+            }
+            _ => {}
+        }
+    }
+    Ok(None)
+}
+
+/// Set `key` in `section` of an INI file.
+///
+/// An existing key keeps its spacing around `=`. A missing key is added at
+/// the end of its section, and a missing section at the end of the file.
+pub fn set_ini(data: &[u8], section: &str, key: &str, value: &str) -> io::Result<Vec<u8>> {
+    let text = std::str::from_utf8(data).map_err(|_| invalid("INI file is not valid UTF-8"))?;
+    let newline = if text.contains("\r\n") { "\r\n" } else { "\n" };
+    let mut lines: Vec<String> = text.lines().map(str::to_string).collect();
+
+    // Follow the file's style for new keys
+    let spaced = lines.iter().any(|line| matches!(parse_ini_line(line), IniLine::Entry(_, eq) if line[..eq].ends_with(' ')));
+    let new_line = if spaced {
+        format!("{} = {}", key, value)
+    } else {
+        format!("{}={}", key, value)
+    };
+
+    let mut in_section = section.is_empty();
+    // Line after the last content line of the section
+    let mut insert_at = None;
+    let mut existing = None;
+    for (index, line) in lines.iter().enumerate() {
+        match parse_ini_line(line) {
+            IniLine::Section(name) => {
+                in_section = name.eq_ignore_ascii_case(section);
+                if in_section {
+                    insert_at = Some(index + 1);
+                }
+            }
+            IniLine::Entry(name, eq) if in_section => {
+                if name.eq_ignore_ascii_case(key) && existing.is_none() {
+                    existing = Some((index, eq));
+                }
+                insert_at = Some(index + 1);
+            }
+            IniLine::Other if in_section && !line.trim().is_empty() => insert_at = Some(index + 1),
+            _ => {}
+        }
+    }
+
+    if let Some((index, eq)) = existing {
+        let line = &lines[index];
+        let rest = &line[eq + 1..];
+        let spacing = &rest[..rest.len() - rest.trim_start().len()];
+        lines[index] = format!("{}{}{}", &line[..=eq], spacing, value);
+    } else if let Some(index) = insert_at.or(section.is_empty().then_some(0)) {
+        lines.insert(index, new_line);
+    } else {
+        if lines.last().is_some_and(|line| !line.trim().is_empty()) {
+            lines.push(String::new());
+        }
+        lines.push(format!("[{}]", section));
+        lines.push(new_line);
+    }
+
+    let mut output = lines.join(newline);
+    if text.is_empty() || text.ends_with('\n') {
+        output.push_str(newline);
+    }
+    Ok(output.into_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn sets_json_values_keeping_key_order() {
+        let data = b"{\n  \"zoom\": 1,\n  \"graphics\": {\"quality\": \"low\"},\n  \"mods\": [\"a\"]\n}\n";
+
+        let edited = set_json(data, "/graphics/quality", &json!("high")).unwrap();
+        let text = String::from_utf8(edited.clone()).unwrap();
+        assert!(text.find("zoom").unwrap() < text.find("graphics").unwrap());
+        assert!(text.ends_with("}\n"));
+        assert_eq!(json_value(&edited, "/graphics/quality").unwrap(), Some(json!("high")));
+
+        let edited = set_json(&edited, "/audio/volume", &json!(80)).unwrap();
+        assert_eq!(json_value(&edited, "/audio/volume").unwrap(), Some(json!(80)));
+        let edited = set_json(&edited, "/mods/0", &json!("b")).unwrap();
+        assert_eq!(json_value(&edited, "/mods").unwrap(), Some(json!(["b"])));
+        assert!(set_json(&edited, "/mods/-", &json!("c")).is_err());
+        assert!(set_json(&edited, "/mods/1", &json!("c")).is_err());
+
+        let compact = set_json(b"{\"zoom\": 1}\n", "/zoom", &json!(2)).unwrap();
+        assert_eq!(compact, b"{\"zoom\":2}\n");
+
+        assert!(set_json(data, "/zoom/level", &json!(2)).is_err());
+        assert!(set_json(data, "/mods/5", &json!("c")).is_err());
+        assert!(set_json(b"not json", "/a", &json!(1)).is_err());
+        assert!(set_json(data, "zoom", &json!(1)).is_err());
+    }
+
+    #[test]
+    fn sets_ini_values_keeping_layout() {
+        let data = b"; settings\r\ntop = 1\r\n\r\n[Video]\r\nWidth = 800\r\nVSync = 0\r\n\r\n[Audio]\r\nVolume = 5\r\n";
+
+        let edited = set_ini(data, "video", "vsync", "1").unwrap();
+        assert_eq!(
+            String::from_utf8(edited.clone()).unwrap(),
+            "; settings\r\ntop = 1\r\n\r\n[Video]\r\nWidth = 800\r\nVSync = 1\r\n\r\n[Audio]\r\nVolume = 5\r\n"
+        );
+
+        let edited = set_ini(&edited, "Video", "Height", "600").unwrap();
+        let text = String::from_utf8(edited.clone()).unwrap();
+        assert!(text.contains("VSync = 1\r\nHeight = 600\r\n\r\n[Audio]"));
+
+        let edited = set_ini(&edited, "Network", "Port", "7777").unwrap();
+        assert!(String::from_utf8(edited.clone()).unwrap().ends_with("Volume = 5\r\n\r\n[Network]\r\nPort = 7777\r\n"));
+        let edited = set_ini(&edited, "", "top", "2").unwrap();
+
+        assert_eq!(ini_value(&edited, "", "TOP").unwrap().as_deref(), Some("2"));
+        assert_eq!(ini_value(&edited, "network", "port").unwrap().as_deref(), Some("7777"));
+        assert_eq!(ini_value(&edited, "Audio", "Missing").unwrap(), None);
+    }
+}
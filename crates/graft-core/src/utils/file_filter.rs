@@ -0,0 +1,182 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use globset::{Glob, GlobSet, GlobSetBuilder};
+
+use crate::patch::{ASSETS_DIR, BACKUP_DIR, BACKUP_MARKER_FILENAME, IGNORE_FILENAME};
+
+/// Decides which files take part in a patch.
+///
+/// A file is compared when it matches one of the include patterns (or there
+/// are none) and none of the exclude patterns. Patterns without a `/` also
+/// match against the file name alone, so `Thumbs.db` excludes it in every
+/// directory. Graft's own backup and asset directories and the ignore file
+/// itself are always skipped.
+#[derive(Debug, Clone)]
+pub struct FileFilter {
+    include: Option<GlobSet>,
+    exclude: GlobSet,
+    exclude_patterns: Vec<String>,
+}
+
+impl FileFilter {
+    pub fn new(include: &[String], exclude: &[String]) -> io::Result<Self> {
+        let include = if include.is_empty() {
+            None
+        } else {
+            Some(build_set(include)?)
+        };
+        Ok(FileFilter {
+            include,
+            exclude: build_set(exclude)?,
+            exclude_patterns: exclude.to_vec(),
+        })
+    }
+
+    /// Build a filter from command line patterns plus the `.graftignore`
+    /// file in `dir`, if there is one.
+    pub fn with_ignore_file(dir: &Path, include: &[String], exclude: &[String]) -> io::Result<Self> {
+        let mut exclude = exclude.to_vec();
+        exclude.extend(read_ignore_file(dir)?);
+        Self::new(include, &exclude)
+    }
+
+    /// Exclude patterns, in the order they were given.
+    pub fn exclude_patterns(&self) -> &[String] {
+        &self.exclude_patterns
+    }
+
+    /// Returns true if `file` (relative, `/`-separated) should be compared.
+    pub fn is_included(&self, file: &str) -> bool {
+        if is_reserved(file) || self.is_excluded(file) {
+            return false;
+        }
+        match &self.include {
+            Some(include) => matches(include, file),
+            None => true,
+        }
+    }
+
+    /// Returns true if `file` matches one of the exclude patterns.
+    pub fn is_excluded(&self, file: &str) -> bool {
+        matches(&self.exclude, file)
+    }
+}
+
+impl Default for FileFilter {
+    fn default() -> Self {
+        FileFilter {
+            include: None,
+            exclude: GlobSet::empty(),
+            exclude_patterns: Vec::new(),
+        }
+    }
+}
+
+/// Read exclude patterns from `dir/.graftignore`.
+///
+/// Blank lines and lines starting with `#` are ignored. A missing file
+/// yields no patterns.
+pub fn read_ignore_file(dir: &Path) -> io::Result<Vec<String>> {
+    let content = match fs::read_to_string(dir.join(IGNORE_FILENAME)) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+
+    Ok(content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect())
+}
+
+/// Paths graft creates itself, which never belong in a patch.
+fn is_reserved(file: &str) -> bool {
+    let first = file.split('/').next().unwrap_or(file);
+    first == BACKUP_DIR || first == ASSETS_DIR || file == IGNORE_FILENAME || file == BACKUP_MARKER_FILENAME
+}
+
+fn build_set(patterns: &[String]) -> io::Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = Glob::new(pattern).map_err(|e| {
+            io::Error::new(io::ErrorKind::InvalidInput, format!("invalid pattern '{}': {}", pattern, e))
+        })?;
+        builder.add(glob);
+    }
+    builder
+        .build()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))
+}
+
+fn matches(set: &GlobSet, file: &str) -> bool {
+    if set.is_match(file) {
+        return true;
+    }
+    match file.rsplit_once('/') {
+        Some((_, name)) => set.is_match(name),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn patterns(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn default_filter_skips_reserved_paths() {
+        let filter = FileFilter::default();
+        assert!(filter.is_included("game.bin"));
+        assert!(!filter.is_included(".patch-backup"));
+        assert!(!filter.is_included(".graft_assets/icon.png"));
+        assert!(!filter.is_included(".graftignore"));
+        assert!(!filter.is_included(".graft-backup"));
+    }
+
+    #[test]
+    fn exclude_matches_path_or_file_name() {
+        let filter = FileFilter::new(&[], &patterns(&["*.log", "Thumbs.db", "saves/**"])).unwrap();
+        assert!(!filter.is_included("debug.log"));
+        assert!(!filter.is_included("textures/Thumbs.db"));
+        assert!(!filter.is_included("saves/slot1.sav"));
+        assert!(filter.is_included("game.bin"));
+    }
+
+    #[test]
+    fn include_limits_files() {
+        let filter = FileFilter::new(&patterns(&["*.pak"]), &patterns(&["debug.pak"])).unwrap();
+        assert!(filter.is_included("data.pak"));
+        assert!(!filter.is_included("readme.txt"));
+        assert!(!filter.is_included("debug.pak"));
+    }
+
+    #[test]
+    fn reads_ignore_file() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join(IGNORE_FILENAME), "# junk\n.DS_Store\n\n  *.tmp  \n").unwrap();
+
+        let filter = FileFilter::with_ignore_file(dir.path(), &[], &patterns(&["*.log"])).unwrap();
+        assert_eq!(filter.exclude_patterns(), ["*.log", ".DS_Store", "*.tmp"]);
+        assert!(!filter.is_included("scratch.tmp"));
+    }
+
+    #[test]
+    fn missing_ignore_file_is_empty() {
+        let dir = tempdir().unwrap();
+        assert!(read_ignore_file(dir.path()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn rejects_invalid_pattern() {
+        let result = FileFilter::new(&[], &patterns(&["[unclosed"]));
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    }
+}
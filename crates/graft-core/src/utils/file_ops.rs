@@ -1,34 +1,176 @@
 use std::fs;
-use std::io;
+use std::io::{self, Read};
 use std::path::Path;
 
+use crate::patch::backup::{restore_backup, store_backup};
+use crate::patch::CancelToken;
+
+/// Bytes read between progress reports of a [`ProgressReader`].
+pub const PROGRESS_INTERVAL: u64 = 1 << 20;
+
 /// Copy a file to a backup directory, preserving the filename.
 /// Creates the backup directory if it doesn't exist.
 pub fn backup_file(file: &Path, backup_dir: &Path) -> io::Result<()> {
-    let filename = file
-        .file_name()
-        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "path has no filename"))?;
+    let filename = file_name(file)?;
+    store_backup(file, backup_dir, filename, false, None, |_| {})
+}
 
-    fs::create_dir_all(backup_dir)?;
+/// Restore a file from a backup directory, overwriting the original.
+pub fn restore_file(file: &Path, backup_dir: &Path) -> io::Result<()> {
+    let filename = file_name(file)?;
+    if restore_backup(backup_dir, filename, file)? {
+        Ok(())
+    } else {
+        Err(io::Error::new(io::ErrorKind::NotFound, format!("no backup of '{}'", filename)))
+    }
+}
 
-    let backup_path = backup_dir.join(filename);
-    fs::copy(file, &backup_path)?;
+fn file_name(file: &Path) -> io::Result<&str> {
+    file.file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "path has no filename"))
+}
 
+/// Recursively copy a directory, creating `dest` if needed.
+pub fn copy_dir(src: &Path, dest: &Path) -> io::Result<()> {
+    fs::create_dir_all(dest)?;
+    for entry in fs::read_dir(src)? {
+        let entry = entry?;
+        let path = entry.path();
+        if path.is_dir() {
+            copy_dir(&path, &dest.join(entry.file_name()))?;
+        } else {
+            fs::copy(&path, dest.join(entry.file_name()))?;
+        }
+    }
     Ok(())
 }
 
-/// Restore a file from a backup directory, overwriting the original.
-pub fn restore_file(file: &Path, backup_dir: &Path) -> io::Result<()> {
-    let filename = file
-        .file_name()
-        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "path has no filename"))?;
+/// Robust directory removal that handles Windows read-only files.
+///
+/// On Windows, `fs::remove_dir_all` can fail with error 87 (ERROR_INVALID_PARAMETER)
+/// when files have read-only attributes. This function clears the attribute before deletion.
+pub fn remove_dir_all_robust(path: &Path) -> io::Result<()> {
+    if path.is_dir() {
+        for entry in fs::read_dir(path)? {
+            let entry = entry?;
+            let entry_path = entry.path();
+            if entry_path.is_dir() {
+                remove_dir_all_robust(&entry_path)?;
+            } else {
+                remove_file_robust(&entry_path)?;
+            }
+        }
+        fs::remove_dir(path)
+    } else {
+        remove_file_robust(path)
+    }
+}
+
+/// Remove a file, handling Windows read-only attribute.
+fn remove_file_robust(path: &Path) -> io::Result<()> {
+    // Try normal removal first
+    match fs::remove_file(path) {
+        Ok(()) => Ok(()),
+        Err(e) => {
+            // On Windows, try clearing read-only attribute and retry
+            #[cfg(windows)]
+            {
+                use std::os::windows::fs::MetadataExt;
+                const FILE_ATTRIBUTE_READONLY: u32 = 0x1;
+
+                if let Ok(metadata) = fs::metadata(path) {
+                    let attrs = metadata.file_attributes();
+                    if attrs & FILE_ATTRIBUTE_READONLY != 0 {
+                        // Clear read-only and retry
+                        let mut perms = metadata.permissions();
+                        perms.set_readonly(false);
+                        if fs::set_permissions(path, perms).is_ok() {
+                            return fs::remove_file(path);
+                        }
+                    }
+                }
+            }
+            Err(e)
+        }
+    }
+}
 
-    let backup_path = backup_dir.join(filename);
-    fs::copy(&backup_path, file)?;
+/// Check whether a file has any execute permission bit set (always false
+/// outside Unix).
+pub fn is_executable(metadata: &fs::Metadata) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        metadata.permissions().mode() & 0o111 != 0
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = metadata;
+        false
+    }
+}
 
+/// Make a file executable by everyone who can read it (no-op outside Unix).
+pub fn set_executable(path: &Path) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mut perms = fs::metadata(path)?.permissions();
+        perms.set_mode(perms.mode() | (perms.mode() & 0o444) >> 2);
+        fs::set_permissions(path, perms)?;
+    }
+    #[cfg(not(unix))]
+    let _ = path;
     Ok(())
 }
 
+/// Reader that reports how many bytes have been read so far.
+///
+/// `on_progress` is called once every [`PROGRESS_INTERVAL`] bytes and at the
+/// end of the stream, so large files can be tracked without a callback per read.
+pub struct ProgressReader<R, F> {
+    inner: R,
+    on_progress: F,
+    done: u64,
+    reported: u64,
+    cancel: Option<CancelToken>,
+}
+
+impl<R: Read, F: FnMut(u64)> ProgressReader<R, F> {
+    pub fn new(inner: R, on_progress: F) -> Self {
+        ProgressReader {
+            inner,
+            on_progress,
+            done: 0,
+            reported: 0,
+            cancel: None,
+        }
+    }
+
+    /// Fail reads once `cancel` is set, so a long copy stops midway.
+    pub fn with_cancel(mut self, cancel: Option<&CancelToken>) -> Self {
+        self.cancel = cancel.cloned();
+        self
+    }
+}
+
+impl<R: Read, F: FnMut(u64)> Read for ProgressReader<R, F> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.cancel.as_ref().is_some_and(CancelToken::is_cancelled) {
+            return Err(io::Error::other("cancelled"));
+        }
+        let n = self.inner.read(buf)?;
+        self.done += n as u64;
+        let at_end = n == 0 && !buf.is_empty();
+        if self.done - self.reported >= PROGRESS_INTERVAL || (at_end && self.done > self.reported) {
+            self.reported = self.done;
+            (self.on_progress)(self.done);
+        }
+        Ok(n)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -116,4 +258,48 @@ mod tests {
 
         assert!(result.is_err());
     }
+
+
+    #[test]
+    fn copy_dir_copies_nested_files() {
+        let source_dir = tempdir().unwrap();
+        let dest_dir = tempdir().unwrap();
+        fs::create_dir_all(source_dir.path().join("sub")).unwrap();
+        fs::write(source_dir.path().join("a.txt"), b"a").unwrap();
+        fs::write(source_dir.path().join("sub/b.txt"), b"b").unwrap();
+
+        let dest = dest_dir.path().join("copy");
+        copy_dir(source_dir.path(), &dest).unwrap();
+
+        assert_eq!(fs::read(dest.join("a.txt")).unwrap(), b"a");
+        assert_eq!(fs::read(dest.join("sub/b.txt")).unwrap(), b"b");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn set_executable_adds_execute_where_readable() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("tool");
+        fs::write(&path, b"#!/bin/sh").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o640)).unwrap();
+        assert!(!is_executable(&fs::metadata(&path).unwrap()));
+
+        set_executable(&path).unwrap();
+
+        let metadata = fs::metadata(&path).unwrap();
+        assert!(is_executable(&metadata));
+        assert_eq!(metadata.permissions().mode() & 0o777, 0o750);
+    }
+
+    #[test]
+    fn progress_reader_reports_per_interval_and_at_end() {
+        let data = vec![0u8; (PROGRESS_INTERVAL * 2 + 10) as usize];
+        let mut reports = Vec::new();
+        let mut reader = ProgressReader::new(&data[..], |done| reports.push(done));
+        io::copy(&mut reader, &mut io::sink()).unwrap();
+
+        assert_eq!(reports, vec![PROGRESS_INTERVAL, PROGRESS_INTERVAL * 2, PROGRESS_INTERVAL * 2 + 10]);
+    }
 }
@@ -1,4 +1,5 @@
 use sha2::{Digest, Sha256};
+use std::io::{self, Read};
 
 pub fn hash_bytes(data: &[u8]) -> String {
     let mut hasher = Sha256::new();
@@ -7,6 +8,13 @@ pub fn hash_bytes(data: &[u8]) -> String {
     format!("{:x}", result)
 }
 
+/// Hash a stream without holding it in memory.
+pub fn hash_reader(mut reader: impl Read) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut reader, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -24,6 +32,12 @@ mod tests {
         assert_eq!(hash1, hash2);
     }
 
+    #[test]
+    fn reader_hash_matches_bytes_hash() {
+        let data = vec![7u8; 3 << 20];
+        assert_eq!(hash_reader(&data[..]).unwrap(), hash_bytes(&data));
+    }
+
     #[test]
     fn different_input_different_hash() {
         let hash1 = hash_bytes(b"data a");
@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 use std::fs;
 use std::io;
 use std::path::Path;
@@ -11,6 +12,14 @@ pub enum ManifestEntry {
         original_hash: String,
         diff_hash: String,
         final_hash: String,
+        /// Other originals this entry patches, each with its own diff to the
+        /// same new file
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        alternatives: Vec<PatchOrigin>,
+        /// If the target file doesn't match `original_hash`, replace it with
+        /// the full new file from files/ instead of refusing to patch.
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        fallback: bool,
     },
     Add {
         file: String,
@@ -20,6 +29,39 @@ pub enum ManifestEntry {
         file: String,
         original_hash: String,
     },
+    /// File that must already exist with this hash. It is left untouched.
+    Require {
+        file: String,
+        hash: String,
+    },
+    /// Setting in a JSON file. The file is checked by parsing rather than
+    /// hashing, so the user's other changes to it are kept.
+    #[serde(rename = "edit_json")]
+    EditJson {
+        file: String,
+        /// JSON pointer to the setting, e.g. `/graphics/quality`
+        pointer: String,
+        value: serde_json::Value,
+    },
+    /// Setting in an INI file, checked by parsing like [`ManifestEntry::EditJson`].
+    #[serde(rename = "edit_ini")]
+    EditIni {
+        file: String,
+        /// Empty for keys before the first section header
+        #[serde(default)]
+        section: String,
+        key: String,
+        value: String,
+    },
+}
+
+/// Another original a Patch entry accepts, e.g. a regional release of the file.
+///
+/// Its diff is stored at `diffs/<file>.<original_hash>.diff`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PatchOrigin {
+    pub original_hash: String,
+    pub diff_hash: String,
 }
 
 impl ManifestEntry {
@@ -28,6 +70,42 @@ impl ManifestEntry {
             ManifestEntry::Patch { file, .. } => file,
             ManifestEntry::Add { file, .. } => file,
             ManifestEntry::Delete { file, .. } => file,
+            ManifestEntry::Require { file, .. } => file,
+            ManifestEntry::EditJson { file, .. } => file,
+            ManifestEntry::EditIni { file, .. } => file,
+        }
+    }
+
+    /// Original and diff hashes a Patch entry accepts, the main one first.
+    /// Empty for other entries.
+    pub fn patch_origins(&self) -> Vec<(&str, &str)> {
+        match self {
+            ManifestEntry::Patch {
+                original_hash,
+                diff_hash,
+                alternatives,
+                ..
+            } => std::iter::once((original_hash.as_str(), diff_hash.as_str()))
+                .chain(alternatives.iter().map(|o| (o.original_hash.as_str(), o.diff_hash.as_str())))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// True for entries that change one setting of a file. Several of them
+    /// may edit the same file.
+    pub fn is_edit(&self) -> bool {
+        matches!(self, ManifestEntry::EditJson { .. } | ManifestEntry::EditIni { .. })
+    }
+
+    /// Name of the setting an edit changes, like `/graphics/quality` or
+    /// `[Video] VSync`. None for other entries.
+    pub fn setting(&self) -> Option<String> {
+        match self {
+            ManifestEntry::EditJson { pointer, .. } => Some(pointer.clone()),
+            ManifestEntry::EditIni { section, key, .. } if section.is_empty() => Some(key.clone()),
+            ManifestEntry::EditIni { section, key, .. } => Some(format!("[{}] {}", section, key)),
+            _ => None,
         }
     }
 }
@@ -43,9 +121,168 @@ pub struct Manifest {
     /// Default is false for security.
     #[serde(default, skip_serializing_if = "std::ops::Not::not")]
     pub allow_restricted: bool,
+    /// Glob patterns excluded when the patch was created.
+    /// Entries matching these are rejected when applying.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub excluded: Vec<String>,
+    /// Where the patcher should look for the target folder.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub discovery: Option<Discovery>,
+    /// Default backup location, used instead of `.patch-backup` in the target.
+    /// Relative paths are placed in the per-user data directory.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backup_dir: Option<String>,
+    /// How backups of patched and deleted files are stored.
+    #[serde(default, skip_serializing_if = "BackupOptions::is_default")]
+    pub backup: BackupOptions,
+    /// Steps after a successful apply, shown to the user beforehand.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub post_apply: Vec<PostAction>,
+    /// Optional parts of the patch. Entries of files listed in no component
+    /// are always applied.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub components: Vec<Component>,
     pub entries: Vec<ManifestEntry>,
 }
 
+/// Optional part of a patch the user can choose, e.g. HD textures or a language.
+///
+/// A file belongs to at most one component, so each can be rolled back on
+/// its own.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Component {
+    /// Identifier used on the command line and in `requires`
+    pub id: String,
+    /// Name shown to the user
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Selected unless the user deselects it
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub default: bool,
+    /// Components that must be applied along with this one
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub requires: Vec<String>,
+    /// Files whose entries belong to this component
+    pub files: Vec<String>,
+}
+
+/// Add the components `requested` ones require, directly or not, keeping
+/// the order of `components`. Fails on an unknown id.
+pub fn resolve_components(components: &[Component], requested: &[String]) -> io::Result<Vec<String>> {
+    let mut selected = HashSet::new();
+    let mut pending: Vec<&str> = requested.iter().map(String::as_str).collect();
+    while let Some(id) = pending.pop() {
+        let component = components.iter().find(|c| c.id == id).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, format!("unknown component '{}'", id))
+        })?;
+        if selected.insert(id) {
+            pending.extend(component.requires.iter().map(String::as_str));
+        }
+    }
+    Ok(components.iter().filter(|c| selected.contains(c.id.as_str())).map(|c| c.id.clone()).collect())
+}
+
+/// The components chosen on a command line: the named `ids`, or None for
+/// the default ones unless `no_defaults` asks for none at all.
+pub fn component_selection(ids: &[String], no_defaults: bool) -> Option<&[String]> {
+    (!ids.is_empty() || no_defaults).then_some(ids)
+}
+
+/// Components that require `id`, directly or not.
+pub fn dependent_components(components: &[Component], id: &str) -> Vec<String> {
+    let mut dependents: Vec<String> = Vec::new();
+    let mut pending = vec![id];
+    while let Some(id) = pending.pop() {
+        for component in components.iter().filter(|c| c.requires.iter().any(|r| r == id)) {
+            if !dependents.contains(&component.id) {
+                dependents.push(component.id.clone());
+                pending.push(&component.id);
+            }
+        }
+    }
+    dependents
+}
+
+/// Step after a patch was applied, with paths relative to the target folder.
+///
+/// Deleting a folder happens on its own; launching and opening are offered
+/// to the user, who may decline.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum PostAction {
+    /// Delete a folder, e.g. a shader cache made stale by the patch
+    DeleteDir { path: String },
+    /// Launch an executable, with the target folder as working directory
+    Launch {
+        path: String,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        args: Vec<String>,
+    },
+    /// Open a file with its default application, e.g. a README
+    OpenFile { path: String },
+    /// Open a web page in the browser; only http and https are allowed
+    OpenUrl { url: String },
+}
+
+impl PostAction {
+    /// Path within the target, if the action has one
+    pub fn path(&self) -> Option<&str> {
+        match self {
+            PostAction::DeleteDir { path } | PostAction::Launch { path, .. } | PostAction::OpenFile { path } => {
+                Some(path)
+            }
+            PostAction::OpenUrl { .. } => None,
+        }
+    }
+
+    /// True if the action runs without asking the user
+    pub fn is_automatic(&self) -> bool {
+        matches!(self, PostAction::DeleteDir { .. })
+    }
+}
+
+impl std::fmt::Display for PostAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PostAction::DeleteDir { path } => write!(f, "delete folder {}", path),
+            PostAction::Launch { path, args } if args.is_empty() => write!(f, "launch {}", path),
+            PostAction::Launch { path, args } => write!(f, "launch {} {}", path, args.join(" ")),
+            PostAction::OpenFile { path } => write!(f, "open {}", path),
+            PostAction::OpenUrl { url } => write!(f, "open {}", url),
+        }
+    }
+}
+
+/// Space saving options for backups.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackupOptions {
+    /// Compress backed up files with zstd
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub compress: bool,
+    /// After applying, replace backups of patched files by reverse diffs where much smaller
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub reverse_diffs: bool,
+}
+
+impl BackupOptions {
+    pub fn is_default(&self) -> bool {
+        *self == BackupOptions::default()
+    }
+}
+
+/// Hints for finding the target folder automatically.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Discovery {
+    /// Install path templates, e.g. `~/.steam/steam/steamapps/common/MyGame`
+    /// or `%ProgramFiles%\MyGame`. `~`, `%VAR%`, `$VAR` and `${VAR}` are expanded.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub paths: Vec<String>,
+    /// Files that must exist in a folder for it to be considered.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub markers: Vec<String>,
+}
+
 impl Manifest {
     pub fn new(version: u32, name: String, title: Option<String>) -> Self {
         Manifest {
@@ -53,13 +290,81 @@ impl Manifest {
             name,
             title,
             allow_restricted: false,
+            excluded: Vec::new(),
+            discovery: None,
+            backup_dir: None,
+            backup: BackupOptions::default(),
+            post_apply: Vec::new(),
+            components: Vec::new(),
             entries: Vec::new(),
         }
     }
 
+    /// The component `file` belongs to, if any.
+    pub fn component_of(&self, file: &str) -> Option<&Component> {
+        self.components.iter().find(|c| c.files.iter().any(|f| f == file))
+    }
+
+    /// Components selected by default, with the ones they require.
+    pub fn default_components(&self) -> Vec<String> {
+        let defaults: Vec<String> = self.components.iter().filter(|c| c.default).map(|c| c.id.clone()).collect();
+        resolve_components(&self.components, &defaults).unwrap_or(defaults)
+    }
+
+    /// This manifest narrowed to the `requested` components and those they
+    /// require: other components and their entries are left out.
+    pub fn with_components(&self, requested: &[String]) -> io::Result<Manifest> {
+        let selected = resolve_components(&self.components, requested)?;
+        let mut manifest = self.clone();
+        manifest
+            .entries
+            .retain(|entry| self.component_of(entry.file()).is_none_or(|c| selected.contains(&c.id)));
+        manifest.components.retain(|c| selected.contains(&c.id));
+        Ok(manifest)
+    }
+
+    /// Entries belonging to the component `id`.
+    pub fn component_entries(&self, id: &str) -> Vec<ManifestEntry> {
+        self.entries
+            .iter()
+            .filter(|entry| self.component_of(entry.file()).is_some_and(|c| c.id == id))
+            .cloned()
+            .collect()
+    }
+
+    /// This manifest without the component `id` and its entries.
+    ///
+    /// Fails if the manifest has no such component, or if one of its other
+    /// components requires it.
+    pub fn without_component(&self, id: &str) -> io::Result<Manifest> {
+        if !self.components.iter().any(|c| c.id == id) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("component '{}' is not part of this patch", id),
+            ));
+        }
+        if let Some(dependent) = dependent_components(&self.components, id).first() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("component '{}' is required by '{}', remove that first", id, dependent),
+            ));
+        }
+        let mut manifest = self.clone();
+        manifest
+            .entries
+            .retain(|entry| self.component_of(entry.file()).is_none_or(|c| c.id != id));
+        manifest.components.retain(|c| c.id != id);
+        Ok(manifest)
+    }
+
     pub fn load(path: &Path) -> io::Result<Manifest> {
-        let content = fs::read_to_string(path)?;
-        serde_json::from_str(&content)
+        let content = fs::read(path)?;
+        Self::from_slice(&content)
+    }
+
+    /// Parse a manifest from JSON bytes.
+    pub fn from_slice(data: &[u8]) -> io::Result<Manifest> {
+        serde_json::from_slice(data)
             .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
     }
 
@@ -86,6 +391,10 @@ pub struct PatchInfo {
     pub patches: usize,
     pub additions: usize,
     pub deletions: usize,
+    pub requirements: usize,
+    pub edits: usize,
+    pub post_apply: Vec<PostAction>,
+    pub components: Vec<Component>,
 }
 
 impl PatchInfo {
@@ -93,11 +402,15 @@ impl PatchInfo {
         let mut patches = 0;
         let mut additions = 0;
         let mut deletions = 0;
+        let mut requirements = 0;
+        let mut edits = 0;
         for entry in &manifest.entries {
             match entry {
                 ManifestEntry::Patch { .. } => patches += 1,
                 ManifestEntry::Add { .. } => additions += 1,
                 ManifestEntry::Delete { .. } => deletions += 1,
+                ManifestEntry::Require { .. } => requirements += 1,
+                ManifestEntry::EditJson { .. } | ManifestEntry::EditIni { .. } => edits += 1,
             }
         }
         PatchInfo {
@@ -108,6 +421,10 @@ impl PatchInfo {
             patches,
             additions,
             deletions,
+            requirements,
+            edits,
+            post_apply: manifest.post_apply.clone(),
+            components: manifest.components.clone(),
         }
     }
 
@@ -121,6 +438,35 @@ impl PatchInfo {
             patches: 35,
             additions: 5,
             deletions: 2,
+            requirements: 0,
+            edits: 0,
+            post_apply: vec![
+                PostAction::DeleteDir {
+                    path: "cache".to_string(),
+                },
+                PostAction::Launch {
+                    path: "demo.exe".to_string(),
+                    args: Vec::new(),
+                },
+            ],
+            components: vec![
+                Component {
+                    id: "hd".to_string(),
+                    name: "HD textures".to_string(),
+                    description: Some("Sharper textures for large screens".to_string()),
+                    default: true,
+                    requires: Vec::new(),
+                    files: Vec::new(),
+                },
+                Component {
+                    id: "voices-de".to_string(),
+                    name: "Language: German".to_string(),
+                    description: None,
+                    default: false,
+                    requires: Vec::new(),
+                    files: Vec::new(),
+                },
+            ],
         }
     }
 }
@@ -137,12 +483,20 @@ mod tests {
             name: "TestPatcher".to_string(),
             title: Some("Test Patcher".to_string()),
             allow_restricted: false,
+            excluded: Vec::new(),
+            discovery: None,
+            backup_dir: None,
+            backup: Default::default(),
+            post_apply: Vec::new(),
+            components: Vec::new(),
             entries: vec![
                 ManifestEntry::Patch {
                     file: "game.bin".to_string(),
                     original_hash: "abc123".to_string(),
                     diff_hash: "def456".to_string(),
                     final_hash: "ghi789".to_string(),
+                    alternatives: Vec::new(),
+                    fallback: false,
                 },
                 ManifestEntry::Add {
                     file: "new_asset.bin".to_string(),
@@ -211,6 +565,12 @@ mod tests {
             name: "TestPatcher".to_string(),
             title: None,
             allow_restricted: false,
+            excluded: Vec::new(),
+            discovery: None,
+            backup_dir: None,
+            backup: Default::default(),
+            post_apply: Vec::new(),
+            components: Vec::new(),
             entries: vec![ManifestEntry::Add {
                 file: "test.bin".to_string(),
                 final_hash: "hash123".to_string(),
@@ -235,6 +595,8 @@ mod tests {
             original_hash: "x".to_string(),
             diff_hash: "y".to_string(),
             final_hash: "z".to_string(),
+            alternatives: Vec::new(),
+            fallback: false,
         };
         let add = ManifestEntry::Add {
             file: "b.bin".to_string(),
@@ -250,6 +612,73 @@ mod tests {
         assert_eq!(delete.file(), "c.bin");
     }
 
+    #[test]
+    fn edit_entries_are_deserialized() {
+        let json = r#"{"version": 1, "name": "TestPatcher", "entries": [
+            {"operation": "edit_json", "file": "settings.json", "pointer": "/gfx/quality", "value": {"level": 3}},
+            {"operation": "edit_ini", "file": "game.ini", "section": "Video", "key": "VSync", "value": "1"},
+            {"operation": "edit_ini", "file": "game.ini", "key": "Version", "value": "2"}
+        ]}"#;
+
+        let manifest = Manifest::from_slice(json.as_bytes()).unwrap();
+        assert!(manifest.entries.iter().all(ManifestEntry::is_edit));
+        assert_eq!(manifest.entries[1].file(), "game.ini");
+        assert!(matches!(&manifest.entries[2], ManifestEntry::EditIni { section, .. } if section.is_empty()));
+        assert_eq!(PatchInfo::from_manifest(&manifest).edits, 3);
+    }
+
+    #[test]
+    fn post_apply_actions_are_deserialized() {
+        let json = r#"{"version": 1, "name": "TestPatcher", "entries": [], "post_apply": [
+            {"action": "delete_dir", "path": "cache"},
+            {"action": "launch", "path": "game.exe", "args": ["-windowed"]},
+            {"action": "open_url", "url": "https://example.com"}
+        ]}"#;
+
+        let manifest = Manifest::from_slice(json.as_bytes()).unwrap();
+        assert_eq!(manifest.post_apply.len(), 3);
+        assert!(manifest.post_apply[0].is_automatic());
+        assert_eq!(manifest.post_apply[1].to_string(), "launch game.exe -windowed");
+        assert_eq!(manifest.post_apply[2].path(), None);
+    }
+
+    #[test]
+    fn components_select_entries_with_their_dependencies() {
+        let json = r#"{"version": 1, "name": "TestPatcher", "components": [
+            {"id": "hd", "name": "HD textures", "default": true, "files": ["textures_hd.pak"]},
+            {"id": "voices", "name": "Voices", "files": ["voices.pak"]},
+            {"id": "voices-de", "name": "Language: German", "requires": ["voices"], "files": ["voices_de.pak"]}
+        ], "entries": [
+            {"operation": "add", "file": "game.bin", "final_hash": "a"},
+            {"operation": "add", "file": "textures_hd.pak", "final_hash": "b"},
+            {"operation": "add", "file": "voices.pak", "final_hash": "c"},
+            {"operation": "add", "file": "voices_de.pak", "final_hash": "d"}
+        ]}"#;
+        let manifest = Manifest::from_slice(json.as_bytes()).unwrap();
+        let files = |manifest: &Manifest| manifest.entries.iter().map(|e| e.file().to_string()).collect::<Vec<_>>();
+
+        assert_eq!(manifest.default_components(), vec!["hd"]);
+        let selected = manifest.with_components(&["voices-de".to_string()]).unwrap();
+        assert_eq!(files(&selected), vec!["game.bin", "voices.pak", "voices_de.pak"]);
+        assert_eq!(selected.components.len(), 2);
+        assert!(manifest.with_components(&["fr".to_string()]).is_err());
+
+        // A component can only be removed once nothing requires it
+        assert!(selected.without_component("voices").is_err());
+        let remaining = selected.without_component("voices-de").unwrap();
+        assert_eq!(files(&remaining), vec!["game.bin", "voices.pak"]);
+        assert_eq!(selected.component_entries("voices-de").len(), 1);
+        assert_eq!(dependent_components(&manifest.components, "voices"), vec!["voices-de"]);
+
+        // Turning the defaults off on the command line selects no component
+        let ids = ["voices".to_string()];
+        assert_eq!(component_selection(&[], false), None);
+        assert_eq!(component_selection(&[], true), Some(&[][..]));
+        assert_eq!(component_selection(&ids, false), Some(&ids[..]));
+        let core = manifest.with_components(component_selection(&[], true).unwrap()).unwrap();
+        assert_eq!(files(&core), vec!["game.bin"]);
+    }
+
     #[test]
     fn title_is_deserialized() {
         let json = r#"{"version": 1, "name": "TestPatcher", "title": "My Custom Title", "entries": []}"#;
@@ -1,5 +1,7 @@
 pub mod diff;
 pub mod dir_scan;
+pub mod edit;
+pub mod file_filter;
 pub mod file_ops;
 pub mod hash;
 pub mod manifest;
@@ -0,0 +1,260 @@
+//! Patcher branding from the patch's `.graft_assets/`.
+//!
+//! - `banner.png`: image shown at the top of the window
+//! - `theme.json`: accent, background and text colors, dark or light mode and fonts
+//! - `README.md` and `CHANGELOG.md`: Markdown shown on the welcome screen
+//! - `EULA.md`: Markdown license the user must accept before applying
+//!
+//! Missing assets keep the defaults. Broken ones are skipped with a warning,
+//! so a bad theme never stops the patch from being applied. The license is
+//! the exception: one that can't be read fails loading, since applying must
+//! never skip it.
+
+use crate::i18n::t;
+use eframe::egui::{self, Color32};
+use graft_core::patch::{
+    PatchSource, ASSETS_DIR, BANNER_FILENAME, CHANGELOG_FILENAME, EULA_FILENAME, README_FILENAME, THEME_FILENAME,
+};
+use serde::Deserialize;
+use std::io;
+use std::sync::Arc;
+
+/// Contents of `theme.json`
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct Theme {
+    /// `#rrggbb` color for selections, links and the progress bar
+    accent: Option<String>,
+    /// `#rrggbb` color of the window background
+    background: Option<String>,
+    /// `#rrggbb` color of all text
+    text: Option<String>,
+    /// Force dark (true) or light (false) mode instead of following the OS
+    dark: Option<bool>,
+    /// Font files within the assets; the first replaces the default font,
+    /// the others are fallbacks for characters it lacks
+    fonts: Vec<String>,
+}
+
+/// Branding assets of a patch, ready to apply to the GUI
+#[derive(Default)]
+pub struct Branding {
+    pub banner: Option<egui::ColorImage>,
+    pub accent: Option<Color32>,
+    pub background: Option<Color32>,
+    pub text: Option<Color32>,
+    pub dark: Option<bool>,
+    /// Font name and data, primary font first
+    pub fonts: Vec<(String, Vec<u8>)>,
+    pub readme: Option<String>,
+    pub changelog: Option<String>,
+    pub eula: Option<String>,
+}
+
+impl Branding {
+    /// Load the branding assets of a patch
+    ///
+    /// Fails only if the patch has a license that cannot be read.
+    pub fn load(source: &dyn PatchSource) -> io::Result<Self> {
+        let mut branding = Branding {
+            readme: read_text(source, README_FILENAME),
+            changelog: read_text(source, CHANGELOG_FILENAME),
+            eula: read_license(source)?,
+            ..Default::default()
+        };
+
+        if let Some(data) = read_asset(source, BANNER_FILENAME) {
+            match image::load_from_memory_with_format(&data, image::ImageFormat::Png) {
+                Ok(image) => {
+                    let image = image.to_rgba8();
+                    let size = [image.width() as usize, image.height() as usize];
+                    branding.banner = Some(egui::ColorImage::from_rgba_unmultiplied(size, image.as_raw()));
+                }
+                Err(e) => warn_invalid(BANNER_FILENAME, e),
+            }
+        }
+
+        let Some(data) = read_asset(source, THEME_FILENAME) else {
+            return Ok(branding);
+        };
+        let theme: Theme = match serde_json::from_slice(&data) {
+            Ok(theme) => theme,
+            Err(e) => {
+                warn_invalid(THEME_FILENAME, e);
+                return Ok(branding);
+            }
+        };
+        branding.accent = parse_color(theme.accent.as_deref());
+        branding.background = parse_color(theme.background.as_deref());
+        branding.text = parse_color(theme.text.as_deref());
+        branding.dark = theme.dark;
+        branding.fonts = theme
+            .fonts
+            .iter()
+            .filter_map(|file| {
+                let data = read_asset(source, file);
+                if data.is_none() {
+                    warn_invalid(THEME_FILENAME, format!("font {} not found", file));
+                }
+                data.map(|data| (file.clone(), data))
+            })
+            .collect();
+        Ok(branding)
+    }
+
+    /// Apply the theme colors and fonts to the GUI
+    pub fn apply(&self, ctx: &egui::Context) {
+        if !self.fonts.is_empty() {
+            let mut fonts = egui::FontDefinitions::default();
+            let family = egui::FontFamily::Proportional;
+            for (i, (name, data)) in self.fonts.iter().enumerate() {
+                fonts
+                    .font_data
+                    .insert(name.clone(), Arc::new(egui::FontData::from_owned(data.clone())));
+                let names = fonts.families.entry(family.clone()).or_default();
+                if i == 0 {
+                    names.insert(0, name.clone());
+                } else {
+                    names.push(name.clone());
+                }
+            }
+            ctx.set_fonts(fonts);
+        }
+
+        if let Some(dark) = self.dark {
+            ctx.set_theme(if dark { egui::Theme::Dark } else { egui::Theme::Light });
+        }
+        ctx.all_styles_mut(|style| {
+            let visuals = &mut style.visuals;
+            if let Some(accent) = self.accent {
+                visuals.selection.bg_fill = accent;
+                visuals.hyperlink_color = accent;
+            }
+            if let Some(background) = self.background {
+                visuals.panel_fill = background;
+                visuals.window_fill = background;
+            }
+            if let Some(text) = self.text {
+                visuals.override_text_color = Some(text);
+            }
+        });
+    }
+}
+
+/// Read a text asset such as the readme or license
+fn read_text(source: &dyn PatchSource, file: &str) -> Option<String> {
+    read_asset(source, file).map(|data| String::from_utf8_lossy(&data).into_owned())
+}
+
+/// Read the license, failing if the patch has one that cannot be read
+pub fn read_license(source: &dyn PatchSource) -> io::Result<Option<String>> {
+    let path = format!("{}/{}", ASSETS_DIR, EULA_FILENAME);
+    if !source.contains(&path) {
+        return Ok(None);
+    }
+    let data = source.read(&path)?;
+    Ok(Some(String::from_utf8_lossy(&data).into_owned()))
+}
+
+/// Read an asset, warning if it exists but cannot be read
+fn read_asset(source: &dyn PatchSource, file: &str) -> Option<Vec<u8>> {
+    let path = format!("{}/{}", ASSETS_DIR, file);
+    if !source.contains(&path) {
+        return None;
+    }
+    match source.read(&path) {
+        Ok(data) => Some(data),
+        Err(e) => {
+            eprintln!("{}", t!("warning", message = t!("asset-unreadable", file = path, error = e)));
+            None
+        }
+    }
+}
+
+/// Parse a `#rrggbb` or `#rrggbbaa` color, warning if it is invalid
+fn parse_color(value: Option<&str>) -> Option<Color32> {
+    let value = value?;
+    match Color32::from_hex(value) {
+        Ok(color) => Some(color),
+        Err(e) => {
+            warn_invalid(THEME_FILENAME, format!("{}: {:?}", value, e));
+            None
+        }
+    }
+}
+
+fn warn_invalid(file: &str, error: impl std::fmt::Display) {
+    let path = format!("{}/{}", ASSETS_DIR, file);
+    eprintln!("{}", t!("warning", message = t!("asset-invalid", file = path, error = error)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn loads_assets_and_skips_invalid_colors() {
+        let patch_dir = tempdir().unwrap();
+        let assets = patch_dir.path().join(ASSETS_DIR);
+        fs::create_dir_all(&assets).unwrap();
+        image::RgbaImage::new(4, 2).save(assets.join(BANNER_FILENAME)).unwrap();
+        fs::write(
+            assets.join(THEME_FILENAME),
+            r##"{"accent": "#ff8800", "text": "orange", "dark": true, "fonts": ["brand.ttf", "missing.ttf"]}"##,
+        )
+        .unwrap();
+        fs::write(assets.join("brand.ttf"), b"font").unwrap();
+        fs::write(assets.join(README_FILENAME), "# Hello").unwrap();
+        fs::write(assets.join(EULA_FILENAME), "Terms").unwrap();
+
+        let branding = Branding::load(&patch_dir.path().to_path_buf()).unwrap();
+
+        assert_eq!(branding.banner.map(|b| b.size), Some([4, 2]));
+        assert_eq!(branding.accent, Some(Color32::from_rgb(0xff, 0x88, 0x00)));
+        assert_eq!(branding.text, None);
+        assert_eq!(branding.dark, Some(true));
+        assert_eq!(branding.fonts, vec![("brand.ttf".to_string(), b"font".to_vec())]);
+        assert_eq!(branding.readme.as_deref(), Some("# Hello"));
+        assert_eq!(branding.changelog, None);
+        assert_eq!(branding.eula.as_deref(), Some("Terms"));
+    }
+
+    #[test]
+    fn broken_theme_keeps_defaults() {
+        let patch_dir = tempdir().unwrap();
+        let assets = patch_dir.path().join(ASSETS_DIR);
+        fs::create_dir_all(&assets).unwrap();
+        fs::write(assets.join(THEME_FILENAME), r##"{"accent": "#ff8800", "colour": 1}"##).unwrap();
+        fs::write(assets.join(BANNER_FILENAME), b"not a png").unwrap();
+
+        let branding = Branding::load(&patch_dir.path().to_path_buf()).unwrap();
+
+        assert!(branding.banner.is_none());
+        assert_eq!(branding.accent, None);
+    }
+
+    /// A patch whose entries are listed but cannot be read
+    struct Unreadable;
+
+    impl PatchSource for Unreadable {
+        fn read(&self, path: &str) -> io::Result<Vec<u8>> {
+            Err(io::Error::new(io::ErrorKind::InvalidData, format!("{} is corrupt", path)))
+        }
+
+        fn contains(&self, _path: &str) -> bool {
+            true
+        }
+
+        fn payload_paths(&self) -> io::Result<Vec<String>> {
+            Ok(Vec::new())
+        }
+    }
+
+    #[test]
+    fn unreadable_license_fails_loading() {
+        assert!(read_license(&Unreadable).is_err());
+        assert!(Branding::load(&Unreadable).is_err());
+    }
+}
@@ -1,209 +1,604 @@
-use crate::runner::{PatchRunner, ProgressAction, ProgressEvent, RollbackEvent};
+use crate::branding;
+use crate::i18n::{action_label, post_action_label, t};
+use crate::progress::{self, Throughput};
+use crate::runner::{PatchRunner, ProgressEvent, RollbackEvent};
+use crate::self_read::PatchData;
 use crate::validator::PatchValidator;
-use std::io::{self, Write};
-use std::path::Path;
+use clap::Subcommand;
+use graft_core::patch::{self, format_size, format_timestamp, CancelToken, PatchError};
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
-fn format_action(action: ProgressAction) -> &'static str {
-    match action {
-        ProgressAction::Validating => "Validating",
-        ProgressAction::CheckingNotExists => "Checking",
-        ProgressAction::BackingUp => "Backing up",
-        ProgressAction::Skipping => "Skipping",
-        ProgressAction::Patching => "Patching",
-        ProgressAction::Adding => "Adding",
-        ProgressAction::Deleting => "Deleting",
-        ProgressAction::Restoring => "Restoring",
-        ProgressAction::Removing => "Removing",
+/// Shortest time between redraws of the progress line
+const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Width of the progress bar, in characters
+const BAR_WIDTH: usize = 30;
+
+/// Longest operation text shown after the bar, in characters
+const MAX_STATUS_WIDTH: usize = 50;
+
+/// Print a heading underlined to its width
+fn print_title(title: &str) {
+    println!("{}", title);
+    println!("{}", "=".repeat(title.chars().count()));
+}
+
+/// Read a yes/no answer; English "y" is accepted in every language
+fn confirmed() -> io::Result<bool> {
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+    let answer = input.trim();
+    Ok(answer.eq_ignore_ascii_case("y") || answer.eq_ignore_ascii_case(t!("yes-answer")))
+}
+
+/// Progress of the current phase on a single line, redrawn in place
+///
+/// When stdout is not a terminal (a log file or CI), every entry is
+/// printed on a line of its own instead.
+struct ProgressLine {
+    interactive: bool,
+    throughput: Throughput,
+    last_draw: Option<Instant>,
+    /// Characters of the last drawn line, to blank out what is left of it
+    drawn: usize,
+}
+
+impl ProgressLine {
+    fn new() -> Self {
+        ProgressLine {
+            interactive: io::stdout().is_terminal(),
+            throughput: Throughput::new(),
+            last_draw: None,
+            drawn: 0,
+        }
     }
+
+    /// End the current line and start measuring a new phase
+    fn start_phase(&mut self) {
+        self.finish();
+        self.throughput = Throughput::new();
+    }
+
+    fn operation(&mut self, event: ProgressEvent) {
+        let ProgressEvent::Operation {
+            file,
+            index,
+            total,
+            action,
+            bytes_done,
+            bytes_total,
+            overall_bytes_done,
+            overall_bytes_total,
+        } = event
+        else {
+            return;
+        };
+        if !self.interactive {
+            if bytes_done == 0 {
+                println!("  [{}/{}] {}: {}", index + 1, total, action_label(action), file);
+            }
+            return;
+        }
+
+        self.throughput.update(overall_bytes_done, overall_bytes_total);
+        let fraction = self
+            .throughput
+            .fraction()
+            .unwrap_or((index + 1) as f32 / total.max(1) as f32);
+        // Always draw the end of the phase, however soon it comes
+        let last = index + 1 == total && bytes_done >= bytes_total;
+        let now = Instant::now();
+        if !last && self.last_draw.is_some_and(|drawn| now - drawn < REDRAW_INTERVAL) {
+            return;
+        }
+        self.last_draw = Some(now);
+
+        let mut text = format!("  {} {:>3}%", progress::bar(fraction, BAR_WIDTH), (fraction * 100.0).round() as u32);
+        if let Some(rate) = self.throughput.summary() {
+            text.push_str("  ");
+            text.push_str(&rate);
+        }
+        let status = format!("[{}/{}] {}: {}", index + 1, total, action_label(action), file);
+        text.push_str("  ");
+        text.push_str(&shorten(&status, MAX_STATUS_WIDTH));
+
+        let width = text.chars().count();
+        print!("\r{}{}", text, " ".repeat(self.drawn.saturating_sub(width)));
+        let _ = io::stdout().flush();
+        self.drawn = width;
+    }
+
+    /// Move past the progress line, if one was drawn
+    fn finish(&mut self) {
+        if self.drawn > 0 {
+            println!();
+            self.drawn = 0;
+            self.last_draw = None;
+        }
+    }
+}
+
+/// Shorten `text` to `width` characters, keeping its end
+fn shorten(text: &str, width: usize) -> String {
+    let count = text.chars().count();
+    if count <= width {
+        return text.to_string();
+    }
+    let tail: String = text.chars().skip(count - width + 3).collect();
+    format!("...{}", tail)
+}
+
+/// Pick the target folder from the patch's known install locations
+///
+/// Prints every candidate and returns the best match. Exits if none is found.
+pub fn discover_target(patch_data: &PatchData) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let runner = PatchRunner::new(patch_data)?;
+
+    print!("{} ", t!("cli-searching"));
+    io::stdout().flush()?;
+    let candidates = runner.discover();
+
+    let Some(best) = candidates.first() else {
+        println!("{}", t!("cli-none-found"));
+        eprintln!("\n{}", t!("error", message = t!("cli-no-install-location")));
+        eprintln!("{}", t!("cli-pass-target"));
+        std::process::exit(1);
+    };
+
+    println!("{}", t!("cli-found", count = candidates.len()));
+    for candidate in &candidates {
+        println!(
+            "  {}",
+            t!("candidate", path = candidate.path.display(), matches = candidate.matches, checked = candidate.checked)
+        );
+    }
+    println!();
+    Ok(best.path.clone())
 }
 
 /// Run in headless (CLI) mode with embedded patch data
+///
+/// `components` replaces the patch's default selection of optional components.
 pub fn run_headless(
-    patch_data: &[u8],
+    patch_data: &PatchData,
     target_path: &Path,
     skip_confirm: bool,
+    backup_dir: Option<&Path>,
+    accept_license: bool,
+    skip_post_apply: bool,
+    components: Option<&[String]>,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    println!("Graft Patcher - Headless Mode");
-    println!("==============================");
+    print_title(t!("cli-apply-title"));
 
     // Validate patch and get info
-    print!("Validating patch data... ");
+    print!("{} ", t!("cli-validating-patch"));
     io::stdout().flush()?;
 
     let info = PatchValidator::validate(patch_data)?;
-    println!("done");
+    println!("{}", t!("done"));
 
     // Show patch info
-    println!("\nPatch Information:");
-    println!("  Version: {}", info.version);
-    println!("  Operations: {}", info.entry_count);
-    println!("    - {} patches", info.patches);
-    println!("    - {} additions", info.additions);
-    println!("    - {} deletions", info.deletions);
-    println!("\nTarget: {}", target_path.display());
+    println!("\n{}", t!("cli-patch-info"));
+    println!("  {}", t!("cli-version", version = info.version));
+    println!("  {}", t!("cli-operations", count = info.entry_count));
+    println!("    - {}", t!("count-patches", count = info.patches));
+    println!("    - {}", t!("count-additions", count = info.additions));
+    println!("    - {}", t!("count-deletions", count = info.deletions));
+    if info.requirements > 0 {
+        println!("    - {}", t!("count-requirements", count = info.requirements));
+    }
+    if info.edits > 0 {
+        println!("    - {}", t!("count-edits", count = info.edits));
+    }
+    if !info.post_apply.is_empty() {
+        println!("  {}", t!("cli-post-apply"));
+        for action in &info.post_apply {
+            println!("    - {}", post_action_label(action));
+        }
+        if skip_post_apply {
+            println!("  {}", t!("cli-post-apply-skipped"));
+        }
+    }
+    let welcome = t!("welcome-message");
+    if !welcome.is_empty() {
+        println!("\n{}", welcome);
+    }
+    println!("\n{}", t!("target", path = target_path.display()));
 
     // Create runner for validation checks
-    let runner = PatchRunner::new(patch_data)?;
+    let mut runner = PatchRunner::new(patch_data)?
+        .with_backup_dir(backup_dir.map(Path::to_path_buf))
+        .with_skip_post_apply(skip_post_apply);
+    if let Some(components) = components {
+        runner = runner.with_components(components)?;
+    }
+    if !runner.components().is_empty() {
+        let selected = runner.selected_components();
+        println!("\n{}", t!("cli-components"));
+        for component in runner.components() {
+            let mark = if selected.contains(&component.id) { "x" } else { " " };
+            println!("  [{}] {} ({})", mark, component.name, component.id);
+        }
+    }
 
-    // Check if already patched (backup exists)
-    if PatchRunner::has_backup(target_path) {
-        eprintln!("\nError: This folder appears to already be patched.");
-        eprintln!("A backup directory (.patch-backup) was found.");
+    // Check if already patched (restore point for this patch exists)
+    if runner.has_restore_point(target_path) {
+        eprintln!("\n{}", t!("error", message = t!("already-patched")));
+        eprintln!("{}", t!("cli-restore-point-found", path = runner.backup_location(target_path).display()));
         eprintln!();
-        eprintln!("To rollback the patch, run:");
+        eprintln!("{}", t!("cli-rollback-command"));
         eprintln!("  {} headless rollback {}", std::env::args().next().unwrap_or_default(), target_path.display());
         std::process::exit(1);
     }
 
+    // Earlier patches stay in place and are rolled back after this one
+    let restore_points = PatchRunner::restore_points(target_path);
+    if !restore_points.is_empty() {
+        println!("\n{}", t!("cli-stacking"));
+        for point in &restore_points {
+            println!("  - {}", point.label());
+        }
+    }
+
     // Pre-validate target folder
-    print!("\nValidating target folder... ");
+    print!("\n{} ", t!("cli-validating-target"));
     io::stdout().flush()?;
 
     if let Err(e) = runner.validate_target(target_path) {
-        println!("failed");
-        eprintln!("\nError: Target folder cannot be patched.");
+        println!("{}", t!("failed"));
+        eprintln!("\n{}", t!("error", message = t!("cli-cannot-patch")));
         eprintln!("{}", e);
         std::process::exit(1);
     }
-    println!("done");
+    println!("{}", t!("done"));
+    println!("{}", t!("cli-backups", path = runner.backup_location(target_path).display()));
+
+    // The license must be accepted even with -y, so never apply if it can't be read
+    let eula = patch_data
+        .open_source()
+        .and_then(|source| branding::read_license(&*source))
+        .map_err(|e| t!("license-unreadable", error = e).to_string())?;
+    if let Some(eula) = eula
+        && !accept_license
+    {
+        println!("\n{}\n", t!("cli-license"));
+        println!("{}", eula.trim_end());
+        print!("\n{} ", t!("cli-confirm-license"));
+        io::stdout().flush()?;
+
+        if !confirmed()? {
+            eprintln!("{}", t!("cli-license-declined"));
+            std::process::exit(1);
+        }
+    }
 
     // Confirm unless -y flag
     if !skip_confirm {
-        print!("\nApply patch? [y/N] ");
+        print!("\n{} ", t!("cli-confirm-apply"));
         io::stdout().flush()?;
 
-        let mut input = String::new();
-        io::stdin().read_line(&mut input)?;
-        if !input.trim().eq_ignore_ascii_case("y") {
-            println!("Aborted.");
+        if !confirmed()? {
+            println!("{}", t!("cli-aborted"));
             return Ok(());
         }
     }
 
+    // Ctrl+C cancels and rolls back; a second Ctrl+C quits immediately
+    let cancel = CancelToken::new();
+    let handler_token = cancel.clone();
+    ctrlc::set_handler(move || {
+        if handler_token.is_cancelled() {
+            std::process::exit(130);
+        }
+        eprintln!("\n{}", t!("cli-cancelling"));
+        handler_token.cancel();
+    })?;
+    let runner = runner.with_cancel(cancel);
+
     // Apply patch
-    println!("\nApplying patch...");
+    println!("\n{}", t!("cli-applying"));
 
+    let mut line = ProgressLine::new();
     let result = runner.apply(target_path, |event| match event {
         ProgressEvent::PhaseStarted { phase } => {
+            line.start_phase();
             println!("\n{}...", phase);
         }
-        ProgressEvent::Operation {
-            file,
-            index,
-            total,
-            action,
-        } => {
-            println!("  [{}/{}] {}: {}", index + 1, total, format_action(action), file);
+        ProgressEvent::Operation { .. } => line.operation(event),
+        ProgressEvent::PostApply { action, error } => {
+            line.finish();
+            match error {
+                None => println!("  {}: {}", post_action_label(&action), t!("done")),
+                Some(error) => eprintln!("  {}: {} ({})", post_action_label(&action), t!("failed"), error),
+            }
         }
-        ProgressEvent::Done { files_patched } => {
-            println!("\n{} files processed.", files_patched);
+        ProgressEvent::Done {
+            files_patched,
+            fallbacks,
+        } => {
+            line.finish();
+            println!("\n{}", t!("cli-files-processed", count = files_patched));
+            if !fallbacks.is_empty() {
+                println!("{}", t!("fallback-files", count = fallbacks.len()));
+                for file in &fallbacks {
+                    println!("  - {}", file);
+                }
+            }
         }
-        ProgressEvent::Error { .. } => {
-            // Error details will be printed by the result handler below
+        ProgressEvent::Cancelled | ProgressEvent::Error { .. } => {
+            // Reported by the result handler below
+            line.finish();
         }
     });
 
     match result {
         Ok(()) => {
-            println!("\nPatch applied successfully!");
+            println!("\n{}", t!("cli-apply-success"));
             println!();
-            println!("To rollback later, run:");
+            println!("{}", t!("cli-rollback-later"));
             println!("  {} headless rollback {}", std::env::args().next().unwrap_or_default(), target_path.display());
+
+            // Launching and opening needs someone to ask, so -y leaves them out
+            if !skip_post_apply && !skip_confirm {
+                for action in runner.post_apply().iter().filter(|a| !a.is_automatic()) {
+                    print!("\n{} ", t!("cli-confirm-post-action", action = post_action_label(action)));
+                    io::stdout().flush()?;
+                    if confirmed()?
+                        && let Err(e) = patch::run_post_action(action, target_path)
+                    {
+                        eprintln!("{}", t!("warning", message = e));
+                    }
+                }
+            }
             Ok(())
         }
+        Err(PatchError::Cancelled) => {
+            eprintln!("\n{}", t!("cli-cancelled"));
+            // Exit code of a process stopped by Ctrl+C
+            std::process::exit(130);
+        }
         Err(e) => {
-            eprintln!("\nError: {}", e);
+            eprintln!("\n{}", t!("error", message = e));
             std::process::exit(1);
         }
     }
 }
 
 /// Run rollback in headless (CLI) mode
+///
+/// With `component`, only that optional component is rolled back and the
+/// backup is kept for the rest of the patch.
 pub fn run_rollback(
-    patch_data: &[u8],
+    patch_data: Option<&PatchData>,
     target_path: &Path,
     force: bool,
+    backup_dir: Option<&Path>,
+    component: Option<&str>,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    println!("Graft Patcher - Headless Rollback");
-    println!("==================================");
-    println!("\nTarget: {}", target_path.display());
+    print_title(t!("cli-rollback-title"));
+    println!("\n{}", t!("target", path = target_path.display()));
 
-    // Create runner
-    let runner = PatchRunner::new(patch_data)?;
+    // Create runner; without patch data (a bare stub), the last applied patch is
+    // rolled back using the manifest saved with its backup
+    let runner = match patch_data {
+        Some(data) => PatchRunner::new(data)?.with_backup_dir(backup_dir.map(Path::to_path_buf)),
+        None => {
+            let runner = PatchRunner::from_backup(target_path, backup_dir.map(Path::to_path_buf))?;
+            if let Some(point) = PatchRunner::restore_points(target_path).last() {
+                println!("{}", t!("cli-patch", label = point.label()));
+            }
+            runner
+        }
+    };
 
-    // Check if backup exists
-    if !PatchRunner::has_backup(target_path) {
-        eprintln!("\nError: No backup directory found.");
-        eprintln!("Cannot rollback without .patch-backup directory.");
+    // Check if a restore point exists (an explicit backup dir is checked by the rollback itself)
+    if backup_dir.is_none() && !runner.has_restore_point(target_path) {
+        eprintln!("\n{}", t!("error", message = t!("cli-no-restore-point")));
+        eprintln!("{}", t!("cli-no-backup", path = runner.backup_location(target_path).display()));
         std::process::exit(1);
     }
 
-    println!("\nRolling back...");
+    println!("\n{}", t!("cli-rolling-back"));
 
     let mut error_occurred = false;
-    let result = runner.rollback(target_path, force, |event| match event {
+    let on_event = |event| match event {
         RollbackEvent::ValidatingTarget => {
-            print!("Validating target files... ");
+            print!("{} ", t!("cli-validating-target-files"));
             let _ = io::stdout().flush();
         }
         RollbackEvent::ValidatingBackup => {
-            println!("done");
-            print!("Validating backup... ");
+            println!("{}", t!("done"));
+            print!("{} ", t!("cli-validating-backup"));
             let _ = io::stdout().flush();
         }
         RollbackEvent::TargetModified { reason } => {
-            println!("failed");
-            eprintln!("\nError: Target files have been modified since patching.");
+            println!("{}", t!("failed"));
+            eprintln!("\n{}", t!("error", message = t!("cli-target-modified")));
             eprintln!("{}", reason);
             eprintln!();
-            eprintln!("To force rollback anyway, run:");
+            eprintln!("{}", t!("cli-force-rollback"));
             eprintln!("  {} headless rollback --force {}", std::env::args().next().unwrap_or_default(), target_path.display());
             error_occurred = true;
         }
         RollbackEvent::Rolling { file, index, total, action } => {
             if index == 0 {
-                println!("done\n");
+                println!("{}\n", t!("done"));
             }
-            println!("  [{}/{}] {}: {}", index + 1, total, format_action(action), file);
+            println!("  [{}/{}] {}: {}", index + 1, total, action_label(action), file);
         }
         RollbackEvent::Done { files_restored } => {
-            println!("\n{} files restored.", files_restored);
+            println!("\n{}", t!("cli-files-restored", count = files_restored));
         }
         RollbackEvent::Error { message } => {
-            eprintln!("\nError: {}", message);
+            eprintln!("\n{}", t!("error", message = message));
             error_occurred = true;
         }
-    });
+    };
+    let result = match component {
+        Some(id) => runner.remove_component(target_path, id, force, on_event),
+        None => runner.rollback(target_path, force, on_event),
+    };
 
     if error_occurred {
         std::process::exit(1);
     }
 
-    match result {
-        Ok(()) => {
-            println!("\nRollback complete!");
+    match (result, component) {
+        (Ok(()), Some(id)) => {
+            println!("\n{}", t!("cli-component-removed", name = id));
+            Ok(())
+        }
+        (Ok(()), None) => {
+            println!("\n{}", t!("cli-rollback-complete"));
 
             // Ask about deleting backup
-            print!("\nDelete backup directory? [y/N] ");
+            print!("\n{} ", t!("cli-confirm-delete-backup"));
             io::stdout().flush()?;
 
-            let mut input = String::new();
-            io::stdin().read_line(&mut input)?;
-            if input.trim().eq_ignore_ascii_case("y") {
-                if let Err(e) = PatchRunner::delete_backup(target_path) {
-                    eprintln!("Warning: Failed to delete backup: {}", e);
+            if confirmed()? {
+                if let Err(e) = runner.delete_backup(target_path) {
+                    eprintln!("{}", t!("warning", message = t!("cli-delete-backup-failed", error = e)));
                 } else {
-                    println!("Backup deleted.");
+                    println!("{}", t!("cli-backup-deleted"));
                 }
             } else {
-                println!("Backup preserved.");
+                println!("{}", t!("cli-backup-preserved"));
             }
 
             Ok(())
         }
-        Err(e) => {
-            eprintln!("\nError: {}", e);
+        (Err(e), _) => {
+            eprintln!("\n{}", t!("error", message = e));
             std::process::exit(1);
         }
     }
 }
+
+/// Backup management subcommands (same as `graft backup`)
+#[derive(Subcommand, Debug)]
+pub enum BackupAction {
+    /// List the backups of a target directory
+    List {
+        /// Patched target directory
+        path: PathBuf,
+    },
+    /// Show the files in a backup
+    Info {
+        /// Patched target directory
+        path: PathBuf,
+        /// Backup set to show (default: the last applied patch)
+        #[arg(long)]
+        id: Option<String>,
+    },
+    /// Check that this patch's backup can restore every file
+    /// (without patch data, the last applied patch's backup)
+    Verify {
+        /// Patched target directory
+        path: PathBuf,
+    },
+    /// Remove backups that are no longer needed
+    Prune {
+        /// Patched target directory
+        path: PathBuf,
+        /// Also remove all but this many of the most recent restore points
+        #[arg(long)]
+        keep: Option<usize>,
+    },
+    /// Copy, move or archive the backups elsewhere
+    Export {
+        /// Patched target directory
+        path: PathBuf,
+        /// Destination directory (or archive file with --archive)
+        dest: PathBuf,
+        /// Move the backups and keep using them from the destination
+        #[arg(long, conflicts_with = "archive")]
+        r#move: bool,
+        /// Write a .tar.zst archive instead of a directory
+        #[arg(long)]
+        archive: bool,
+    },
+}
+
+/// Run a backup management subcommand
+///
+/// Only `verify` needs the patch data.
+pub fn run_backup(patch_data: Option<&PatchData>, action: BackupAction) -> Result<(), Box<dyn std::error::Error>> {
+    match action {
+        BackupAction::List { path } => {
+            let sets = patch::backup_sets(&path)?;
+            let root = patch::backup_root(&path);
+            if sets.is_empty() {
+                println!("{}", t!("backup-none", path = root.display()));
+                return Ok(());
+            }
+            println!("{}", t!("backup-list-heading", path = root.display()));
+            for set in &sets {
+                let applied = set
+                    .restore_point
+                    .as_ref()
+                    .map(|p| t!("backup-list-applied", date = format_timestamp(p.created)))
+                    .unwrap_or_default();
+                let entry = t!(
+                    "backup-list-entry",
+                    label = set.label(),
+                    id = set.id,
+                    count = set.files.len(),
+                    size = format_size(set.size()),
+                );
+                println!("  {}{}", entry, applied);
+            }
+        }
+        BackupAction::Info { path, id } => {
+            let Some(set) = patch::find_backup_set(&path, id.as_deref())? else {
+                eprintln!("{}", t!("error", message = t!("backup-not-found", path = path.display())));
+                std::process::exit(1);
+            };
+            println!("{}", t!("backup-info", label = set.label()));
+            if let Some(point) = &set.restore_point {
+                println!("  {}", t!("backup-applied", date = format_timestamp(point.created)));
+            }
+            println!("  {}", t!("backup-location", path = set.dir.display()));
+            println!("  {}", t!("backup-size", size = format_size(set.size()), count = set.files.len()));
+            for file in &set.files {
+                println!("    {} ({}, {})", file.file, file.format, format_size(file.size));
+            }
+        }
+        BackupAction::Verify { path } => {
+            let runner = match patch_data {
+                Some(data) => PatchRunner::new(data)?,
+                None => PatchRunner::from_backup(&path, None)?,
+            };
+            print!("{} ", t!("backup-verifying"));
+            io::stdout().flush()?;
+            if let Err(e) = runner.verify_backup(&path) {
+                println!("{}", t!("failed"));
+                eprintln!("\n{}", t!("error", message = e));
+                std::process::exit(1);
+            }
+            println!("{}", t!("done"));
+            println!("{}", t!("backup-intact"));
+        }
+        BackupAction::Prune { path, keep } => {
+            let removed = patch::prune_backups(&path, keep)?;
+            if removed.is_empty() {
+                println!("{}", t!("backup-nothing-to-prune"));
+            }
+            for id in &removed {
+                println!("{}", t!("backup-removed", id = id));
+            }
+        }
+        BackupAction::Export { path, dest, r#move, archive } => {
+            if archive {
+                patch::archive_backups(&path, &dest)?;
+            } else if r#move {
+                patch::move_backups(&path, &dest)?;
+            } else {
+                patch::export_backups(&path, &dest)?;
+            }
+            println!("{}", t!("backup-exported", path = dest.display()));
+        }
+    }
+    Ok(())
+}
@@ -1,23 +1,20 @@
-use crate::runner::{PatchRunner, Phase, ProgressAction, ProgressEvent, RollbackEvent};
+use crate::branding::Branding;
+use crate::i18n::{action_label, post_action_label, t};
+use crate::markdown;
+use crate::progress::Throughput;
+use crate::runner::{PatchRunner, Phase, ProgressEvent, RollbackEvent};
+use crate::self_read::PatchData;
 use crate::validator::{PatchInfo, PatchValidationError, PatchValidator};
 use eframe::egui;
-use std::path::PathBuf;
+use graft_core::discovery::Candidate;
+use graft_core::patch::{run_post_action, CancelToken, RestorePoint};
+use graft_core::utils::manifest::{dependent_components, resolve_components, Component};
+use std::path::{Path, PathBuf};
 use std::sync::mpsc;
 use std::thread;
 
-fn format_action(action: ProgressAction) -> &'static str {
-    match action {
-        ProgressAction::Validating => "Validating",
-        ProgressAction::CheckingNotExists => "Checking",
-        ProgressAction::BackingUp => "Backing up",
-        ProgressAction::Skipping => "Skipping",
-        ProgressAction::Patching => "Patching",
-        ProgressAction::Adding => "Adding",
-        ProgressAction::Deleting => "Deleting",
-        ProgressAction::Restoring => "Restoring",
-        ProgressAction::Removing => "Removing",
-    }
-}
+/// Largest height of the banner image, in points
+const BANNER_MAX_HEIGHT: f32 = 100.0;
 
 /// Application state machine states
 #[derive(Debug, Clone)]
@@ -28,10 +25,17 @@ pub enum AppState {
     ValidatingFolder { path: PathBuf },
     /// Folder validation failed - cannot patch this folder
     FolderInvalid { path: PathBuf, reason: String },
-    /// Folder already has backup - appears to be patched already
-    AlreadyPatched { path: PathBuf, modified: bool },
-    /// User has selected a folder, ready to apply
-    FolderSelected { path: PathBuf },
+    /// Folder already has a restore point for this patch - appears to be patched already
+    AlreadyPatched {
+        path: PathBuf,
+        modified: bool,
+        /// Optional components the patch was applied with
+        components: Vec<Component>,
+    },
+    /// User has selected a folder, ready to apply (on top of any earlier patches)
+    FolderSelected { path: PathBuf, restore_points: Vec<RestorePoint> },
+    /// The patch's license must be accepted before applying
+    License { path: PathBuf, accepted: bool },
     /// Patch is being applied
     Applying {
         path: PathBuf,
@@ -39,23 +43,38 @@ pub enum AppState {
         current_phase: Option<Phase>,
         completed_phases: usize,
         phase_total: usize,
+        /// Bytes processed in the current phase, for speed and time left
+        throughput: Throughput,
         log: Vec<String>,
     },
+    /// Apply was cancelled and the folder left unchanged
+    Cancelled { log: Vec<String> },
     /// Patch applied successfully
     Success {
         path: PathBuf,
         files_patched: usize,
+        /// Files replaced by their full new version instead of being patched
+        fallbacks: Vec<String>,
         log: Vec<String>,
     },
-    /// Rollback in progress
+    /// Rollback in progress, of the whole patch or only one component
     RollingBack {
         path: PathBuf,
+        component: Option<String>,
         log: Vec<String>,
     },
     /// Rollback needs confirmation - target files have changed
-    RollbackWarning { path: PathBuf, reason: String },
+    RollbackWarning {
+        path: PathBuf,
+        component: Option<String>,
+        reason: String,
+    },
     /// Rollback completed successfully
-    RollbackComplete { path: PathBuf, log: Vec<String> },
+    RollbackComplete {
+        path: PathBuf,
+        component: Option<String>,
+        log: Vec<String>,
+    },
     /// An error occurred
     Error {
         message: String,
@@ -68,12 +87,12 @@ pub enum AppState {
 /// Result of folder validation
 #[derive(Debug, Clone)]
 pub enum ValidationResult {
-    /// Folder is valid for patching
-    Valid,
+    /// Folder is valid for patching, with the patches already applied to it
+    Valid { restore_points: Vec<RestorePoint> },
     /// Folder cannot be patched
     Invalid { reason: String },
-    /// Folder appears to already be patched
-    AlreadyPatched { modified: bool },
+    /// Folder appears to already be patched, with these optional components
+    AlreadyPatched { modified: bool, components: Vec<Component> },
 }
 
 /// Application mode
@@ -82,13 +101,15 @@ pub enum Mode {
     Demo,
     /// Real mode with embedded patch data
     Embedded {
-        patch_data: Vec<u8>,
+        patch_data: PatchData,
         /// Channel for receiving progress updates from worker thread (Some when applying)
         progress_rx: Option<mpsc::Receiver<ProgressEvent>>,
         /// Channel for receiving validation results
         validation_rx: Option<mpsc::Receiver<ValidationResult>>,
         /// Channel for receiving rollback events
         rollback_rx: Option<mpsc::Receiver<RollbackEvent>>,
+        /// Channel for receiving discovered target folders
+        discovery_rx: Option<mpsc::Receiver<Vec<Candidate>>>,
     },
 }
 
@@ -99,24 +120,55 @@ pub struct GraftApp {
     mode: Mode,
     /// Text input for manual path entry
     path_input: String,
+    /// Backup location setting; empty uses the target folder or manifest default
+    backup_dir_input: String,
+    /// Target folders found by discovery, best match first
+    candidates: Vec<Candidate>,
     /// Window title from manifest
     title: String,
+    /// Banner, theme, readme and license from the patch's assets
+    branding: Branding,
+    /// Banner uploaded to the GPU, once the window exists
+    banner: Option<egui::TextureHandle>,
+    /// The license was accepted during this session
+    license_accepted: bool,
+    /// Cancels the apply in progress (Some while the worker runs)
+    cancel: Option<CancelToken>,
+    /// Asking whether to cancel the apply because the window is closing
+    confirm_close: bool,
+    /// Close the window once the cancelled apply has stopped
+    quit_when_stopped: bool,
+    /// Why the last launch or open offered after applying failed
+    post_apply_error: Option<String>,
+    /// Optional components to apply, with the ones they require
+    components: Vec<String>,
 }
 
 impl GraftApp {
     /// Create a new app in demo mode with mock data
     pub fn demo() -> Self {
         let patch_info = PatchInfo::mock();
+        let components = default_selection(&patch_info.components);
         let title = patch_info
             .title
             .clone()
-            .unwrap_or_else(|| "Graft Patcher".to_string());
+            .unwrap_or_else(|| t!("app-title").to_string());
         GraftApp {
             state: AppState::Welcome,
             patch_info,
             mode: Mode::Demo,
             path_input: String::new(),
+            backup_dir_input: String::new(),
+            candidates: Vec::new(),
             title,
+            branding: Branding::default(),
+            banner: None,
+            license_accepted: false,
+            cancel: None,
+            confirm_close: false,
+            quit_when_stopped: false,
+            post_apply_error: None,
+            components,
         }
     }
 
@@ -124,12 +176,26 @@ impl GraftApp {
     ///
     /// Validates the patch to get PatchInfo for display, then stores
     /// the raw data for the worker thread to use when applying.
-    pub fn new(patch_data: Vec<u8>) -> Result<Self, PatchValidationError> {
+    pub fn new(patch_data: PatchData) -> Result<Self, PatchValidationError> {
         let patch_info = PatchValidator::validate(&patch_data)?;
+        let components = default_selection(&patch_info.components);
         let title = patch_info
             .title
             .clone()
-            .unwrap_or_else(|| "Graft Patcher".to_string());
+            .unwrap_or_else(|| t!("app-title").to_string());
+        let branding = patch_data
+            .open_source()
+            .and_then(|source| Branding::load(&*source))
+            .map_err(|e| PatchValidationError::DecompressionFailed(format!("Failed to read license: {}", e)))?;
+
+        // Scan known install locations in the background
+        let (tx, rx) = mpsc::channel();
+        let data = patch_data.clone();
+        thread::spawn(move || {
+            if let Ok(runner) = PatchRunner::new(&data) {
+                let _ = tx.send(runner.discover());
+            }
+        });
 
         Ok(GraftApp {
             state: AppState::Welcome,
@@ -139,12 +205,31 @@ impl GraftApp {
                 progress_rx: None,
                 validation_rx: None,
                 rollback_rx: None,
+                discovery_rx: Some(rx),
             },
             path_input: String::new(),
+            backup_dir_input: String::new(),
+            candidates: Vec::new(),
             title,
+            branding,
+            banner: None,
+            license_accepted: false,
+            cancel: None,
+            confirm_close: false,
+            quit_when_stopped: false,
+            post_apply_error: None,
+            components,
         })
     }
 
+    /// Apply the patch's theme and upload its banner
+    fn init_branding(&mut self, ctx: &egui::Context) {
+        self.branding.apply(ctx);
+        if let Some(image) = self.branding.banner.take() {
+            self.banner = Some(ctx.load_texture("banner", image, egui::TextureOptions::LINEAR));
+        }
+    }
+
     fn select_folder(&mut self) {
         if let Some(path) = rfd::FileDialog::new().pick_folder() {
             self.start_validation(path);
@@ -155,7 +240,7 @@ impl GraftApp {
         let patch_data = match &mut self.mode {
             Mode::Demo => {
                 // Demo mode: skip validation, go directly to FolderSelected
-                self.state = AppState::FolderSelected { path };
+                self.state = AppState::FolderSelected { path, restore_points: Vec::new() };
                 return;
             }
             Mode::Embedded { patch_data, validation_rx, .. } => {
@@ -182,18 +267,21 @@ impl GraftApp {
                 }
             };
 
-            // Check if backup exists (already patched scenario)
-            if PatchRunner::has_backup(&path) {
+            // Check if this patch has a restore point (already patched scenario)
+            if runner.has_restore_point(&path) {
                 // Check if files are in patched state
                 let modified = !runner.is_patched(&path);
-                let _ = tx.send(ValidationResult::AlreadyPatched { modified });
+                let components = runner.applied_components(&path);
+                let _ = tx.send(ValidationResult::AlreadyPatched { modified, components });
                 return;
             }
 
             // Validate folder can be patched
             match runner.validate_target(&path) {
                 Ok(()) => {
-                    let _ = tx.send(ValidationResult::Valid);
+                    let _ = tx.send(ValidationResult::Valid {
+                        restore_points: PatchRunner::restore_points(&path),
+                    });
                 }
                 Err(e) => {
                     let _ = tx.send(ValidationResult::Invalid {
@@ -204,6 +292,21 @@ impl GraftApp {
         });
     }
 
+    /// Backup location chosen in the settings, if any
+    fn backup_dir(&self) -> Option<PathBuf> {
+        let input = self.backup_dir_input.trim();
+        (!input.is_empty()).then(|| PathBuf::from(input))
+    }
+
+    /// Apply the patch, asking to accept its license first if it has one
+    fn request_apply(&mut self, target_path: PathBuf) {
+        if self.branding.eula.is_some() && !self.license_accepted {
+            self.state = AppState::License { path: target_path, accepted: false };
+        } else {
+            self.start_apply(target_path);
+        }
+    }
+
     fn start_apply(&mut self, target_path: PathBuf) {
         let patch_data = match &mut self.mode {
             Mode::Demo => {
@@ -213,7 +316,8 @@ impl GraftApp {
                     progress: 0.0,
                     current_phase: Some(Phase::Applying),
                     completed_phases: 0,
-                    phase_total: self.patch_info.entry_count,
+                    phase_total: self.selected_entry_count(),
+                    throughput: Throughput::new(),
                     log: vec!["[Demo] Starting patch application...".to_string()],
                 };
                 return;
@@ -226,7 +330,8 @@ impl GraftApp {
             }
         };
 
-        let total = self.patch_info.entry_count;
+        let total = self.selected_entry_count();
+        self.post_apply_error = None;
 
         self.state = AppState::Applying {
             path: target_path.clone(),
@@ -234,18 +339,23 @@ impl GraftApp {
             current_phase: None,
             completed_phases: 0,
             phase_total: total,
+            throughput: Throughput::new(),
             log: Vec::new(),
         };
 
         let (patch_data, tx) = patch_data;
+        let backup_dir = self.backup_dir();
+        let components = self.components.clone();
+        let cancel = CancelToken::new();
+        self.cancel = Some(cancel.clone());
 
         // Worker thread creates and owns its own runner
         thread::spawn(move || {
-            let runner = match PatchRunner::new(&patch_data) {
-                Ok(r) => r,
+            let runner = match PatchRunner::new(&patch_data).and_then(|r| r.with_components(&components)) {
+                Ok(r) => r.with_backup_dir(backup_dir).with_cancel(cancel),
                 Err(e) => {
                     let _ = tx.send(ProgressEvent::Error {
-                        message: "Failed to create patch runner".to_string(),
+                        message: t!("error-create-runner").to_string(),
                         details: Some(e.to_string()),
                     });
                     return;
@@ -281,6 +391,7 @@ impl GraftApp {
                         completed_phases,
                         progress,
                         phase_total,
+                        throughput,
                         ..
                     } = &mut self.state
                     {
@@ -289,6 +400,7 @@ impl GraftApp {
                             *completed_phases += 1;
                         }
                         *current_phase = Some(phase);
+                        *throughput = Throughput::new();
                         log.push(format!("[{}]", phase));
                         // Update progress: each phase is 1/3 of total
                         *progress = *completed_phases as f32 / 3.0;
@@ -301,32 +413,63 @@ impl GraftApp {
                     index,
                     total,
                     action,
+                    bytes_done,
+                    overall_bytes_done,
+                    overall_bytes_total,
+                    ..
                 } => {
                     if let AppState::Applying {
                         log,
                         progress,
                         completed_phases,
                         phase_total,
+                        throughput,
                         ..
                     } = &mut self.state
                     {
-                        log.push(format!("  [{}/{}] {}: {}", index + 1, total, format_action(action), file));
+                        // Entries are reported again as their data is processed; log them once
+                        if bytes_done == 0 {
+                            log.push(format!("  [{}/{}] {}: {}", index + 1, total, action_label(action), file));
+                        }
                         *phase_total = total;
-                        // Progress: completed phases + current phase progress
-                        let phase_progress = (index + 1) as f32 / total.max(1) as f32;
+                        throughput.update(overall_bytes_done, overall_bytes_total);
+                        // Progress: completed phases + current phase progress, by bytes
+                        // when the phase has file data and by entries otherwise
+                        let phase_progress = throughput
+                            .fraction()
+                            .unwrap_or((index + 1) as f32 / total.max(1) as f32);
                         *progress = (*completed_phases as f32 + phase_progress) / 3.0;
                     }
                 }
-                ProgressEvent::Done { files_patched } => {
+                ProgressEvent::Done {
+                    files_patched,
+                    fallbacks,
+                } => {
                     if let AppState::Applying { path, log, .. } = &self.state {
                         self.state = AppState::Success {
                             path: path.clone(),
                             files_patched,
+                            fallbacks,
                             log: log.clone(),
                         };
                     }
                     should_clear_rx = true;
                 }
+                ProgressEvent::PostApply { action, error } => {
+                    if let AppState::Applying { log, .. } = &mut self.state {
+                        let label = post_action_label(&action);
+                        log.push(match error {
+                            None => format!("  {}: {}", label, t!("done")),
+                            Some(error) => format!("  {}: {} ({})", label, t!("failed"), error),
+                        });
+                    }
+                }
+                ProgressEvent::Cancelled => {
+                    if let AppState::Applying { log, .. } = &self.state {
+                        self.state = AppState::Cancelled { log: log.clone() };
+                    }
+                    should_clear_rx = true;
+                }
                 ProgressEvent::Error { message, details } => {
                     let log = if let AppState::Applying { log, .. } = &self.state {
                         log.clone()
@@ -346,6 +489,7 @@ impl GraftApp {
 
         if should_clear_rx {
             *progress_rx = None;
+            self.cancel = None;
         }
     }
 
@@ -364,14 +508,14 @@ impl GraftApp {
             if let AppState::ValidatingFolder { path } = &self.state {
                 let path = path.clone();
                 match result {
-                    ValidationResult::Valid => {
-                        self.state = AppState::FolderSelected { path };
+                    ValidationResult::Valid { restore_points } => {
+                        self.state = AppState::FolderSelected { path, restore_points };
                     }
                     ValidationResult::Invalid { reason } => {
                         self.state = AppState::FolderInvalid { path, reason };
                     }
-                    ValidationResult::AlreadyPatched { modified } => {
-                        self.state = AppState::AlreadyPatched { path, modified };
+                    ValidationResult::AlreadyPatched { modified, components } => {
+                        self.state = AppState::AlreadyPatched { path, modified, components };
                     }
                 }
             }
@@ -379,6 +523,28 @@ impl GraftApp {
         }
     }
 
+    fn process_discovery_messages(&mut self) {
+        let discovery_rx = match &mut self.mode {
+            Mode::Demo => return,
+            Mode::Embedded { discovery_rx, .. } => discovery_rx,
+        };
+
+        let candidates = discovery_rx
+            .as_ref()
+            .and_then(|rx| rx.try_recv().ok());
+
+        if let Some(candidates) = candidates {
+            // Pre-fill the best match unless the user already typed something
+            if self.path_input.is_empty()
+                && let Some(best) = candidates.first()
+            {
+                self.path_input = best.path.display().to_string();
+            }
+            self.candidates = candidates;
+            *discovery_rx = None;
+        }
+    }
+
     fn process_rollback_messages(&mut self) {
         let rollback_rx = match &mut self.mode {
             Mode::Demo => return,
@@ -399,9 +565,10 @@ impl GraftApp {
                     // Could add log messages here if desired
                 }
                 RollbackEvent::TargetModified { reason } => {
-                    if let AppState::RollingBack { path, .. } = &self.state {
+                    if let AppState::RollingBack { path, component, .. } = &self.state {
                         self.state = AppState::RollbackWarning {
                             path: path.clone(),
+                            component: component.clone(),
                             reason,
                         };
                     }
@@ -409,13 +576,14 @@ impl GraftApp {
                 }
                 RollbackEvent::Rolling { file, index, total, action } => {
                     if let AppState::RollingBack { log, .. } = &mut self.state {
-                        log.push(format!("  [{}/{}] {}: {}", index + 1, total, format_action(action), file));
+                        log.push(format!("  [{}/{}] {}: {}", index + 1, total, action_label(action), file));
                     }
                 }
                 RollbackEvent::Done { .. } => {
-                    if let AppState::RollingBack { path, log, .. } = &self.state {
+                    if let AppState::RollingBack { path, component, log } = &self.state {
                         self.state = AppState::RollbackComplete {
                             path: path.clone(),
+                            component: component.clone(),
                             log: log.clone(),
                         };
                     }
@@ -424,7 +592,7 @@ impl GraftApp {
                 RollbackEvent::Error { message } => {
                     if let AppState::RollingBack { log, .. } = &self.state {
                         self.state = AppState::Error {
-                            message: "Rollback failed".to_string(),
+                            message: t!("error-rollback").to_string(),
                             details: Some(message),
                             show_details: false,
                             log: log.clone(),
@@ -440,12 +608,14 @@ impl GraftApp {
         }
     }
 
-    fn start_rollback(&mut self, path: PathBuf, force: bool) {
+    /// Roll back the patch, or only `component` of it
+    fn start_rollback(&mut self, path: PathBuf, force: bool, component: Option<String>) {
         let patch_data = match &mut self.mode {
             Mode::Demo => {
                 // Demo mode: simulate rollback complete
                 self.state = AppState::RollbackComplete {
                     path,
+                    component,
                     log: vec!["[Demo] Rollback complete".to_string()],
                 };
                 return;
@@ -460,7 +630,8 @@ impl GraftApp {
 
         self.state = AppState::RollingBack {
             path: path.clone(),
-            log: vec!["[Rolling back]".to_string()],
+            component: component.clone(),
+            log: vec![t!("gui-rolling-back-log").to_string()],
         };
 
         let (patch_data, tx) = patch_data;
@@ -477,9 +648,13 @@ impl GraftApp {
                 }
             };
 
-            let _ = runner.rollback(&path, force, |event| {
+            let send = |event| {
                 let _ = tx.send(event);
-            });
+            };
+            let _ = match &component {
+                Some(id) => runner.remove_component(&path, id, force, send),
+                None => runner.rollback(&path, force, send),
+            };
         });
     }
 
@@ -507,43 +682,94 @@ impl GraftApp {
     }
 
     fn render_welcome(&mut self, ui: &mut egui::Ui) {
-        ui.heading("Patch Ready to Apply");
+        ui.heading(t!("gui-welcome-heading"));
         ui.add_space(16.0);
 
         ui.group(|ui| {
-            ui.label(format!("Graft version: {}", env!("CARGO_PKG_VERSION")));
-            ui.label(format!("Patch version: {}", self.patch_info.version));
-            ui.label(format!("Total operations: {}", self.patch_info.entry_count));
+            ui.label(t!("gui-graft-version", version = env!("CARGO_PKG_VERSION")));
+            ui.label(t!("gui-patch-version", version = self.patch_info.version));
+            ui.label(t!("gui-total-operations", count = self.patch_info.entry_count));
             ui.add_space(8.0);
             ui.horizontal(|ui| {
-                ui.label(format!("{} patches", self.patch_info.patches));
+                ui.label(t!("count-patches", count = self.patch_info.patches));
                 ui.separator();
-                ui.label(format!("{} additions", self.patch_info.additions));
+                ui.label(t!("count-additions", count = self.patch_info.additions));
                 ui.separator();
-                ui.label(format!("{} deletions", self.patch_info.deletions));
+                ui.label(t!("count-deletions", count = self.patch_info.deletions));
+                if self.patch_info.requirements > 0 {
+                    ui.separator();
+                    ui.label(t!("count-requirements", count = self.patch_info.requirements));
+                }
+                if self.patch_info.edits > 0 {
+                    ui.separator();
+                    ui.label(t!("count-edits", count = self.patch_info.edits));
+                }
             });
         });
 
+        let welcome = t!("welcome-message");
+        if !welcome.is_empty() {
+            ui.add_space(8.0);
+            ui.label(welcome);
+        }
+
+        if let Some(readme) = &self.branding.readme {
+            ui.add_space(8.0);
+            egui::ScrollArea::vertical()
+                .id_salt("readme")
+                .max_height(140.0)
+                .show(ui, |ui| markdown::show(ui, readme));
+        }
+        if let Some(changelog) = &self.branding.changelog {
+            ui.add_space(8.0);
+            ui.collapsing(t!("gui-changelog"), |ui| {
+                egui::ScrollArea::vertical()
+                    .id_salt("changelog")
+                    .max_height(120.0)
+                    .show(ui, |ui| markdown::show(ui, changelog));
+            });
+        }
+
         ui.add_space(24.0);
 
+        if !self.candidates.is_empty() {
+            ui.label(t!("gui-detected-installations"));
+            let mut chosen = None;
+            for candidate in &self.candidates {
+                let label = t!(
+                    "candidate",
+                    path = candidate.path.display(),
+                    matches = candidate.matches,
+                    checked = candidate.checked,
+                );
+                if ui.button(label).clicked() {
+                    chosen = Some(candidate.path.clone());
+                }
+            }
+            if let Some(path) = chosen {
+                self.start_validation(path);
+            }
+            ui.add_space(8.0);
+        }
+
         ui.horizontal(|ui| {
-            if ui.button("Select Folder...").clicked() {
+            if ui.button(t!("gui-select-folder")).clicked() {
                 self.select_folder();
             }
         });
 
         ui.add_space(8.0);
-        ui.label("Or enter path manually:");
+        ui.label(t!("gui-enter-path"));
         ui.horizontal(|ui| {
             ui.add(
                 egui::TextEdit::singleline(&mut self.path_input)
-                    .hint_text("/path/to/folder")
+                    .hint_text(t!("gui-path-hint"))
                     .desired_width(250.0),
             );
             let path = PathBuf::from(&self.path_input);
             let valid = !self.path_input.is_empty() && path.is_absolute();
             if ui
-                .add_enabled(valid, egui::Button::new("Use Path"))
+                .add_enabled(valid, egui::Button::new(t!("gui-use-path")))
                 .clicked()
             {
                 self.start_validation(path);
@@ -560,59 +786,200 @@ impl GraftApp {
         }
     }
 
-    fn render_folder_selected(&mut self, ui: &mut egui::Ui, path: PathBuf) {
-        ui.heading("Ready to Apply");
+    fn render_folder_selected(&mut self, ui: &mut egui::Ui, path: PathBuf, restore_points: Vec<RestorePoint>) {
+        ui.heading(t!("gui-ready-heading"));
         ui.add_space(16.0);
 
         ui.group(|ui| {
-            ui.label("Target folder:");
+            ui.label(t!("gui-target-folder"));
             ui.label(egui::RichText::new(path.display().to_string()).monospace());
         });
 
+        if !restore_points.is_empty() {
+            ui.add_space(8.0);
+            ui.label(t!("gui-already-applied"));
+            for point in &restore_points {
+                ui.label(egui::RichText::new(format!("  \u{2022} {}", point.label())).small());
+            }
+        }
+
+        ui.add_space(8.0);
+        ui.collapsing(t!("gui-backup-location"), |ui| {
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.backup_dir_input)
+                        .hint_text(t!("gui-backup-location-hint"))
+                        .desired_width(250.0),
+                );
+                if ui.button(t!("gui-browse")).clicked()
+                    && let Some(dir) = rfd::FileDialog::new().pick_folder()
+                {
+                    self.backup_dir_input = dir.display().to_string();
+                }
+            });
+            ui.label(
+                egui::RichText::new(t!("gui-backup-location-help"))
+                    .small()
+                    .color(egui::Color32::GRAY),
+            );
+        });
+
+        if !self.patch_info.components.is_empty() {
+            ui.add_space(8.0);
+            ui.label(t!("gui-components"));
+            self.render_component_choice(ui);
+        }
+
         ui.add_space(16.0);
-        ui.label(format!(
-            "This will apply {} operations to the selected folder.",
-            self.patch_info.entry_count
-        ));
+        ui.label(t!("gui-apply-summary", count = self.selected_entry_count()));
+        if !self.patch_info.post_apply.is_empty() {
+            ui.add_space(8.0);
+            ui.label(t!("gui-post-apply"));
+            for action in &self.patch_info.post_apply {
+                ui.label(format!("  \u{2022} {}", post_action_label(action)));
+            }
+        }
 
         ui.add_space(24.0);
 
+        let backup_dir_valid = self.backup_dir().is_none_or(|dir| dir.is_absolute());
         ui.horizontal(|ui| {
-            if ui.button("Apply Patch").clicked() {
-                self.start_apply(path.clone());
+            if ui.add_enabled(backup_dir_valid, egui::Button::new(t!("gui-apply-patch"))).clicked() {
+                self.request_apply(path.clone());
             }
-            if ui.button("Change Folder...").clicked() {
+            if ui.button(t!("gui-change-folder")).clicked() {
                 self.select_folder();
             }
         });
     }
 
+    /// Checkboxes for the optional components
+    ///
+    /// Selecting a component selects the ones it requires, and deselecting
+    /// it deselects the ones requiring it.
+    fn render_component_choice(&mut self, ui: &mut egui::Ui) {
+        let components = self.patch_info.components.clone();
+        for component in &components {
+            let mut selected = self.components.contains(&component.id);
+            if ui.checkbox(&mut selected, &component.name).changed() {
+                if selected {
+                    let mut requested = self.components.clone();
+                    requested.push(component.id.clone());
+                    self.components = resolve_components(&components, &requested).unwrap_or(requested);
+                } else {
+                    let dropped = dependent_components(&components, &component.id);
+                    self.components.retain(|id| id != &component.id && !dropped.contains(id));
+                }
+            }
+            let mut details = Vec::new();
+            if let Some(description) = &component.description {
+                details.push(description.clone());
+            }
+            if !component.requires.is_empty() {
+                let names: Vec<&str> = component
+                    .requires
+                    .iter()
+                    .filter_map(|id| components.iter().find(|c| &c.id == id))
+                    .map(|c| c.name.as_str())
+                    .collect();
+                details.push(t!("component-requires", names = names.join(", ")).to_string());
+            }
+            if !details.is_empty() {
+                ui.indent(&component.id, |ui| {
+                    ui.label(egui::RichText::new(details.join(" \u{2022} ")).small().color(egui::Color32::GRAY));
+                });
+            }
+        }
+    }
+
+    /// Operations the patch applies with the selected components
+    fn selected_entry_count(&self) -> usize {
+        let left_out: usize = self
+            .patch_info
+            .components
+            .iter()
+            .filter(|c| !self.components.contains(&c.id))
+            .map(|c| c.files.len())
+            .sum();
+        self.patch_info.entry_count.saturating_sub(left_out)
+    }
+
+    fn render_license(&mut self, ui: &mut egui::Ui, path: PathBuf, mut accepted: bool) {
+        ui.heading(t!("gui-license-heading"));
+        ui.add_space(8.0);
+
+        let eula = self.branding.eula.as_deref().unwrap_or_default();
+        egui::Frame::NONE
+            .fill(ui.visuals().extreme_bg_color)
+            .corner_radius(4.0)
+            .inner_margin(8.0)
+            .show(ui, |ui| {
+                ui.set_width(ui.available_width());
+                egui::ScrollArea::vertical()
+                    .id_salt("license")
+                    .max_height(200.0)
+                    .show(ui, |ui| markdown::show(ui, eula));
+            });
+
+        ui.add_space(8.0);
+        if ui.checkbox(&mut accepted, t!("gui-accept-license")).changed() {
+            self.state = AppState::License { path: path.clone(), accepted };
+        }
+
+        ui.add_space(16.0);
+        ui.horizontal(|ui| {
+            if ui.add_enabled(accepted, egui::Button::new(t!("gui-continue"))).clicked() {
+                self.license_accepted = true;
+                self.start_apply(path.clone());
+            }
+            if ui.button(t!("gui-decline")).clicked() {
+                self.state = AppState::Welcome;
+            }
+        });
+    }
+
     fn render_applying(
         &mut self,
         ui: &mut egui::Ui,
         log: Vec<String>,
         progress: f32,
         current_phase: Option<Phase>,
+        rate: Option<String>,
     ) {
-        ui.heading("Applying Patch...");
+        ui.heading(t!("gui-applying-heading"));
         ui.add_space(16.0);
 
         ui.add(egui::ProgressBar::new(progress).show_percentage());
         ui.add_space(8.0);
 
         if let Some(phase) = current_phase {
-            ui.label(format!("Phase: {}", phase));
+            ui.label(t!("gui-phase", phase = phase));
+        }
+        if let Some(rate) = rate {
+            ui.label(rate);
         }
 
         ui.add_space(8.0);
         Self::render_log(ui, &log);
 
+        if let Some(cancel) = &self.cancel {
+            ui.add_space(16.0);
+            if cancel.is_cancelled() {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label(t!("gui-cancelling"));
+                });
+            } else if ui.button(t!("gui-cancel")).clicked() {
+                cancel.cancel();
+            }
+        }
+
         // Demo mode: simulate progress
         if matches!(self.mode, Mode::Demo) {
             ui.add_space(16.0);
             ui.horizontal(|ui| {
-                if ui.button("Simulate Progress").clicked() {
-                    if let AppState::Applying {
+                if ui.button("Simulate Progress").clicked()
+                    && let AppState::Applying {
                         path,
                         phase_total,
                         completed_phases,
@@ -620,34 +987,35 @@ impl GraftApp {
                         current_phase,
                         ..
                     } = &self.state
-                    {
-                        let mut new_log = log.clone();
-                        let batch_size = 10; // Simulate ~10 ops per click
-                        let new_completed = (completed_phases + batch_size).min(*phase_total);
-                        // Log each simulated operation in the batch
-                        for i in (*completed_phases + 1)..=new_completed {
-                            new_log.push(format!(
-                                "  [{}/{}] Patching: file_{}.bin",
-                                i, phase_total, i
-                            ));
-                        }
-                        let new_progress = new_completed as f32 / *phase_total as f32;
-                        if new_completed >= *phase_total {
-                            self.state = AppState::Success {
-                                path: path.clone(),
-                                files_patched: *phase_total,
-                                log: new_log,
-                            };
-                        } else {
-                            self.state = AppState::Applying {
-                                path: path.clone(),
-                                progress: new_progress,
-                                current_phase: *current_phase,
-                                completed_phases: new_completed,
-                                phase_total: *phase_total,
-                                log: new_log,
-                            };
-                        }
+                {
+                    let mut new_log = log.clone();
+                    let batch_size = 10; // Simulate ~10 ops per click
+                    let new_completed = (completed_phases + batch_size).min(*phase_total);
+                    // Log each simulated operation in the batch
+                    for i in (*completed_phases + 1)..=new_completed {
+                        new_log.push(format!(
+                            "  [{}/{}] Patching: file_{}.bin",
+                            i, phase_total, i
+                        ));
+                    }
+                    let new_progress = new_completed as f32 / *phase_total as f32;
+                    if new_completed >= *phase_total {
+                        self.state = AppState::Success {
+                            path: path.clone(),
+                            files_patched: *phase_total,
+                            fallbacks: Vec::new(),
+                            log: new_log,
+                        };
+                    } else {
+                        self.state = AppState::Applying {
+                            path: path.clone(),
+                            progress: new_progress,
+                            current_phase: *current_phase,
+                            completed_phases: new_completed,
+                            phase_total: *phase_total,
+                            throughput: Throughput::new(),
+                            log: new_log,
+                        };
                     }
                 }
                 if ui.button("Simulate Error").clicked() {
@@ -676,6 +1044,7 @@ impl GraftApp {
         ui: &mut egui::Ui,
         path: PathBuf,
         files_patched: usize,
+        fallbacks: &[String],
         log: &[String],
     ) {
         ui.vertical_centered(|ui| {
@@ -694,9 +1063,9 @@ impl GraftApp {
             );
 
             ui.add_space(8.0);
-            ui.heading("Patch Applied Successfully!");
+            ui.heading(t!("gui-success-heading"));
             ui.add_space(4.0);
-            ui.label(format!("{} operations completed", files_patched));
+            ui.label(t!("gui-operations-completed", count = files_patched));
             ui.label(
                 egui::RichText::new(path.display().to_string())
                     .monospace()
@@ -704,22 +1073,52 @@ impl GraftApp {
             );
         });
 
+        if !fallbacks.is_empty() {
+            ui.add_space(8.0);
+            ui.colored_label(
+                egui::Color32::from_rgb(245, 158, 11),
+                t!("fallback-files", count = fallbacks.len()),
+            );
+            for file in fallbacks {
+                ui.label(egui::RichText::new(file).monospace().small());
+            }
+        }
+
         ui.add_space(8.0);
         Self::render_log(ui, log);
         ui.add_space(8.0);
 
         ui.label(
-            egui::RichText::new("To rollback later, relaunch this patcher and select the same folder.")
+            egui::RichText::new(t!("gui-rollback-hint"))
                 .small()
                 .color(egui::Color32::GRAY),
         );
         ui.add_space(8.0);
 
+        // Launching and opening is up to the user
+        let offers: Vec<_> = self.patch_info.post_apply.iter().filter(|a| !a.is_automatic()).cloned().collect();
+        if !offers.is_empty() {
+            ui.horizontal_wrapped(|ui| {
+                for action in &offers {
+                    if ui.button(post_action_label(action)).clicked() {
+                        self.post_apply_error = match self.mode {
+                            Mode::Demo => None,
+                            Mode::Embedded { .. } => run_post_action(action, &path).err().map(|e| e.to_string()),
+                        };
+                    }
+                }
+            });
+            if let Some(error) = &self.post_apply_error {
+                ui.colored_label(egui::Color32::from_rgb(239, 68, 68), error);
+            }
+            ui.add_space(8.0);
+        }
+
         ui.horizontal(|ui| {
-            if ui.button("Rollback").clicked() {
-                self.start_rollback(path.clone(), false);
+            if ui.button(t!("gui-rollback")).clicked() {
+                self.start_rollback(path.clone(), false, None);
             }
-            if ui.button("Quit").clicked() {
+            if ui.button(t!("gui-quit")).clicked() {
                 ctx.send_viewport_cmd(egui::ViewportCommand::Close);
             }
         });
@@ -750,7 +1149,7 @@ impl GraftApp {
             );
 
             ui.add_space(8.0);
-            ui.heading("Error");
+            ui.heading(t!("gui-error-heading"));
         });
 
         ui.add_space(4.0);
@@ -759,9 +1158,9 @@ impl GraftApp {
         if let Some(ref detail_text) = details {
             ui.add_space(4.0);
             let button_text = if show_details {
-                "Hide Details"
+                t!("gui-hide-details")
             } else {
-                "Show Details"
+                t!("gui-show-details")
             };
             if ui.button(button_text).clicked() {
                 self.state = AppState::Error {
@@ -787,27 +1186,86 @@ impl GraftApp {
         ui.add_space(8.0);
 
         ui.horizontal(|ui| {
-            if ui.button("Try Again").clicked() {
+            if ui.button(t!("gui-try-again")).clicked() {
+                self.state = AppState::Welcome;
+            }
+            if ui.button(t!("gui-quit")).clicked() {
+                ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+            }
+        });
+    }
+
+    fn render_cancelled(&mut self, ctx: &egui::Context, ui: &mut egui::Ui, log: &[String]) {
+        ui.vertical_centered(|ui| {
+            ui.add_space(8.0);
+
+            // Amber circle with exclamation mark
+            let (rect, _) = ui.allocate_exact_size(egui::vec2(60.0, 60.0), egui::Sense::hover());
+            ui.painter()
+                .circle_filled(rect.center(), 30.0, egui::Color32::from_rgb(245, 158, 11));
+            ui.painter().text(
+                rect.center(),
+                egui::Align2::CENTER_CENTER,
+                "!",
+                egui::FontId::proportional(36.0),
+                egui::Color32::WHITE,
+            );
+
+            ui.add_space(8.0);
+            ui.heading(t!("gui-cancelled-heading"));
+        });
+
+        ui.add_space(4.0);
+        ui.label(t!("gui-cancelled"));
+
+        ui.add_space(8.0);
+        Self::render_log(ui, log);
+        ui.add_space(8.0);
+
+        ui.horizontal(|ui| {
+            if ui.button(t!("gui-try-again")).clicked() {
                 self.state = AppState::Welcome;
             }
-            if ui.button("Quit").clicked() {
+            if ui.button(t!("gui-quit")).clicked() {
                 ctx.send_viewport_cmd(egui::ViewportCommand::Close);
             }
         });
     }
 
-    fn render_validating_folder(&self, ui: &mut egui::Ui, path: &PathBuf) {
-        ui.heading("Validating Folder...");
+    /// Ask whether to cancel the apply in progress so the window can close
+    fn render_close_confirmation(&mut self, ctx: &egui::Context) {
+        egui::Modal::new(egui::Id::new("confirm_close")).show(ctx, |ui| {
+            ui.heading(t!("gui-close-heading"));
+            ui.add_space(8.0);
+            ui.label(t!("gui-close-prompt"));
+            ui.add_space(16.0);
+            ui.horizontal(|ui| {
+                if ui.button(t!("gui-cancel-and-quit")).clicked() {
+                    if let Some(cancel) = &self.cancel {
+                        cancel.cancel();
+                    }
+                    self.quit_when_stopped = true;
+                    self.confirm_close = false;
+                }
+                if ui.button(t!("gui-keep-patching")).clicked() {
+                    self.confirm_close = false;
+                }
+            });
+        });
+    }
+
+    fn render_validating_folder(&self, ui: &mut egui::Ui, path: &Path) {
+        ui.heading(t!("gui-validating-heading"));
         ui.add_space(16.0);
 
         ui.group(|ui| {
-            ui.label("Checking:");
+            ui.label(t!("gui-checking"));
             ui.label(egui::RichText::new(path.display().to_string()).monospace());
         });
 
         ui.add_space(16.0);
         ui.spinner();
-        ui.label("Verifying files can be patched...");
+        ui.label(t!("gui-verifying-files"));
     }
 
     fn render_folder_invalid(&mut self, ui: &mut egui::Ui, path: PathBuf, reason: String) {
@@ -827,17 +1285,17 @@ impl GraftApp {
             );
 
             ui.add_space(8.0);
-            ui.heading("Cannot Patch This Folder");
+            ui.heading(t!("gui-invalid-heading"));
         });
 
         ui.add_space(8.0);
         ui.group(|ui| {
-            ui.label("Target folder:");
+            ui.label(t!("gui-target-folder"));
             ui.label(egui::RichText::new(path.display().to_string()).monospace().small());
         });
 
         ui.add_space(8.0);
-        ui.label("Reason:");
+        ui.label(t!("gui-reason"));
         egui::ScrollArea::vertical()
             .max_height(80.0)
             .show(ui, |ui| {
@@ -846,13 +1304,13 @@ impl GraftApp {
 
         ui.add_space(16.0);
         ui.horizontal(|ui| {
-            if ui.button("Choose Different Folder...").clicked() {
+            if ui.button(t!("gui-choose-different-folder")).clicked() {
                 self.select_folder();
             }
         });
     }
 
-    fn render_already_patched(&mut self, ui: &mut egui::Ui, path: PathBuf, modified: bool) {
+    fn render_already_patched(&mut self, ui: &mut egui::Ui, path: PathBuf, modified: bool, components: Vec<Component>) {
         ui.vertical_centered(|ui| {
             ui.add_space(8.0);
 
@@ -870,47 +1328,59 @@ impl GraftApp {
 
             ui.add_space(8.0);
             if modified {
-                ui.heading("Folder Was Patched (Modified)");
+                ui.heading(t!("gui-patched-modified-heading"));
             } else {
-                ui.heading("Folder Already Patched");
+                ui.heading(t!("gui-patched-heading"));
             }
         });
 
         ui.add_space(8.0);
         ui.group(|ui| {
-            ui.label("Target folder:");
+            ui.label(t!("gui-target-folder"));
             ui.label(egui::RichText::new(path.display().to_string()).monospace().small());
         });
 
         ui.add_space(8.0);
         if modified {
-            ui.label("This folder was patched but files have been modified since.");
+            ui.label(t!("gui-patched-modified"));
             ui.add_space(16.0);
             ui.horizontal(|ui| {
-                if ui.button("Re-apply Patch").clicked() {
-                    self.start_apply(path.clone());
+                if ui.button(t!("gui-reapply")).clicked() {
+                    self.request_apply(path.clone());
                 }
-                if ui.button("Rollback Anyway").clicked() {
-                    self.start_rollback(path.clone(), true);
+                if ui.button(t!("gui-rollback-anyway")).clicked() {
+                    self.start_rollback(path.clone(), true, None);
                 }
             });
         } else {
-            ui.label("This folder appears to already be patched.");
+            ui.label(t!("already-patched"));
+            if !components.is_empty() {
+                ui.add_space(8.0);
+                ui.label(t!("gui-applied-components"));
+                for component in &components {
+                    ui.horizontal(|ui| {
+                        ui.label(format!("  \u{2022} {}", component.name));
+                        if ui.small_button(t!("gui-remove-component")).clicked() {
+                            self.start_rollback(path.clone(), false, Some(component.id.clone()));
+                        }
+                    });
+                }
+            }
             ui.add_space(16.0);
             ui.horizontal(|ui| {
-                if ui.button("Rollback").clicked() {
-                    self.start_rollback(path.clone(), false);
+                if ui.button(t!("gui-rollback")).clicked() {
+                    self.start_rollback(path.clone(), false, None);
                 }
             });
         }
     }
 
-    fn render_rolling_back(&self, ui: &mut egui::Ui, path: &PathBuf, log: &[String]) {
-        ui.heading("Rolling Back...");
+    fn render_rolling_back(&self, ui: &mut egui::Ui, path: &Path, log: &[String]) {
+        ui.heading(t!("gui-rolling-back-heading"));
         ui.add_space(16.0);
 
         ui.group(|ui| {
-            ui.label("Target folder:");
+            ui.label(t!("gui-target-folder"));
             ui.label(egui::RichText::new(path.display().to_string()).monospace().small());
         });
 
@@ -920,7 +1390,7 @@ impl GraftApp {
         Self::render_log(ui, log);
     }
 
-    fn render_rollback_warning(&mut self, ui: &mut egui::Ui, path: PathBuf, reason: String) {
+    fn render_rollback_warning(&mut self, ui: &mut egui::Ui, path: PathBuf, component: Option<String>, reason: String) {
         ui.vertical_centered(|ui| {
             ui.add_space(8.0);
 
@@ -937,11 +1407,11 @@ impl GraftApp {
             );
 
             ui.add_space(8.0);
-            ui.heading("Files Modified Since Patching");
+            ui.heading(t!("gui-modified-heading"));
         });
 
         ui.add_space(8.0);
-        ui.label("Some files have been changed after the patch was applied.");
+        ui.label(t!("gui-modified"));
         ui.add_space(4.0);
         egui::ScrollArea::vertical()
             .max_height(80.0)
@@ -951,20 +1421,31 @@ impl GraftApp {
 
         ui.add_space(16.0);
         ui.horizontal(|ui| {
-            if ui.button("Rollback Anyway").clicked() {
-                self.start_rollback(path.clone(), true);
+            if ui.button(t!("gui-rollback-anyway")).clicked() {
+                self.start_rollback(path.clone(), true, component.clone());
             }
-            if ui.button("Cancel").clicked() {
+            if ui.button(t!("gui-cancel")).clicked() {
                 self.state = AppState::Welcome;
             }
         });
     }
 
+    /// Delete this patch's backup set from target
+    fn delete_backup(&self, target: &Path) -> std::io::Result<()> {
+        match &self.mode {
+            Mode::Demo => Ok(()),
+            Mode::Embedded { patch_data, .. } => PatchRunner::new(patch_data)
+                .map_err(|e| std::io::Error::other(e.to_string()))?
+                .delete_backup(target),
+        }
+    }
+
     fn render_rollback_complete(
         &mut self,
         ctx: &egui::Context,
         ui: &mut egui::Ui,
         path: PathBuf,
+        component: Option<String>,
         log: &[String],
     ) {
         ui.vertical_centered(|ui| {
@@ -983,7 +1464,7 @@ impl GraftApp {
             );
 
             ui.add_space(8.0);
-            ui.heading("Rollback Complete");
+            ui.heading(t!("gui-rollback-complete-heading"));
         });
 
         ui.add_space(8.0);
@@ -997,14 +1478,30 @@ impl GraftApp {
         Self::render_log(ui, log);
         ui.add_space(8.0);
 
-        ui.label("Would you like to delete the backup files?");
+        // The backup is still needed for the rest of the patch
+        if let Some(id) = component {
+            let name = self
+                .patch_info
+                .components
+                .iter()
+                .find(|c| c.id == id)
+                .map_or(id.clone(), |c| c.name.clone());
+            ui.label(t!("gui-component-removed", name = name));
+            ui.add_space(8.0);
+            if ui.button(t!("gui-quit")).clicked() {
+                ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+            }
+            return;
+        }
+
+        ui.label(t!("gui-delete-backup-prompt"));
         ui.add_space(8.0);
 
         ui.horizontal(|ui| {
-            if ui.button("Delete Backup").clicked() {
-                if let Err(e) = PatchRunner::delete_backup(&path) {
+            if ui.button(t!("gui-delete-backup")).clicked() {
+                if let Err(e) = self.delete_backup(&path) {
                     self.state = AppState::Error {
-                        message: "Failed to delete backup".to_string(),
+                        message: t!("error-delete-backup").to_string(),
                         details: Some(e.to_string()),
                         show_details: false,
                         log: log.to_vec(),
@@ -1013,7 +1510,7 @@ impl GraftApp {
                     ctx.send_viewport_cmd(egui::ViewportCommand::Close);
                 }
             }
-            if ui.button("Keep Backup").clicked() {
+            if ui.button(t!("gui-keep-backup")).clicked() {
                 ctx.send_viewport_cmd(egui::ViewportCommand::Close);
             }
         });
@@ -1026,18 +1523,47 @@ impl eframe::App for GraftApp {
         self.process_progress_messages();
         self.process_validation_messages();
         self.process_rollback_messages();
+        self.process_discovery_messages();
+
+        // Closing mid-apply would leave a half-patched folder; cancel first
+        if self.cancel.is_some() && ctx.input(|i| i.viewport().close_requested()) {
+            ctx.send_viewport_cmd(egui::ViewportCommand::CancelClose);
+            self.confirm_close = true;
+        }
+        if self.quit_when_stopped && self.cancel.is_none() {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+        }
+
+        let discovering = matches!(
+            self.mode,
+            Mode::Embedded {
+                discovery_rx: Some(_),
+                ..
+            }
+        );
 
         // Request repaint for async states (to get progress updates)
-        if matches!(
-            self.state,
-            AppState::Applying { .. }
-                | AppState::ValidatingFolder { .. }
-                | AppState::RollingBack { .. }
-        ) {
+        if discovering
+            || matches!(
+                self.state,
+                AppState::Applying { .. }
+                    | AppState::ValidatingFolder { .. }
+                    | AppState::RollingBack { .. }
+            )
+        {
             ctx.request_repaint();
         }
 
         egui::CentralPanel::default().show(ctx, |ui| {
+            if let Some(banner) = &self.banner {
+                ui.vertical_centered(|ui| {
+                    ui.add(
+                        egui::Image::new(banner)
+                            .max_height(BANNER_MAX_HEIGHT)
+                            .max_width(ui.available_width()),
+                    );
+                });
+            }
             ui.add_space(16.0);
 
             // Clone state to avoid borrow issues
@@ -1048,29 +1574,34 @@ impl eframe::App for GraftApp {
                 AppState::FolderInvalid { path, reason } => {
                     self.render_folder_invalid(ui, path, reason)
                 }
-                AppState::AlreadyPatched { path, modified } => {
-                    self.render_already_patched(ui, path, modified)
+                AppState::AlreadyPatched { path, modified, components } => {
+                    self.render_already_patched(ui, path, modified, components)
                 }
-                AppState::FolderSelected { path } => self.render_folder_selected(ui, path),
+                AppState::FolderSelected { path, restore_points } => {
+                    self.render_folder_selected(ui, path, restore_points)
+                }
+                AppState::License { path, accepted } => self.render_license(ui, path, accepted),
                 AppState::Applying {
                     log,
                     progress,
                     current_phase,
+                    throughput,
                     ..
-                } => self.render_applying(ui, log, progress, current_phase),
+                } => self.render_applying(ui, log, progress, current_phase, throughput.summary()),
                 AppState::Success {
                     path,
                     files_patched,
+                    fallbacks,
                     log,
-                } => self.render_success(ctx, ui, path, files_patched, &log),
+                } => self.render_success(ctx, ui, path, files_patched, &fallbacks, &log),
                 AppState::RollingBack { path, log, .. } => {
                     self.render_rolling_back(ui, &path, &log)
                 }
-                AppState::RollbackWarning { path, reason } => {
-                    self.render_rollback_warning(ui, path, reason)
+                AppState::RollbackWarning { path, component, reason } => {
+                    self.render_rollback_warning(ui, path, component, reason)
                 }
-                AppState::RollbackComplete { path, log } => {
-                    self.render_rollback_complete(ctx, ui, path, &log)
+                AppState::RollbackComplete { path, component, log } => {
+                    self.render_rollback_complete(ctx, ui, path, component, &log)
                 }
                 AppState::Error {
                     message,
@@ -1078,22 +1609,26 @@ impl eframe::App for GraftApp {
                     show_details,
                     log,
                 } => self.render_error(ctx, ui, message, details, show_details, log),
+                AppState::Cancelled { log } => self.render_cancelled(ctx, ui, &log),
             }
         });
+
+        if self.confirm_close {
+            self.render_close_confirmation(ctx);
+        }
     }
 }
 
-/// Run the GUI application
-pub fn run(patch_data: Option<&[u8]>) -> eframe::Result<()> {
-    let options = eframe::NativeOptions {
-        viewport: egui::ViewportBuilder::default()
-            .with_inner_size([400.0, 380.0])
-            .with_min_inner_size([350.0, 340.0]),
-        ..Default::default()
-    };
+/// The default components and the ones they require
+fn default_selection(components: &[Component]) -> Vec<String> {
+    let defaults: Vec<String> = components.iter().filter(|c| c.default).map(|c| c.id.clone()).collect();
+    resolve_components(components, &defaults).unwrap_or(defaults)
+}
 
+/// Run the GUI application
+pub fn run(patch_data: Option<PatchData>) -> eframe::Result<()> {
     let app: GraftApp = if let Some(data) = patch_data {
-        match GraftApp::new(data.to_vec()) {
+        match GraftApp::new(data) {
             Ok(app) => app,
             Err(e) => {
                 eprintln!("Failed to load embedded patch: {}", e);
@@ -1107,10 +1642,24 @@ pub fn run(patch_data: Option<&[u8]>) -> eframe::Result<()> {
         GraftApp::demo()
     };
 
+    // Make room for the banner and readme
+    let branded = app.branding.banner.is_some() || app.branding.readme.is_some();
+    let size = if branded { [480.0, 560.0] } else { [400.0, 380.0] };
+    let options = eframe::NativeOptions {
+        viewport: egui::ViewportBuilder::default()
+            .with_inner_size(size)
+            .with_min_inner_size([350.0, 340.0]),
+        ..Default::default()
+    };
+
     let title = app.title.clone();
     eframe::run_native(
         &title,
         options,
-        Box::new(|_cc| Ok(Box::new(app))),
+        Box::new(move |cc| {
+            let mut app = app;
+            app.init_branding(&cc.egui_ctx);
+            Ok(Box::new(app))
+        }),
     )
 }
@@ -0,0 +1,251 @@
+//! Message catalogs for the GUI and headless output.
+//!
+//! A catalog is a plain text file of `key = value` lines. Blank lines and
+//! lines starting with `#` are ignored, `\n` in a value is a line break and
+//! `{name}` is replaced by an argument of the message.
+//!
+//! Catalogs are layered from English up to the most specific language, so
+//! `pt-BR` falls back to `pt` and then English for missing keys. Each layer
+//! is the built-in catalog followed by the patch's own
+//! `.graft_assets/lang/<lang>.lang`, which lets patch authors add languages
+//! and replace any message, including the custom `welcome-message`.
+
+use graft_core::patch::{PatchSource, ProgressAction, ASSETS_DIR, LANG_DIR, LANG_EXTENSION};
+use graft_core::utils::manifest::PostAction;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::sync::OnceLock;
+
+/// Language used when nothing else matches
+pub const DEFAULT_LANG: &str = "en";
+
+/// Catalogs compiled into the stub
+const BUILTIN: &[(&str, &str)] = &[
+    ("en", include_str!("../locales/en.lang")),
+    ("de", include_str!("../locales/de.lang")),
+];
+
+static CATALOG: OnceLock<Catalog> = OnceLock::new();
+
+/// Messages for one language, with fallbacks already merged in
+#[derive(Debug, Default)]
+pub struct Catalog {
+    messages: HashMap<String, String>,
+}
+
+impl Catalog {
+    /// Build the catalog for `lang`, or the OS locale if None
+    ///
+    /// Translations in the patch's assets are read from `source`.
+    pub fn load(lang: Option<&str>, source: Option<&dyn PatchSource>) -> Self {
+        let lang = lang
+            .map(str::to_string)
+            .or_else(sys_locale::get_locale)
+            .unwrap_or_else(|| DEFAULT_LANG.to_string());
+
+        let mut catalog = Catalog::default();
+        for lang in fallback_chain(&lang).iter().rev() {
+            if let Some((_, text)) = BUILTIN.iter().find(|(code, _)| code == lang) {
+                catalog.merge(text);
+            }
+            let path = format!("{}/{}/{}{}", ASSETS_DIR, LANG_DIR, lang, LANG_EXTENSION);
+            if let Some(source) = source
+                && source.contains(&path)
+            {
+                match source.read(&path) {
+                    Ok(data) => catalog.merge(&String::from_utf8_lossy(&data)),
+                    Err(e) => eprintln!("Warning: cannot read {}: {}", path, e),
+                }
+            }
+        }
+        catalog
+    }
+
+    /// Add the messages of a catalog file, replacing existing ones
+    fn merge(&mut self, text: &str) {
+        self.messages.extend(parse(text));
+    }
+
+    /// Look up a message, falling back to the key itself
+    pub fn get<'a>(&'a self, key: &'a str) -> &'a str {
+        self.messages.get(key).map(String::as_str).unwrap_or(key)
+    }
+}
+
+/// Parse a catalog file into its messages
+///
+/// Lines without `=` are ignored.
+pub fn parse(text: &str) -> HashMap<String, String> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (key.trim().to_string(), value.trim().replace("\\n", "\n")))
+        .collect()
+}
+
+/// Languages to try for `lang`, most specific first, ending with English
+///
+/// Accepts OS style names like `pt_BR.UTF-8` as well as `pt-BR`.
+fn fallback_chain(lang: &str) -> Vec<String> {
+    let lang = lang.split(['.', '@']).next().unwrap_or_default();
+    let lang = lang.replace('_', "-").to_ascii_lowercase();
+
+    let mut chain = Vec::new();
+    let mut parts: Vec<&str> = lang.split('-').filter(|p| !p.is_empty()).collect();
+    while !parts.is_empty() {
+        chain.push(parts.join("-"));
+        parts.pop();
+    }
+    if !chain.iter().any(|l| l == DEFAULT_LANG) {
+        chain.push(DEFAULT_LANG.to_string());
+    }
+    chain
+}
+
+/// Set the catalog used by [`tr`]; only the first call has an effect
+pub fn init(catalog: Catalog) {
+    let _ = CATALOG.set(catalog);
+}
+
+/// Look up a message in the active catalog
+///
+/// Uses the OS locale and built-in catalogs if [`init`] was not called.
+pub fn tr(key: &'static str) -> &'static str {
+    CATALOG.get_or_init(|| Catalog::load(None, None)).get(key)
+}
+
+/// Replace the `{name}` placeholders of a message
+pub fn format(message: &str, args: &[(&str, &dyn Display)]) -> String {
+    args.iter().fold(message.to_string(), |message, (name, value)| {
+        message.replace(&format!("{{{}}}", name), &value.to_string())
+    })
+}
+
+/// Translate a message, filling in `name = value` arguments
+///
+/// `t!("key")` is a `&str`; with arguments it is a `String`.
+macro_rules! t {
+    ($key:literal) => {
+        $crate::i18n::tr($key)
+    };
+    ($key:literal, $($name:ident = $value:expr),+ $(,)?) => {
+        $crate::i18n::format(
+            $crate::i18n::tr($key),
+            &[$((stringify!($name), &$value as &dyn std::fmt::Display)),+],
+        )
+    };
+}
+pub(crate) use t;
+
+/// Localized name of a file operation
+pub fn action_label(action: ProgressAction) -> &'static str {
+    match action {
+        ProgressAction::Validating => t!("action-validating"),
+        ProgressAction::CheckingNotExists => t!("action-checking"),
+        ProgressAction::BackingUp => t!("action-backing-up"),
+        ProgressAction::Skipping => t!("action-skipping"),
+        ProgressAction::Patching => t!("action-patching"),
+        ProgressAction::Adding => t!("action-adding"),
+        ProgressAction::Deleting => t!("action-deleting"),
+        ProgressAction::Editing => t!("action-editing"),
+        ProgressAction::Restoring => t!("action-restoring"),
+        ProgressAction::Removing => t!("action-removing"),
+    }
+}
+
+/// Localized description of a post-apply step
+pub fn post_action_label(action: &PostAction) -> String {
+    match action {
+        PostAction::DeleteDir { path } => t!("post-delete-dir", path = path),
+        PostAction::Launch { path, .. } => t!("post-launch", path = path),
+        PostAction::OpenFile { path } => t!("post-open-file", path = path),
+        PostAction::OpenUrl { url } => t!("post-open-url", url = url),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn placeholders(message: &str) -> HashSet<&str> {
+        message
+            .split('{')
+            .skip(1)
+            .filter_map(|rest| rest.split_once('}').map(|(name, _)| name))
+            .collect()
+    }
+
+    #[test]
+    fn falls_back_to_more_generic_languages() {
+        assert_eq!(fallback_chain("pt_BR.UTF-8"), vec!["pt-br", "pt", "en"]);
+        assert_eq!(fallback_chain("de-DE"), vec!["de-de", "de", "en"]);
+        assert_eq!(fallback_chain("en-US"), vec!["en-us", "en"]);
+        assert_eq!(fallback_chain(""), vec!["en"]);
+    }
+
+    #[test]
+    fn builtin_catalogs_match_english() {
+        let english = parse(BUILTIN[0].1);
+        for (code, text) in BUILTIN {
+            for (key, message) in parse(text) {
+                let Some(original) = english.get(&key) else {
+                    panic!("{}: unknown key {}", code, key);
+                };
+                assert_eq!(placeholders(&message), placeholders(original), "{}: {}", code, key);
+            }
+        }
+    }
+
+    #[test]
+    fn every_used_key_is_in_english() {
+        let english = parse(BUILTIN[0].1);
+        let sources = [
+            include_str!("branding.rs"),
+            include_str!("cli.rs"),
+            include_str!("gui.rs"),
+            include_str!("main.rs"),
+            include_str!("progress.rs"),
+            include_str!("runner.rs"),
+        ];
+        for source in sources {
+            for (pos, _) in source.match_indices("t!(\"") {
+                // Skip print!( and friends
+                if source[..pos].ends_with(|c: char| c.is_alphanumeric() || c == '_') {
+                    continue;
+                }
+                let key = source[pos + 4..].split('"').next().unwrap();
+                assert!(english.contains_key(key), "missing key {}", key);
+            }
+        }
+    }
+
+    #[test]
+    fn patch_translations_override_builtin_messages() {
+        let patch_dir = tempdir().unwrap();
+        let lang_dir = patch_dir.path().join(ASSETS_DIR).join(LANG_DIR);
+        fs::create_dir_all(&lang_dir).unwrap();
+        fs::write(lang_dir.join("en.lang"), "welcome-message = Thanks for playing!\n").unwrap();
+        fs::write(lang_dir.join("de-at.lang"), "gui-quit = Servus\n").unwrap();
+        let source = patch_dir.path().to_path_buf();
+
+        let catalog = Catalog::load(Some("de_AT"), Some(&source));
+        assert_eq!(catalog.get("gui-quit"), "Servus");
+        assert_eq!(catalog.get("gui-rollback"), parse(BUILTIN[1].1)["gui-rollback"]);
+        assert_eq!(catalog.get("welcome-message"), "Thanks for playing!");
+        assert_eq!(catalog.get("no-such-key"), "no-such-key");
+
+        let catalog = Catalog::load(Some("fr"), None);
+        assert_eq!(catalog.get("gui-quit"), "Quit");
+    }
+
+    #[test]
+    fn formats_placeholders() {
+        let message = format("{count} files in {path}", &[("count", &3), ("path", &"/tmp")]);
+        assert_eq!(message, "3 files in /tmp");
+        assert_eq!(parse("a = one\\ntwo\n# b = x\nbad line")["a"], "one\ntwo");
+    }
+}
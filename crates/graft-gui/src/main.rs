@@ -19,9 +19,12 @@
 //!
 //! The binary format for self-appending:
 //! ```text
-//! [executable] + [patch.tar.gz] + [size: u64 LE] + [magic: "GRAFTPCH"]
+//! [executable] + [patch container] + [size: u64 LE] + [magic: "GRAFTPCH"]
 //! ```
 //!
+//! The patch container is either a compressed tar archive or an indexed container
+//! (see `graft build --container`).
+//!
 //! Alternatively, the `embedded_patch` feature can be used for compile-time
 //! embedding via `include_bytes!`.
 //!
@@ -30,16 +33,27 @@
 //! - **GUI mode** (default): graphical interface with embedded/appended patch data
 //! - **Demo mode** (automatic): if no patch data is found, runs with mock data
 //! - **Headless apply**: `graft-gui headless apply <path>` - CLI-only for scripting
+//!   (`--auto` finds the target from the manifest's discovery section)
 //! - **Headless rollback**: `graft-gui headless rollback <path>` - undo a patch
+//! - **Headless backup**: `graft-gui headless backup list|info|verify|prune|export <path>`
+//!
+//! Messages follow the OS locale, or `--lang`; see the `i18n` module.
 
+mod branding;
 mod cli;
 mod gui;
+mod i18n;
+mod markdown;
+mod progress;
 mod runner;
 mod self_read;
 mod validator;
 
 use clap::{Parser, Subcommand};
-use std::path::PathBuf;
+use graft_core::utils::manifest::component_selection;
+use i18n::{t, Catalog};
+use self_read::PatchData;
+use std::path::{Path, PathBuf};
 
 #[derive(Parser, Debug)]
 #[command(name = "graft-gui")]
@@ -48,6 +62,10 @@ use std::path::PathBuf;
 struct Args {
     #[command(subcommand)]
     command: Option<Command>,
+
+    /// Language of the messages, e.g. "de" or "pt-BR" (default: the OS language)
+    #[arg(long, global = true, value_name = "LANG")]
+    lang: Option<String>,
 }
 
 #[derive(Subcommand, Debug)]
@@ -64,14 +82,41 @@ enum HeadlessAction {
     /// Apply the patch to a target directory
     Apply {
         /// Target directory to apply the patch to
-        path: PathBuf,
+        #[arg(required_unless_present = "auto")]
+        path: Option<PathBuf>,
+
+        /// Find the target directory from the patch's known install locations
+        #[arg(long, conflicts_with = "path")]
+        auto: bool,
 
         /// Skip confirmation prompt
         #[arg(short = 'y', long)]
         yes: bool,
+
+        /// Store backups here instead of inside the target directory
+        #[arg(long, value_name = "DIR")]
+        backup_dir: Option<PathBuf>,
+
+        /// Accept the patch's license agreement without showing it
+        #[arg(long)]
+        accept_license: bool,
+
+        /// Don't run the patch's post-apply steps, like clearing a cache or launching the program
+        #[arg(long)]
+        skip_post_apply: bool,
+
+        /// Optional component to apply instead of the default ones (can be repeated)
+        #[arg(long, value_name = "ID")]
+        component: Vec<String>,
+
+        /// Apply no optional component unless one is given with --component
+        #[arg(long)]
+        no_default_components: bool,
     },
 
     /// Rollback a previously applied patch
+    ///
+    /// Works without patch data too, rolling back the last applied patch.
     Rollback {
         /// Target directory to rollback
         path: PathBuf,
@@ -79,18 +124,61 @@ enum HeadlessAction {
         /// Force rollback even if files have been modified
         #[arg(short, long)]
         force: bool,
+
+        /// Read backups from here (needed only if they were moved)
+        #[arg(long, value_name = "DIR")]
+        backup_dir: Option<PathBuf>,
+
+        /// Only roll back this optional component, keeping the rest of the patch
+        #[arg(long, value_name = "ID")]
+        component: Option<String>,
+    },
+
+    /// Manage the backups of a patched directory
+    Backup {
+        #[command(subcommand)]
+        action: cli::BackupAction,
     },
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args = Args::parse();
+    let patch_data = get_patch_data();
+
+    // The patch may ship its own translations. The catalog isn't loaded yet,
+    // so a patch that can't be opened is reported untranslated; applying it
+    // fails later with the same error.
+    let source = match patch_data.as_ref().map(|data| data.open_source()) {
+        Ok(Ok(source)) => Some(source),
+        Ok(Err(e)) => {
+            eprintln!("Warning: cannot read the patch: {}", e);
+            None
+        }
+        Err(_) => None,
+    };
+    i18n::init(Catalog::load(args.lang.as_deref(), source.as_deref().map(|s| s as _)));
 
     match args.command {
         Some(Command::Headless { action }) => match action {
-            HeadlessAction::Apply { path, yes } => run_headless(&path, yes),
-            HeadlessAction::Rollback { path, force } => run_rollback(&path, force),
+            HeadlessAction::Apply {
+                path,
+                auto: _,
+                yes,
+                backup_dir,
+                accept_license,
+                skip_post_apply,
+                component,
+                no_default_components,
+            } => {
+                let components = component_selection(&component, no_default_components);
+                run_headless(patch_data, path.as_deref(), yes, backup_dir.as_deref(), accept_license, skip_post_apply, components)
+            }
+            HeadlessAction::Rollback { path, force, backup_dir, component } => {
+                run_rollback(patch_data.ok(), &path, force, backup_dir.as_deref(), component.as_deref())
+            }
+            HeadlessAction::Backup { action } => cli::run_backup(patch_data.ok().as_ref(), action),
         },
-        None => run_gui(),
+        None => run_gui(patch_data),
     }
 }
 
@@ -100,12 +188,12 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 /// 1. Compile-time embedded data (if `embedded_patch` feature is enabled)
 /// 2. macOS: Read from Contents/Resources/patch.data (preserves code signature)
 /// 3. Other platforms: Runtime self-reading (appended data at end of executable)
-fn get_patch_data() -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+fn get_patch_data() -> Result<PatchData, Box<dyn std::error::Error>> {
     // Try compile-time embedded data first
     #[cfg(feature = "embedded_patch")]
     {
         const PATCH_DATA: &[u8] = include_bytes!(env!("GRAFT_PATCH_ARCHIVE"));
-        return Ok(PATCH_DATA.to_vec());
+        return Ok(PATCH_DATA.to_vec().into());
     }
 
     // Platform-specific runtime reading
@@ -128,9 +216,9 @@ fn get_patch_data() -> Result<Vec<u8>, Box<dyn std::error::Error>> {
 /// Run the GUI application
 ///
 /// If no patch data is embedded/appended, automatically runs in demo mode.
-fn run_gui() -> Result<(), Box<dyn std::error::Error>> {
-    match get_patch_data() {
-        Ok(data) => gui::run(Some(&data)).map_err(|e| e.into()),
+fn run_gui(patch_data: Result<PatchData, Box<dyn std::error::Error>>) -> Result<(), Box<dyn std::error::Error>> {
+    match patch_data {
+        Ok(data) => gui::run(Some(data)).map_err(|e| e.into()),
         Err(_) => {
             // No patch data - run in demo mode
             gui::run(None).map_err(|e| e.into())
@@ -139,25 +227,41 @@ fn run_gui() -> Result<(), Box<dyn std::error::Error>> {
 }
 
 /// Run in headless (CLI) mode
-fn run_headless(target_path: &PathBuf, skip_confirm: bool) -> Result<(), Box<dyn std::error::Error>> {
-    match get_patch_data() {
-        Ok(data) => cli::run_headless(&data, target_path, skip_confirm),
+///
+/// Without a target path, the target is discovered from the manifest.
+fn run_headless(
+    patch_data: Result<PatchData, Box<dyn std::error::Error>>,
+    target_path: Option<&Path>,
+    skip_confirm: bool,
+    backup_dir: Option<&Path>,
+    accept_license: bool,
+    skip_post_apply: bool,
+    components: Option<&[String]>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match patch_data {
+        Ok(data) => {
+            let target_path = match target_path {
+                Some(path) => path.to_path_buf(),
+                None => cli::discover_target(&data)?,
+            };
+            cli::run_headless(&data, &target_path, skip_confirm, backup_dir, accept_license, skip_post_apply, components)
+        }
         Err(e) => {
-            eprintln!("Error: {}", e);
-            eprintln!("Headless mode requires patch data.");
+            eprintln!("{}", t!("error", message = e));
+            eprintln!("{}", t!("cli-requires-patch-data"));
             std::process::exit(1);
         }
     }
 }
 
 /// Run rollback in headless (CLI) mode
-fn run_rollback(target_path: &PathBuf, force: bool) -> Result<(), Box<dyn std::error::Error>> {
-    match get_patch_data() {
-        Ok(data) => cli::run_rollback(&data, target_path, force),
-        Err(e) => {
-            eprintln!("Error: {}", e);
-            eprintln!("Rollback mode requires patch data.");
-            std::process::exit(1);
-        }
-    }
+fn run_rollback(
+    patch_data: Option<PatchData>,
+    target_path: &Path,
+    force: bool,
+    backup_dir: Option<&Path>,
+    component: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    // A bare stub rolls back using the manifest saved with the backup
+    cli::run_rollback(patch_data.as_ref(), target_path, force, backup_dir, component)
 }
@@ -0,0 +1,257 @@
+//! Minimal Markdown rendering for the patch's README, changelog and EULA.
+//!
+//! Supports headings, paragraphs, emphasis, inline code, links, nested lists,
+//! code blocks and rules. Images and HTML are shown as plain text.
+
+use eframe::egui;
+use pulldown_cmark::{Event, Options, Parser, Tag, TagEnd};
+
+/// Indentation per list level, in points
+const LIST_INDENT: f32 = 16.0;
+
+/// A run of text with the same style
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Span {
+    pub text: String,
+    pub strong: bool,
+    pub italics: bool,
+    pub strikethrough: bool,
+    pub code: bool,
+    /// Link target, if the span is a link
+    pub link: Option<String>,
+}
+
+/// A block of the document, rendered on its own row(s)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Block {
+    Heading { level: usize, spans: Vec<Span> },
+    /// A paragraph or list item; `marker` is the bullet or number of an item
+    Paragraph { spans: Vec<Span>, depth: usize, marker: Option<String> },
+    Code(String),
+    Rule,
+}
+
+/// Parse Markdown into blocks
+pub fn blocks(text: &str) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    let mut spans: Vec<Span> = Vec::new();
+    let mut style = Span::default();
+    let mut heading = None;
+    let mut code = None;
+    // Next number of each open list; None for bullet lists
+    let mut lists: Vec<Option<u64>> = Vec::new();
+    let mut marker = None;
+
+    for event in Parser::new_ext(text, Options::ENABLE_STRIKETHROUGH) {
+        let depth = lists.len().saturating_sub(1);
+        match event {
+            Event::Start(Tag::Heading { level, .. }) => heading = Some(level as usize),
+            Event::End(TagEnd::Heading(_)) => {
+                flush(&mut blocks, &mut spans, &mut marker, depth, heading);
+                heading = None;
+            }
+            Event::Start(Tag::List(start)) => {
+                // Text of the enclosing item goes before the nested list
+                flush(&mut blocks, &mut spans, &mut marker, depth, None);
+                lists.push(start);
+            }
+            Event::End(TagEnd::List(_)) => {
+                lists.pop();
+            }
+            Event::Start(Tag::Item) => {
+                marker = Some(match lists.last_mut() {
+                    Some(Some(n)) => {
+                        *n += 1;
+                        format!("{}.", *n - 1)
+                    }
+                    _ => "\u{2022}".to_string(),
+                });
+            }
+            Event::End(TagEnd::Item) | Event::End(TagEnd::Paragraph) => {
+                flush(&mut blocks, &mut spans, &mut marker, depth, None);
+            }
+            Event::Start(Tag::CodeBlock(_)) => code = Some(String::new()),
+            Event::End(TagEnd::CodeBlock) => {
+                if let Some(text) = code.take() {
+                    flush(&mut blocks, &mut spans, &mut marker, depth, None);
+                    blocks.push(Block::Code(text.trim_end().to_string()));
+                }
+            }
+            Event::Start(Tag::Strong) => style.strong = true,
+            Event::End(TagEnd::Strong) => style.strong = false,
+            Event::Start(Tag::Emphasis) => style.italics = true,
+            Event::End(TagEnd::Emphasis) => style.italics = false,
+            Event::Start(Tag::Strikethrough) => style.strikethrough = true,
+            Event::End(TagEnd::Strikethrough) => style.strikethrough = false,
+            Event::Start(Tag::Link { dest_url, .. }) => style.link = Some(dest_url.to_string()),
+            Event::End(TagEnd::Link) => style.link = None,
+            Event::Text(text) | Event::Html(text) | Event::InlineHtml(text) => match &mut code {
+                Some(code) => code.push_str(&text),
+                None => push_span(&mut spans, &style, &text, false),
+            },
+            Event::Code(text) => push_span(&mut spans, &style, &text, true),
+            Event::SoftBreak => push_span(&mut spans, &style, " ", false),
+            Event::HardBreak => flush(&mut blocks, &mut spans, &mut marker, depth, heading),
+            Event::Rule => blocks.push(Block::Rule),
+            _ => {}
+        }
+    }
+    flush(&mut blocks, &mut spans, &mut marker, 0, heading);
+    blocks
+}
+
+/// End the current heading, paragraph or list item
+fn flush(
+    blocks: &mut Vec<Block>,
+    spans: &mut Vec<Span>,
+    marker: &mut Option<String>,
+    depth: usize,
+    heading: Option<usize>,
+) {
+    if spans.is_empty() && marker.is_none() {
+        return;
+    }
+    let spans = std::mem::take(spans);
+    blocks.push(match heading {
+        Some(level) => Block::Heading { level, spans },
+        None => Block::Paragraph { spans, depth, marker: marker.take() },
+    });
+}
+
+/// Append text, merging it into the last span if the style is the same
+fn push_span(spans: &mut Vec<Span>, style: &Span, text: &str, code: bool) {
+    if let Some(last) = spans.last_mut()
+        && last.code == code
+        && last.strong == style.strong
+        && last.italics == style.italics
+        && last.strikethrough == style.strikethrough
+        && last.link == style.link
+    {
+        last.text.push_str(text);
+        return;
+    }
+    spans.push(Span { text: text.to_string(), code, ..style.clone() });
+}
+
+/// Render Markdown into `ui`
+pub fn show(ui: &mut egui::Ui, text: &str) {
+    for block in blocks(text) {
+        match block {
+            Block::Heading { level, spans } => {
+                ui.add_space(4.0);
+                let size = match level {
+                    1 => egui::TextStyle::Heading.resolve(ui.style()).size,
+                    2 => egui::TextStyle::Body.resolve(ui.style()).size * 1.25,
+                    _ => egui::TextStyle::Body.resolve(ui.style()).size,
+                };
+                show_spans(ui, &spans, 0.0, None, |text| text.size(size).strong());
+            }
+            Block::Paragraph { spans, depth, marker } => {
+                let indent = if marker.is_some() { (depth + 1) as f32 * LIST_INDENT } else { 0.0 };
+                show_spans(ui, &spans, indent, marker.as_deref(), |text| text);
+            }
+            Block::Code(text) => {
+                egui::Frame::NONE
+                    .fill(ui.visuals().extreme_bg_color)
+                    .corner_radius(4.0)
+                    .inner_margin(4.0)
+                    .show(ui, |ui| {
+                        ui.set_width(ui.available_width());
+                        ui.label(egui::RichText::new(text).monospace());
+                    });
+            }
+            Block::Rule => {
+                ui.separator();
+            }
+        }
+    }
+}
+
+fn show_spans(
+    ui: &mut egui::Ui,
+    spans: &[Span],
+    indent: f32,
+    marker: Option<&str>,
+    style: impl Fn(egui::RichText) -> egui::RichText,
+) {
+    ui.horizontal_wrapped(|ui| {
+        ui.spacing_mut().item_spacing.x = 0.0;
+        ui.add_space(indent);
+        if let Some(marker) = marker {
+            ui.label(format!("{} ", marker));
+        }
+        for span in spans {
+            let mut text = style(egui::RichText::new(&span.text));
+            if span.strong {
+                text = text.strong();
+            }
+            if span.italics {
+                text = text.italics();
+            }
+            if span.strikethrough {
+                text = text.strikethrough();
+            }
+            if span.code {
+                text = text.code();
+            }
+            match &span.link {
+                Some(url) => {
+                    ui.hyperlink_to(text, url);
+                }
+                None => {
+                    ui.label(text);
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plain(text: &str) -> Span {
+        Span { text: text.to_string(), ..Default::default() }
+    }
+
+    #[test]
+    fn parses_headings_paragraphs_and_styles() {
+        let blocks = blocks("# Title\n\nSome **bold** and `code`,\nwrapped.\n\n---\n");
+        assert_eq!(
+            blocks,
+            vec![
+                Block::Heading { level: 1, spans: vec![plain("Title")] },
+                Block::Paragraph {
+                    spans: vec![
+                        plain("Some "),
+                        Span { strong: true, ..plain("bold") },
+                        plain(" and "),
+                        Span { code: true, ..plain("code") },
+                        plain(", wrapped."),
+                    ],
+                    depth: 0,
+                    marker: None,
+                },
+                Block::Rule,
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_nested_lists_links_and_code_blocks() {
+        let blocks = blocks("1. First\n   - [site](https://example.com)\n2. Second\n\n```\nlet x = 1;\n```\n");
+        assert_eq!(
+            blocks,
+            vec![
+                Block::Paragraph { spans: vec![plain("First")], depth: 0, marker: Some("1.".to_string()) },
+                Block::Paragraph {
+                    spans: vec![Span { link: Some("https://example.com".to_string()), ..plain("site") }],
+                    depth: 1,
+                    marker: Some("\u{2022}".to_string()),
+                },
+                Block::Paragraph { spans: vec![plain("Second")], depth: 0, marker: Some("2.".to_string()) },
+                Block::Code("let x = 1;".to_string()),
+            ]
+        );
+    }
+}
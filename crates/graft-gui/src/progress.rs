@@ -0,0 +1,110 @@
+//! Transfer speed and remaining time for the byte progress of a phase.
+
+use crate::i18n::t;
+use graft_core::patch::format_size;
+use std::time::{Duration, Instant};
+
+/// Time before the speed is estimated, so the first chunks don't skew it
+const WARMUP: Duration = Duration::from_millis(500);
+
+/// Bytes processed in the current phase
+#[derive(Debug, Clone)]
+pub struct Throughput {
+    started: Instant,
+    done: u64,
+    total: u64,
+}
+
+impl Throughput {
+    pub fn new() -> Self {
+        Throughput {
+            started: Instant::now(),
+            done: 0,
+            total: 0,
+        }
+    }
+
+    pub fn update(&mut self, done: u64, total: u64) {
+        self.done = done;
+        self.total = total;
+    }
+
+    /// Share of the phase's bytes done; None if the phase has no file data
+    pub fn fraction(&self) -> Option<f32> {
+        (self.total > 0).then(|| self.done as f32 / self.total as f32)
+    }
+
+    pub fn bytes_per_second(&self) -> Option<f64> {
+        let elapsed = self.started.elapsed();
+        (elapsed >= WARMUP && self.done > 0).then(|| self.done as f64 / elapsed.as_secs_f64())
+    }
+
+    /// Estimated time until the phase is done
+    pub fn remaining(&self) -> Option<Duration> {
+        let speed = self.bytes_per_second()?;
+        Some(Duration::from_secs_f64(self.total.saturating_sub(self.done) as f64 / speed))
+    }
+
+    /// Speed and remaining time, e.g. "12.5 MB/s, 0:42 left"
+    pub fn summary(&self) -> Option<String> {
+        let speed = self.bytes_per_second()?;
+        let remaining = self.remaining()?;
+        Some(t!("progress-rate", speed = format_size(speed as u64), eta = format_duration(remaining)))
+    }
+}
+
+impl Default for Throughput {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Format a duration as "m:ss", or "h:mm:ss" from an hour up
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let (hours, minutes, seconds) = (secs / 3600, secs / 60 % 60, secs % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{}:{:02}", minutes, seconds)
+    }
+}
+
+/// Text progress bar `width` characters wide between brackets
+pub fn bar(fraction: f32, width: usize) -> String {
+    let filled = ((fraction.clamp(0.0, 1.0) * width as f32).round() as usize).min(width);
+    format!("[{}{}]", "#".repeat(filled), "-".repeat(width - filled))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn estimates_speed_and_remaining_time() {
+        let throughput = Throughput {
+            started: Instant::now() - Duration::from_secs(2),
+            done: 20 << 20,
+            total: 100 << 20,
+        };
+
+        let speed = throughput.bytes_per_second().unwrap();
+        assert!((speed - (10 << 20) as f64).abs() < (1 << 20) as f64);
+        let remaining = throughput.remaining().unwrap().as_secs_f64();
+        assert!((7.0..9.0).contains(&remaining));
+        assert_eq!(throughput.fraction(), Some(0.2));
+
+        // Too early to tell
+        assert_eq!(Throughput::new().bytes_per_second(), None);
+        assert_eq!(Throughput::new().fraction(), None);
+    }
+
+    #[test]
+    fn formats_durations_and_bars() {
+        assert_eq!(format_duration(Duration::from_secs(42)), "0:42");
+        assert_eq!(format_duration(Duration::from_secs(3 * 60 + 5)), "3:05");
+        assert_eq!(format_duration(Duration::from_secs(2 * 3600 + 61)), "2:01:01");
+        assert_eq!(bar(0.5, 10), "[#####-----]");
+        assert_eq!(bar(1.5, 4), "[####]");
+    }
+}
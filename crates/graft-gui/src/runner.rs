@@ -1,11 +1,11 @@
-use flate2::read::GzDecoder;
-use graft_core::patch::{self, PatchError, Progress, BACKUP_DIR};
-use graft_core::utils::manifest::Manifest;
+use crate::i18n::t;
+use crate::self_read::PatchData;
+use graft_core::discovery::{self, Candidate};
+use graft_core::patch::{self, CancelToken, PatchError, PatchSource, Progress, RestorePoint, BACKUP_INDEX_FILENAME};
+use graft_core::utils::manifest::{Component, Manifest, ManifestEntry, PostAction};
 use std::cell::RefCell;
 use std::fmt;
-use std::fs;
 use std::path::{Path, PathBuf};
-use tar::Archive;
 
 /// Processing phases for orchestration
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -18,9 +18,9 @@ pub enum Phase {
 impl fmt::Display for Phase {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Phase::Validating => write!(f, "Validating"),
-            Phase::BackingUp => write!(f, "Backing up"),
-            Phase::Applying => write!(f, "Applying"),
+            Phase::Validating => write!(f, "{}", t!("phase-validating")),
+            Phase::BackingUp => write!(f, "{}", t!("phase-backing-up")),
+            Phase::Applying => write!(f, "{}", t!("phase-applying")),
         }
     }
 }
@@ -36,9 +36,23 @@ pub enum ProgressEvent {
         index: usize,
         total: usize,
         action: ProgressAction,
+        /// Bytes of this entry processed so far; 0 when the entry starts
+        bytes_done: u64,
+        bytes_total: u64,
+        /// Bytes processed and expected across the whole phase
+        overall_bytes_done: u64,
+        overall_bytes_total: u64,
     },
     /// Patch completed successfully
-    Done { files_patched: usize },
+    Done {
+        files_patched: usize,
+        /// Files that didn't match their original and got the full new version
+        fallbacks: Vec<String>,
+    },
+    /// An automatic post-apply step ran; a failure doesn't undo the patch
+    PostApply { action: PostAction, error: Option<String> },
+    /// Cancelled by the user; the target was left as it was
+    Cancelled,
     /// An error occurred
     Error { message: String, details: Option<String> },
 }
@@ -68,40 +82,120 @@ pub enum RollbackEvent {
     Error { message: String },
 }
 
-/// Core patch runner that handles extraction and application
+/// Core patch runner that reads the patch archive and applies it
 pub struct PatchRunner {
-    patch_dir: PathBuf,
+    /// Patch payloads; None for a runner created from a backup, which can only roll back
+    source: Option<Box<dyn PatchSource + Send + Sync>>,
+    /// The whole patch, with every optional component
+    patch: Manifest,
+    /// What gets applied: the patch narrowed to the selected components
     manifest: Manifest,
+    /// Backup location chosen by the user, instead of the target or manifest default
+    backup_dir: Option<PathBuf>,
+    /// Stops an apply in progress
+    cancel: Option<CancelToken>,
+    /// Leave out the automatic post-apply steps
+    skip_post_apply: bool,
 }
 
 impl PatchRunner {
-    /// Create a new runner from compressed patch data
-    pub fn new(data: &[u8]) -> Result<Self, PatchRunnerError> {
-        // Create temp directory for extracted patch
-        let temp_dir = tempfile::tempdir()
-            .map_err(|e| PatchRunnerError::ExtractionFailed(format!("Failed to create temp directory: {}", e)))?;
-
-        // Decompress and extract
-        let decoder = GzDecoder::new(data);
-        let mut archive = Archive::new(decoder);
-        archive
-            .unpack(temp_dir.path())
-            .map_err(|e| PatchRunnerError::ExtractionFailed(format!("Failed to extract patch archive: {}", e)))?;
-
-        // Load manifest
-        let manifest_path = temp_dir.path().join(patch::MANIFEST_FILENAME);
-        let manifest = Manifest::load(&manifest_path)
+    /// Create a new runner from patch data
+    ///
+    /// Payloads are read from the container on demand; nothing is extracted to disk.
+    /// The default components are selected.
+    pub fn new(data: &PatchData) -> Result<Self, PatchRunnerError> {
+        let source = data
+            .open_source()
+            .map_err(|e| PatchRunnerError::ExtractionFailed(format!("Failed to read patch archive: {}", e)))?;
+
+        let patch = source
+            .read_manifest()
             .map_err(|e| PatchRunnerError::ManifestLoadFailed(format!("Failed to load manifest: {}", e)))?;
+        let manifest = patch
+            .with_components(&patch.default_components())
+            .map_err(|e| PatchRunnerError::ManifestLoadFailed(format!("Invalid components: {}", e)))?;
 
-        // Keep temp_dir alive by converting to path
-        let patch_dir = temp_dir.keep();
+        Ok(PatchRunner {
+            source: Some(source),
+            patch,
+            manifest,
+            backup_dir: None,
+            cancel: None,
+            skip_post_apply: false,
+        })
+    }
+
+    /// Create a rollback-only runner for the last patch applied to `target`
+    ///
+    /// Uses the manifest saved with its backup, so no patch data is needed.
+    /// `backup_dir` points the target at backups that were moved.
+    pub fn from_backup(target: &Path, backup_dir: Option<PathBuf>) -> Result<Self, PatchRunnerError> {
+        if let Some(dir) = &backup_dir {
+            patch::set_backup_root(target, dir).map_err(|e| {
+                PatchRunnerError::ManifestLoadFailed(format!("Cannot use backup directory {}: {}", dir.display(), e))
+            })?;
+        }
+        let manifest = patch::latest_backup_manifest(target)
+            .map_err(|e| PatchRunnerError::ManifestLoadFailed(e.to_string()))?;
 
         Ok(PatchRunner {
-            patch_dir,
+            source: None,
+            patch: manifest.clone(),
             manifest,
+            backup_dir,
+            cancel: None,
+            skip_post_apply: false,
         })
     }
 
+    /// Apply these optional components, and the ones they require, instead
+    /// of the default ones
+    pub fn with_components(mut self, components: &[String]) -> Result<Self, PatchRunnerError> {
+        self.manifest = self
+            .patch
+            .with_components(components)
+            .map_err(|e| PatchRunnerError::InvalidComponents(e.to_string()))?;
+        Ok(self)
+    }
+
+    /// Optional components of the patch, selected or not
+    pub fn components(&self) -> &[Component] {
+        &self.patch.components
+    }
+
+    /// Ids of the optional components that will be applied
+    pub fn selected_components(&self) -> Vec<String> {
+        self.manifest.components.iter().map(|c| c.id.clone()).collect()
+    }
+
+    /// Store backups in `dir` instead of the default location
+    ///
+    /// Used on apply, and on rollback to find backups that were moved.
+    pub fn with_backup_dir(mut self, dir: Option<PathBuf>) -> Self {
+        self.backup_dir = dir;
+        self
+    }
+
+    /// Stop applying once `token` is cancelled
+    ///
+    /// Files already patched are rolled back and the apply reports
+    /// [`ProgressEvent::Cancelled`].
+    pub fn with_cancel(mut self, token: CancelToken) -> Self {
+        self.cancel = Some(token);
+        self
+    }
+
+    /// Don't run the manifest's automatic post-apply steps after applying
+    pub fn with_skip_post_apply(mut self, skip: bool) -> Self {
+        self.skip_post_apply = skip;
+        self
+    }
+
+    /// Steps the manifest asks for after applying
+    pub fn post_apply(&self) -> &[PostAction] {
+        &self.manifest.post_apply
+    }
+
     /// Apply patch to target directory with progress callback
     ///
     /// The callback is invoked for each progress event. Returns Ok(()) on success,
@@ -109,13 +203,23 @@ impl PatchRunner {
     ///
     /// This uses the full patch workflow including:
     /// - Validation before making any changes
-    /// - Backup of files that will be modified/deleted (to .patch-backup)
-    /// - Atomic rollback on failure
+    /// - Backup of files that will be modified/deleted (to .patch-backup/<name>-v<version>,
+    ///   or the chosen/manifest backup location)
+    /// - Recording a restore point on top of any earlier ones
+    /// - Atomic rollback on failure or cancellation
+    /// - Automatic post-apply steps, unless skipped; launching and opening
+    ///   is left to the caller, which asks the user
     pub fn apply<F>(&self, target: &Path, on_progress: F) -> Result<(), PatchError>
     where
         F: FnMut(ProgressEvent),
     {
-        let backup_dir = target.join(patch::BACKUP_DIR);
+        let Some(source) = &self.source else {
+            return Err(PatchError::ManifestError {
+                reason: "no patch data to apply".to_string(),
+            });
+        };
+        let id = patch::restore_point_id(&self.manifest);
+        let backup_dir = patch::backup_set_dir(target, &id);
 
         // Use RefCell to allow multiple closures to borrow on_progress
         let on_progress = RefCell::new(on_progress);
@@ -127,6 +231,10 @@ impl PatchRunner {
                 index: p.index,
                 total: p.total,
                 action: p.action,
+                bytes_done: p.bytes_done,
+                bytes_total: p.bytes_total,
+                overall_bytes_done: p.overall_bytes_done,
+                overall_bytes_total: p.overall_bytes_total,
             });
         };
 
@@ -138,16 +246,21 @@ impl PatchRunner {
         // Check path restrictions first (unless allow_restricted is set in manifest)
         if let Err(e) = patch::validate_path_restrictions(&self.manifest, target) {
             (on_progress.borrow_mut())(ProgressEvent::Error {
-                message: "Path restrictions violated".to_string(),
+                message: t!("error-path-restrictions").to_string(),
                 details: Some(e.to_string()),
             });
             return Err(e);
         }
 
-        if let Err(e) = patch::validate_entries(&self.manifest.entries, target, Some(&send_operation))
+        if let Err(e) =
+            patch::validate_entries(&self.manifest.entries, target, self.cancel.as_ref(), Some(&send_operation))
         {
+            if e == PatchError::Cancelled {
+                (on_progress.borrow_mut())(ProgressEvent::Cancelled);
+                return Err(e);
+            }
             (on_progress.borrow_mut())(ProgressEvent::Error {
-                message: "Validation failed".to_string(),
+                message: t!("error-validation").to_string(),
                 details: Some(e.to_string()),
             });
             return Err(e);
@@ -157,36 +270,97 @@ impl PatchRunner {
         (on_progress.borrow_mut())(ProgressEvent::PhaseStarted {
             phase: Phase::BackingUp,
         });
-        if let Err(e) =
-            patch::backup_entries(&self.manifest.entries, target, &backup_dir, Some(&send_operation))
-        {
+        if let Err(e) = patch::prepare_backup_root(target, self.backup_dir.as_deref(), &self.manifest) {
+            (on_progress.borrow_mut())(ProgressEvent::Error {
+                message: t!("error-backup").to_string(),
+                details: Some(e.to_string()),
+            });
+            return Err(PatchError::BackupFailed {
+                file: patch::BACKUP_MARKER_FILENAME.to_string(),
+                reason: e.to_string(),
+            });
+        }
+        if let Err(e) = patch::record_restore_point(target, &self.manifest) {
+            let e = PatchError::BackupFailed {
+                file: BACKUP_INDEX_FILENAME.to_string(),
+                reason: e.to_string(),
+            };
             (on_progress.borrow_mut())(ProgressEvent::Error {
-                message: "Backup failed".to_string(),
+                message: t!("error-backup").to_string(),
                 details: Some(e.to_string()),
             });
             return Err(e);
         }
-
-        // Apply phase
-        (on_progress.borrow_mut())(ProgressEvent::PhaseStarted {
-            phase: Phase::Applying,
-        });
-        if let Err(e) = patch::apply_entries(
+        if let Err(e) = patch::backup_entries(
             &self.manifest.entries,
             target,
-            &self.patch_dir,
             &backup_dir,
+            self.manifest.backup,
+            self.cancel.as_ref(),
             Some(&send_operation),
         ) {
+            // A partial backup leaves nothing to roll back to
+            let _ = patch::forget_restore_point(target, &id);
+            let _ = patch::delete_backup_set(target, &id);
+            if e == PatchError::Cancelled {
+                (on_progress.borrow_mut())(ProgressEvent::Cancelled);
+                return Err(e);
+            }
             (on_progress.borrow_mut())(ProgressEvent::Error {
-                message: "Apply failed".to_string(),
+                message: t!("error-backup").to_string(),
                 details: Some(e.to_string()),
             });
             return Err(e);
         }
 
+        // Apply phase
+        (on_progress.borrow_mut())(ProgressEvent::PhaseStarted {
+            phase: Phase::Applying,
+        });
+        let applied = patch::apply_entries(
+            &self.manifest.entries,
+            target,
+            &**source,
+            &backup_dir,
+            self.cancel.as_ref(),
+            Some(&send_operation),
+        );
+        let fallbacks = match applied {
+            Ok(fallbacks) => fallbacks,
+            Err(e) => {
+                // A failed or cancelled apply leaves nothing to roll back to
+                let _ = patch::forget_restore_point(target, &id);
+                let _ = patch::delete_backup_set(target, &id);
+                if e == PatchError::Cancelled {
+                    (on_progress.borrow_mut())(ProgressEvent::Cancelled);
+                    return Err(e);
+                }
+                (on_progress.borrow_mut())(ProgressEvent::Error {
+                    message: t!("error-apply").to_string(),
+                    details: Some(e.to_string()),
+                });
+                return Err(e);
+            }
+        };
+
+        // Shrinking the backup is best effort; the full copies remain usable
+        if self.manifest.backup.reverse_diffs {
+            let _ = patch::store_reverse_diffs(&self.manifest.entries, target, &backup_dir);
+        }
+
+        if !self.skip_post_apply {
+            for action in self.manifest.post_apply.iter().filter(|a| a.is_automatic()) {
+                let error = patch::run_post_action(action, target).err().map(|e| e.to_string());
+                (on_progress.borrow_mut())(ProgressEvent::PostApply {
+                    action: action.clone(),
+                    error,
+                });
+            }
+        }
+
         (on_progress.borrow_mut())(ProgressEvent::Done {
             files_patched: self.manifest.entries.len(),
+            fallbacks,
         });
 
         Ok(())
@@ -201,19 +375,59 @@ impl PatchRunner {
     pub fn validate_target(&self, target: &Path) -> Result<(), PatchError> {
         // Check path restrictions first
         patch::validate_path_restrictions(&self.manifest, target)?;
-        patch::validate_entries(&self.manifest.entries, target, None::<fn(Progress)>)
+        patch::validate_entries(&self.manifest.entries, target, None, None::<fn(Progress)>)
     }
 
     /// Check if target appears to be in patched state
     ///
     /// Returns true if all files match their expected post-patch hashes.
     pub fn is_patched(&self, target: &Path) -> bool {
-        patch::validate_patched_entries(&self.manifest.entries, target, None::<fn(Progress)>).is_ok()
+        patch::validate_patched_entries(&self.applied(target).entries, target, None::<fn(Progress)>).is_ok()
     }
 
-    /// Check if backup directory exists in target
-    pub fn has_backup(target: &Path) -> bool {
-        target.join(BACKUP_DIR).exists()
+    /// Optional components this patch was applied with in target
+    pub fn applied_components(&self, target: &Path) -> Vec<Component> {
+        if !self.has_restore_point(target) {
+            return Vec::new();
+        }
+        self.applied(target).components
+    }
+
+    /// The part of the patch applied to target, or the selection when it isn't
+    fn applied(&self, target: &Path) -> Manifest {
+        if self.has_restore_point(target) {
+            patch::applied_manifest(target, &self.patch)
+        } else {
+            self.manifest.clone()
+        }
+    }
+
+    /// Find likely target folders from the manifest's discovery section
+    ///
+    /// Candidates are ordered best match first.
+    pub fn discover(&self) -> Vec<Candidate> {
+        discovery::discover(&self.manifest)
+    }
+
+    /// List the patches applied to target that can be rolled back, oldest first
+    pub fn restore_points(target: &Path) -> Vec<RestorePoint> {
+        patch::list_restore_points(target).unwrap_or_default()
+    }
+
+    /// Directory that holds (or will hold) the backups of target
+    pub fn backup_location(&self, target: &Path) -> PathBuf {
+        match &self.backup_dir {
+            Some(dir) => dir.clone(),
+            None if !patch::has_backups(target) => {
+                patch::default_backup_root(&self.manifest, target).unwrap_or_else(|| patch::backup_root(target))
+            }
+            None => patch::backup_root(target),
+        }
+    }
+
+    /// Check if this patch has a restore point in target
+    pub fn has_restore_point(&self, target: &Path) -> bool {
+        patch::has_restore_point(target, &self.manifest)
     }
 
     /// Perform rollback with validation and progress reporting
@@ -221,16 +435,93 @@ impl PatchRunner {
     /// If `force` is false, validates that target files are in expected patched state first.
     /// If target files have been modified, returns TargetModified event and does not rollback.
     /// Always validates backup integrity before proceeding.
+    ///
+    /// Stacked patches are rolled back last-applied first; rolling back an
+    /// earlier patch fails until the later ones are rolled back. On success the
+    /// restore point is removed, while the backup files are kept until
+    /// [`PatchRunner::delete_backup`].
     pub fn rollback<F>(&self, target: &Path, force: bool, mut on_progress: F) -> Result<(), PatchError>
     where
         F: FnMut(RollbackEvent),
     {
-        let backup_dir = target.join(BACKUP_DIR);
+        let manifest = self.applied(target);
+        let total = self.restore(target, &manifest, &manifest.entries, force, &mut on_progress)?;
+
+        patch::forget_restore_point(target, &patch::restore_point_id(&self.manifest)).map_err(|e| {
+            PatchError::RollbackFailed {
+                reason: format!("files restored but restore point could not be removed: {}", e),
+            }
+        })?;
+
+        on_progress(RollbackEvent::Done {
+            files_restored: total,
+        });
+
+        Ok(())
+    }
+
+    /// Roll back one optional component, leaving the rest of the patch applied
+    ///
+    /// Works like [`PatchRunner::rollback`]. Components that require this one
+    /// must be removed first. The component's backup files are deleted and
+    /// the restore point is kept for the rest of the patch.
+    pub fn remove_component<F>(&self, target: &Path, id: &str, force: bool, mut on_progress: F) -> Result<(), PatchError>
+    where
+        F: FnMut(RollbackEvent),
+    {
+        let manifest = self.applied(target);
+        if let Err(e) = manifest.without_component(id) {
+            on_progress(RollbackEvent::Error { message: e.to_string() });
+            return Err(PatchError::RollbackFailed { reason: e.to_string() });
+        }
+
+        let total = self.restore(target, &manifest, &manifest.component_entries(id), force, &mut on_progress)?;
+
+        patch::forget_component(target, &manifest, id).map_err(|e| PatchError::RollbackFailed {
+            reason: format!("files restored but restore point could not be updated: {}", e),
+        })?;
+
+        on_progress(RollbackEvent::Done {
+            files_restored: total,
+        });
+
+        Ok(())
+    }
+
+    /// Validate and restore `entries` of `manifest` from its backup set,
+    /// returning how many were restored
+    fn restore<F>(
+        &self,
+        target: &Path,
+        manifest: &Manifest,
+        entries: &[ManifestEntry],
+        force: bool,
+        on_progress: &mut F,
+    ) -> Result<usize, PatchError>
+    where
+        F: FnMut(RollbackEvent),
+    {
+        if let Some(dir) = &self.backup_dir
+            && let Err(e) = patch::set_backup_root(target, dir)
+        {
+            on_progress(RollbackEvent::Error {
+                message: t!("error-backup-dir", path = dir.display(), error = e),
+            });
+            return Err(PatchError::RollbackFailed { reason: e.to_string() });
+        }
+
+        let backup_dir = match patch::rollback_set_dir(target, manifest) {
+            Ok(dir) => dir,
+            Err(e) => {
+                on_progress(RollbackEvent::Error { message: e.to_string() });
+                return Err(e);
+            }
+        };
 
         // Check backup exists
         if !backup_dir.exists() {
             on_progress(RollbackEvent::Error {
-                message: format!("Backup directory not found: {}", backup_dir.display()),
+                message: t!("error-backup-not-found", path = backup_dir.display()),
             });
             return Err(PatchError::RollbackFailed {
                 reason: "backup directory not found".to_string(),
@@ -241,7 +532,7 @@ impl PatchRunner {
         if !force {
             on_progress(RollbackEvent::ValidatingTarget);
             if let Err(e) = patch::validate_patched_entries(
-                &self.manifest.entries,
+                entries,
                 target,
                 None::<fn(Progress)>,
             ) {
@@ -254,16 +545,15 @@ impl PatchRunner {
 
         // Always validate backup integrity
         on_progress(RollbackEvent::ValidatingBackup);
-        if let Err(e) = patch::validate_backup(&self.manifest.entries, &backup_dir, None::<fn(Progress)>) {
+        if let Err(e) = patch::validate_backup(entries, target, &backup_dir, None::<fn(Progress)>) {
             on_progress(RollbackEvent::Error {
-                message: format!("Backup validation failed: {}", e),
+                message: t!("error-backup-validation", error = e),
             });
             return Err(e);
         }
 
         // Perform rollback
-        let entries: Vec<_> = self.manifest.entries.iter().collect();
-        let total = entries.len();
+        let entries: Vec<_> = entries.iter().collect();
         patch::rollback(&entries, target, &backup_dir, Some(|p: Progress| {
             on_progress(RollbackEvent::Rolling {
                 file: p.file.to_owned(),
@@ -273,70 +563,29 @@ impl PatchRunner {
             });
         }))?;
 
-        on_progress(RollbackEvent::Done {
-            files_restored: total,
-        });
-
-        Ok(())
-    }
-
-    /// Delete the backup directory
-    pub fn delete_backup(target: &Path) -> std::io::Result<()> {
-        let backup_dir = target.join(BACKUP_DIR);
-        if backup_dir.exists() {
-            remove_dir_all_robust(&backup_dir)?;
-        }
-        Ok(())
+        Ok(entries.len())
     }
-}
 
-/// Robust directory removal that handles Windows read-only files.
-///
-/// On Windows, `fs::remove_dir_all` can fail with error 87 (ERROR_INVALID_PARAMETER)
-/// when files have read-only attributes. This function clears the attribute before deletion.
-fn remove_dir_all_robust(path: &Path) -> std::io::Result<()> {
-    if path.is_dir() {
-        for entry in fs::read_dir(path)? {
-            let entry = entry?;
-            let entry_path = entry.path();
-            if entry_path.is_dir() {
-                remove_dir_all_robust(&entry_path)?;
-            } else {
-                remove_file_robust(&entry_path)?;
-            }
+    /// Check that this patch's backup in target can restore every file
+    pub fn verify_backup(&self, target: &Path) -> Result<(), PatchError> {
+        if !self.has_restore_point(target) {
+            return Err(PatchError::RollbackFailed {
+                reason: format!("no restore point for this patch in {}", patch::backup_root(target).display()),
+            });
         }
-        fs::remove_dir(path)
-    } else {
-        remove_file_robust(path)
+        let backup_dir = if patch::is_legacy_backup(target) {
+            patch::backup_root(target)
+        } else {
+            patch::backup_set_dir(target, &patch::restore_point_id(&self.manifest))
+        };
+        patch::validate_backup(&self.applied(target).entries, target, &backup_dir, None::<fn(Progress)>)
     }
-}
 
-/// Remove a file, handling Windows read-only attribute.
-fn remove_file_robust(path: &Path) -> std::io::Result<()> {
-    // Try normal removal first
-    match fs::remove_file(path) {
-        Ok(()) => Ok(()),
-        Err(e) => {
-            // On Windows, try clearing read-only attribute and retry
-            #[cfg(windows)]
-            {
-                use std::os::windows::fs::MetadataExt;
-                const FILE_ATTRIBUTE_READONLY: u32 = 0x1;
-
-                if let Ok(metadata) = fs::metadata(path) {
-                    let attrs = metadata.file_attributes();
-                    if attrs & FILE_ATTRIBUTE_READONLY != 0 {
-                        // Clear read-only and retry
-                        let mut perms = metadata.permissions();
-                        perms.set_readonly(false);
-                        if fs::set_permissions(path, perms).is_ok() {
-                            return fs::remove_file(path);
-                        }
-                    }
-                }
-            }
-            Err(e)
-        }
+    /// Delete this patch's backup set
+    ///
+    /// The `.patch-backup` directory is removed once no restore points remain.
+    pub fn delete_backup(&self, target: &Path) -> std::io::Result<()> {
+        patch::delete_backup_set(target, &patch::restore_point_id(&self.manifest))
     }
 }
 
@@ -345,6 +594,7 @@ fn remove_file_robust(path: &Path) -> std::io::Result<()> {
 pub enum PatchRunnerError {
     ExtractionFailed(String),
     ManifestLoadFailed(String),
+    InvalidComponents(String),
 }
 
 impl std::fmt::Display for PatchRunnerError {
@@ -352,6 +602,7 @@ impl std::fmt::Display for PatchRunnerError {
         match self {
             PatchRunnerError::ExtractionFailed(msg) => write!(f, "Extraction failed: {}", msg),
             PatchRunnerError::ManifestLoadFailed(msg) => write!(f, "Manifest load failed: {}", msg),
+            PatchRunnerError::InvalidComponents(msg) => write!(f, "Invalid component selection: {}", msg),
         }
     }
 }
@@ -10,19 +10,23 @@
 //! ┌─────────────────────────┐
 //! │   Executable Code       │  ← Original stub binary
 //! ├─────────────────────────┤
-//! │   Patch Archive         │  ← tar.gz data (variable size)
+//! │   Patch Archive         │  ← tar or indexed container (variable size)
 //! ├─────────────────────────┤
 //! │   Size (8 bytes)        │  ← Archive size as u64 LE
 //! ├─────────────────────────┤
 //! │   Magic (8 bytes)       │  ← "GRAFTPCH"
 //! └─────────────────────────┘
 //! ```
+//!
+//! Indexed containers are left in the executable and read in place;
+//! tar archives are loaded into memory.
 
-use graft_core::archive::MAGIC_MARKER;
+use graft_core::archive::{self, ContainerFormat, MAGIC_MARKER};
+use graft_core::patch::PatchSource;
 use std::fs::File;
 use std::io::{self, Read, Seek, SeekFrom};
-#[cfg(target_os = "macos")]
-use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 /// Errors that can occur when reading appended patch data.
 #[derive(Debug)]
@@ -60,19 +64,68 @@ impl From<io::Error> for SelfReadError {
     }
 }
 
+/// Location of the patch container.
+#[derive(Debug, Clone)]
+pub enum PatchData {
+    /// Container loaded into memory
+    Bytes(Arc<[u8]>),
+    /// Indexed container read in place from a region of a file
+    FileRegion { path: PathBuf, offset: u64, len: u64 },
+}
+
+impl PatchData {
+    /// Open the container for reading payloads.
+    pub fn open_source(&self) -> io::Result<Box<dyn PatchSource + Send + Sync>> {
+        match self {
+            PatchData::Bytes(data) => archive::open_bytes(data.clone()),
+            PatchData::FileRegion { path, offset, len } => {
+                archive::open_file_region(path, *offset, *len)
+            }
+        }
+    }
+}
+
+impl From<Vec<u8>> for PatchData {
+    fn from(data: Vec<u8>) -> Self {
+        PatchData::Bytes(data.into())
+    }
+}
+
+/// Reference a container region in `path`, loading it into memory unless
+/// it is an indexed container.
+fn locate_region(path: &Path, offset: u64, len: u64) -> Result<PatchData, SelfReadError> {
+    if archive::read_container_format(path, offset, len)? == ContainerFormat::Indexed {
+        return Ok(PatchData::FileRegion {
+            path: path.to_path_buf(),
+            offset,
+            len,
+        });
+    }
+
+    let mut file = File::open(path)?;
+    file.seek(SeekFrom::Start(offset))?;
+    let mut patch_data = vec![0u8; len as usize];
+    file.read_exact(&mut patch_data)?;
+    Ok(patch_data.into())
+}
+
 /// Read appended patch data from the current executable.
 ///
 /// This function reads the executable file itself, looks for the magic
-/// marker at the end, and extracts the patch archive data.
+/// marker at the end, and locates the patch archive data.
 ///
 /// # Returns
 ///
-/// Returns the patch archive bytes (tar.gz format) if found,
-/// or an error if no appended data is present or is invalid.
-pub fn read_appended_data() -> Result<Vec<u8>, SelfReadError> {
+/// Returns the patch container if found, or an error if no appended data
+/// is present or is invalid.
+pub fn read_appended_data() -> Result<PatchData, SelfReadError> {
     let exe_path = std::env::current_exe().map_err(SelfReadError::IoError)?;
+    read_appended_data_from(&exe_path)
+}
 
-    let mut file = File::open(&exe_path)?;
+/// Locate patch data appended to the file at `exe_path`.
+fn read_appended_data_from(exe_path: &Path) -> Result<PatchData, SelfReadError> {
+    let mut file = File::open(exe_path)?;
     let file_len = file.metadata()?.len();
 
     // Need at least magic (8) + size (8) = 16 bytes
@@ -103,14 +156,9 @@ pub fn read_appended_data() -> Result<Vec<u8>, SelfReadError> {
         return Err(SelfReadError::InvalidSize);
     }
 
-    // Read patch data
+    // Locate patch data
     let patch_start = file_len - 16 - patch_size;
-    file.seek(SeekFrom::Start(patch_start))?;
-
-    let mut patch_data = vec![0u8; patch_size as usize];
-    file.read_exact(&mut patch_data)?;
-
-    Ok(patch_data)
+    locate_region(exe_path, patch_start, patch_size)
 }
 
 /// Read patch data from the Resources folder in a macOS .app bundle.
@@ -123,7 +171,7 @@ pub fn read_appended_data() -> Result<Vec<u8>, SelfReadError> {
 ///
 /// This approach preserves the executable's code signature.
 #[cfg(target_os = "macos")]
-pub fn read_resources_patch_data() -> Result<Vec<u8>, SelfReadError> {
+pub fn read_resources_patch_data() -> Result<PatchData, SelfReadError> {
     let exe_path = std::env::current_exe().map_err(SelfReadError::IoError)?;
 
     // exe_path: /path/to/MyApp.app/Contents/MacOS/graft-gui
@@ -144,12 +192,14 @@ pub fn read_resources_patch_data() -> Result<Vec<u8>, SelfReadError> {
         return Err(SelfReadError::NoAppendedData);
     }
 
-    fs::read(&patch_data_path).map_err(SelfReadError::IoError)
+    let len = std::fs::metadata(&patch_data_path)?.len();
+    locate_region(&patch_data_path, 0, len)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use graft_core::test_support::create_patch_dir;
 
     #[test]
     fn no_appended_data_returns_error() {
@@ -157,4 +207,42 @@ mod tests {
         let result = read_appended_data();
         assert!(matches!(result, Err(SelfReadError::NoAppendedData)));
     }
+
+    fn append_container(exe_path: &Path, container: &[u8]) {
+        let mut exe = b"stub executable".to_vec();
+        exe.extend_from_slice(container);
+        exe.extend_from_slice(&(container.len() as u64).to_le_bytes());
+        exe.extend_from_slice(MAGIC_MARKER);
+        std::fs::write(exe_path, exe).unwrap();
+    }
+
+    #[test]
+    fn indexed_container_is_read_in_place() {
+        let dir = tempfile::tempdir().unwrap();
+        create_patch_dir(dir.path());
+        let options = archive::ArchiveOptions {
+            container: ContainerFormat::Indexed,
+            ..Default::default()
+        };
+        let container = archive::create_container_bytes(dir.path(), &options).unwrap();
+        let exe_path = dir.path().join("patcher");
+        append_container(&exe_path, &container);
+
+        let data = read_appended_data_from(&exe_path).unwrap();
+        assert!(matches!(data, PatchData::FileRegion { offset: 15, .. }));
+        assert_eq!(data.open_source().unwrap().read_manifest().unwrap().name, "TestPatcher");
+    }
+
+    #[test]
+    fn tar_container_is_loaded_into_memory() {
+        let dir = tempfile::tempdir().unwrap();
+        create_patch_dir(dir.path());
+        let container = archive::create_archive_bytes(dir.path()).unwrap();
+        let exe_path = dir.path().join("patcher");
+        append_container(&exe_path, &container);
+
+        let data = read_appended_data_from(&exe_path).unwrap();
+        assert!(matches!(&data, PatchData::Bytes(bytes) if bytes[..] == container[..]));
+        assert_eq!(data.open_source().unwrap().read_manifest().unwrap().name, "TestPatcher");
+    }
 }
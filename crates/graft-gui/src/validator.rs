@@ -1,4 +1,5 @@
-use flate2::read::GzDecoder;
+use crate::self_read::PatchData;
+use graft_core::archive::{self, ContainerFormat};
 use graft_core::patch;
 use graft_core::utils::manifest::Manifest;
 use std::io::Read;
@@ -13,8 +14,42 @@ pub struct PatchValidator;
 impl PatchValidator {
     /// Validate patch data and extract info by reading only the manifest.
     /// Does NOT extract files to disk - just reads manifest from archive.
-    pub fn validate(data: &[u8]) -> Result<PatchInfo, PatchValidationError> {
-        let decoder = GzDecoder::new(data);
+    ///
+    /// For indexed containers only the index and the manifest entry are read.
+    pub fn validate(data: &PatchData) -> Result<PatchInfo, PatchValidationError> {
+        match data {
+            PatchData::Bytes(bytes) if ContainerFormat::detect(bytes) == ContainerFormat::Tar => {
+                Self::validate_tar(bytes)
+            }
+            _ => Self::validate_indexed(data),
+        }
+    }
+
+    /// Read the manifest through the container index.
+    fn validate_indexed(data: &PatchData) -> Result<PatchInfo, PatchValidationError> {
+        let source = data.open_source().map_err(|e| {
+            PatchValidationError::DecompressionFailed(format!("Failed to read container: {}", e))
+        })?;
+
+        if !source.contains(patch::MANIFEST_FILENAME) {
+            return Err(PatchValidationError::ManifestNotFound);
+        }
+        let manifest = source
+            .read_manifest()
+            .map_err(|e| PatchValidationError::ManifestInvalid(e.to_string()))?;
+
+        Ok(PatchInfo::from_manifest(&manifest))
+    }
+
+    /// Stream the tar archive until the manifest is found.
+    ///
+    /// The decoder is picked from the codec recorded in the archive trailer.
+    fn validate_tar(data: &[u8]) -> Result<PatchInfo, PatchValidationError> {
+        let decoder = archive::split_codec(data)
+            .and_then(|(codec, stream)| archive::decoder(codec, stream))
+            .map_err(|e| {
+                PatchValidationError::DecompressionFailed(format!("Failed to read archive: {}", e))
+            })?;
         let mut archive = Archive::new(decoder);
 
         let entries = archive.entries().map_err(|e| {
@@ -73,7 +108,9 @@ impl std::error::Error for PatchValidationError {}
 #[cfg(test)]
 mod tests {
     use super::*;
-    use graft_core::archive::create_archive_bytes;
+    use graft_core::archive::{
+        create_archive_bytes, create_container_bytes, ArchiveCodec, ArchiveOptions,
+    };
     use std::fs;
     use tempfile::tempdir;
 
@@ -92,8 +129,50 @@ mod tests {
         let archive_data = create_archive_bytes(patch_dir.path()).unwrap();
 
         // Validate and check title
-        let info = PatchValidator::validate(&archive_data).unwrap();
+        let info = PatchValidator::validate(&archive_data.into()).unwrap();
+        assert_eq!(info.name, "TestPatcher");
+        assert_eq!(info.title, Some("My Test Title".to_string()));
+    }
+
+    #[test]
+    fn extracts_title_from_indexed_container() {
+        let patch_dir = tempdir().unwrap();
+
+        fs::write(
+            patch_dir.path().join("manifest.json"),
+            r#"{"version": 1, "name": "TestPatcher", "title": "My Test Title", "entries": []}"#,
+        )
+        .unwrap();
+
+        let options = ArchiveOptions {
+            container: ContainerFormat::Indexed,
+            ..Default::default()
+        };
+        let container_data = create_container_bytes(patch_dir.path(), &options).unwrap();
+
+        let info = PatchValidator::validate(&container_data.into()).unwrap();
         assert_eq!(info.name, "TestPatcher");
         assert_eq!(info.title, Some("My Test Title".to_string()));
     }
+
+    #[test]
+    fn extracts_title_from_xz_archive() {
+        let patch_dir = tempdir().unwrap();
+
+        fs::write(
+            patch_dir.path().join("manifest.json"),
+            r#"{"version": 1, "name": "TestPatcher", "title": "My Test Title", "entries": []}"#,
+        )
+        .unwrap();
+
+        let options = ArchiveOptions {
+            container: ContainerFormat::Tar,
+            codec: ArchiveCodec::Xz,
+            ..Default::default()
+        };
+        let archive_data = create_container_bytes(patch_dir.path(), &options).unwrap();
+
+        let info = PatchValidator::validate(&archive_data.into()).unwrap();
+        assert_eq!(info.name, "TestPatcher");
+    }
 }
@@ -0,0 +1,24 @@
+use std::io;
+use std::path::Path;
+
+use graft_core::patch::{archive_backups, export_backups, move_backups};
+
+/// What to do with the backups of a target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportMode {
+    /// Copy them to a directory
+    Copy,
+    /// Move them to a directory and keep using them from there
+    Move,
+    /// Write them to a `.tar.zst` archive
+    Archive,
+}
+
+/// Copy, move or archive all backups of a target directory.
+pub fn run(target_dir: &Path, dest: &Path, mode: ExportMode) -> io::Result<()> {
+    match mode {
+        ExportMode::Copy => export_backups(target_dir, dest),
+        ExportMode::Move => move_backups(target_dir, dest),
+        ExportMode::Archive => archive_backups(target_dir, dest),
+    }
+}
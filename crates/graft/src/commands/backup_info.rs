@@ -0,0 +1,33 @@
+use std::io;
+use std::path::Path;
+
+use graft_core::patch::{find_backup_set, format_size, format_timestamp};
+
+/// Show the files of a backup set, by default the latest restore point.
+pub fn run(target_dir: &Path, id: Option<&str>) -> io::Result<()> {
+    let Some(set) = find_backup_set(target_dir, id)? else {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            match id {
+                Some(id) => format!("no backup set '{}' in {}", id, target_dir.display()),
+                None => format!("no restore points in {}", target_dir.display()),
+            },
+        ));
+    };
+
+    println!("Backup: {}", set.label());
+    if let Some(point) = &set.restore_point {
+        println!("  Patch: {} v{}", point.name, point.version);
+        println!("  Applied: {}", format_timestamp(point.created));
+    }
+    println!("  Location: {}", set.dir.display());
+    println!("  Size: {} in {} files", format_size(set.size()), set.files.len());
+
+    if !set.files.is_empty() {
+        println!("\nFiles:");
+        for file in &set.files {
+            println!("  {} ({}, {})", file.file, file.format, format_size(file.size));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,34 @@
+use std::io;
+use std::path::Path;
+
+use graft_core::patch::{backup_root, backup_sets, format_size, format_timestamp};
+
+/// List the backup sets of a target directory, oldest first.
+pub fn run(target_dir: &Path) -> io::Result<()> {
+    let sets = backup_sets(target_dir)?;
+    if sets.is_empty() {
+        println!("No backups found in {}", backup_root(target_dir).display());
+        return Ok(());
+    }
+
+    println!("Backups in {}:", backup_root(target_dir).display());
+    for set in &sets {
+        let applied = set
+            .restore_point
+            .as_ref()
+            .map(|p| format!(", applied {}", format_timestamp(p.created)))
+            .unwrap_or_default();
+        println!(
+            "  {} [{}]: {} files, {}{}",
+            set.label(),
+            set.id,
+            set.files.len(),
+            format_size(set.size()),
+            applied
+        );
+    }
+    if sets.iter().filter(|s| s.restore_point.is_some()).count() > 1 {
+        println!("\nRoll back in reverse order (last applied first).");
+    }
+    Ok(())
+}
@@ -0,0 +1,18 @@
+use std::io;
+use std::path::Path;
+
+use graft_core::patch::prune_backups;
+
+/// Remove backup sets without a restore point, and with `keep`, all but the
+/// `keep` most recent restore points.
+pub fn run(target_dir: &Path, keep: Option<usize>) -> io::Result<Vec<String>> {
+    let removed = prune_backups(target_dir, keep)?;
+    if removed.is_empty() {
+        println!("Nothing to prune");
+    } else {
+        for id in &removed {
+            println!("Removed {}", id);
+        }
+    }
+    Ok(removed)
+}
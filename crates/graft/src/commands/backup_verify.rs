@@ -0,0 +1,74 @@
+use std::path::Path;
+
+use graft_core::patch::{
+    backup_root, backup_set_dir, has_restore_point, is_legacy_backup, latest_backup_manifest, restore_point_id,
+    validate_backup, PatchError, Progress,
+};
+use graft_core::utils::manifest::Manifest;
+
+/// Check that a patch's backup set can restore every file it backed up.
+///
+/// Reverse diff backups are checked against the patched files in the target.
+/// Without `manifest_path`, the last applied patch is checked using the
+/// manifest saved in its backup.
+pub fn run(target_dir: &Path, manifest_path: Option<&Path>) -> Result<(), PatchError> {
+    let manifest = match manifest_path {
+        Some(path) => Manifest::load(path).map_err(|e| PatchError::ManifestError {
+            reason: e.to_string(),
+        })?,
+        None => latest_backup_manifest(target_dir)?,
+    };
+
+    if !has_restore_point(target_dir, &manifest) {
+        return Err(PatchError::RollbackFailed {
+            reason: format!(
+                "no restore point for {} in {}",
+                restore_point_id(&manifest),
+                backup_root(target_dir).display()
+            ),
+        });
+    }
+    let backup_dir = if is_legacy_backup(target_dir) {
+        backup_root(target_dir)
+    } else {
+        backup_set_dir(target_dir, &restore_point_id(&manifest))
+    };
+
+    validate_backup(&manifest.entries, target_dir, &backup_dir, Some(|p: Progress| {
+        println!("Checking [{}/{}]: {}", p.index + 1, p.total, p.file);
+    }))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::{patch_apply, patch_create};
+    use graft_core::patch::MANIFEST_FILENAME;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn detects_corrupted_backup() {
+        let orig_dir = tempdir().unwrap();
+        let new_dir = tempdir().unwrap();
+        let patch_dir = tempdir().unwrap();
+        let target_dir = tempdir().unwrap();
+
+        fs::write(orig_dir.path().join("file.bin"), b"original").unwrap();
+        fs::write(new_dir.path().join("file.bin"), b"modified").unwrap();
+        patch_create::run(orig_dir.path(), new_dir.path(), patch_dir.path(), 1, "TestPatcher", None, true).unwrap();
+        fs::write(target_dir.path().join("file.bin"), b"original").unwrap();
+        patch_apply::run(target_dir.path(), patch_dir.path(), None, Default::default()).unwrap();
+
+        let manifest = patch_dir.path().join(MANIFEST_FILENAME);
+        run(target_dir.path(), Some(&manifest)).unwrap();
+
+        fs::write(backup_set_dir(target_dir.path(), "TestPatcher-v1").join("file.bin"), b"corrupt").unwrap();
+        assert!(matches!(
+            run(target_dir.path(), None),
+            Err(PatchError::RollbackFailed { reason }) if reason.contains("hash mismatch")
+        ));
+    }
+}
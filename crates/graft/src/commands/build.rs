@@ -11,7 +11,7 @@ use crate::stubs::{self, StubError};
 use crate::targets::{self, Target};
 #[cfg(feature = "embedded-stubs")]
 use crate::targets::ALL_TARGETS;
-use graft_core::archive::{self, MAGIC_MARKER};
+use graft_core::archive::{self, ArchiveCodec, ArchiveOptions, ContainerFormat, MAGIC_MARKER};
 use graft_core::patch::{self, ASSETS_DIR, ICON_FILENAME};
 use graft_core::utils::manifest::PatchInfo;
 use std::fs;
@@ -40,6 +40,12 @@ pub enum PatcherError {
     OutputError(io::Error),
     /// Invalid target specified.
     InvalidTarget(String),
+    /// Invalid container format specified.
+    InvalidContainer(String),
+    /// Invalid compression codec or level specified.
+    InvalidCompression(String),
+    /// `SOURCE_DATE_EPOCH` is not a valid timestamp.
+    InvalidSourceDateEpoch(String),
     /// Failed to create macOS bundle.
     BundleError(BundleError),
     /// Failed to embed Windows icon.
@@ -54,6 +60,11 @@ impl std::fmt::Display for PatcherError {
             PatcherError::StubError(e) => write!(f, "Stub error: {}", e),
             PatcherError::OutputError(e) => write!(f, "Output error: {}", e),
             PatcherError::InvalidTarget(t) => write!(f, "Invalid target: {}", t),
+            PatcherError::InvalidContainer(c) => write!(f, "Invalid container format: {}", c),
+            PatcherError::InvalidCompression(msg) => write!(f, "Invalid compression: {}", msg),
+            PatcherError::InvalidSourceDateEpoch(value) => {
+                write!(f, "Invalid SOURCE_DATE_EPOCH: {}", value)
+            }
             PatcherError::BundleError(e) => write!(f, "Bundle creation failed: {}", e),
             PatcherError::WindowsIconError(e) => write!(f, "Windows icon embedding failed: {}", e),
         }
@@ -117,6 +128,102 @@ fn resolve_targets(
     }
 }
 
+/// Parse container, codec and level into archive options.
+///
+/// Without a container, a codec or level selects the tar container and the
+/// indexed container is used otherwise.
+fn parse_archive_options(
+    container: Option<&str>,
+    compression: Option<&str>,
+    level: Option<u32>,
+) -> Result<ArchiveOptions, PatcherError> {
+    let container = match container {
+        Some(name) => {
+            ContainerFormat::parse(name).ok_or_else(|| PatcherError::InvalidContainer(name.to_string()))?
+        }
+        None if compression.is_some() || level.is_some() => ContainerFormat::Tar,
+        None => ContainerFormat::default(),
+    };
+
+    if container == ContainerFormat::Indexed && (compression.is_some() || level.is_some()) {
+        return Err(PatcherError::InvalidCompression(
+            "indexed containers choose compression per entry".to_string(),
+        ));
+    }
+
+    let codec = match compression {
+        Some(name) => ArchiveCodec::parse(name)
+            .ok_or_else(|| PatcherError::InvalidCompression(format!("unknown codec '{}'", name)))?,
+        None => ArchiveCodec::default(),
+    };
+
+    if let Some(level) = level
+        && !codec.levels().contains(&level)
+    {
+        return Err(PatcherError::InvalidCompression(format!(
+            "{} level must be between {} and {}",
+            codec.name(),
+            codec.levels().start(),
+            codec.levels().end()
+        )));
+    }
+
+    Ok(ArchiveOptions {
+        container,
+        codec,
+        level,
+        ..Default::default()
+    })
+}
+
+/// Timestamp for archive entries, from the `SOURCE_DATE_EPOCH` environment
+/// variable (0 when unset) so builds are reproducible.
+fn source_date_epoch() -> Result<u64, PatcherError> {
+    match std::env::var("SOURCE_DATE_EPOCH") {
+        Ok(value) => value
+            .trim()
+            .parse()
+            .map_err(|_| PatcherError::InvalidSourceDateEpoch(value)),
+        Err(_) => Ok(0),
+    }
+}
+
+/// Print the packed size against the uncompressed and default-packed sizes.
+fn print_size_comparison(
+    patch_dir: &Path,
+    options: &ArchiveOptions,
+    packed_len: usize,
+) -> Result<(), PatcherError> {
+    let percent = |len: usize, of: usize| len as f64 * 100.0 / of.max(1) as f64;
+
+    let tar_len = archive::tar_size(patch_dir, options.mtime).map_err(PatcherError::ArchiveCreation)? as usize;
+    println!(
+        "  Uncompressed: {} bytes, packed: {} bytes ({:.1}%)",
+        tar_len,
+        packed_len,
+        percent(packed_len, tar_len)
+    );
+
+    let default_options = ArchiveOptions {
+        mtime: options.mtime,
+        ..Default::default()
+    };
+    if *options != default_options {
+        let default_len = archive::create_container_bytes(patch_dir, &default_options)
+            .map_err(PatcherError::ArchiveCreation)?
+            .len();
+        println!(
+            "  Default ({}): {} bytes, {} is {:.1}% of that",
+            default_options.describe(),
+            default_len,
+            options.describe(),
+            percent(packed_len, default_len)
+        );
+    }
+
+    Ok(())
+}
+
 /// Create a patcher executable (production mode with embedded stubs).
 ///
 /// # Arguments
@@ -124,13 +231,22 @@ fn resolve_targets(
 /// * `output_dir` - Output directory for patcher executables
 /// * `stub_dir` - Optional directory with stubs (overrides embedded)
 /// * `targets` - Target platforms to build for (empty = all available)
+/// * `container` - Patch container format ("tar" or "indexed"; `None` = see [`parse_archive_options`])
+/// * `compression` - Codec for the tar container ("gzip", "zstd" or "xz"; `None` = gzip)
+/// * `level` - Compression level (`None` = codec default)
 #[cfg(feature = "embedded-stubs")]
 pub fn run(
     patch_dir: &Path,
     output_dir: &Path,
     stub_dir: Option<&Path>,
     targets: &[String],
+    container: Option<&str>,
+    compression: Option<&str>,
+    level: Option<u32>,
 ) -> Result<(), PatcherError> {
+    let mut options = parse_archive_options(container, compression, level)?;
+    options.mtime = source_date_epoch()?;
+
     let stub_source = match stub_dir {
         Some(dir) => StubSource::Directory(dir),
         None => StubSource::Embedded,
@@ -141,8 +257,9 @@ pub fn run(
     // Ensure output directory exists
     fs::create_dir_all(output_dir).map_err(PatcherError::OutputError)?;
 
+    let (info, archive_data) = create_archive(patch_dir, &options)?;
     for target in &targets_to_build {
-        build_single(patch_dir, target, output_dir, &stub_source)?;
+        build_single(patch_dir, &info, &archive_data, target, output_dir, &stub_source)?;
     }
 
     Ok(())
@@ -155,13 +272,22 @@ pub fn run(
 /// * `output_dir` - Output directory for patcher executables
 /// * `stub_dir` - Directory containing stub binaries (required)
 /// * `targets` - Target platforms to build for (empty = all available)
+/// * `container` - Patch container format ("tar" or "indexed"; `None` = see [`parse_archive_options`])
+/// * `compression` - Codec for the tar container ("gzip", "zstd" or "xz"; `None` = gzip)
+/// * `level` - Compression level (`None` = codec default)
 #[cfg(not(feature = "embedded-stubs"))]
 pub fn run(
     patch_dir: &Path,
     output_dir: &Path,
     stub_dir: &Path,
     targets: &[String],
+    container: Option<&str>,
+    compression: Option<&str>,
+    level: Option<u32>,
 ) -> Result<(), PatcherError> {
+    let mut options = parse_archive_options(container, compression, level)?;
+    options.mtime = source_date_epoch()?;
+
     println!("Development mode: no embedded stubs");
     println!("Using stubs from: {}", stub_dir.display());
     println!();
@@ -172,21 +298,16 @@ pub fn run(
     // Ensure output directory exists
     fs::create_dir_all(output_dir).map_err(PatcherError::OutputError)?;
 
+    let (info, archive_data) = create_archive(patch_dir, &options)?;
     for target in &targets_to_build {
-        build_single(patch_dir, target, output_dir, &stub_source)?;
+        build_single(patch_dir, &info, &archive_data, target, output_dir, &stub_source)?;
     }
 
     Ok(())
 }
 
-/// Build a patcher for a single target.
-fn build_single(
-    patch_dir: &Path,
-    target: &Target,
-    output_dir: &Path,
-    stub_source: &StubSource<'_>,
-) -> Result<(), PatcherError> {
-    // Validate patch directory
+/// Validate the patch directory and pack it once for all targets.
+fn create_archive(patch_dir: &Path, options: &ArchiveOptions) -> Result<(PatchInfo, Vec<u8>), PatcherError> {
     let manifest = patch::validate_patch_dir(patch_dir)
         .map_err(|e| PatcherError::PatchValidation(e.to_string()))?;
     let info = PatchInfo::from_manifest(&manifest);
@@ -195,14 +316,28 @@ fn build_single(
         "Creating patcher '{}' for patch v{} ({} operations: {} patches, {} additions, {} deletions)",
         info.name, info.version, info.entry_count, info.patches, info.additions, info.deletions
     );
-    println!("Target: {}", target.name);
 
-    // Create archive
-    print!("Creating patch archive... ");
+    print!("Creating patch archive ({})... ", options.describe());
     io::stdout().flush().ok();
-    let archive_data =
-        archive::create_archive_bytes(patch_dir).map_err(PatcherError::ArchiveCreation)?;
+    let archive_data = archive::create_container_bytes(patch_dir, options)
+        .map_err(PatcherError::ArchiveCreation)?;
     println!("done ({} bytes)", archive_data.len());
+    print_size_comparison(patch_dir, options, archive_data.len())?;
+
+    Ok((info, archive_data))
+}
+
+/// Build a patcher for a single target from the packed patch archive.
+fn build_single(
+    patch_dir: &Path,
+    info: &PatchInfo,
+    archive_data: &[u8],
+    target: &Target,
+    output_dir: &Path,
+    stub_source: &StubSource<'_>,
+) -> Result<(), PatcherError> {
+    println!();
+    println!("Target: {}", target.name);
 
     // Determine output path
     let output = output_dir.join(output_filename(&info.name, target));
@@ -221,7 +356,7 @@ fn build_single(
                 .map_err(PatcherError::StubError)?;
             macos_bundle::finalize_bundle(
                 &output,
-                &archive_data,
+                archive_data,
                 patch_dir,
                 info.title.as_deref(),
                 &info.version.to_string(),
@@ -232,7 +367,7 @@ fn build_single(
             macos_bundle::modify_bundle(
                 &stub_bundle_path,
                 &output,
-                &archive_data,
+                archive_data,
                 patch_dir,
                 info.title.as_deref(),
                 &info.version.to_string(),
@@ -246,7 +381,7 @@ fn build_single(
             macos_bundle::modify_bundle(
                 &stub_bundle_path,
                 &output,
-                &archive_data,
+                archive_data,
                 patch_dir,
                 info.title.as_deref(),
                 &info.version.to_string(),
@@ -264,7 +399,7 @@ fn build_single(
         let stub_data = get_stub(target, stub_source)?;
         println!("done ({} bytes)", stub_data.len());
 
-        let executable_data = create_executable_bytes(&stub_data, &archive_data);
+        let executable_data = create_executable_bytes(&stub_data, archive_data);
         let total_size = executable_data.len();
 
         print!("Writing patcher to {}... ", output.display());
@@ -365,10 +500,10 @@ mod tests {
         let targets = vec!["linux-x64".to_string()];
 
         #[cfg(feature = "embedded-stubs")]
-        let result = run(temp.path(), &output_dir, Some(&stub_dir), &targets);
+        let result = run(temp.path(), &output_dir, Some(&stub_dir), &targets, None, None, None);
 
         #[cfg(not(feature = "embedded-stubs"))]
-        let result = run(temp.path(), &output_dir, &stub_dir, &targets);
+        let result = run(temp.path(), &output_dir, &stub_dir, &targets, None, None, None);
 
         assert!(matches!(result, Err(PatcherError::PatchValidation(_))));
     }
@@ -390,11 +525,62 @@ mod tests {
         let targets = vec!["invalid-target".to_string()];
 
         #[cfg(feature = "embedded-stubs")]
-        let result = run(temp.path(), &output_dir, Some(&stub_dir), &targets);
+        let result = run(temp.path(), &output_dir, Some(&stub_dir), &targets, None, None, None);
 
         #[cfg(not(feature = "embedded-stubs"))]
-        let result = run(temp.path(), &output_dir, &stub_dir, &targets);
+        let result = run(temp.path(), &output_dir, &stub_dir, &targets, None, None, None);
 
         assert!(matches!(result, Err(PatcherError::InvalidTarget(_))));
     }
+
+    #[test]
+    fn run_fails_with_invalid_container() {
+        let temp = tempdir().unwrap();
+        let output_dir = temp.path().join("output");
+        let stub_dir = temp.path().join("stubs");
+        fs::create_dir_all(&stub_dir).unwrap();
+
+        let targets = vec!["linux-x64".to_string()];
+
+        #[cfg(feature = "embedded-stubs")]
+        let result = run(temp.path(), &output_dir, Some(&stub_dir), &targets, Some("zip"), None, None);
+
+        #[cfg(not(feature = "embedded-stubs"))]
+        let result = run(temp.path(), &output_dir, &stub_dir, &targets, Some("zip"), None, None);
+
+        assert!(matches!(result, Err(PatcherError::InvalidContainer(_))));
+    }
+
+    #[test]
+    fn parses_archive_options() {
+        let options = parse_archive_options(Some("tar"), Some("zstd"), Some(19)).unwrap();
+        assert_eq!(options.codec, ArchiveCodec::Zstd);
+        assert_eq!(options.level, Some(19));
+
+        assert_eq!(
+            parse_archive_options(None, None, None).unwrap(),
+            ArchiveOptions::default()
+        );
+        assert_eq!(ArchiveOptions::default().container, ContainerFormat::Indexed);
+
+        let options = parse_archive_options(None, Some("xz"), None).unwrap();
+        assert_eq!(options.container, ContainerFormat::Tar);
+        assert_eq!(options.codec, ArchiveCodec::Xz);
+    }
+
+    #[test]
+    fn rejects_invalid_compression() {
+        assert!(matches!(
+            parse_archive_options(Some("tar"), Some("lz4"), None),
+            Err(PatcherError::InvalidCompression(_))
+        ));
+        assert!(matches!(
+            parse_archive_options(Some("tar"), Some("gzip"), Some(12)),
+            Err(PatcherError::InvalidCompression(_))
+        ));
+        assert!(matches!(
+            parse_archive_options(Some("indexed"), Some("xz"), None),
+            Err(PatcherError::InvalidCompression(_))
+        ));
+    }
 }
@@ -0,0 +1,56 @@
+//! Helpers shared by the commands that rewrite patch directories
+//! (`patch squash` and `patch reverse`).
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use graft_core::patch::PatchError;
+use graft_core::utils::hash::hash_bytes;
+
+/// Check that `data` hashes to `expected`.
+///
+/// `what` names the version of `file` being checked in the error (e.g. "original").
+pub(crate) fn check_hash(file: &str, data: &[u8], expected: &str, what: &str) -> Result<(), PatchError> {
+    let actual = hash_bytes(data);
+    if actual != expected {
+        return Err(PatchError::VerificationFailed {
+            file: format!("{} ({})", file, what),
+            expected: expected.to_string(),
+            actual,
+        });
+    }
+    Ok(())
+}
+
+/// Read `file` from the original tree and check its hash.
+pub(crate) fn read_original(original_dir: &Path, file: &str, hash: &str) -> Result<Vec<u8>, PatchError> {
+    let data = fs::read(original_dir.join(file)).map_err(|_| PatchError::FileNotFound(file.to_string()))?;
+    check_hash(file, &data, hash, "original")?;
+    Ok(data)
+}
+
+pub(crate) fn write_error(file: &str, e: io::Error) -> PatchError {
+    PatchError::ApplyFailed {
+        file: file.to_string(),
+        reason: e.to_string(),
+    }
+}
+
+/// Write a payload of `file` to `path`, creating its parent directories.
+pub(crate) fn write_payload(path: &Path, file: &str, data: &[u8]) -> Result<(), PatchError> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| write_error(file, e))?;
+    }
+    fs::write(path, data).map_err(|e| write_error(file, e))
+}
+
+/// Write a set of files into a fresh directory.
+#[cfg(test)]
+pub(crate) fn tree(files: &[(&str, &[u8])]) -> tempfile::TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    for (name, content) in files {
+        fs::write(dir.path().join(name), content).unwrap();
+    }
+    dir
+}
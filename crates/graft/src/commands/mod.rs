@@ -1,11 +1,21 @@
+pub mod backup_export;
+pub mod backup_info;
+pub mod backup_list;
+pub mod backup_prune;
+pub mod backup_verify;
 pub mod build;
 pub mod calculate;
 pub mod check;
+mod common;
 pub mod compare;
 pub mod diff_apply;
 pub mod diff_create;
 pub mod macos_bundle;
 pub mod patch_apply;
 pub mod patch_create;
+pub mod patch_inspect;
+pub mod patch_reverse;
 pub mod patch_rollback;
+pub mod patch_squash;
+pub mod patch_verify;
 pub mod windows_icon;
@@ -1,10 +1,12 @@
 use std::path::Path;
 
 use graft_core::patch::{
-    apply_entries, backup_entries, validate_entries, validate_path_restrictions, PatchError,
-    Progress, ProgressAction, BACKUP_DIR, MANIFEST_FILENAME,
+    apply_entries, backup_entries, backup_set_dir, delete_backup_set, forget_restore_point,
+    prepare_backup_root, record_restore_point, restore_point_id, run_post_action, store_reverse_diffs, validate_entries,
+    validate_path_restrictions, PatchError, Progress, ProgressAction, BACKUP_INDEX_FILENAME,
+    BACKUP_MARKER_FILENAME, MANIFEST_FILENAME,
 };
-use graft_core::utils::manifest::Manifest;
+use graft_core::utils::manifest::{BackupOptions, Manifest};
 
 fn format_action(action: ProgressAction) -> &'static str {
     match action {
@@ -15,44 +17,128 @@ fn format_action(action: ProgressAction) -> &'static str {
         ProgressAction::Patching => "Patching",
         ProgressAction::Adding => "Adding",
         ProgressAction::Deleting => "Deleting",
+        ProgressAction::Editing => "Editing",
         ProgressAction::Restoring => "Restoring",
         ProgressAction::Removing => "Removing",
     }
 }
 
+/// Print a line as each entry starts; byte progress within it is not shown.
+fn print_progress(p: Progress) {
+    if p.bytes_done == 0 {
+        println!("{} [{}/{}]: {}", format_action(p.action), p.index + 1, p.total, p.file);
+    }
+}
+
 /// Apply a patch to a target directory.
 ///
 /// Workflow:
 /// 1. Load and parse manifest
 /// 2. Validate all entries (files exist, hashes match)
-/// 3. Backup all files that will be modified/deleted
+/// 3. Backup all files that will be modified/deleted into this patch's
+///    backup set (`.patch-backup/<name>-v<version>/`) and record a restore point
 /// 4. Apply each entry, verifying immediately after
 /// 5. On any failure, rollback to original state
-pub fn run(target_dir: &Path, patch_dir: &Path) -> Result<(), PatchError> {
+/// 6. Run the automatic post-apply steps; launching and opening is left to
+///    the patcher, which asks the user
+///
+/// Patches can be stacked: earlier restore points are left untouched.
+///
+/// `backup_dir` stores the backups outside the target instead (falling back to
+/// the manifest's default location); a marker file in the target points to it.
+/// `options` enables compressed and reverse diff backups in addition to those
+/// the manifest asks for.
+///
+/// Of the patch's optional components, the default ones are applied.
+pub fn run(
+    target_dir: &Path,
+    patch_dir: &Path,
+    backup_dir: Option<&Path>,
+    options: BackupOptions,
+) -> Result<(), PatchError> {
+    run_with_components(target_dir, patch_dir, backup_dir, options, None, false)
+}
+
+/// Apply a patch with the optional `components` (and those they require)
+/// instead of the default ones.
+///
+/// Only the chosen components are saved with the restore point, so each can
+/// be rolled back on its own later. With `skip_post_apply`, none of the
+/// patch's post-apply steps run.
+pub fn run_with_components(
+    target_dir: &Path,
+    patch_dir: &Path,
+    backup_dir: Option<&Path>,
+    options: BackupOptions,
+    components: Option<&[String]>,
+    skip_post_apply: bool,
+) -> Result<(), PatchError> {
     // Load manifest
     let manifest_path = patch_dir.join(MANIFEST_FILENAME);
     let manifest = Manifest::load(&manifest_path).map_err(|e| PatchError::ManifestError {
         reason: e.to_string(),
     })?;
 
+    // Leave out the components not chosen
+    let components = components.map_or_else(|| manifest.default_components(), <[String]>::to_vec);
+    let manifest = manifest.with_components(&components).map_err(|e| PatchError::ManifestError {
+        reason: e.to_string(),
+    })?;
+    if !manifest.components.is_empty() {
+        let names: Vec<_> = manifest.components.iter().map(|c| c.name.as_str()).collect();
+        println!("Components: {}", names.join(", "));
+    }
+
     // Check path restrictions (unless allow_restricted is set in manifest)
     validate_path_restrictions(&manifest, target_dir)?;
 
     // Validate all entries before making any changes
-    validate_entries(&manifest.entries, target_dir, Some(|p: Progress| {
-        println!("{} [{}/{}]: {}", format_action(p.action), p.index + 1, p.total, p.file);
-    }))?;
+    validate_entries(&manifest.entries, target_dir, None, Some(print_progress))?;
 
     // Backup all files that will be modified/deleted
-    let backup_dir = target_dir.join(BACKUP_DIR);
-    backup_entries(&manifest.entries, target_dir, &backup_dir, Some(|p: Progress| {
-        println!("{} [{}/{}]: {}", format_action(p.action), p.index + 1, p.total, p.file);
-    }))?;
+    prepare_backup_root(target_dir, backup_dir, &manifest).map_err(|e| PatchError::BackupFailed {
+        file: BACKUP_MARKER_FILENAME.to_string(),
+        reason: e.to_string(),
+    })?;
+    let id = restore_point_id(&manifest);
+    let backup_dir = backup_set_dir(target_dir, &id);
+    let options = BackupOptions {
+        compress: options.compress || manifest.backup.compress,
+        reverse_diffs: options.reverse_diffs || manifest.backup.reverse_diffs,
+    };
+    record_restore_point(target_dir, &manifest).map_err(|e| PatchError::BackupFailed {
+        file: BACKUP_INDEX_FILENAME.to_string(),
+        reason: e.to_string(),
+    })?;
 
-    // Apply each entry with automatic rollback on failure
-    apply_entries(&manifest.entries, target_dir, patch_dir, &backup_dir, Some(|p: Progress| {
-        println!("{} [{}/{}]: {}", format_action(p.action), p.index + 1, p.total, p.file);
-    }))?;
+    // Back up, then apply each entry with automatic rollback on failure
+    let result = backup_entries(&manifest.entries, target_dir, &backup_dir, options, None, Some(print_progress))
+        .and_then(|()| apply_entries(&manifest.entries, target_dir, patch_dir, &backup_dir, None, Some(print_progress)));
+
+    // A failed backup or apply leaves nothing to roll back to
+    if result.is_err() {
+        let _ = forget_restore_point(target_dir, &id);
+        let _ = delete_backup_set(target_dir, &id);
+    }
+    for file in result? {
+        println!("Replaced with the full new file (did not match the original): {}", file);
+    }
+
+    if options.reverse_diffs {
+        match store_reverse_diffs(&manifest.entries, target_dir, &backup_dir) {
+            Ok(count) => println!("Stored {} backups as reverse diffs", count),
+            Err(e) => eprintln!("Warning: keeping full backups: {}", e),
+        }
+    }
+
+    // The patch is in place; a failed step is only worth a warning
+    for action in &manifest.post_apply {
+        if skip_post_apply || !action.is_automatic() {
+            println!("Skipping post-apply step: {}", action);
+        } else if let Err(e) = run_post_action(action, target_dir) {
+            eprintln!("Warning: {}", e);
+        }
+    }
 
     Ok(())
 }
@@ -60,7 +146,8 @@ pub fn run(target_dir: &Path, patch_dir: &Path) -> Result<(), PatchError> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::commands::patch_create;
+    use crate::commands::{patch_create, patch_rollback};
+    use graft_core::utils::manifest::{Component, ManifestEntry, PostAction};
     use std::fs;
     use tempfile::tempdir;
 
@@ -85,7 +172,7 @@ mod tests {
         fs::write(target_dir.path().join("deleted.bin"), b"to delete").unwrap();
 
         // Apply patch
-        run(target_dir.path(), patch_dir.path()).unwrap();
+        run(target_dir.path(), patch_dir.path(), None, BackupOptions::default()).unwrap();
 
         // Verify results
         assert_eq!(
@@ -112,7 +199,7 @@ mod tests {
         patch_create::run(orig_dir.path(), new_dir.path(), patch_dir.path(), 1, "TestPatcher", None, true).unwrap();
 
         // Target is missing the file
-        let result = run(target_dir.path(), patch_dir.path());
+        let result = run(target_dir.path(), patch_dir.path(), None, BackupOptions::default());
 
         assert!(matches!(result, Err(PatchError::ValidationFailed { .. })));
     }
@@ -132,7 +219,7 @@ mod tests {
         // Target has different content
         fs::write(target_dir.path().join("file.bin"), b"different").unwrap();
 
-        let result = run(target_dir.path(), patch_dir.path());
+        let result = run(target_dir.path(), patch_dir.path(), None, BackupOptions::default());
 
         assert!(matches!(result, Err(PatchError::ValidationFailed { .. })));
     }
@@ -151,7 +238,7 @@ mod tests {
         // Target already has that file
         fs::write(target_dir.path().join("new.bin"), b"existing").unwrap();
 
-        let result = run(target_dir.path(), patch_dir.path());
+        let result = run(target_dir.path(), patch_dir.path(), None, BackupOptions::default());
 
         assert!(matches!(result, Err(PatchError::ValidationFailed { .. })));
     }
@@ -168,7 +255,7 @@ mod tests {
         patch_create::run(orig_dir.path(), new_dir.path(), patch_dir.path(), 1, "TestPatcher", None, true).unwrap();
 
         // Target doesn't have the file (already deleted)
-        let result = run(target_dir.path(), patch_dir.path());
+        let result = run(target_dir.path(), patch_dir.path(), None, BackupOptions::default());
 
         assert!(result.is_ok());
     }
@@ -195,7 +282,7 @@ mod tests {
         let diffs_dir = patch_dir.path().join("diffs");
         fs::write(diffs_dir.join("b.bin.diff"), b"corrupted diff data").unwrap();
 
-        let result = run(target_dir.path(), patch_dir.path());
+        let result = run(target_dir.path(), patch_dir.path(), None, BackupOptions::default());
 
         // Should fail
         assert!(result.is_err());
@@ -207,6 +294,58 @@ mod tests {
         );
     }
 
+    #[test]
+    fn json_append_is_rejected_before_changing_anything() {
+        let orig_dir = tempdir().unwrap();
+        let patch_dir = tempdir().unwrap();
+        let target_dir = tempdir().unwrap();
+
+        patch_create::run(orig_dir.path(), orig_dir.path(), patch_dir.path(), 1, "TestPatcher", None, true).unwrap();
+        let manifest_path = patch_dir.path().join(MANIFEST_FILENAME);
+        let mut manifest = Manifest::load(&manifest_path).unwrap();
+        manifest.entries.push(ManifestEntry::EditJson {
+            file: "settings.json".to_string(),
+            pointer: "/mods/-".to_string(),
+            value: "extra".into(),
+        });
+        manifest.save(&manifest_path).unwrap();
+
+        fs::write(target_dir.path().join("settings.json"), b"{\"mods\": [\"base\"]}").unwrap();
+
+        assert!(run(target_dir.path(), patch_dir.path(), None, BackupOptions::default()).is_err());
+        assert_eq!(fs::read(target_dir.path().join("settings.json")).unwrap(), b"{\"mods\": [\"base\"]}");
+    }
+
+    #[test]
+    fn edits_are_rolled_back_on_failure() {
+        let orig_dir = tempdir().unwrap();
+        let new_dir = tempdir().unwrap();
+        let patch_dir = tempdir().unwrap();
+        let target_dir = tempdir().unwrap();
+
+        fs::write(orig_dir.path().join("z.bin"), b"original z").unwrap();
+        fs::write(new_dir.path().join("z.bin"), b"modified z").unwrap();
+        patch_create::run(orig_dir.path(), new_dir.path(), patch_dir.path(), 1, "TestPatcher", None, true).unwrap();
+        fs::write(patch_dir.path().join("diffs").join("z.bin.diff"), b"corrupted").unwrap();
+        let manifest_path = patch_dir.path().join(MANIFEST_FILENAME);
+        let mut manifest = Manifest::load(&manifest_path).unwrap();
+        manifest.entries.insert(
+            0,
+            ManifestEntry::EditJson {
+                file: "settings.json".to_string(),
+                pointer: "/volume".to_string(),
+                value: 80.into(),
+            },
+        );
+        manifest.save(&manifest_path).unwrap();
+
+        fs::write(target_dir.path().join("z.bin"), b"original z").unwrap();
+        fs::write(target_dir.path().join("settings.json"), b"{\"volume\": 50}").unwrap();
+
+        assert!(run(target_dir.path(), patch_dir.path(), None, BackupOptions::default()).is_err());
+        assert_eq!(fs::read(target_dir.path().join("settings.json")).unwrap(), b"{\"volume\": 50}");
+    }
+
     #[test]
     fn backup_preserved_on_success() {
         let orig_dir = tempdir().unwrap();
@@ -220,20 +359,311 @@ mod tests {
 
         fs::write(target_dir.path().join("file.bin"), b"original").unwrap();
 
-        run(target_dir.path(), patch_dir.path()).unwrap();
+        run(target_dir.path(), patch_dir.path(), None, BackupOptions::default()).unwrap();
 
-        // Backup directory should exist with original file
-        let backup_dir = target_dir.path().join(BACKUP_DIR);
+        // Backup set should exist with original file
+        let backup_dir = backup_set_dir(target_dir.path(), "TestPatcher-v1");
         assert!(backup_dir.exists());
         assert_eq!(fs::read(backup_dir.join("file.bin")).unwrap(), b"original");
     }
 
+    #[test]
+    fn automatic_post_apply_steps_run_after_success() {
+        let orig_dir = tempdir().unwrap();
+        let new_dir = tempdir().unwrap();
+        let patch_dir = tempdir().unwrap();
+        let target_dir = tempdir().unwrap();
+
+        fs::write(orig_dir.path().join("file.bin"), b"original").unwrap();
+        fs::write(new_dir.path().join("file.bin"), b"modified").unwrap();
+        patch_create::run(orig_dir.path(), new_dir.path(), patch_dir.path(), 1, "TestPatcher", None, true).unwrap();
+        let manifest_path = patch_dir.path().join(MANIFEST_FILENAME);
+        let mut manifest = Manifest::load(&manifest_path).unwrap();
+        manifest.post_apply = vec![
+            PostAction::DeleteDir { path: "cache".to_string() },
+            PostAction::Launch { path: "missing-game".to_string(), args: Vec::new() },
+        ];
+        manifest.save(&manifest_path).unwrap();
+
+        fs::write(target_dir.path().join("file.bin"), b"original").unwrap();
+        fs::create_dir(target_dir.path().join("cache")).unwrap();
+        fs::write(target_dir.path().join("cache/shader.bin"), b"stale").unwrap();
+
+        run(target_dir.path(), patch_dir.path(), None, BackupOptions::default()).unwrap();
+
+        assert!(!target_dir.path().join("cache").exists());
+        assert_eq!(fs::read(target_dir.path().join("file.bin")).unwrap(), b"modified");
+
+        let skipped_dir = tempdir().unwrap();
+        fs::write(skipped_dir.path().join("file.bin"), b"original").unwrap();
+        fs::create_dir(skipped_dir.path().join("cache")).unwrap();
+        run_with_components(skipped_dir.path(), patch_dir.path(), None, BackupOptions::default(), None, true).unwrap();
+        assert!(skipped_dir.path().join("cache").exists());
+    }
+
+    #[test]
+    fn fallback_patches_modified_file_and_rolls_back() {
+        let orig_dir = tempdir().unwrap();
+        let new_dir = tempdir().unwrap();
+        let patch_dir = tempdir().unwrap();
+        let target_dir = tempdir().unwrap();
+
+        for (name, original, modified) in [("data.pak", "original data", "new data"), ("other.bin", "one", "two")] {
+            fs::write(orig_dir.path().join(name), original).unwrap();
+            fs::write(new_dir.path().join(name), modified).unwrap();
+            fs::write(target_dir.path().join(name), original).unwrap();
+        }
+        let manifest = Manifest::new(1, "TestPatcher".to_string(), None);
+        let filter = graft_core::utils::file_filter::FileFilter::default();
+        patch_create::run_with_filter(
+            orig_dir.path(),
+            new_dir.path(),
+            patch_dir.path(),
+            manifest,
+            &filter,
+            &patch_create::CreateOptions {
+                fallback: vec!["*.pak".to_string()],
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        // Only the entry with a fallback accepts a file changed by something else
+        fs::write(target_dir.path().join("data.pak"), b"data changed by a mod").unwrap();
+        run(target_dir.path(), patch_dir.path(), None, BackupOptions::default()).unwrap();
+        assert_eq!(fs::read(target_dir.path().join("data.pak")).unwrap(), b"new data");
+
+        patch_rollback::run(target_dir.path(), None, false, None).unwrap();
+        assert_eq!(fs::read(target_dir.path().join("data.pak")).unwrap(), b"data changed by a mod");
+
+        fs::write(target_dir.path().join("other.bin"), b"changed").unwrap();
+        assert!(matches!(
+            run(target_dir.path(), patch_dir.path(), None, BackupOptions::default()),
+            Err(PatchError::ValidationFailed { .. })
+        ));
+    }
+
+    #[test]
+    fn patches_each_release_of_the_original() {
+        let orig_dir = tempdir().unwrap();
+        let alt_dir = tempdir().unwrap();
+        let new_dir = tempdir().unwrap();
+        let patch_dir = tempdir().unwrap();
+
+        fs::write(orig_dir.path().join("game.bin"), b"game, US release").unwrap();
+        fs::write(alt_dir.path().join("game.bin"), b"game, EU release").unwrap();
+        fs::write(new_dir.path().join("game.bin"), b"game, patched").unwrap();
+        for dir in [&orig_dir, &alt_dir] {
+            fs::write(dir.path().join("data.bin"), b"shared data").unwrap();
+        }
+        fs::write(new_dir.path().join("data.bin"), b"new shared data").unwrap();
+        let manifest = Manifest::new(1, "TestPatcher".to_string(), None);
+        let filter = graft_core::utils::file_filter::FileFilter::default();
+        patch_create::run_with_filter(
+            orig_dir.path(),
+            new_dir.path(),
+            patch_dir.path(),
+            manifest,
+            &filter,
+            &patch_create::CreateOptions {
+                alt_origs: vec![alt_dir.path().to_path_buf()],
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        // Only the file that differs between the releases gets a second diff
+        let manifest = Manifest::load(&patch_dir.path().join("manifest.json")).unwrap();
+        let alternatives: Vec<_> = manifest.entries.iter().map(|entry| entry.patch_origins().len()).collect();
+        assert_eq!(alternatives, vec![1, 2]);
+
+        for release in [&orig_dir, &alt_dir] {
+            let target_dir = tempdir().unwrap();
+            for name in ["game.bin", "data.bin"] {
+                fs::copy(release.path().join(name), target_dir.path().join(name)).unwrap();
+            }
+            run(target_dir.path(), patch_dir.path(), None, BackupOptions::default()).unwrap();
+            assert_eq!(fs::read(target_dir.path().join("game.bin")).unwrap(), b"game, patched");
+
+            patch_rollback::run(target_dir.path(), None, false, None).unwrap();
+            assert_eq!(
+                fs::read(target_dir.path().join("game.bin")).unwrap(),
+                fs::read(release.path().join("game.bin")).unwrap()
+            );
+        }
+
+        // A file only the other release needs changed would get no entry
+        fs::write(orig_dir.path().join("lang.bin"), b"english").unwrap();
+        fs::write(new_dir.path().join("lang.bin"), b"english").unwrap();
+        fs::write(alt_dir.path().join("lang.bin"), b"german").unwrap();
+        let result = patch_create::run_with_filter(
+            orig_dir.path(),
+            new_dir.path(),
+            tempdir().unwrap().path(),
+            Manifest::new(1, "TestPatcher".to_string(), None),
+            &filter,
+            &patch_create::CreateOptions {
+                alt_origs: vec![alt_dir.path().to_path_buf()],
+                ..Default::default()
+            },
+        );
+        assert_eq!(result.unwrap_err().kind(), std::io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn failed_apply_leaves_no_restore_point() {
+        let orig_dir = tempdir().unwrap();
+        let new_dir = tempdir().unwrap();
+        let patch_dir = tempdir().unwrap();
+        let target_dir = tempdir().unwrap();
+
+        fs::write(orig_dir.path().join("file.bin"), b"original").unwrap();
+        fs::write(new_dir.path().join("file.bin"), b"modified").unwrap();
+        patch_create::run(orig_dir.path(), new_dir.path(), patch_dir.path(), 1, "TestPatcher", None, true).unwrap();
+        fs::write(patch_dir.path().join("diffs").join("file.bin.diff"), b"corrupted").unwrap();
+
+        fs::write(target_dir.path().join("file.bin"), b"original").unwrap();
+        assert!(run(target_dir.path(), patch_dir.path(), None, BackupOptions::default()).is_err());
+
+        assert!(!target_dir.path().join(graft_core::patch::BACKUP_DIR).exists());
+    }
+
+    #[test]
+    fn stacked_patches_roll_back_in_reverse_order() {
+        let v1_dir = tempdir().unwrap();
+        let v2_dir = tempdir().unwrap();
+        let v3_dir = tempdir().unwrap();
+        let patch1 = tempdir().unwrap();
+        let patch2 = tempdir().unwrap();
+        let target_dir = tempdir().unwrap();
+
+        fs::write(v1_dir.path().join("file.bin"), b"one").unwrap();
+        fs::write(v2_dir.path().join("file.bin"), b"two").unwrap();
+        fs::write(v3_dir.path().join("file.bin"), b"three").unwrap();
+        patch_create::run(v1_dir.path(), v2_dir.path(), patch1.path(), 1, "Game", None, true).unwrap();
+        patch_create::run(v2_dir.path(), v3_dir.path(), patch2.path(), 2, "Game", None, true).unwrap();
+
+        fs::write(target_dir.path().join("file.bin"), b"one").unwrap();
+        run(target_dir.path(), patch1.path(), None, BackupOptions::default()).unwrap();
+        run(target_dir.path(), patch2.path(), None, BackupOptions::default()).unwrap();
+        assert_eq!(fs::read(target_dir.path().join("file.bin")).unwrap(), b"three");
+
+        let manifest1 = patch1.path().join(MANIFEST_FILENAME);
+
+        // The first patch can't be rolled back while the second is applied
+        assert!(matches!(
+            patch_rollback::run(target_dir.path(), Some(&manifest1), true, None),
+            Err(PatchError::RollbackFailed { .. })
+        ));
+
+        // Without a manifest, the last patch is rolled back using the one saved in its backup
+        patch_rollback::run(target_dir.path(), None, false, None).unwrap();
+        assert_eq!(fs::read(target_dir.path().join("file.bin")).unwrap(), b"two");
+        patch_rollback::run(target_dir.path(), Some(&manifest1), false, None).unwrap();
+        assert_eq!(fs::read(target_dir.path().join("file.bin")).unwrap(), b"one");
+        assert!(!target_dir.path().join(graft_core::patch::BACKUP_DIR).exists());
+    }
+
+    #[test]
+    fn external_backup_dir_keeps_target_clean() {
+        let orig_dir = tempdir().unwrap();
+        let new_dir = tempdir().unwrap();
+        let patch_dir = tempdir().unwrap();
+        let target_dir = tempdir().unwrap();
+        let backups = tempdir().unwrap();
+
+        fs::write(orig_dir.path().join("file.bin"), b"original").unwrap();
+        fs::write(new_dir.path().join("file.bin"), b"modified").unwrap();
+        patch_create::run(orig_dir.path(), new_dir.path(), patch_dir.path(), 1, "TestPatcher", None, true).unwrap();
+
+        fs::write(target_dir.path().join("file.bin"), b"original").unwrap();
+        run(target_dir.path(), patch_dir.path(), Some(backups.path()), BackupOptions::default()).unwrap();
+
+        assert!(!target_dir.path().join(graft_core::patch::BACKUP_DIR).exists());
+        assert!(target_dir.path().join(BACKUP_MARKER_FILENAME).exists());
+        assert_eq!(fs::read(backups.path().join("TestPatcher-v1").join("file.bin")).unwrap(), b"original");
+
+        // Rollback finds the backups through the marker
+        patch_rollback::run(target_dir.path(), Some(&patch_dir.path().join(MANIFEST_FILENAME)), false, None).unwrap();
+        assert_eq!(fs::read(target_dir.path().join("file.bin")).unwrap(), b"original");
+        assert!(!target_dir.path().join(BACKUP_MARKER_FILENAME).exists());
+    }
+
+    #[test]
+    fn components_are_applied_and_removed_individually() {
+        let orig_dir = tempdir().unwrap();
+        let new_dir = tempdir().unwrap();
+        let patch_dir = tempdir().unwrap();
+        let target_dir = tempdir().unwrap();
+
+        for name in ["core.bin", "textures_a.pak", "textures_b.pak", "voices_de.pak"] {
+            fs::write(orig_dir.path().join(name), b"original").unwrap();
+            fs::write(new_dir.path().join(name), b"modified").unwrap();
+            fs::write(target_dir.path().join(name), b"original").unwrap();
+        }
+        let component = |id: &str, default: bool, requires: &[&str], files: &str| Component {
+            id: id.to_string(),
+            name: id.to_string(),
+            description: None,
+            default,
+            requires: requires.iter().map(|r| r.to_string()).collect(),
+            files: vec![files.to_string()],
+        };
+        let mut manifest = Manifest::new(1, "TestPatcher".to_string(), None);
+        manifest.components = vec![
+            component("hd", false, &[], "textures_*"),
+            component("voices", true, &["hd"], "voices_*"),
+        ];
+        let filter = graft_core::utils::file_filter::FileFilter::default();
+        patch_create::run_with_filter(
+            orig_dir.path(),
+            new_dir.path(),
+            patch_dir.path(),
+            manifest,
+            &filter,
+            &patch_create::CreateOptions::default(),
+        )
+        .unwrap();
+        let read = |name: &str| fs::read(target_dir.path().join(name)).unwrap();
+
+        // Unknown components are rejected
+        let unknown = ["music".to_string()];
+        assert!(run_with_components(target_dir.path(), patch_dir.path(), None, BackupOptions::default(), Some(&unknown), false).is_err());
+        assert_eq!(read("core.bin"), b"original");
+
+        // The default component brings in the one it requires
+        run(target_dir.path(), patch_dir.path(), None, BackupOptions::default()).unwrap();
+        for name in ["core.bin", "textures_a.pak", "textures_b.pak", "voices_de.pak"] {
+            assert_eq!(read(name), b"modified");
+        }
+
+        // A required component can only go after the ones requiring it
+        assert!(patch_rollback::remove_component(target_dir.path(), None, "hd", false, None).is_err());
+        patch_rollback::remove_component(target_dir.path(), None, "voices", false, None).unwrap();
+        assert_eq!(read("voices_de.pak"), b"original");
+        assert_eq!(read("textures_a.pak"), b"modified");
+        patch_rollback::remove_component(target_dir.path(), None, "hd", false, None).unwrap();
+        assert_eq!(read("textures_b.pak"), b"original");
+        assert_eq!(read("core.bin"), b"modified");
+
+        // A full rollback restores what is left, even from the patch's manifest
+        patch_rollback::run(target_dir.path(), Some(&patch_dir.path().join(MANIFEST_FILENAME)), false, None).unwrap();
+        assert_eq!(read("core.bin"), b"original");
+
+        // Without a selection, only the core is applied
+        run_with_components(target_dir.path(), patch_dir.path(), None, BackupOptions::default(), Some(&[]), false).unwrap();
+        assert_eq!(read("core.bin"), b"modified");
+        assert_eq!(read("textures_a.pak"), b"original");
+        patch_rollback::run(target_dir.path(), None, false, None).unwrap();
+        assert_eq!(read("core.bin"), b"original");
+    }
+
     #[test]
     fn missing_manifest_returns_error() {
         let target_dir = tempdir().unwrap();
         let patch_dir = tempdir().unwrap();
 
-        let result = run(target_dir.path(), patch_dir.path());
+        let result = run(target_dir.path(), patch_dir.path(), None, BackupOptions::default());
 
         assert!(matches!(result, Err(PatchError::ManifestError { .. })));
     }
@@ -1,12 +1,17 @@
+use std::collections::HashSet;
 use std::fs;
 use std::io;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use graft_core::patch::{ASSETS_DIR, DIFFS_DIR, DIFF_EXTENSION, FILES_DIR, ICON_FILENAME, MANIFEST_FILENAME};
 use graft_core::utils::diff::create_diff;
-use graft_core::utils::dir_scan::{categorize_files, FileChange};
+use graft_core::utils::dir_scan::{categorize_files, list_files, FileChange};
+use graft_core::utils::edit::{set_ini, set_json};
+use graft_core::utils::file_filter::FileFilter;
 use graft_core::utils::hash::hash_bytes;
-use graft_core::utils::manifest::{Manifest, ManifestEntry};
+use graft_core::utils::manifest::{resolve_components, Component, Manifest, ManifestEntry, PatchOrigin};
+
+use crate::commands::patch_reverse::{reverse_edit, reverse_name, reverse_output_dir};
 
 /// Default icon embedded at compile time
 const DEFAULT_ICON: &[u8] = include_bytes!("../../assets/default_icon.png");
@@ -16,6 +21,8 @@ const DEFAULT_ICON: &[u8] = include_bytes!("../../assets/default_icon.png");
 ///
 /// If `allow_restricted` is true, the resulting manifest will allow patching
 /// restricted paths (system directories, executables). Default is false for security.
+///
+/// Patterns from a `.graftignore` file in `new_dir` are excluded.
 pub fn run(
     orig_dir: &Path,
     new_dir: &Path,
@@ -25,7 +32,132 @@ pub fn run(
     title: Option<&str>,
     allow_restricted: bool,
 ) -> io::Result<()> {
-    let changes = categorize_files(orig_dir, new_dir)?;
+    let filter = FileFilter::with_ignore_file(new_dir, &[], &[])?;
+    let mut manifest = Manifest::new(version, name.to_string(), title.map(|s| s.to_string()));
+    manifest.allow_restricted = allow_restricted;
+    run_with_filter(orig_dir, new_dir, output_dir, manifest, &filter, &CreateOptions::default())
+}
+
+/// Extra entries and payloads to record besides the changes between versions.
+#[derive(Debug, Clone, Default)]
+pub struct CreateOptions {
+    /// Unchanged files matching one of these globs are recorded as Require
+    /// entries, so the patcher refuses targets where they are missing or
+    /// different.
+    pub require: Vec<String>,
+    /// Modified files matching one of these globs also get a full copy of
+    /// their new version, which the patcher uses when the target file doesn't
+    /// match the original.
+    pub fallback: Vec<String>,
+    /// Other releases of the original directory, e.g. regional ones. A
+    /// modified file that differs in one of them also gets a diff from that
+    /// version. A file unchanged in the main original must not differ in
+    /// them, since it gets no entry. Require and Delete entries only accept
+    /// the main original's version of their file.
+    pub alt_origs: Vec<PathBuf>,
+}
+
+/// Inputs of `graft patch create` besides the directories and options.
+#[derive(Debug, Clone, Default)]
+pub struct CreateInputs {
+    /// Globs of files to compare (empty = all)
+    pub include: Vec<String>,
+    /// Globs of files to leave out, on top of `.graftignore`
+    pub exclude: Vec<String>,
+    /// JSON file with setting edits (see [`read_edits`])
+    pub edits: Option<PathBuf>,
+    /// JSON file with optional components (see [`read_components`])
+    pub components: Option<PathBuf>,
+    /// Also create the patch from the new version back to the original
+    pub bidirectional: bool,
+}
+
+/// Create a patch the way `graft patch create` does.
+///
+/// Reads the edits and components named in `inputs` into `manifest`, then
+/// creates the patch. With `inputs.bidirectional`, also creates the reverse
+/// patch next to `output_dir` (see [`reverse_output_dir`]) and named after
+/// the patch (see [`reverse_name`]): its edits set the values found in
+/// `orig_dir`, and it restores only the main original.
+///
+/// Returns the directory of the reverse patch, if one was created.
+pub fn run_with_inputs(
+    orig_dir: &Path,
+    new_dir: &Path,
+    output_dir: &Path,
+    mut manifest: Manifest,
+    inputs: &CreateInputs,
+    options: &CreateOptions,
+) -> io::Result<Option<PathBuf>> {
+    let filter = FileFilter::with_ignore_file(new_dir, &inputs.include, &inputs.exclude)?;
+    if let Some(edits) = &inputs.edits {
+        manifest.entries = read_edits(edits)?;
+    }
+    if let Some(components) = &inputs.components {
+        manifest.components = read_components(components)?;
+    }
+
+    run_with_filter(orig_dir, new_dir, output_dir, manifest.clone(), &filter, options)?;
+    if !inputs.bidirectional {
+        return Ok(None);
+    }
+
+    let reverse_dir = reverse_output_dir(output_dir);
+    manifest.name = reverse_name(&manifest.name);
+    manifest.entries = manifest
+        .entries
+        .iter()
+        .map(|entry| reverse_edit(entry, orig_dir))
+        .collect::<Result<_, _>>()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+    let options = CreateOptions {
+        alt_origs: Vec::new(),
+        ..options.clone()
+    };
+    run_with_filter(new_dir, orig_dir, &reverse_dir, manifest, &filter, &options)?;
+    Ok(Some(reverse_dir))
+}
+
+/// Create a patch, comparing only the files accepted by `filter`.
+///
+/// `manifest` provides the metadata (version, name, title, allow_restricted);
+/// its entries are filled in from the comparison and the filter's exclude
+/// patterns are recorded so the patcher never touches those paths.
+///
+/// Setting edits already in `manifest` (see [`read_edits`]) are kept. Their
+/// files must be the same in both versions and the edits must apply to them.
+///
+/// Components already in `manifest` (see [`read_components`]) list globs as
+/// their files; these are replaced by the files of the matching entries.
+///
+/// See [`CreateOptions`] for the other entries and payloads recorded.
+pub fn run_with_filter(
+    orig_dir: &Path,
+    new_dir: &Path,
+    output_dir: &Path,
+    mut manifest: Manifest,
+    filter: &FileFilter,
+    options: &CreateOptions,
+) -> io::Result<()> {
+    let changes = categorize_files(orig_dir, new_dir, filter)?;
+    let fallback = (!options.fallback.is_empty())
+        .then(|| FileFilter::new(&options.fallback, &[]))
+        .transpose()?;
+    let has_fallback = |file: &str| fallback.as_ref().is_some_and(|fallback| fallback.is_included(file));
+    let changed: HashSet<String> = changes.iter().map(|c| c.file().to_string()).collect();
+
+    let mut edited = HashSet::new();
+    for entry in &manifest.entries {
+        if changed.contains(entry.file()) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("'{}' is edited but also changed between the versions", entry.file()),
+            ));
+        }
+        check_edit(orig_dir, entry)?;
+        edited.insert(entry.file().to_string());
+    }
+    check_alt_origs(new_dir, &options.alt_origs, filter, |file| changed.contains(file) || edited.contains(file))?;
 
     // Create output directory structure
     fs::create_dir_all(output_dir)?;
@@ -34,7 +166,11 @@ pub fn run(
 
     // Only create subdirs if we need them
     let has_diffs = changes.iter().any(|c| matches!(c, FileChange::Diff { .. }));
-    let has_new = changes.iter().any(|c| matches!(c, FileChange::New { .. }));
+    let has_new = changes.iter().any(|c| match c {
+        FileChange::New { .. } => true,
+        FileChange::Diff { file, .. } => has_fallback(file),
+        FileChange::Old { .. } => false,
+    });
 
     if has_diffs {
         fs::create_dir_all(&diffs_dir)?;
@@ -43,8 +179,7 @@ pub fn run(
         fs::create_dir_all(&files_dir)?;
     }
 
-    let mut manifest = Manifest::new(version, name.to_string(), title.map(|s| s.to_string()));
-    manifest.allow_restricted = allow_restricted;
+    manifest.excluded = filter.exclude_patterns().to_vec();
 
     for change in changes {
         let entry = match change {
@@ -65,11 +200,42 @@ pub fn run(
                 // Compute diff hash
                 let diff_hash = hash_bytes(&diff_data);
 
+                // Diffs from the other releases of the original
+                let mut alternatives: Vec<PatchOrigin> = Vec::new();
+                for alt_dir in &options.alt_origs {
+                    let alt_path = alt_dir.join(&file);
+                    if !alt_path.is_file() {
+                        continue;
+                    }
+                    let alt_data = fs::read(&alt_path)?;
+                    let alt_hash = hash_bytes(&alt_data);
+                    if alt_hash == original_hash
+                        || alt_hash == final_hash
+                        || alternatives.iter().any(|o| o.original_hash == alt_hash)
+                    {
+                        continue;
+                    }
+                    let alt_diff = create_diff(&alt_data, &new_data)?;
+                    fs::write(diffs_dir.join(format!("{}.{}{}", file, alt_hash, DIFF_EXTENSION)), &alt_diff)?;
+                    alternatives.push(PatchOrigin {
+                        original_hash: alt_hash,
+                        diff_hash: hash_bytes(&alt_diff),
+                    });
+                }
+
+                // Keep the new file for targets that don't match the original
+                let fallback = has_fallback(&file);
+                if fallback {
+                    fs::copy(new_dir.join(&file), files_dir.join(&file))?;
+                }
+
                 ManifestEntry::Patch {
                     file,
                     original_hash,
                     diff_hash,
                     final_hash,
+                    alternatives,
+                    fallback,
                 }
             }
             FileChange::New { file, final_hash } => {
@@ -92,8 +258,24 @@ pub fn run(
         manifest.entries.push(entry);
     }
 
+    if !options.require.is_empty() {
+        let required = FileFilter::new(&options.require, &[])?;
+        for file in list_files(orig_dir)? {
+            if filter.is_included(&file)
+                && required.is_included(&file)
+                && !changed.contains(&file)
+                && !edited.contains(&file)
+                && new_dir.join(&file).is_file()
+            {
+                let hash = hash_bytes(&fs::read(orig_dir.join(&file))?);
+                manifest.entries.push(ManifestEntry::Require { file, hash });
+            }
+        }
+    }
+
     // Sort entries by filename for consistent output
     manifest.entries.sort_by(|a, b| a.file().cmp(b.file()));
+    expand_components(&mut manifest)?;
 
     // Write manifest
     let manifest_path = output_dir.join(MANIFEST_FILENAME);
@@ -107,6 +289,111 @@ pub fn run(
     Ok(())
 }
 
+/// Read setting edits from a JSON file holding an array of manifest entries.
+///
+/// Only `edit_json` and `edit_ini` entries are accepted.
+pub fn read_edits(path: &Path) -> io::Result<Vec<ManifestEntry>> {
+    let entries: Vec<ManifestEntry> = serde_json::from_slice(&fs::read(path)?)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), e)))?;
+    if let Some(entry) = entries.iter().find(|entry| !entry.is_edit()) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: '{}' is not a setting edit", path.display(), entry.file()),
+        ));
+    }
+    Ok(entries)
+}
+
+/// Read optional components from a JSON file holding an array of them.
+///
+/// Their `files` are globs, matched against the patch's entries when it is created.
+pub fn read_components(path: &Path) -> io::Result<Vec<Component>> {
+    serde_json::from_slice(&fs::read(path)?)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), e)))
+}
+
+/// Replace the file globs of each component by the files of the entries
+/// they match. A file can belong to only one component.
+fn expand_components(manifest: &mut Manifest) -> io::Result<()> {
+    let invalid = |id: &str, reason: String| {
+        io::Error::new(io::ErrorKind::InvalidInput, format!("component '{}' {}", id, reason))
+    };
+    let mut ids = HashSet::new();
+    let mut claimed = HashSet::new();
+    for component in &mut manifest.components {
+        if !ids.insert(component.id.clone()) {
+            return Err(invalid(&component.id, "is defined more than once".to_string()));
+        }
+        let patterns = FileFilter::new(&component.files, &[])?;
+        let mut files: Vec<String> = Vec::new();
+        for file in manifest.entries.iter().map(ManifestEntry::file) {
+            if patterns.is_included(file) && !files.iter().any(|f| f == file) {
+                if !claimed.insert(file.to_string()) {
+                    let reason = format!("matches '{}', which belongs to another component", file);
+                    return Err(invalid(&component.id, reason));
+                }
+                files.push(file.to_string());
+            }
+        }
+        if files.is_empty() {
+            return Err(invalid(&component.id, "matches no file of the patch".to_string()));
+        }
+        component.files = files;
+    }
+
+    // Every required component must exist
+    let ids: Vec<String> = ids.into_iter().collect();
+    resolve_components(&manifest.components, &ids)?;
+    Ok(())
+}
+
+/// Check that the other releases of the original have no file that differs
+/// from the new tree while getting no entry, as the patcher would leave it
+/// as is. `has_entry` tells which files of the new tree get one.
+fn check_alt_origs(
+    new_dir: &Path,
+    alt_origs: &[PathBuf],
+    filter: &FileFilter,
+    has_entry: impl Fn(&str) -> bool,
+) -> io::Result<()> {
+    if alt_origs.is_empty() {
+        return Ok(());
+    }
+    for file in list_files(new_dir)? {
+        if !filter.is_included(&file) || has_entry(&file) {
+            continue;
+        }
+        for alt_dir in alt_origs {
+            let alt_path = alt_dir.join(&file);
+            if alt_path.is_file() && fs::read(&alt_path)? != fs::read(new_dir.join(&file))? {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!(
+                        "'{}' differs in {} but is unchanged in the main original, so the patch wouldn't update it",
+                        file,
+                        alt_dir.display()
+                    ),
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Check that an edit applies to its file in the original tree.
+fn check_edit(orig_dir: &Path, entry: &ManifestEntry) -> io::Result<()> {
+    let data = fs::read(orig_dir.join(entry.file()))
+        .map_err(|e| io::Error::new(e.kind(), format!("edited file '{}': {}", entry.file(), e)))?;
+    let result = match entry {
+        ManifestEntry::EditJson { pointer, value, .. } => set_json(&data, pointer, value),
+        ManifestEntry::EditIni { section, key, value, .. } => set_ini(&data, section, key, value),
+        _ => Ok(data),
+    };
+    result
+        .map(|_| ())
+        .map_err(|e| io::Error::new(e.kind(), format!("edited file '{}': {}", entry.file(), e)))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -257,6 +544,140 @@ mod tests {
         assert!(manifest.entries.is_empty());
     }
 
+    #[test]
+    fn excludes_filtered_files_and_records_patterns() {
+        let orig_dir = tempdir().unwrap();
+        let new_dir = tempdir().unwrap();
+        let output_dir = tempdir().unwrap();
+
+        fs::write(orig_dir.path().join("game.bin"), b"old").unwrap();
+        fs::write(new_dir.path().join("game.bin"), b"new").unwrap();
+        fs::write(orig_dir.path().join("slot1.sav"), b"progress").unwrap();
+        fs::write(new_dir.path().join("debug.log"), b"noise").unwrap();
+        fs::write(new_dir.path().join(".graftignore"), "*.log\n").unwrap();
+
+        let filter = FileFilter::with_ignore_file(new_dir.path(), &[], &["*.sav".to_string()]).unwrap();
+        let manifest = Manifest::new(1, "TestPatcher".to_string(), None);
+        run_with_filter(orig_dir.path(), new_dir.path(), output_dir.path(), manifest, &filter, &CreateOptions::default())
+            .unwrap();
+
+        let manifest = Manifest::load(&output_dir.path().join("manifest.json")).unwrap();
+        assert_eq!(manifest.entries.len(), 1);
+        assert_eq!(manifest.entries[0].file(), "game.bin");
+        assert_eq!(manifest.excluded, vec!["*.sav", "*.log"]);
+    }
+
+    #[test]
+    fn records_required_unchanged_files() {
+        let orig_dir = tempdir().unwrap();
+        let new_dir = tempdir().unwrap();
+        let output_dir = tempdir().unwrap();
+
+        fs::write(orig_dir.path().join("data.bin"), b"old").unwrap();
+        fs::write(new_dir.path().join("data.bin"), b"new").unwrap();
+        for dir in [&orig_dir, &new_dir] {
+            fs::write(dir.path().join("game.exe"), b"binary").unwrap();
+            fs::write(dir.path().join("readme.txt"), b"hello").unwrap();
+        }
+
+        let manifest = Manifest::new(1, "TestPatcher".to_string(), None);
+        let options = CreateOptions {
+            require: vec!["*.exe".to_string(), "data.bin".to_string()],
+            ..Default::default()
+        };
+        run_with_filter(orig_dir.path(), new_dir.path(), output_dir.path(), manifest, &FileFilter::default(), &options)
+            .unwrap();
+
+        let manifest = Manifest::load(&output_dir.path().join("manifest.json")).unwrap();
+        assert_eq!(manifest.entries.len(), 2);
+        assert!(matches!(&manifest.entries[0], ManifestEntry::Patch { file, .. } if file == "data.bin"));
+        assert_eq!(
+            manifest.entries[1],
+            ManifestEntry::Require {
+                file: "game.exe".to_string(),
+                hash: hash_bytes(b"binary"),
+            }
+        );
+    }
+
+    #[test]
+    fn keeps_setting_edits_of_unchanged_files() {
+        let orig_dir = tempdir().unwrap();
+        let new_dir = tempdir().unwrap();
+        let output_dir = tempdir().unwrap();
+        for dir in [&orig_dir, &new_dir] {
+            fs::write(dir.path().join("game.ini"), b"[Video]\nVSync=0\n").unwrap();
+        }
+        fs::write(orig_dir.path().join("data.bin"), b"old").unwrap();
+        fs::write(new_dir.path().join("data.bin"), b"new").unwrap();
+        let edits_path = output_dir.path().join("edits.json");
+        fs::write(
+            &edits_path,
+            r#"[{"operation": "edit_ini", "file": "game.ini", "section": "Video", "key": "VSync", "value": "1"}]"#,
+        )
+        .unwrap();
+
+        let mut manifest = Manifest::new(1, "TestPatcher".to_string(), None);
+        manifest.entries = read_edits(&edits_path).unwrap();
+        let options = CreateOptions {
+            require: vec!["*.ini".to_string()],
+            ..Default::default()
+        };
+        run_with_filter(orig_dir.path(), new_dir.path(), output_dir.path(), manifest.clone(), &FileFilter::default(), &options)
+            .unwrap();
+
+        let saved = Manifest::load(&output_dir.path().join("manifest.json")).unwrap();
+        assert_eq!(saved.entries.len(), 2);
+        assert!(matches!(&saved.entries[0], ManifestEntry::Patch { file, .. } if file == "data.bin"));
+        assert_eq!(saved.entries[1], manifest.entries[0]);
+
+        // An edited file can't also change between the versions
+        fs::write(new_dir.path().join("game.ini"), b"[Video]\nVSync=2\n").unwrap();
+        let result = run_with_filter(orig_dir.path(), new_dir.path(), output_dir.path(), manifest, &FileFilter::default(), &options);
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+
+        fs::write(&edits_path, r#"[{"operation": "delete", "file": "a.bin", "original_hash": "x"}]"#).unwrap();
+        assert!(read_edits(&edits_path).is_err());
+    }
+
+    #[test]
+    fn bidirectional_reverse_patch_restores_edited_values() {
+        let orig_dir = tempdir().unwrap();
+        let new_dir = tempdir().unwrap();
+        let patches = tempdir().unwrap();
+        let output_dir = patches.path().join("patch");
+        for dir in [&orig_dir, &new_dir] {
+            fs::write(dir.path().join("game.ini"), b"[Video]\nVSync=0\n").unwrap();
+        }
+        fs::write(orig_dir.path().join("data.bin"), b"old").unwrap();
+        fs::write(new_dir.path().join("data.bin"), b"new").unwrap();
+        let edits_path = patches.path().join("edits.json");
+        fs::write(
+            &edits_path,
+            r#"[{"operation": "edit_ini", "file": "game.ini", "section": "Video", "key": "VSync", "value": "1"}]"#,
+        )
+        .unwrap();
+
+        let inputs = CreateInputs {
+            edits: Some(edits_path),
+            bidirectional: true,
+            ..Default::default()
+        };
+        let manifest = Manifest::new(1, "TestPatcher".to_string(), None);
+        let reverse_dir =
+            run_with_inputs(orig_dir.path(), new_dir.path(), &output_dir, manifest, &inputs, &CreateOptions::default())
+                .unwrap()
+                .unwrap();
+        assert_eq!(reverse_dir, patches.path().join("patch-reverse"));
+
+        let forward = Manifest::load(&output_dir.join(MANIFEST_FILENAME)).unwrap();
+        let reverse = Manifest::load(&reverse_dir.join(MANIFEST_FILENAME)).unwrap();
+        assert!(forward.entries.iter().any(|e| matches!(e, ManifestEntry::EditIni { value, .. } if value == "1")));
+        assert!(reverse.entries.iter().any(|e| matches!(e, ManifestEntry::EditIni { value, .. } if value == "0")));
+        assert!(matches!(&reverse.entries[0], ManifestEntry::Patch { file, .. } if file == "data.bin"));
+        assert_eq!(reverse.name, "TestPatcher-reverse");
+    }
+
     #[test]
     fn skips_unnecessary_subdirs() {
         let orig_dir = tempdir().unwrap();
@@ -0,0 +1,471 @@
+//! Inspect patch directories and built patchers without running them.
+//!
+//! Accepts a patch directory, a self-appending patcher executable (reads the
+//! `GRAFTPCH` trailer) or a macOS `.app` bundle (reads
+//! `Contents/Resources/patch.data`).
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Component, Path, PathBuf};
+
+use graft_core::archive::{self, ArchiveCodec, ContainerFormat, MAGIC_MARKER};
+use graft_core::patch::source::{diff_payload_path, file_payload_path};
+use graft_core::patch::{PatchError, PatchSource, ASSETS_DIR, ICON_FILENAME, MANIFEST_FILENAME};
+use graft_core::utils::file_ops;
+use graft_core::utils::manifest::{Manifest, ManifestEntry, PatchInfo};
+
+use crate::targets::{self, Target};
+
+/// Number of leading executable bytes used to detect the stub target.
+const HEADER_PROBE_LEN: u64 = 4096;
+
+/// Errors from patch inspection.
+#[derive(Debug)]
+pub enum InspectError {
+    /// The path is not a patch directory, patcher or bundle with patch data.
+    NoPatchData(PathBuf),
+    /// The appended trailer is corrupt.
+    InvalidTrailer,
+    /// The patch data could not be read.
+    Patch(PatchError),
+    /// A payload path would escape the extraction directory.
+    UnsafePath(String),
+    /// An I/O error occurred.
+    Io(io::Error),
+}
+
+impl std::fmt::Display for InspectError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InspectError::NoPatchData(path) => {
+                write!(f, "no patch data found in {}", path.display())
+            }
+            InspectError::InvalidTrailer => write!(f, "invalid patch data trailer"),
+            InspectError::Patch(e) => write!(f, "{}", e),
+            InspectError::UnsafePath(path) => write!(f, "unsafe payload path: {}", path),
+            InspectError::Io(e) => write!(f, "I/O error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for InspectError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            InspectError::Patch(e) => Some(e),
+            InspectError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for InspectError {
+    fn from(e: io::Error) -> Self {
+        InspectError::Io(e)
+    }
+}
+
+/// What kind of input was inspected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatchKind {
+    Directory,
+    Executable,
+    Bundle,
+}
+
+impl PatchKind {
+    fn describe(self) -> &'static str {
+        match self {
+            PatchKind::Directory => "patch directory",
+            PatchKind::Executable => "patcher executable",
+            PatchKind::Bundle => "macOS app bundle",
+        }
+    }
+}
+
+/// Packed patch data found in a patcher.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContainerSummary {
+    pub format: ContainerFormat,
+    /// Codec of a tar container
+    pub codec: Option<ArchiveCodec>,
+    pub packed_size: u64,
+}
+
+/// Result of inspecting a patch.
+pub struct Inspection {
+    pub kind: PatchKind,
+    /// Stub target detected from the executable header
+    pub target: Option<Target>,
+    /// Container details (`None` for patch directories)
+    pub container: Option<ContainerSummary>,
+    pub manifest: Manifest,
+    pub has_icon: bool,
+    /// Size of every payload, by payload path
+    pub payload_sizes: HashMap<String, u64>,
+    source: Box<dyn PatchSource>,
+}
+
+impl Inspection {
+    /// Total size of all payloads.
+    pub fn unpacked_size(&self) -> u64 {
+        self.payload_sizes.values().sum()
+    }
+}
+
+/// Inspect a patch directory, patcher executable or `.app` bundle.
+pub fn inspect(path: &Path) -> Result<Inspection, InspectError> {
+    if path.is_dir() && path.join(MANIFEST_FILENAME).is_file() {
+        return build_inspection(PatchKind::Directory, None, None, Box::new(path.to_path_buf()));
+    }
+
+    if path.is_dir() {
+        let contents_dir = path.join("Contents");
+        let data_path = contents_dir.join("Resources").join("patch.data");
+        if !data_path.is_file() {
+            return Err(InspectError::NoPatchData(path.to_path_buf()));
+        }
+
+        let target = match bundle_executable(&contents_dir.join("MacOS"))? {
+            Some(exe) => targets::detect_target(&read_header(&exe)?),
+            None => None,
+        };
+        let data = fs::read(&data_path)?;
+        return open_container(PatchKind::Bundle, target, data);
+    }
+
+    let data = read_appended(path)?.ok_or_else(|| InspectError::NoPatchData(path.to_path_buf()))?;
+    let target = targets::detect_target(&read_header(path)?);
+    open_container(PatchKind::Executable, target, data)
+}
+
+/// Inspect a patch and print a report, optionally extracting its payloads.
+pub fn run(path: &Path, extract_dir: Option<&Path>) -> Result<(), InspectError> {
+    let inspection = inspect(path)?;
+    let info = PatchInfo::from_manifest(&inspection.manifest);
+
+    println!("Patch: {} v{}", info.name, info.version);
+    if let Some(title) = &info.title {
+        println!("Title: {}", title);
+    }
+    match inspection.target {
+        Some(target) => println!("Source: {} ({})", inspection.kind.describe(), target),
+        None if inspection.kind == PatchKind::Directory => {
+            println!("Source: {}", inspection.kind.describe())
+        }
+        None => println!("Source: {} (unknown target)", inspection.kind.describe()),
+    }
+
+    let unpacked = inspection.unpacked_size();
+    match &inspection.container {
+        Some(container) => {
+            let format = match container.codec {
+                Some(codec) => format!("{}, {}", container.format.name(), codec.name()),
+                None => container.format.name().to_string(),
+            };
+            println!("Container: {}, {} bytes", format, container.packed_size);
+            println!(
+                "Payload: {} bytes unpacked, compression ratio {:.1}%",
+                unpacked,
+                container.packed_size as f64 * 100.0 / unpacked.max(1) as f64
+            );
+        }
+        None => println!("Payload: {} bytes", unpacked),
+    }
+    println!("Icon: {}", if inspection.has_icon { "yes" } else { "no" });
+    println!(
+        "Operations: {} ({} patches, {} additions, {} deletions, {} requirements, {} edits)",
+        info.entry_count, info.patches, info.additions, info.deletions, info.requirements, info.edits
+    );
+
+    println!("\nEntries:");
+    for entry in &inspection.manifest.entries {
+        let (operation, payload) = match entry {
+            ManifestEntry::Patch { file, .. } => ("patch", Some(diff_payload_path(file))),
+            ManifestEntry::Add { file, .. } => ("add", Some(file_payload_path(file))),
+            ManifestEntry::Delete { .. } => ("delete", None),
+            ManifestEntry::Require { .. } => ("require", None),
+            ManifestEntry::EditJson { .. } | ManifestEntry::EditIni { .. } => ("edit", None),
+        };
+        if let Some(setting) = entry.setting() {
+            println!("  {:<7} {}  ({})", operation, entry.file(), setting);
+            continue;
+        }
+        let size_of = |payload: Option<String>| {
+            payload
+                .and_then(|p| inspection.payload_sizes.get(&p).copied())
+                .map(|size| format!("{} bytes", size))
+                .unwrap_or_else(|| "-".to_string())
+        };
+        let mut size = size_of(payload);
+        if let ManifestEntry::Patch { alternatives, .. } = entry
+            && !alternatives.is_empty()
+        {
+            size = format!("{}, {} more originals", size, alternatives.len());
+        }
+        if let ManifestEntry::Patch { file, fallback: true, .. } = entry {
+            size = format!("{}, fallback {}", size, size_of(Some(file_payload_path(file))));
+        }
+        println!("  {:<7} {}  ({})", operation, entry.file(), size);
+    }
+
+    if !info.components.is_empty() {
+        println!("\nComponents:");
+        for component in &info.components {
+            let mut details = vec![format!("{} files", component.files.len())];
+            if component.default {
+                details.push("default".to_string());
+            }
+            if !component.requires.is_empty() {
+                details.push(format!("requires {}", component.requires.join(", ")));
+            }
+            println!("  {:<12} {}  ({})", component.id, component.name, details.join(", "));
+        }
+    }
+
+    if !inspection.manifest.post_apply.is_empty() {
+        println!("\nAfter applying:");
+        for action in &inspection.manifest.post_apply {
+            println!("  {}", action);
+        }
+    }
+
+    if let Some(dir) = extract_dir {
+        let count = extract(&*inspection.source, dir)?;
+        println!("\nExtracted {} files to {}", count, dir.display());
+    }
+
+    Ok(())
+}
+
+/// Write every payload of `source` below `output_dir`, recreating the patch directory.
+///
+/// Returns the number of files written.
+pub fn extract<S: PatchSource + ?Sized>(source: &S, output_dir: &Path) -> Result<usize, InspectError> {
+    let paths = source.payload_paths()?;
+
+    for payload in &paths {
+        let relative = Path::new(payload);
+        if !relative.components().all(|c| matches!(c, Component::Normal(_))) {
+            return Err(InspectError::UnsafePath(payload.clone()));
+        }
+
+        let dest = output_dir.join(relative);
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&dest, source.read(payload)?)?;
+        if source.is_executable(payload) {
+            file_ops::set_executable(&dest)?;
+        }
+    }
+
+    Ok(paths.len())
+}
+
+/// Collect manifest, payload sizes and icon presence from a source.
+fn build_inspection(
+    kind: PatchKind,
+    target: Option<Target>,
+    container: Option<ContainerSummary>,
+    source: Box<dyn PatchSource>,
+) -> Result<Inspection, InspectError> {
+    let manifest = source.read_manifest().map_err(InspectError::Patch)?;
+
+    let mut payload_sizes = HashMap::new();
+    for path in source.payload_paths()? {
+        let size = source.size(&path)?;
+        payload_sizes.insert(path, size);
+    }
+
+    let icon_path = format!("{}/{}", ASSETS_DIR, ICON_FILENAME);
+    let has_icon = source.contains(&icon_path);
+
+    Ok(Inspection {
+        kind,
+        target,
+        container,
+        manifest,
+        has_icon,
+        payload_sizes,
+        source,
+    })
+}
+
+/// Open packed patch data and inspect it.
+fn open_container(
+    kind: PatchKind,
+    target: Option<Target>,
+    data: Vec<u8>,
+) -> Result<Inspection, InspectError> {
+    let format = ContainerFormat::detect(&data);
+    let codec = match format {
+        ContainerFormat::Tar => Some(archive::split_codec(&data)?.0),
+        ContainerFormat::Indexed => None,
+    };
+    let container = ContainerSummary {
+        format,
+        codec,
+        packed_size: data.len() as u64,
+    };
+
+    let source = archive::open_bytes(data.into())?;
+    build_inspection(kind, target, Some(container), source)
+}
+
+/// Read the patch data appended to an executable, if any.
+fn read_appended(path: &Path) -> Result<Option<Vec<u8>>, InspectError> {
+    let mut file = File::open(path)?;
+    let file_len = file.metadata()?.len();
+    if file_len < 16 {
+        return Ok(None);
+    }
+
+    file.seek(SeekFrom::End(-16))?;
+    let mut trailer = [0u8; 16];
+    file.read_exact(&mut trailer)?;
+    if &trailer[8..] != MAGIC_MARKER {
+        return Ok(None);
+    }
+
+    let mut size_bytes = [0u8; 8];
+    size_bytes.copy_from_slice(&trailer[..8]);
+    let size = u64::from_le_bytes(size_bytes);
+    if size == 0 || size > file_len - 16 {
+        return Err(InspectError::InvalidTrailer);
+    }
+
+    file.seek(SeekFrom::Start(file_len - 16 - size))?;
+    let mut data = vec![0u8; size as usize];
+    file.read_exact(&mut data)?;
+    Ok(Some(data))
+}
+
+/// Read the leading bytes of an executable.
+fn read_header(path: &Path) -> io::Result<Vec<u8>> {
+    let mut header = Vec::new();
+    File::open(path)?.take(HEADER_PROBE_LEN).read_to_end(&mut header)?;
+    Ok(header)
+}
+
+/// Find the executable inside a bundle's `Contents/MacOS` directory.
+fn bundle_executable(macos_dir: &Path) -> io::Result<Option<PathBuf>> {
+    if !macos_dir.is_dir() {
+        return Ok(None);
+    }
+    let mut files: Vec<_> = fs::read_dir(macos_dir)?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.is_file())
+        .collect();
+    files.sort();
+    Ok(files.into_iter().next())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use graft_core::archive::ArchiveOptions;
+    use graft_core::test_support::create_patch_dir;
+    use tempfile::tempdir;
+
+    fn create_patcher(dir: &Path, options: &ArchiveOptions) -> PathBuf {
+        let patch_dir = dir.join("patch");
+        fs::create_dir_all(&patch_dir).unwrap();
+        create_patch_dir(&patch_dir);
+
+        let archive_data = archive::create_container_bytes(&patch_dir, options).unwrap();
+        let mut exe = fs::read(std::env::current_exe().unwrap()).unwrap();
+        exe.extend_from_slice(&archive_data);
+        exe.extend_from_slice(&(archive_data.len() as u64).to_le_bytes());
+        exe.extend_from_slice(MAGIC_MARKER);
+
+        let exe_path = dir.join("TestPatcher");
+        fs::write(&exe_path, exe).unwrap();
+        exe_path
+    }
+
+    #[test]
+    fn inspects_patch_directory() {
+        let dir = tempdir().unwrap();
+        create_patch_dir(dir.path());
+
+        let inspection = inspect(dir.path()).unwrap();
+        assert_eq!(inspection.kind, PatchKind::Directory);
+        assert!(inspection.container.is_none());
+        assert!(inspection.has_icon);
+        assert_eq!(inspection.manifest.entries.len(), 3);
+        assert_eq!(inspection.payload_sizes["diffs/game.bin.diff"], 300);
+    }
+
+    #[test]
+    fn inspects_patcher_executable() {
+        let dir = tempdir().unwrap();
+        let options = ArchiveOptions {
+            container: ContainerFormat::Tar,
+            codec: ArchiveCodec::Zstd,
+            ..Default::default()
+        };
+        let exe_path = create_patcher(dir.path(), &options);
+
+        let inspection = inspect(&exe_path).unwrap();
+        assert_eq!(inspection.kind, PatchKind::Executable);
+        assert_eq!(inspection.target, targets::current_target());
+        let container = inspection.container.unwrap();
+        assert_eq!(container.format, ContainerFormat::Tar);
+        assert_eq!(container.codec, Some(ArchiveCodec::Zstd));
+        assert!(container.packed_size < inspection.unpacked_size());
+        assert_eq!(inspection.manifest.name, "TestPatcher");
+    }
+
+    #[test]
+    fn inspects_bundle() {
+        let dir = tempdir().unwrap();
+        let patch_dir = dir.path().join("patch");
+        fs::create_dir_all(&patch_dir).unwrap();
+        create_patch_dir(&patch_dir);
+
+        let bundle = dir.path().join("TestPatcher.app");
+        let resources = bundle.join("Contents/Resources");
+        fs::create_dir_all(&resources).unwrap();
+        let options = ArchiveOptions {
+            container: ContainerFormat::Indexed,
+            ..Default::default()
+        };
+        let data = archive::create_container_bytes(&patch_dir, &options).unwrap();
+        fs::write(resources.join("patch.data"), data).unwrap();
+
+        let inspection = inspect(&bundle).unwrap();
+        assert_eq!(inspection.kind, PatchKind::Bundle);
+        assert_eq!(inspection.container.unwrap().format, ContainerFormat::Indexed);
+        assert!(inspection.has_icon);
+    }
+
+    #[test]
+    fn extract_recovers_patch_directory() {
+        let dir = tempdir().unwrap();
+        let exe_path = create_patcher(dir.path(), &ArchiveOptions::default());
+        let out = dir.path().join("extracted");
+
+        run(&exe_path, Some(&out)).unwrap();
+
+        let original = dir.path().join("patch");
+        for payload in original.payload_paths().unwrap() {
+            assert_eq!(
+                fs::read(out.join(&payload)).unwrap(),
+                fs::read(original.join(&payload)).unwrap(),
+                "{} differs",
+                payload
+            );
+        }
+    }
+
+    #[test]
+    fn plain_file_has_no_patch_data() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("plain.bin");
+        fs::write(&path, b"just some bytes without a trailer").unwrap();
+
+        assert!(matches!(inspect(&path), Err(InspectError::NoPatchData(_))));
+    }
+}
@@ -0,0 +1,278 @@
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use graft_core::patch::{
+    audit_patch, PatchError, PatchSource, ASSETS_DIR, DIFFS_DIR, DIFF_EXTENSION, FILES_DIR,
+    MANIFEST_FILENAME,
+};
+use graft_core::utils::diff::{apply_diff, create_diff};
+use graft_core::utils::edit::{ini_value, json_value};
+use graft_core::utils::file_ops::copy_dir;
+use graft_core::utils::hash::hash_bytes;
+use graft_core::utils::manifest::{Manifest, ManifestEntry};
+
+use crate::commands::common::{check_hash, read_original, write_error, write_payload};
+
+/// Generate the inverse of a patch (v2→v1) from the patch and the v1 tree.
+///
+/// Diffs are replayed against the original files and recomputed in the
+/// opposite direction, added files become deletions, and deleted files are
+/// captured from the original tree as additions. A file patched from several
+/// originals is restored to the one in the original tree. A fallback of a
+/// modified file is replaced by the original file. Required files are the same
+/// in both versions and are kept as they are. Edits set the value the original
+/// tree has, so that value must exist. The result keeps the metadata of the
+/// forward patch, except for its name (see [`reverse_name`]), and can be
+/// built with `graft build`.
+pub fn run(patch_dir: &Path, original_dir: &Path, output_dir: &Path) -> Result<(), PatchError> {
+    let forward = patch_dir.read_manifest()?;
+
+    let mut manifest = Manifest::new(forward.version, reverse_name(&forward.name), forward.title.clone());
+    manifest.allow_restricted = forward.allow_restricted;
+    manifest.excluded = forward.excluded.clone();
+    manifest.backup_dir = forward.backup_dir.clone();
+    manifest.backup = forward.backup;
+    manifest.post_apply = forward.post_apply.clone();
+    manifest.components = forward.components.clone();
+    manifest.discovery = forward.discovery.clone();
+
+    fs::create_dir_all(output_dir).map_err(|e| write_error(&output_dir.display().to_string(), e))?;
+
+    for entry in &forward.entries {
+        manifest.entries.push(reverse_entry(entry, patch_dir, original_dir, output_dir)?);
+    }
+    manifest.entries.sort_by(|a, b| a.file().cmp(b.file()));
+
+    manifest
+        .save(&output_dir.join(MANIFEST_FILENAME))
+        .map_err(|e| write_error(MANIFEST_FILENAME, e))?;
+
+    let assets = patch_dir.join(ASSETS_DIR);
+    if assets.is_dir() {
+        copy_dir(&assets, &output_dir.join(ASSETS_DIR)).map_err(|e| write_error(ASSETS_DIR, e))?;
+    }
+
+    // Sanity check the result
+    let report = audit_patch(output_dir, None)?;
+    if let Some(issue) = report.issues.first() {
+        return Err(PatchError::ManifestError {
+            reason: format!("reverse patch is inconsistent: {}", issue),
+        });
+    }
+
+    Ok(())
+}
+
+/// Name of the reverse of the patch named `name`.
+///
+/// Restore points are identified by name and version, so the reverse patch
+/// needs a name of its own to be applied on top of the forward patch without
+/// reusing its backups.
+pub fn reverse_name(name: &str) -> String {
+    format!("{}-reverse", name)
+}
+
+/// Directory used for the reverse patch when creating both directions at once.
+///
+/// This is a sibling of `output` with `-reverse` appended to its name.
+pub fn reverse_output_dir(output: &Path) -> PathBuf {
+    let mut name = output.file_name().map(OsString::from).unwrap_or_else(|| OsString::from("patch"));
+    name.push("-reverse");
+    output.with_file_name(name)
+}
+
+/// Invert a single entry, writing its payload into the output directory.
+fn reverse_entry(
+    entry: &ManifestEntry,
+    patch_dir: &Path,
+    original_dir: &Path,
+    output_dir: &Path,
+) -> Result<ManifestEntry, PatchError> {
+    match entry {
+        ManifestEntry::Patch {
+            file,
+            original_hash,
+            final_hash,
+            fallback,
+            ..
+        } => {
+            let original = fs::read(original_dir.join(file)).map_err(|_| PatchError::FileNotFound(file.clone()))?;
+            let actual_hash = hash_bytes(&original);
+            let diff = match entry.patch_origins().iter().position(|(h, _)| *h == actual_hash) {
+                Some(0) => patch_dir.read_diff(file),
+                Some(_) => patch_dir.read_alternative_diff(file, &actual_hash),
+                None => {
+                    return Err(PatchError::VerificationFailed {
+                        file: format!("{} (original)", file),
+                        expected: original_hash.clone(),
+                        actual: actual_hash,
+                    });
+                }
+            }
+            .map_err(|_| PatchError::DiffNotFound(file.clone()))?;
+            let patched = apply_diff(&original, &diff).map_err(|e| apply_error(file, e))?;
+            check_hash(file, &patched, final_hash, "patched")?;
+
+            let reverse = create_diff(&patched, &original).map_err(|e| apply_error(file, e))?;
+            write_payload(
+                &output_dir.join(DIFFS_DIR).join(format!("{}{}", file, DIFF_EXTENSION)),
+                file,
+                &reverse,
+            )?;
+            if *fallback {
+                write_payload(&output_dir.join(FILES_DIR).join(file), file, &original)?;
+            }
+            Ok(ManifestEntry::Patch {
+                file: file.clone(),
+                original_hash: final_hash.clone(),
+                diff_hash: hash_bytes(&reverse),
+                final_hash: actual_hash,
+                alternatives: Vec::new(),
+                fallback: *fallback,
+            })
+        }
+        ManifestEntry::Add { file, final_hash } => Ok(ManifestEntry::Delete {
+            file: file.clone(),
+            original_hash: final_hash.clone(),
+        }),
+        ManifestEntry::Delete { file, original_hash } => {
+            let original = read_original(original_dir, file, original_hash)?;
+            write_payload(&output_dir.join(FILES_DIR).join(file), file, &original)?;
+            Ok(ManifestEntry::Add {
+                file: file.clone(),
+                final_hash: original_hash.clone(),
+            })
+        }
+        ManifestEntry::Require { .. } => Ok(entry.clone()),
+        ManifestEntry::EditJson { .. } | ManifestEntry::EditIni { .. } => reverse_edit(entry, original_dir),
+    }
+}
+
+/// Invert a setting edit by setting the value found in the original tree.
+pub fn reverse_edit(entry: &ManifestEntry, original_dir: &Path) -> Result<ManifestEntry, PatchError> {
+    match entry {
+        ManifestEntry::EditJson { file, pointer, .. } => {
+            let original = fs::read(original_dir.join(file)).map_err(|_| PatchError::FileNotFound(file.clone()))?;
+            let value = json_value(&original, pointer).map_err(|e| apply_error(file, e))?;
+            Ok(ManifestEntry::EditJson {
+                file: file.clone(),
+                pointer: pointer.clone(),
+                value: value.ok_or_else(|| unset_error(file, pointer))?,
+            })
+        }
+        ManifestEntry::EditIni { file, section, key, .. } => {
+            let original = fs::read(original_dir.join(file)).map_err(|_| PatchError::FileNotFound(file.clone()))?;
+            let value = ini_value(&original, section, key).map_err(|e| apply_error(file, e))?;
+            Ok(ManifestEntry::EditIni {
+                file: file.clone(),
+                section: section.clone(),
+                key: key.clone(),
+                value: value.ok_or_else(|| unset_error(file, &entry.setting().unwrap_or_default()))?,
+            })
+        }
+        _ => Ok(entry.clone()),
+    }
+}
+
+/// An edited setting has no value in the original tree, so there is nothing to set it back to.
+fn unset_error(file: &str, setting: &str) -> PatchError {
+    apply_error(file, format!("{} is not set in the original, so the edit cannot be reversed", setting))
+}
+
+/// Read a file from the original tree and check it matches the manifest.
+fn apply_error(file: &str, e: impl std::fmt::Display) -> PatchError {
+    PatchError::ApplyFailed {
+        file: file.to_string(),
+        reason: e.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::common::tree;
+    use crate::commands::{patch_apply, patch_create, patch_rollback};
+    use graft_core::utils::manifest::Discovery;
+    use tempfile::tempdir;
+
+    fn read_tree(dir: &Path) -> Vec<(String, Vec<u8>)> {
+        let mut files: Vec<_> = fs::read_dir(dir)
+            .unwrap()
+            .map(|e| e.unwrap())
+            .filter(|e| e.file_type().unwrap().is_file())
+            .map(|e| (e.file_name().to_string_lossy().into_owned(), fs::read(e.path()).unwrap()))
+            .collect();
+        files.sort();
+        files
+    }
+
+    #[test]
+    fn reverse_patch_restores_original() {
+        let v1: &[(&str, &[u8])] = &[("game.bin", b"version one"), ("old.bin", b"removed in v2")];
+        let v2: &[(&str, &[u8])] = &[("game.bin", b"version two"), ("new.bin", b"added in v2")];
+        let (v1_dir, v2_dir) = (tree(v1), tree(v2));
+        let patches = tempdir().unwrap();
+        let forward = patches.path().join("forward");
+        let reverse = patches.path().join("reverse");
+        patch_create::run(v1_dir.path(), v2_dir.path(), &forward, 2, "TestPatcher", None, false).unwrap();
+
+        run(&forward, v1_dir.path(), &reverse).unwrap();
+
+        let target = tree(v1);
+        patch_apply::run(target.path(), &forward, None, Default::default()).unwrap();
+        assert_eq!(read_tree(target.path()), read_tree(v2_dir.path()));
+
+        // The reverse patch has its own restore point on top of the forward one
+        patch_apply::run(target.path(), &reverse, None, Default::default()).unwrap();
+        assert_eq!(read_tree(target.path()), read_tree(v1_dir.path()));
+        assert_eq!(reverse.read_manifest().unwrap().name, "TestPatcher-reverse");
+
+        patch_rollback::run(target.path(), None, false, None).unwrap();
+        assert_eq!(read_tree(target.path()), read_tree(v2_dir.path()));
+        patch_rollback::run(target.path(), None, false, None).unwrap();
+        assert_eq!(read_tree(target.path()), read_tree(v1_dir.path()));
+    }
+
+    #[test]
+    fn swaps_add_and_delete() {
+        let (v1, v2) = (tree(&[("a.bin", b"a")]), tree(&[("b.bin", b"b")]));
+        let patches = tempdir().unwrap();
+        let (forward, reverse) = (patches.path().join("forward"), patches.path().join("reverse"));
+        patch_create::run(v1.path(), v2.path(), &forward, 1, "TestPatcher", None, false).unwrap();
+        let mut manifest = forward.read_manifest().unwrap();
+        manifest.discovery = Some(Discovery {
+            paths: vec!["~/Game".to_string()],
+            markers: vec!["a.bin".to_string()],
+        });
+        manifest.save(&forward.join(MANIFEST_FILENAME)).unwrap();
+
+        run(&forward, v1.path(), &reverse).unwrap();
+
+        let manifest = reverse.read_manifest().unwrap();
+        assert_eq!(manifest.discovery, forward.read_manifest().unwrap().discovery);
+        assert!(matches!(&manifest.entries[0], ManifestEntry::Add { file, .. } if file == "a.bin"));
+        assert!(matches!(&manifest.entries[1], ManifestEntry::Delete { file, .. } if file == "b.bin"));
+        assert_eq!(fs::read(reverse.join(FILES_DIR).join("a.bin")).unwrap(), b"a");
+    }
+
+    #[test]
+    fn rejects_mismatched_original() {
+        let (v1, v2) = (tree(&[("a.bin", b"one")]), tree(&[("a.bin", b"two")]));
+        let other = tree(&[("a.bin", b"something else")]);
+        let patches = tempdir().unwrap();
+        let forward = patches.path().join("forward");
+        patch_create::run(v1.path(), v2.path(), &forward, 1, "TestPatcher", None, false).unwrap();
+
+        let result = run(&forward, other.path(), &patches.path().join("reverse"));
+        assert!(matches!(result, Err(PatchError::VerificationFailed { .. })));
+    }
+
+    #[test]
+    fn reverse_output_dir_is_sibling() {
+        assert_eq!(
+            reverse_output_dir(Path::new("out/patch")),
+            PathBuf::from("out/patch-reverse")
+        );
+    }
+}
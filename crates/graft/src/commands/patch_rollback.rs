@@ -1,10 +1,11 @@
 use std::path::Path;
 
 use graft_core::patch::{
-    rollback, validate_backup, validate_patched_entries, PatchError, Progress, ProgressAction,
-    BACKUP_DIR,
+    applied_manifest, delete_backup_set, forget_component, forget_restore_point, latest_backup_manifest,
+    restore_point_id, rollback, rollback_set_dir, set_backup_root, validate_backup, validate_patched_entries,
+    PatchError, Progress, ProgressAction,
 };
-use graft_core::utils::manifest::Manifest;
+use graft_core::utils::manifest::{Manifest, ManifestEntry};
 
 fn format_action(action: ProgressAction) -> &'static str {
     match action {
@@ -15,25 +16,105 @@ fn format_action(action: ProgressAction) -> &'static str {
         ProgressAction::Patching => "Patching",
         ProgressAction::Adding => "Adding",
         ProgressAction::Deleting => "Deleting",
+        ProgressAction::Editing => "Editing",
         ProgressAction::Restoring => "Restoring",
         ProgressAction::Removing => "Removing",
     }
 }
 
-/// Rollback a previously applied patch using the backup directory.
+/// Rollback a previously applied patch using its backup set.
 ///
-/// This restores files from `.patch-backup` to their original state.
+/// This restores files from `.patch-backup/<name>-v<version>` (or the external
+/// backup location) to their original
+/// state, then removes the restore point. Stacked patches must be rolled back
+/// in reverse order.
+///
+/// Without `manifest_path`, the last applied patch is rolled back using the
+/// manifest saved in its backup. Only the optional components it was applied
+/// with are rolled back.
 ///
 /// If `force` is false, validates that patched files are in expected state first.
 /// If `force` is true, skips patched files validation (but still validates backups).
-pub fn run(target_dir: &Path, manifest_path: &Path, force: bool) -> Result<(), PatchError> {
-    // Load manifest
-    let manifest = Manifest::load(manifest_path).map_err(|e| PatchError::ManifestError {
+///
+/// Backups stored outside the target are found through its marker file;
+/// `backup_dir` points the target at a different location (e.g. after moving them).
+pub fn run(
+    target_dir: &Path,
+    manifest_path: Option<&Path>,
+    force: bool,
+    backup_dir: Option<&Path>,
+) -> Result<(), PatchError> {
+    let manifest = load_manifest(target_dir, manifest_path, backup_dir)?;
+    restore(target_dir, &manifest, &manifest.entries, force)?;
+
+    // The restore point has been used up
+    let id = restore_point_id(&manifest);
+    forget_restore_point(target_dir, &id)
+        .and_then(|()| delete_backup_set(target_dir, &id))
+        .map_err(|e| PatchError::RollbackFailed {
+            reason: format!("files restored but backup could not be removed: {}", e),
+        })?;
+
+    Ok(())
+}
+
+/// Roll back the optional component `component` of a previously applied
+/// patch, leaving the rest of it in place.
+///
+/// The component is dropped from the patch's restore point, so the rest can
+/// still be rolled back later. Components that require it must be rolled
+/// back first. `manifest_path`, `force` and `backup_dir` work as in [`run`].
+pub fn remove_component(
+    target_dir: &Path,
+    manifest_path: Option<&Path>,
+    component: &str,
+    force: bool,
+    backup_dir: Option<&Path>,
+) -> Result<(), PatchError> {
+    let manifest = load_manifest(target_dir, manifest_path, backup_dir)?;
+    // Check it can be removed before touching any file
+    manifest.without_component(component).map_err(|e| PatchError::RollbackFailed {
         reason: e.to_string(),
     })?;
 
-    // Get backup directory
-    let backup_dir = target_dir.join(BACKUP_DIR);
+    restore(target_dir, &manifest, &manifest.component_entries(component), force)?;
+
+    forget_component(target_dir, &manifest, component).map_err(|e| PatchError::RollbackFailed {
+        reason: format!("files restored but the restore point could not be updated: {}", e),
+    })
+}
+
+/// Load the manifest to roll back, narrowed to the components that were applied.
+fn load_manifest(
+    target_dir: &Path,
+    manifest_path: Option<&Path>,
+    backup_dir: Option<&Path>,
+) -> Result<Manifest, PatchError> {
+    if let Some(dir) = backup_dir {
+        set_backup_root(target_dir, dir).map_err(|e| PatchError::RollbackFailed {
+            reason: format!("cannot use backup directory {}: {}", dir.display(), e),
+        })?;
+    }
+
+    match manifest_path {
+        Some(path) => {
+            let manifest = Manifest::load(path).map_err(|e| PatchError::ManifestError {
+                reason: e.to_string(),
+            })?;
+            Ok(applied_manifest(target_dir, &manifest))
+        }
+        None => {
+            let manifest = latest_backup_manifest(target_dir)?;
+            println!("Rolling back {} v{}", manifest.title.as_deref().unwrap_or(&manifest.name), manifest.version);
+            Ok(manifest)
+        }
+    }
+}
+
+/// Validate and restore `entries` of `manifest` from its backup set.
+fn restore(target_dir: &Path, manifest: &Manifest, entries: &[ManifestEntry], force: bool) -> Result<(), PatchError> {
+    // Get backup set (fails if a later patch must be rolled back first)
+    let backup_dir = rollback_set_dir(target_dir, manifest)?;
     if !backup_dir.exists() {
         return Err(PatchError::RollbackFailed {
             reason: format!("backup directory not found: {}", backup_dir.display()),
@@ -42,21 +123,19 @@ pub fn run(target_dir: &Path, manifest_path: &Path, force: bool) -> Result<(), P
 
     // Validate patched files are in expected state (skip if --force)
     if !force {
-        validate_patched_entries(&manifest.entries, target_dir, Some(|p: Progress| {
+        validate_patched_entries(entries, target_dir, Some(|p: Progress| {
             println!("{} [{}/{}]: {}", format_action(p.action), p.index + 1, p.total, p.file);
         }))?;
     }
 
     // Validate backup integrity before rolling back (always required)
-    validate_backup(&manifest.entries, &backup_dir, Some(|p: Progress| {
+    validate_backup(entries, target_dir, &backup_dir, Some(|p: Progress| {
         println!("{} [{}/{}]: {}", format_action(p.action), p.index + 1, p.total, p.file);
     }))?;
 
     // Rollback all entries (treat all as "applied")
-    let entries: Vec<_> = manifest.entries.iter().collect();
+    let entries: Vec<_> = entries.iter().collect();
     rollback(&entries, target_dir, &backup_dir, Some(|p: Progress| {
         println!("{} [{}/{}]: {}", format_action(p.action), p.index + 1, p.total, p.file);
-    }))?;
-
-    Ok(())
+    }))
 }
@@ -0,0 +1,686 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use graft_core::patch::{
+    audit_patch, PatchError, PatchSource, ASSETS_DIR, DIFFS_DIR, DIFF_EXTENSION, FILES_DIR,
+    MANIFEST_FILENAME,
+};
+use graft_core::utils::diff::{apply_diff, create_diff};
+use graft_core::utils::file_ops::copy_dir;
+use graft_core::utils::hash::hash_bytes;
+use graft_core::utils::manifest::{Component, Manifest, ManifestEntry};
+
+use crate::commands::common::{check_hash, read_original, write_error, write_payload};
+
+/// Content of a squashed entry's payload.
+enum Payload {
+    /// Diff bytes for a Patch entry, the diffs from its alternative originals
+    /// by original hash, and the new file if it has a fallback
+    Diff(Vec<u8>, Vec<(String, Vec<u8>)>, Option<Vec<u8>>),
+    /// New file content for an Add entry
+    File(Vec<u8>),
+    /// Delete, Require and edit entries have no payload
+    None,
+}
+
+/// Combine two sequential patches (v1→v2 and v2→v3) into a single v1→v3 patch.
+///
+/// Entries for the same file are composed (e.g. add then patch becomes an
+/// add of the final content, add then delete disappears) and every
+/// intermediate hash is checked against the next patch's expected hash.
+/// Setting edits of both patches are kept, the second patch's value winning
+/// when both edit the same setting.
+///
+/// Composing two diffs, or a delete followed by an add, needs the v1 file
+/// contents: pass the v1 tree as `original_dir` in that case. A first patch
+/// entry that accepts several originals can only be composed with a
+/// requirement.
+///
+/// The output uses the name, title and version of the second patch.
+pub fn run(
+    first_dir: &Path,
+    second_dir: &Path,
+    output_dir: &Path,
+    original_dir: Option<&Path>,
+) -> Result<(), PatchError> {
+    let first = first_dir.read_manifest()?;
+    let second = second_dir.read_manifest()?;
+
+    let first_entries = entries_by_file(&first)?;
+    let second_entries = entries_by_file(&second)?;
+
+    let mut files: Vec<&str> = first_entries.keys().chain(second_entries.keys()).copied().collect();
+    files.sort();
+    files.dedup();
+
+    let mut manifest = Manifest::new(second.version, second.name.clone(), second.title.clone());
+    manifest.allow_restricted = first.allow_restricted || second.allow_restricted;
+    manifest.backup_dir = second.backup_dir.clone().or_else(|| first.backup_dir.clone());
+    manifest.discovery = second.discovery.clone().or_else(|| first.discovery.clone());
+    manifest.backup = second.backup;
+    manifest.post_apply = second.post_apply.clone();
+    manifest.excluded = first.excluded.clone();
+    for pattern in &second.excluded {
+        if !manifest.excluded.contains(pattern) {
+            manifest.excluded.push(pattern.clone());
+        }
+    }
+    let mut payloads = Vec::new();
+
+    for file in files {
+        let composed = match (first_entries.get(file), second_entries.get(file)) {
+            (Some(&e1), None) => Some((e1.clone(), read_payload(first_dir, e1)?)),
+            (None, Some(&e2)) => Some((e2.clone(), read_payload(second_dir, e2)?)),
+            (Some(&e1), Some(&e2)) => compose(file, e1, e2, first_dir, second_dir, original_dir)?,
+            (None, None) => None,
+        };
+
+        if let Some((entry, payload)) = composed {
+            manifest.entries.push(entry);
+            payloads.push(payload);
+        }
+    }
+
+    for entry in merge_edits(&first, &second, &first_entries, &second_entries)? {
+        manifest.entries.push(entry);
+        payloads.push(Payload::None);
+    }
+    manifest.components = merge_components(&first, &second, &manifest.entries);
+
+    write_patch(&manifest, &payloads, output_dir)?;
+    copy_assets(first_dir, second_dir, output_dir)?;
+
+    // Sanity check the result
+    let report = audit_patch(output_dir, None)?;
+    if let Some(issue) = report.issues.first() {
+        return Err(PatchError::ManifestError {
+            reason: format!("squashed patch is inconsistent: {}", issue),
+        });
+    }
+
+    Ok(())
+}
+
+/// Merge the optional components of both patches by id. Files are combined,
+/// the rest is taken from the second patch, which also decides the component
+/// of a file both patches assign. Files without an entry in the squashed
+/// patch are dropped, and so are components left without files.
+fn merge_components(first: &Manifest, second: &Manifest, entries: &[ManifestEntry]) -> Vec<Component> {
+    let mut components: Vec<Component> = first.components.clone();
+    for component in &second.components {
+        for other in components.iter_mut().filter(|c| c.id != component.id) {
+            other.files.retain(|file| !component.files.contains(file));
+        }
+        match components.iter_mut().find(|c| c.id == component.id) {
+            Some(merged) => {
+                let mut files = std::mem::take(&mut merged.files);
+                *merged = component.clone();
+                for file in files.drain(..) {
+                    if !merged.files.contains(&file) {
+                        merged.files.push(file);
+                    }
+                }
+            }
+            None => components.push(component.clone()),
+        }
+    }
+    for component in &mut components {
+        component.files.retain(|file| entries.iter().any(|entry| entry.file() == file));
+        component.files.sort();
+    }
+    components.retain(|component| !component.files.is_empty());
+    let ids: Vec<String> = components.iter().map(|c| c.id.clone()).collect();
+    for component in &mut components {
+        component.requires.retain(|id| ids.contains(id));
+    }
+    components
+}
+
+/// Index manifest entries by file, rejecting duplicates. Edits are left out,
+/// see [`merge_edits`].
+fn entries_by_file(manifest: &Manifest) -> Result<BTreeMap<&str, &ManifestEntry>, PatchError> {
+    let mut entries = BTreeMap::new();
+    for entry in manifest.entries.iter().filter(|entry| !entry.is_edit()) {
+        if entries.insert(entry.file(), entry).is_some() {
+            return Err(PatchError::ManifestError {
+                reason: format!("'{}' appears in more than one entry", entry.file()),
+            });
+        }
+    }
+    Ok(entries)
+}
+
+/// Combine the setting edits of both patches, the second replacing the
+/// first's value for the same setting.
+///
+/// A file that is edited can't also be patched, added or deleted, since its
+/// content would no longer match either patch's expectations.
+fn merge_edits(
+    first: &Manifest,
+    second: &Manifest,
+    first_entries: &BTreeMap<&str, &ManifestEntry>,
+    second_entries: &BTreeMap<&str, &ManifestEntry>,
+) -> Result<Vec<ManifestEntry>, PatchError> {
+    let mut merged: Vec<ManifestEntry> = Vec::new();
+    for entry in first.entries.iter().chain(&second.entries).filter(|entry| entry.is_edit()) {
+        let file = entry.file();
+        if first_entries.contains_key(file) || second_entries.contains_key(file) {
+            return Err(chain_error(file, "file is both edited and replaced by the patches"));
+        }
+        match merged.iter_mut().find(|other| other.file() == file && setting_key(other) == setting_key(entry)) {
+            Some(existing) => *existing = entry.clone(),
+            None => merged.push(entry.clone()),
+        }
+    }
+    Ok(merged)
+}
+
+/// Name of an edited setting for comparisons; INI names are case-insensitive.
+fn setting_key(entry: &ManifestEntry) -> String {
+    let mut name = entry.setting().unwrap_or_default();
+    if matches!(entry, ManifestEntry::EditIni { .. }) {
+        name.make_ascii_lowercase();
+    }
+    name
+}
+
+/// Read the payload belonging to an entry.
+fn read_payload(patch_dir: &Path, entry: &ManifestEntry) -> Result<Payload, PatchError> {
+    match entry {
+        ManifestEntry::Patch {
+            file,
+            alternatives,
+            fallback,
+            ..
+        } => {
+            let diff = read_diff(patch_dir, file)?;
+            let alternatives = alternatives
+                .iter()
+                .map(|origin| {
+                    let diff = read_alternative_diff(patch_dir, file, &origin.original_hash)?;
+                    Ok((origin.original_hash.clone(), diff))
+                })
+                .collect::<Result<_, PatchError>>()?;
+            let fallback = if *fallback { Some(read_file(patch_dir, file)?) } else { None };
+            Ok(Payload::Diff(diff, alternatives, fallback))
+        }
+        ManifestEntry::Add { file, .. } => patch_dir
+            .read_file(file)
+            .map(Payload::File)
+            .map_err(|_| PatchError::FileNotFound(file.clone())),
+        ManifestEntry::Delete { .. }
+        | ManifestEntry::Require { .. }
+        | ManifestEntry::EditJson { .. }
+        | ManifestEntry::EditIni { .. } => Ok(Payload::None),
+    }
+}
+
+fn chain_error(file: &str, reason: impl Into<String>) -> PatchError {
+    PatchError::ValidationFailed {
+        file: file.to_string(),
+        reason: reason.into(),
+    }
+}
+
+/// Check that the state after the first patch is what the second expects.
+fn check_chain(file: &str, first_final: &str, second_original: &str) -> Result<(), PatchError> {
+    if first_final != second_original {
+        return Err(chain_error(
+            file,
+            format!(
+                "first patch produces {} but second patch expects {}",
+                first_final, second_original
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// Check that the state after the first patch is one of the originals the
+/// second patch's Patch entry accepts, and read that original's diff.
+fn chained_diff(
+    file: &str,
+    first_final: &str,
+    second: &ManifestEntry,
+    second_dir: &Path,
+) -> Result<Vec<u8>, PatchError> {
+    let originals: Vec<&str> = second.patch_origins().into_iter().map(|(hash, _)| hash).collect();
+    match originals.iter().position(|hash| *hash == first_final) {
+        Some(0) => read_diff(second_dir, file),
+        Some(_) => read_alternative_diff(second_dir, file, first_final),
+        None => Err(chain_error(
+            file,
+            format!(
+                "first patch produces {} but second patch expects {}",
+                first_final,
+                originals.join(" or ")
+            ),
+        )),
+    }
+}
+
+/// Composing with a diff needs the single v1 file the first patch starts from.
+fn check_single_origin(file: &str, first: &ManifestEntry) -> Result<(), PatchError> {
+    if first.patch_origins().len() > 1 {
+        return Err(chain_error(
+            file,
+            "first patch accepts several originals, so its change can't be composed",
+        ));
+    }
+    Ok(())
+}
+
+/// Read a v1 file from the original tree, which composing this entry needs.
+fn require_original(original_dir: Option<&Path>, file: &str, hash: &str) -> Result<Vec<u8>, PatchError> {
+    let original_dir = original_dir.ok_or_else(|| {
+        chain_error(file, "composing this change needs the original files (use --original)")
+    })?;
+    read_original(original_dir, file, hash)
+}
+
+/// Build a Patch entry from v1 to v3 content, or nothing if they're identical.
+fn patch_entry(
+    file: &str,
+    original_hash: &str,
+    original: &[u8],
+    final_data: &[u8],
+    fallback: bool,
+) -> Result<Option<(ManifestEntry, Payload)>, PatchError> {
+    let final_hash = hash_bytes(final_data);
+    if final_hash == original_hash {
+        return Ok(None);
+    }
+
+    let diff = create_diff(original, final_data).map_err(|e| PatchError::ApplyFailed {
+        file: file.to_string(),
+        reason: e.to_string(),
+    })?;
+    let entry = ManifestEntry::Patch {
+        file: file.to_string(),
+        original_hash: original_hash.to_string(),
+        diff_hash: hash_bytes(&diff),
+        final_hash,
+        alternatives: Vec::new(),
+        fallback,
+    };
+    let fallback = fallback.then(|| final_data.to_vec());
+    Ok(Some((entry, Payload::Diff(diff, Vec::new(), fallback))))
+}
+
+/// Apply a diff in memory and check the result.
+fn apply_checked(file: &str, base: &[u8], diff: &[u8], final_hash: &str) -> Result<Vec<u8>, PatchError> {
+    let data = apply_diff(base, diff).map_err(|e| PatchError::ApplyFailed {
+        file: file.to_string(),
+        reason: e.to_string(),
+    })?;
+    check_hash(file, &data, final_hash, "patched")?;
+    Ok(data)
+}
+
+/// Compose the entries of both patches for the same file.
+fn compose(
+    file: &str,
+    first: &ManifestEntry,
+    second: &ManifestEntry,
+    first_dir: &Path,
+    second_dir: &Path,
+    original_dir: Option<&Path>,
+) -> Result<Option<(ManifestEntry, Payload)>, PatchError> {
+    use ManifestEntry::{Add, Delete, EditIni, EditJson, Patch, Require};
+
+    match (first, second) {
+        (
+            Patch {
+                original_hash: o1,
+                final_hash: f1,
+                fallback,
+                ..
+            },
+            Patch { final_hash: f2, .. },
+        ) => {
+            // A target that doesn't match v1 gets v2 from the first fallback,
+            // which the second diff applies to, so only the first one counts
+            check_single_origin(file, first)?;
+            let d2 = chained_diff(file, f1, second, second_dir)?;
+            let v1 = require_original(original_dir, file, o1)?;
+            let d1 = read_diff(first_dir, file)?;
+            let v2 = apply_checked(file, &v1, &d1, f1)?;
+            let v3 = apply_checked(file, &v2, &d2, f2)?;
+            patch_entry(file, o1, &v1, &v3, *fallback)
+        }
+        (
+            Patch {
+                original_hash: o1,
+                final_hash: f1,
+                ..
+            },
+            Delete { original_hash: o2, .. },
+        ) => {
+            check_single_origin(file, first)?;
+            check_chain(file, f1, o2)?;
+            let entry = Delete {
+                file: file.to_string(),
+                original_hash: o1.clone(),
+            };
+            Ok(Some((entry, Payload::None)))
+        }
+        (Add { final_hash: f1, .. }, Patch { final_hash: f2, .. }) => {
+            let d2 = chained_diff(file, f1, second, second_dir)?;
+            let v2 = read_file(first_dir, file)?;
+            check_hash(file, &v2, f1, "added")?;
+            let v3 = apply_checked(file, &v2, &d2, f2)?;
+            let entry = Add {
+                file: file.to_string(),
+                final_hash: f2.clone(),
+            };
+            Ok(Some((entry, Payload::File(v3))))
+        }
+        (Add { final_hash: f1, .. }, Delete { original_hash: o2, .. }) => {
+            // Added then removed: the file never existed from v1's point of view
+            check_chain(file, f1, o2)?;
+            Ok(None)
+        }
+        (Delete { original_hash: o1, .. }, Add { final_hash: f2, .. }) => {
+            let v1 = require_original(original_dir, file, o1)?;
+            let v3 = read_file(second_dir, file)?;
+            check_hash(file, &v3, f2, "added")?;
+            patch_entry(file, o1, &v1, &v3, false)
+        }
+        (Delete { .. }, Delete { .. }) => Err(chain_error(
+            file,
+            "second patch deletes a file deleted by the first",
+        )),
+        (Require { hash: h1, .. }, Patch { .. } | Delete { .. }) => {
+            // The second patch's own check on the file supersedes the requirement
+            match second {
+                Delete { original_hash: o2, .. } => check_chain(file, h1, o2)?,
+                _ => {
+                    chained_diff(file, h1, second, second_dir)?;
+                }
+            }
+            Ok(Some((second.clone(), read_payload(second_dir, second)?)))
+        }
+        (Require { hash: h1, .. }, Require { hash: h2, .. }) => {
+            check_chain(file, h1, h2)?;
+            Ok(Some((first.clone(), Payload::None)))
+        }
+        (Patch { final_hash: f1, .. } | Add { final_hash: f1, .. }, Require { hash: h2, .. }) => {
+            check_chain(file, f1, h2)?;
+            Ok(Some((first.clone(), read_payload(first_dir, first)?)))
+        }
+        (Delete { .. }, Patch { .. }) => Err(chain_error(
+            file,
+            "second patch modifies a file deleted by the first",
+        )),
+        (Delete { .. }, Require { .. }) => Err(chain_error(
+            file,
+            "second patch requires a file deleted by the first",
+        )),
+        (Patch { .. } | Add { .. } | Require { .. }, Add { .. }) => Err(chain_error(
+            file,
+            "second patch adds a file that exists after the first",
+        )),
+        (EditJson { .. } | EditIni { .. }, _) | (_, EditJson { .. } | EditIni { .. }) => {
+            unreachable!("edits are merged separately")
+        }
+    }
+}
+
+fn read_diff(patch_dir: &Path, file: &str) -> Result<Vec<u8>, PatchError> {
+    patch_dir
+        .read_diff(file)
+        .map_err(|_| PatchError::DiffNotFound(file.to_string()))
+}
+
+fn read_alternative_diff(patch_dir: &Path, file: &str, original_hash: &str) -> Result<Vec<u8>, PatchError> {
+    patch_dir
+        .read_alternative_diff(file, original_hash)
+        .map_err(|_| PatchError::DiffNotFound(file.to_string()))
+}
+
+fn read_file(patch_dir: &Path, file: &str) -> Result<Vec<u8>, PatchError> {
+    patch_dir
+        .read_file(file)
+        .map_err(|_| PatchError::FileNotFound(file.to_string()))
+}
+
+/// Write manifest and payloads to the output directory.
+fn write_patch(manifest: &Manifest, payloads: &[Payload], output_dir: &Path) -> Result<(), PatchError> {
+    fs::create_dir_all(output_dir).map_err(|e| write_error(&output_dir.display().to_string(), e))?;
+
+    for (entry, payload) in manifest.entries.iter().zip(payloads) {
+        let (path, data) = match payload {
+            Payload::Diff(data, alternatives, fallback) => {
+                for (original_hash, diff) in alternatives {
+                    let name = format!("{}.{}{}", entry.file(), original_hash, DIFF_EXTENSION);
+                    write_payload(&output_dir.join(DIFFS_DIR).join(name), entry.file(), diff)?;
+                }
+                if let Some(fallback) = fallback {
+                    write_payload(&output_dir.join(FILES_DIR).join(entry.file()), entry.file(), fallback)?;
+                }
+                (
+                    output_dir.join(DIFFS_DIR).join(format!("{}{}", entry.file(), DIFF_EXTENSION)),
+                    data,
+                )
+            }
+            Payload::File(data) => (output_dir.join(FILES_DIR).join(entry.file()), data),
+            Payload::None => continue,
+        };
+        write_payload(&path, entry.file(), data)?;
+    }
+
+    manifest
+        .save(&output_dir.join(MANIFEST_FILENAME))
+        .map_err(|e| write_error(MANIFEST_FILENAME, e))
+}
+
+/// Copy the assets of the second patch (or the first, if the second has none).
+fn copy_assets(first_dir: &Path, second_dir: &Path, output_dir: &Path) -> Result<(), PatchError> {
+    let source = [second_dir, first_dir]
+        .into_iter()
+        .map(|dir| dir.join(ASSETS_DIR))
+        .find(|dir| dir.is_dir());
+    let Some(source) = source else {
+        return Ok(());
+    };
+
+    copy_dir(&source, &output_dir.join(ASSETS_DIR)).map_err(|e| PatchError::ApplyFailed {
+        file: ASSETS_DIR.to_string(),
+        reason: e.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::common::tree;
+    use crate::commands::{patch_apply, patch_create};
+    use graft_core::utils::manifest::Discovery;
+    use tempfile::tempdir;
+
+    /// Write a set of files into a fresh directory.
+    fn create(orig: &Path, new: &Path, out: &Path, version: u32) {
+        patch_create::run(orig, new, out, version, "TestPatcher", None, false).unwrap();
+    }
+
+    /// Squash v1→v2 and v2→v3, apply the result to v1 and compare with v3.
+    fn assert_squash_matches(v1: &[(&str, &[u8])], v2: &[(&str, &[u8])], v3: &[(&str, &[u8])]) {
+        let (v1_dir, v2_dir, v3_dir) = (tree(v1), tree(v2), tree(v3));
+        let patches = tempdir().unwrap();
+        let p1 = patches.path().join("p1");
+        let p2 = patches.path().join("p2");
+        let out = patches.path().join("out");
+        create(v1_dir.path(), v2_dir.path(), &p1, 2);
+        create(v2_dir.path(), v3_dir.path(), &p2, 3);
+        for (dir, path) in [(&p1, "~/old/Game"), (&p2, "~/new/Game")] {
+            let mut manifest = dir.read_manifest().unwrap();
+            manifest.discovery = Some(Discovery {
+                paths: vec![path.to_string()],
+                markers: Vec::new(),
+            });
+            manifest.save(&dir.join(MANIFEST_FILENAME)).unwrap();
+        }
+
+        run(&p1, &p2, &out, Some(v1_dir.path())).unwrap();
+
+        let target = tree(v1);
+        patch_apply::run(target.path(), &out, None, Default::default()).unwrap();
+        for (name, content) in v3 {
+            assert_eq!(fs::read(target.path().join(name)).unwrap(), *content, "{}", name);
+        }
+        for (name, _) in v1.iter().chain(v2) {
+            if !v3.iter().any(|(n, _)| n == name) {
+                assert!(!target.path().join(name).exists(), "{} should be gone", name);
+            }
+        }
+        let manifest = out.read_manifest().unwrap();
+        assert_eq!(manifest.version, 3);
+        assert_eq!(manifest.discovery.unwrap().paths, vec!["~/new/Game"]);
+    }
+
+    #[test]
+    fn squashes_patch_then_patch() {
+        assert_squash_matches(
+            &[("game.bin", b"version one")],
+            &[("game.bin", b"version two")],
+            &[("game.bin", b"version three")],
+        );
+    }
+
+    #[test]
+    fn squashes_add_then_patch_and_add_then_delete() {
+        assert_squash_matches(
+            &[("keep.bin", b"same")],
+            &[("keep.bin", b"same"), ("a.bin", b"added v2"), ("b.bin", b"temporary")],
+            &[("keep.bin", b"same"), ("a.bin", b"added v3")],
+        );
+    }
+
+    #[test]
+    fn squashes_delete_then_add_and_patch_then_delete() {
+        assert_squash_matches(
+            &[("x.bin", b"x v1"), ("y.bin", b"y v1")],
+            &[("y.bin", b"y v2")],
+            &[("x.bin", b"x v3")],
+        );
+    }
+
+    #[test]
+    fn change_reverted_by_second_patch_is_dropped() {
+        let (v1, v2) = (tree(&[("a.bin", b"one")]), tree(&[("a.bin", b"two")]));
+        let patches = tempdir().unwrap();
+        let (p1, p2, out) = (
+            patches.path().join("p1"),
+            patches.path().join("p2"),
+            patches.path().join("out"),
+        );
+        create(v1.path(), v2.path(), &p1, 2);
+        create(v2.path(), v1.path(), &p2, 3);
+
+        run(&p1, &p2, &out, Some(v1.path())).unwrap();
+        assert!(out.read_manifest().unwrap().entries.is_empty());
+    }
+
+    #[test]
+    fn second_patch_wins_for_the_same_setting() {
+        let v1 = tree(&[("game.ini", b"[Video]\nVSync=0\nWidth=800\n")]);
+        let patches = tempdir().unwrap();
+        let (p1, p2, out) = (
+            patches.path().join("p1"),
+            patches.path().join("p2"),
+            patches.path().join("out"),
+        );
+        let ini_edit = |key: &str, value: &str| ManifestEntry::EditIni {
+            file: "game.ini".to_string(),
+            section: "Video".to_string(),
+            key: key.to_string(),
+            value: value.to_string(),
+        };
+        for (dir, version, entries) in [
+            (&p1, 2, vec![ini_edit("VSync", "1"), ini_edit("Width", "1024")]),
+            (&p2, 3, vec![ini_edit("vsync", "2")]),
+        ] {
+            create(v1.path(), v1.path(), dir, version);
+            let mut manifest = dir.read_manifest().unwrap();
+            manifest.entries = entries;
+            manifest.save(&dir.join(MANIFEST_FILENAME)).unwrap();
+        }
+
+        run(&p1, &p2, &out, None).unwrap();
+
+        let entries = out.read_manifest().unwrap().entries;
+        assert_eq!(entries, vec![ini_edit("vsync", "2"), ini_edit("Width", "1024")]);
+        patch_apply::run(v1.path(), &out, None, Default::default()).unwrap();
+        assert_eq!(fs::read(v1.path().join("game.ini")).unwrap(), b"[Video]\nVSync=2\nWidth=1024\n");
+    }
+
+    #[test]
+    fn composing_diffs_requires_original() {
+        let (v1, v2, v3) = (tree(&[("a.bin", b"1")]), tree(&[("a.bin", b"2")]), tree(&[("a.bin", b"3")]));
+        let patches = tempdir().unwrap();
+        let (p1, p2) = (patches.path().join("p1"), patches.path().join("p2"));
+        create(v1.path(), v2.path(), &p1, 2);
+        create(v2.path(), v3.path(), &p2, 3);
+
+        let result = run(&p1, &p2, &patches.path().join("out"), None);
+        assert!(matches!(result, Err(PatchError::ValidationFailed { .. })));
+    }
+
+    #[test]
+    fn rejects_broken_hash_chain() {
+        let (v1, v2) = (tree(&[("a.bin", b"1")]), tree(&[("a.bin", b"2")]));
+        let (other, v3) = (tree(&[("a.bin", b"other")]), tree(&[("a.bin", b"3")]));
+        let patches = tempdir().unwrap();
+        let (p1, p2) = (patches.path().join("p1"), patches.path().join("p2"));
+        create(v1.path(), v2.path(), &p1, 2);
+        create(other.path(), v3.path(), &p2, 3);
+
+        let result = run(&p1, &p2, &patches.path().join("out"), Some(v1.path()));
+        assert!(matches!(result, Err(PatchError::ValidationFailed { file, .. }) if file == "a.bin"));
+    }
+
+    #[test]
+    fn rejects_deleting_a_deleted_file() {
+        let (v1, v2) = (tree(&[("a.bin", b"1"), ("b.bin", b"b")]), tree(&[("b.bin", b"b")]));
+        let (other, v3) = (tree(&[("a.bin", b"1"), ("b.bin", b"b")]), tree(&[("b.bin", b"b")]));
+        let patches = tempdir().unwrap();
+        let (p1, p2) = (patches.path().join("p1"), patches.path().join("p2"));
+        create(v1.path(), v2.path(), &p1, 2);
+        create(other.path(), v3.path(), &p2, 3);
+
+        let result = run(&p1, &p2, &patches.path().join("out"), None);
+        assert!(matches!(result, Err(PatchError::ValidationFailed { file, .. }) if file == "a.bin"));
+    }
+
+    #[test]
+    fn merges_components_by_id() {
+        let component = |id: &str, requires: &[&str], files: &[&str]| Component {
+            id: id.to_string(),
+            name: id.to_string(),
+            description: None,
+            default: false,
+            requires: requires.iter().map(|r| r.to_string()).collect(),
+            files: files.iter().map(|f| f.to_string()).collect(),
+        };
+        let entry = |file: &str| ManifestEntry::Delete {
+            file: file.to_string(),
+            original_hash: String::new(),
+        };
+        let mut first = Manifest::new(1, "Test".to_string(), None);
+        first.components = vec![
+            component("hd", &[], &["a.pak", "b.pak"]),
+            component("old", &[], &["gone.pak"]),
+            component("voices", &["old"], &["c.pak"]),
+        ];
+        let mut second = Manifest::new(2, "Test".to_string(), None);
+        second.components = vec![component("voices", &["old"], &["b.pak", "d.pak"])];
+        let entries: Vec<_> = ["a.pak", "b.pak", "c.pak", "d.pak"].into_iter().map(entry).collect();
+
+        let merged = merge_components(&first, &second, &entries);
+        assert_eq!(merged, vec![
+            component("hd", &[], &["a.pak"]),
+            component("voices", &[], &["b.pak", "c.pak", "d.pak"]),
+        ]);
+    }
+}
@@ -0,0 +1,69 @@
+use std::path::Path;
+
+use graft_core::patch::{audit_patch, AuditReport, PatchError};
+
+/// Audit a patch directory and print a report.
+///
+/// Checks payload hashes, duplicate entries and orphaned payloads. If
+/// `original_dir` is given, also applies every diff in memory against it and
+/// confirms the resulting hashes.
+///
+/// Returns the report; callers should treat any issue as a failure.
+pub fn run(patch_dir: &Path, original_dir: Option<&Path>) -> Result<AuditReport, PatchError> {
+    let report = audit_patch(patch_dir, original_dir)?;
+
+    println!("Checked {} entries", report.entries_checked);
+    if original_dir.is_some() {
+        println!("Replayed {} diffs against original", report.diffs_replayed);
+    }
+
+    if report.is_ok() {
+        println!("No issues found");
+    } else {
+        println!("\n{} issues found:", report.issues.len());
+        for issue in &report.issues {
+            println!("  - {}", issue);
+        }
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::patch_create;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn created_patch_verifies_against_original() {
+        let orig_dir = tempdir().unwrap();
+        let new_dir = tempdir().unwrap();
+        let patch_dir = tempdir().unwrap();
+
+        fs::write(orig_dir.path().join("game.bin"), b"original data").unwrap();
+        fs::write(new_dir.path().join("game.bin"), b"modified data").unwrap();
+        fs::write(new_dir.path().join("added.bin"), b"added").unwrap();
+
+        patch_create::run(orig_dir.path(), new_dir.path(), patch_dir.path(), 1, "TestPatcher", None, false).unwrap();
+
+        let report = run(patch_dir.path(), Some(orig_dir.path())).unwrap();
+        assert!(report.is_ok(), "{:?}", report.issues);
+        assert_eq!(report.diffs_replayed, 1);
+    }
+
+    #[test]
+    fn tampered_patch_reports_issues() {
+        let orig_dir = tempdir().unwrap();
+        let new_dir = tempdir().unwrap();
+        let patch_dir = tempdir().unwrap();
+
+        fs::write(new_dir.path().join("added.bin"), b"added").unwrap();
+        patch_create::run(orig_dir.path(), new_dir.path(), patch_dir.path(), 1, "TestPatcher", None, false).unwrap();
+        fs::write(patch_dir.path().join("files/added.bin"), b"tampered").unwrap();
+
+        let report = run(patch_dir.path(), None).unwrap();
+        assert!(!report.is_ok());
+    }
+}
@@ -1,8 +1,10 @@
 use std::path::PathBuf;
 use std::process;
 
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
+use graft::commands::backup_export::ExportMode;
 use graft::commands::check::CheckResult;
+use graft_core::utils::manifest::{component_selection, BackupOptions, Discovery, Manifest, PostAction};
 
 fn version_string() -> &'static str {
     #[cfg(feature = "embedded-stubs")]
@@ -42,10 +44,60 @@ enum Commands {
         #[command(subcommand)]
         command: PatchCommands,
     },
+    /// Manage the backups of patched directories
+    Backup {
+        #[command(subcommand)]
+        command: BackupCommands,
+    },
     /// Build standalone patcher executables
     Build(BuildArgs),
 }
 
+#[derive(Subcommand)]
+enum BackupCommands {
+    /// List the backups of a target directory
+    List {
+        /// Patched target directory
+        target: PathBuf,
+    },
+    /// Show the files in a backup
+    Info {
+        /// Patched target directory
+        target: PathBuf,
+        /// Backup set to show (default: the last applied patch)
+        #[arg(long)]
+        id: Option<String>,
+    },
+    /// Check that a patch's backup can restore every file
+    Verify {
+        /// Patched target directory
+        target: PathBuf,
+        /// Path to manifest.json (default: the one saved with the last applied patch's backup)
+        manifest: Option<PathBuf>,
+    },
+    /// Remove backups that are no longer needed
+    Prune {
+        /// Patched target directory
+        target: PathBuf,
+        /// Also remove all but this many of the most recent restore points
+        #[arg(long)]
+        keep: Option<usize>,
+    },
+    /// Copy, move or archive the backups elsewhere
+    Export {
+        /// Patched target directory
+        target: PathBuf,
+        /// Destination directory (or archive file with --archive)
+        dest: PathBuf,
+        /// Move the backups and keep using them from the destination
+        #[arg(long, conflicts_with = "archive")]
+        r#move: bool,
+        /// Write a .tar.zst archive instead of a directory
+        #[arg(long)]
+        archive: bool,
+    },
+}
+
 /// Build arguments for production mode (with embedded stubs)
 #[cfg(feature = "embedded-stubs")]
 #[derive(clap::Args, Debug)]
@@ -65,6 +117,21 @@ struct BuildArgs {
     /// Available: linux-x64, linux-arm64, windows-x64, macos-x64, macos-arm64
     #[arg(short, long)]
     target: Vec<String>,
+
+    /// Patch container format.
+    /// Available: indexed (default, seekable, read in place by the patcher), tar
+    /// (default when --compression or --level is given)
+    #[arg(long)]
+    container: Option<String>,
+
+    /// Compression codec for the tar container.
+    /// Available: gzip (default), zstd, xz
+    #[arg(long)]
+    compression: Option<String>,
+
+    /// Compression level (gzip/xz: 0-9, zstd: 1-22)
+    #[arg(long)]
+    level: Option<u32>,
 }
 
 /// Build arguments for development mode (no embedded stubs)
@@ -86,6 +153,21 @@ struct BuildArgs {
     /// Available: linux-x64, linux-arm64, windows-x64, macos-x64, macos-arm64
     #[arg(short, long)]
     target: Vec<String>,
+
+    /// Patch container format.
+    /// Available: indexed (default, seekable, read in place by the patcher), tar
+    /// (default when --compression or --level is given)
+    #[arg(long)]
+    container: Option<String>,
+
+    /// Compression codec for the tar container.
+    /// Available: gzip (default), zstd, xz
+    #[arg(long)]
+    compression: Option<String>,
+
+    /// Compression level (gzip/xz: 0-9, zstd: 1-22)
+    #[arg(long)]
+    level: Option<u32>,
 }
 
 #[derive(Subcommand)]
@@ -155,6 +237,28 @@ enum PatchCommands {
         /// Allow patching restricted paths (system dirs, executables)
         #[arg(long)]
         allow_restricted: bool,
+        /// Also create the reverse patch (new → orig) in <OUTPUT>-reverse
+        #[arg(long)]
+        bidirectional: bool,
+        /// Only compare files matching this glob (can be repeated)
+        #[arg(long)]
+        include: Vec<String>,
+        /// Skip files matching this glob (can be repeated, adds to .graftignore)
+        #[arg(long)]
+        exclude: Vec<String>,
+        /// Require unchanged files matching this glob to be present with the same hash (can be repeated)
+        #[arg(long, value_name = "GLOB")]
+        require_unchanged: Vec<String>,
+        /// Also ship the full new version of modified files matching this glob, used when
+        /// the target file doesn't match the original (can be repeated)
+        #[arg(long, value_name = "GLOB")]
+        fallback: Vec<String>,
+        /// Another release of the original directory (e.g. a regional one) the patch should
+        /// also apply to (can be repeated)
+        #[arg(long, value_name = "DIR")]
+        alt_orig: Vec<PathBuf>,
+        #[command(flatten)]
+        patcher: Box<PatcherArgs>,
     },
     /// Apply a patch to a target directory
     Apply {
@@ -162,17 +266,126 @@ enum PatchCommands {
         target: PathBuf,
         /// Directory containing patch files
         patch: PathBuf,
+        /// Store backups here instead of <TARGET>/.patch-backup
+        #[arg(long, value_name = "DIR")]
+        backup_dir: Option<PathBuf>,
+        /// Compress backups with zstd
+        #[arg(long)]
+        compress_backup: bool,
+        /// Store backups of patched files as reverse diffs when much smaller
+        #[arg(long)]
+        reverse_diff_backup: bool,
+        /// Optional component to apply instead of the default ones (can be repeated)
+        #[arg(long, value_name = "ID")]
+        component: Vec<String>,
+        /// Apply no optional component unless one is given with --component
+        #[arg(long)]
+        no_default_components: bool,
+        /// Don't run the patch's post-apply steps, like clearing a cache
+        #[arg(long)]
+        skip_post_apply: bool,
     },
     /// Rollback a previously applied patch using backup
     Rollback {
         /// Target directory to restore
         target: PathBuf,
-        /// Path to manifest.json (from original patch)
-        manifest: PathBuf,
+        /// Path to manifest.json (default: the one saved with the last applied patch's backup)
+        manifest: Option<PathBuf>,
         /// Skip validation of patched files (use when files have been modified)
         #[arg(long, short)]
         force: bool,
+        /// Read backups from here (needed only if they were moved)
+        #[arg(long, value_name = "DIR")]
+        backup_dir: Option<PathBuf>,
+        /// Only roll back this optional component, keeping the rest of the patch
+        #[arg(long, value_name = "ID")]
+        component: Option<String>,
+    },
+    /// Combine two sequential patches (v1→v2, v2→v3) into one (v1→v3)
+    Squash {
+        /// First patch directory (v1→v2)
+        first: PathBuf,
+        /// Second patch directory (v2→v3)
+        second: PathBuf,
+        /// Output directory for the combined patch
+        #[arg(short, long)]
+        output: PathBuf,
+        /// Original (v1) directory, needed when both patches modify the same file
+        #[arg(long)]
+        original: Option<PathBuf>,
+    },
+    /// Generate the inverse of a patch (v2→v1)
+    Reverse {
+        /// Directory containing patch files
+        patch: PathBuf,
+        /// Original directory the patch was created from
+        #[arg(long)]
+        original: PathBuf,
+        /// Output directory for the reverse patch
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+    /// Check a patch directory for consistency before release
+    Verify {
+        /// Directory containing patch files
+        patch: PathBuf,
+        /// Original directory to replay diffs against
+        #[arg(long)]
+        original: Option<PathBuf>,
     },
+    /// Show the contents of a patch directory, patcher executable or .app bundle
+    Inspect {
+        /// Patch directory, patcher executable or .app bundle
+        path: PathBuf,
+        /// Extract the patch directory to this location
+        #[arg(long)]
+        extract: Option<PathBuf>,
+    },
+}
+
+/// How the generated patcher finds its target and behaves around an apply
+#[derive(Args)]
+struct PatcherArgs {
+    /// Known install location for automatic target discovery (can be repeated)
+    #[arg(long, value_name = "TEMPLATE")]
+    discover_path: Vec<String>,
+    /// File that must exist in a discovered target folder (can be repeated)
+    #[arg(long, value_name = "FILE")]
+    discover_marker: Vec<String>,
+    /// Default backup location for the patcher (relative paths go in the user data dir)
+    #[arg(long, value_name = "DIR")]
+    default_backup_dir: Option<String>,
+    /// Make the patcher compress backups with zstd
+    #[arg(long)]
+    compress_backup: bool,
+    /// Make the patcher store backups of patched files as reverse diffs when much smaller
+    #[arg(long)]
+    reverse_diff_backup: bool,
+    /// Step after applying: delete_dir=DIR, launch=FILE, open_file=FILE or open_url=URL
+    /// (can be repeated, runs in order)
+    #[arg(long, value_name = "ACTION=VALUE", value_parser = parse_post_action)]
+    post_apply: Vec<PostAction>,
+    /// JSON file with setting edits (edit_json/edit_ini entries) to include
+    #[arg(long, value_name = "FILE")]
+    edits: Option<PathBuf>,
+    /// JSON file defining optional components the user can choose from
+    #[arg(long, value_name = "FILE")]
+    components: Option<PathBuf>,
+}
+
+/// Parse a `--post-apply` step like `delete_dir=cache/shaders`
+fn parse_post_action(arg: &str) -> Result<PostAction, String> {
+    let (action, value) = arg
+        .split_once('=')
+        .ok_or_else(|| "expected ACTION=VALUE".to_string())?;
+    let value = value.to_string();
+    match action {
+        "delete_dir" => Ok(PostAction::DeleteDir { path: value }),
+        "launch" => Ok(PostAction::Launch { path: value, args: Vec::new() }),
+        "open_file" => Ok(PostAction::OpenFile { path: value }),
+        "open_url" => Ok(PostAction::OpenUrl { url: value }),
+        _ => Err(format!("unknown action '{}' (expected delete_dir, launch, open_file or open_url)", action)),
+    }
 }
 
 fn main() {
@@ -203,6 +416,55 @@ fn main() {
                 }
             }
         }
+        Commands::Backup { command } => match command {
+            BackupCommands::List { target } => {
+                if let Err(e) = graft::commands::backup_list::run(&target) {
+                    eprintln!("Error: {}", e);
+                    process::exit(2);
+                }
+            }
+            BackupCommands::Info { target, id } => {
+                if let Err(e) = graft::commands::backup_info::run(&target, id.as_deref()) {
+                    eprintln!("Error: {}", e);
+                    process::exit(2);
+                }
+            }
+            BackupCommands::Verify { target, manifest } => {
+                match graft::commands::backup_verify::run(&target, manifest.as_deref()) {
+                    Ok(()) => {
+                        println!("Backup is intact");
+                    }
+                    Err(e) => {
+                        eprintln!("Error: {}", e);
+                        process::exit(1);
+                    }
+                }
+            }
+            BackupCommands::Prune { target, keep } => {
+                if let Err(e) = graft::commands::backup_prune::run(&target, keep) {
+                    eprintln!("Error: {}", e);
+                    process::exit(2);
+                }
+            }
+            BackupCommands::Export { target, dest, r#move, archive } => {
+                let mode = if archive {
+                    ExportMode::Archive
+                } else if r#move {
+                    ExportMode::Move
+                } else {
+                    ExportMode::Copy
+                };
+                match graft::commands::backup_export::run(&target, &dest, mode) {
+                    Ok(()) => {
+                        println!("Backups exported to {}", dest.display());
+                    }
+                    Err(e) => {
+                        eprintln!("Error: {}", e);
+                        process::exit(2);
+                    }
+                }
+            }
+        },
         Commands::Hash { command } => match command {
             HashCommands::Calculate { file } => {
                 match graft::commands::calculate::run(&file) {
@@ -261,10 +523,57 @@ fn main() {
                 name,
                 title,
                 allow_restricted,
+                bidirectional,
+                include,
+                exclude,
+                require_unchanged,
+                fallback,
+                alt_orig,
+                patcher,
             } => {
-                match graft::commands::patch_create::run(&orig, &new, &output, version, &name, title.as_deref(), allow_restricted) {
-                    Ok(()) => {
+                let PatcherArgs {
+                    discover_path,
+                    discover_marker,
+                    default_backup_dir,
+                    compress_backup,
+                    reverse_diff_backup,
+                    post_apply,
+                    edits,
+                    components,
+                } = *patcher;
+                let mut manifest = Manifest::new(version, name, title);
+                manifest.allow_restricted = allow_restricted;
+                manifest.backup_dir = default_backup_dir;
+                manifest.backup = BackupOptions {
+                    compress: compress_backup,
+                    reverse_diffs: reverse_diff_backup,
+                };
+                if !discover_path.is_empty() || !discover_marker.is_empty() {
+                    manifest.discovery = Some(Discovery {
+                        paths: discover_path,
+                        markers: discover_marker,
+                    });
+                }
+                manifest.post_apply = post_apply;
+
+                let inputs = graft::commands::patch_create::CreateInputs {
+                    include,
+                    exclude,
+                    edits,
+                    components,
+                    bidirectional,
+                };
+                let options = graft::commands::patch_create::CreateOptions {
+                    require: require_unchanged,
+                    fallback,
+                    alt_origs: alt_orig,
+                };
+                match graft::commands::patch_create::run_with_inputs(&orig, &new, &output, manifest, &inputs, &options) {
+                    Ok(reverse) => {
                         println!("Patch created at {}", output.display());
+                        if let Some(reverse) = reverse {
+                            println!("Reverse patch created at {}", reverse.display());
+                        }
                     }
                     Err(e) => {
                         eprintln!("Error: {}", e);
@@ -272,8 +581,29 @@ fn main() {
                     }
                 }
             }
-            PatchCommands::Apply { target, patch } => {
-                match graft::commands::patch_apply::run(&target, &patch) {
+            PatchCommands::Apply {
+                target,
+                patch,
+                backup_dir,
+                compress_backup,
+                reverse_diff_backup,
+                component,
+                no_default_components,
+                skip_post_apply,
+            } => {
+                let options = BackupOptions {
+                    compress: compress_backup,
+                    reverse_diffs: reverse_diff_backup,
+                };
+                let components = component_selection(&component, no_default_components);
+                match graft::commands::patch_apply::run_with_components(
+                    &target,
+                    &patch,
+                    backup_dir.as_deref(),
+                    options,
+                    components,
+                    skip_post_apply,
+                ) {
                     Ok(()) => {
                         println!("Patch applied successfully");
                     }
@@ -283,10 +613,56 @@ fn main() {
                     }
                 }
             }
-            PatchCommands::Rollback { target, manifest, force } => {
-                match graft::commands::patch_rollback::run(&target, &manifest, force) {
+            PatchCommands::Rollback { target, manifest, force, backup_dir, component } => {
+                let result = match &component {
+                    Some(id) => graft::commands::patch_rollback::remove_component(
+                        &target,
+                        manifest.as_deref(),
+                        id,
+                        force,
+                        backup_dir.as_deref(),
+                    ),
+                    None => graft::commands::patch_rollback::run(&target, manifest.as_deref(), force, backup_dir.as_deref()),
+                };
+                match result {
+                    Ok(()) => match component {
+                        Some(id) => println!("Component '{}' rolled back", id),
+                        None => println!("Rollback complete"),
+                    },
+                    Err(e) => {
+                        eprintln!("Error: {}", e);
+                        process::exit(2);
+                    }
+                }
+            }
+            PatchCommands::Squash { first, second, output, original } => {
+                match graft::commands::patch_squash::run(&first, &second, &output, original.as_deref()) {
+                    Ok(()) => {
+                        println!("Squashed patch created at {}", output.display());
+                    }
+                    Err(e) => {
+                        eprintln!("Error: {}", e);
+                        process::exit(2);
+                    }
+                }
+            }
+            PatchCommands::Reverse { patch, original, output } => {
+                match graft::commands::patch_reverse::run(&patch, &original, &output) {
                     Ok(()) => {
-                        println!("Rollback complete");
+                        println!("Reverse patch created at {}", output.display());
+                    }
+                    Err(e) => {
+                        eprintln!("Error: {}", e);
+                        process::exit(2);
+                    }
+                }
+            }
+            PatchCommands::Verify { patch, original } => {
+                match graft::commands::patch_verify::run(&patch, original.as_deref()) {
+                    Ok(report) => {
+                        if !report.is_ok() {
+                            process::exit(1);
+                        }
                     }
                     Err(e) => {
                         eprintln!("Error: {}", e);
@@ -294,6 +670,12 @@ fn main() {
                     }
                 }
             }
+            PatchCommands::Inspect { path, extract } => {
+                if let Err(e) = graft::commands::patch_inspect::run(&path, extract.as_deref()) {
+                    eprintln!("Error: {}", e);
+                    process::exit(2);
+                }
+            }
         },
         Commands::Build(args) => {
             #[cfg(feature = "embedded-stubs")]
@@ -303,6 +685,9 @@ fn main() {
                     &args.output,
                     args.stub_dir.as_deref(),
                     &args.target,
+                    args.container.as_deref(),
+                    args.compression.as_deref(),
+                    args.level,
                 ) {
                     Ok(()) => {}
                     Err(e) => {
@@ -319,6 +704,9 @@ fn main() {
                     &args.output,
                     &args.stub_dir,
                     &args.target,
+                    args.container.as_deref(),
+                    args.compression.as_deref(),
+                    args.level,
                 ) {
                     Ok(()) => {}
                     Err(e) => {
@@ -110,6 +110,49 @@ pub fn stub_filename(target: &Target) -> String {
     }
 }
 
+/// Detect the target of an executable from its leading bytes.
+///
+/// Recognizes ELF (Linux), PE (Windows) and 64-bit Mach-O (macOS) headers.
+/// Returns `None` for unknown formats and architectures, including
+/// universal macOS binaries.
+pub fn detect_target(header: &[u8]) -> Option<Target> {
+    let u16_at = |pos: usize| header.get(pos..pos + 2).map(|b| u16::from_le_bytes([b[0], b[1]]));
+    let u32_at = |pos: usize| {
+        header
+            .get(pos..pos + 4)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    };
+
+    if header.starts_with(b"\x7fELF") {
+        return match u16_at(18)? {
+            0x3E => Some(LINUX_X64),
+            0xB7 => Some(LINUX_ARM64),
+            _ => None,
+        };
+    }
+
+    if header.starts_with(b"MZ") {
+        let pe_offset = u32_at(0x3C)? as usize;
+        if header.get(pe_offset..pe_offset + 4)? != b"PE\0\0" {
+            return None;
+        }
+        return match u16_at(pe_offset + 4)? {
+            0x8664 => Some(WINDOWS_X64),
+            _ => None,
+        };
+    }
+
+    if header.starts_with(&[0xCF, 0xFA, 0xED, 0xFE]) {
+        return match u32_at(4)? {
+            0x0100_0007 => Some(MACOS_X64),
+            0x0100_000C => Some(MACOS_ARM64),
+            _ => None,
+        };
+    }
+
+    None
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -137,4 +180,31 @@ mod tests {
         assert_eq!(stub_filename(&WINDOWS_X64), "graft-gui-stub-windows-x64.exe");
         assert_eq!(stub_filename(&MACOS_ARM64), "graft-gui-stub-macos-arm64.app.zip");
     }
+
+    #[test]
+    fn detect_target_reads_headers() {
+        let mut elf = vec![0u8; 64];
+        elf[..4].copy_from_slice(b"\x7fELF");
+        elf[18] = 0xB7;
+        assert_eq!(detect_target(&elf), Some(LINUX_ARM64));
+
+        let mut pe = vec![0u8; 0x90];
+        pe[..2].copy_from_slice(b"MZ");
+        pe[0x3C] = 0x80;
+        pe[0x80..0x84].copy_from_slice(b"PE\0\0");
+        pe[0x84..0x86].copy_from_slice(&0x8664u16.to_le_bytes());
+        assert_eq!(detect_target(&pe), Some(WINDOWS_X64));
+
+        let mut macho = vec![0xCF, 0xFA, 0xED, 0xFE];
+        macho.extend_from_slice(&0x0100_000Cu32.to_le_bytes());
+        assert_eq!(detect_target(&macho), Some(MACOS_ARM64));
+
+        assert_eq!(detect_target(b"#!/bin/sh"), None);
+    }
+
+    #[test]
+    fn detect_target_matches_current_executable() {
+        let exe = std::fs::read(std::env::current_exe().unwrap()).unwrap();
+        assert_eq!(detect_target(&exe[..exe.len().min(4096)]), current_target());
+    }
 }